@@ -0,0 +1,104 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use log::{debug, warn};
+use sha2::{Digest, Sha256};
+use wasmtime::Engine;
+
+use crate::{AnyResult, BlocklessRunner, BlsRunTarget};
+
+/// The cache of the compiled modules on the disk. The compiled module is keyed by the
+/// sha256 of the module and the fingerprint of the engine, so the module is compiled
+/// again when the engine configure, e.g. the optimize options, is changed.
+///
+/// The compiled modules are loaded without validation, the directory must be trusted.
+#[derive(Clone, Debug)]
+pub struct ModuleCache {
+    dir: PathBuf,
+}
+
+impl ModuleCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// the fingerprint of the engine, the engines with the same fingerprint can load
+    /// the modules compiled by each other.
+    pub fn engine_fingerprint(engine: &Engine) -> String {
+        let mut hasher = DefaultHasher::new();
+        engine.precompile_compatibility_hash().hash(&mut hasher);
+        format!("{:016x}", hasher.finish())
+    }
+
+    /// the path of the compiled module in the cache.
+    pub fn path(&self, engine: &Engine, wasm: &[u8]) -> PathBuf {
+        let digest = Sha256::digest(wasm);
+        let fingerprint = Self::engine_fingerprint(engine);
+        self.dir.join(format!("{digest:x}-{fingerprint}.cwasm"))
+    }
+
+    /// load the compiled module from the cache, the module is compiled and stored in
+    /// the cache if it's not cached. The precompiled file is loaded directly.
+    pub fn load(&self, engine: &Engine, path: impl AsRef<Path>) -> AnyResult<BlsRunTarget> {
+        let path = path.as_ref();
+        let wasm = std::fs::read(path)
+            .with_context(|| format!("failed to read file: {}", path.display()))?;
+        if engine.detect_precompiled(&wasm).is_some() {
+            return BlocklessRunner::load_module(engine, path);
+        }
+        let cached = self.path(engine, &wasm);
+        if cached.exists() {
+            match BlocklessRunner::load_module(engine, &cached) {
+                Ok(target) => {
+                    debug!("load the cached module: {}", cached.display());
+                    return Ok(target);
+                }
+                // the broken file is replaced by the module compiled again.
+                Err(e) => warn!(
+                    "failed to load the cached module {}: {}",
+                    cached.display(),
+                    e
+                ),
+            }
+        }
+        let target = BlocklessRunner::load_module_contents(
+            engine,
+            path,
+            &wasm,
+            || unreachable!("the module is not precompiled"),
+            || unreachable!("the module is not precompiled"),
+        )?;
+        if let Err(e) = self.store(&cached, &target) {
+            warn!("failed to cache the module {}: {}", path.display(), e);
+        }
+        Ok(target)
+    }
+
+    /// write the compiled module to the unique temporary file and rename it, so the other
+    /// processes and threads never load the partial file.
+    fn store(&self, cached: &Path, target: &BlsRunTarget) -> AnyResult<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        // the temporary file is removed when it's dropped without persisting.
+        let mut tmp = tempfile::NamedTempFile::new_in(&self.dir)?;
+        tmp.write_all(&target.serialize()?)?;
+        tmp.persist(cached)?;
+        Ok(())
+    }
+}
+
+/// load the module with the cache directory, the module is loaded without the cache
+/// if the directory is not set or the module is read from the stdin.
+pub(crate) fn load_module(
+    engine: &Engine,
+    path: impl AsRef<Path>,
+    cache_dir: Option<&str>,
+) -> AnyResult<BlsRunTarget> {
+    let path = path.as_ref();
+    match cache_dir {
+        Some(dir) if path.to_str() != Some("-") => ModuleCache::new(dir).load(engine, path),
+        _ => BlocklessRunner::load_module(engine, path),
+    }
+}
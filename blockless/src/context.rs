@@ -1,9 +1,13 @@
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, RwLock};
 
-use wasmtime::StoreLimits;
+use blockless_drivers::DriverConetxt;
+use wasmtime::{ResourceLimiter, StoreLimits};
 use wasmtime_wasi::preview1::WasiP1Ctx;
 use wasmtime_wasi_threads::WasiThreadsCtx;
 
+use crate::modules::InstanceCtx;
+use crate::stdio::StdioCapture;
+
 #[derive(Clone)]
 pub(crate) struct BlocklessContext {
     pub(crate) preview1_ctx: Option<wasi_common::WasiCtx>,
@@ -16,7 +20,16 @@ pub(crate) struct BlocklessContext {
 
     pub(crate) wasi_nn_witx: Option<Arc<wasmtime_wasi_nn::witx::WasiNnCtx>>,
 
-    pub(crate) store_limits: StoreLimits,
+    pub(crate) wasi_http: Option<Arc<wasmtime_wasi_http::WasiHttpCtx>>,
+
+    pub(crate) store_limits: BlocklessLimits,
+
+    pub(crate) instance_ctx: Arc<tokio::sync::Mutex<InstanceCtx>>,
+
+    pub(crate) stdio_capture: StdioCapture,
+
+    /// the driver registry of the run, it's shared by the jobs of the runtime.
+    pub(crate) drivers: Arc<RwLock<DriverConetxt>>,
 }
 
 impl Default for BlocklessContext {
@@ -24,11 +37,75 @@ impl Default for BlocklessContext {
         Self {
             wasi_nn_wit: None,
             wasi_nn_witx: None,
+            wasi_http: None,
             preview1_ctx: None,
             preview2_ctx: None,
             wasi_threads: None,
             store_limits: Default::default(),
+            instance_ctx: Arc::new(tokio::sync::Mutex::new(InstanceCtx::new())),
+            stdio_capture: Default::default(),
+            drivers: Default::default(),
+        }
+    }
+}
+
+/// The store limits which also record the peak size of the linear memory.
+#[derive(Clone, Default)]
+pub(crate) struct BlocklessLimits {
+    limits: StoreLimits,
+    pub(crate) peak_memory: usize,
+}
+
+impl BlocklessLimits {
+    pub(crate) fn new(limits: StoreLimits) -> Self {
+        Self {
+            limits,
+            peak_memory: 0,
+        }
+    }
+}
+
+impl ResourceLimiter for BlocklessLimits {
+    fn memory_growing(
+        &mut self,
+        current: usize,
+        desired: usize,
+        maximum: Option<usize>,
+    ) -> anyhow::Result<bool> {
+        let allowed = self.limits.memory_growing(current, desired, maximum)?;
+        if allowed {
+            self.peak_memory = self.peak_memory.max(desired);
         }
+        Ok(allowed)
+    }
+
+    fn memory_grow_failed(&mut self, error: anyhow::Error) -> anyhow::Result<()> {
+        self.limits.memory_grow_failed(error)
+    }
+
+    fn table_growing(
+        &mut self,
+        current: usize,
+        desired: usize,
+        maximum: Option<usize>,
+    ) -> anyhow::Result<bool> {
+        self.limits.table_growing(current, desired, maximum)
+    }
+
+    fn table_grow_failed(&mut self, error: anyhow::Error) -> anyhow::Result<()> {
+        self.limits.table_grow_failed(error)
+    }
+
+    fn instances(&self) -> usize {
+        self.limits.instances()
+    }
+
+    fn tables(&self) -> usize {
+        self.limits.tables()
+    }
+
+    fn memories(&self) -> usize {
+        self.limits.memories()
     }
 }
 
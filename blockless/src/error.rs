@@ -41,3 +41,15 @@ impl Display for McallError {
         }
     }
 }
+
+/// The error of the job which is cancelled by the `JobHandle`.
+#[derive(Debug)]
+pub struct JobCancelled(pub u64);
+
+impl std::error::Error for JobCancelled {}
+
+impl Display for JobCancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the job {} is cancelled", self.0)
+    }
+}
@@ -0,0 +1,135 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use blockless_drivers::http_server_driver::{self, ReadError, Request, Response};
+use log::{debug, error};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, oneshot, Semaphore};
+use tokio::task::JoinSet;
+use tokio::time::Instant;
+use wasmtime::{Instance, Store, Trap};
+
+use crate::context::BlocklessContext;
+
+/// the time of reading the request, the request is answered with 408 if it's expired.
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// the requests waiting for the guest handler.
+const QUEUE_SIZE: usize = 128;
+
+/// the connections served at the same time, the listens don't accept more.
+const MAX_CONNECTIONS: usize = 1024;
+
+type Exchange = (Request, oneshot::Sender<Response>);
+
+/// serve the http on the listens, the requests are read by the accept tasks and handled by
+/// the exported handler of the guest one by one. The handler is `(handle: u32) -> ()`, it
+/// reads the request and writes the response with the `blockless_http_server` module.
+///
+/// The waiting for the requests is outside of the guest, so the epoch deadline doesn't
+/// stop it, the serving is interrupted when the time limit in milliseconds is reached.
+pub(crate) async fn serve(
+    store: &mut Store<BlocklessContext>,
+    instance: &Instance,
+    handler: &str,
+    listens: &[(SocketAddr, Option<u32>)],
+    time_limit: Option<u64>,
+) -> anyhow::Result<()> {
+    let func = instance
+        .get_typed_func::<u32, ()>(&mut *store, handler)
+        .with_context(|| format!("the http handler `{handler}` is not exported."))?;
+    if listens.is_empty() {
+        bail!("the http handler requires the tcp listens.");
+    }
+    let deadline = time_limit.map(|t| Instant::now() + Duration::from_millis(t));
+    let (tx, mut rx) = mpsc::channel::<Exchange>(QUEUE_SIZE);
+    let connections = Arc::new(Semaphore::new(MAX_CONNECTIONS));
+    // the accept tasks are aborted when the serving is finished.
+    let mut accepts = JoinSet::new();
+    for (addr, _) in listens.iter() {
+        let listener = TcpListener::bind(addr)
+            .await
+            .with_context(|| format!("failed to listen on {addr}."))?;
+        accepts.spawn(accept(listener, tx.clone(), connections.clone()));
+    }
+    drop(tx);
+    loop {
+        let exchange = match deadline {
+            Some(deadline) => match tokio::time::timeout_at(deadline, rx.recv()).await {
+                Ok(exchange) => exchange,
+                Err(_) => return Err(Trap::Interrupt.into()),
+            },
+            None => rx.recv().await,
+        };
+        let Some((request, reply)) = exchange else {
+            break;
+        };
+        let table = store.data().preview1_ctx.as_ref().unwrap().resource_table();
+        let handle = match http_server_driver::push_request(table, request) {
+            Some(handle) => handle,
+            None => {
+                let _ = reply.send(Response::text(503, http_server_driver::reason(503)));
+                continue;
+            }
+        };
+        let result = func.call_async(&mut *store, handle).await;
+        let table = store.data().preview1_ctx.as_ref().unwrap().resource_table();
+        let response = http_server_driver::take_response(table, handle).await;
+        if let Err(e) = result {
+            let _ = reply.send(Response::text(500, http_server_driver::reason(500)));
+            return Err(e);
+        }
+        let _ = reply.send(response.unwrap_or_default());
+    }
+    Ok(())
+}
+
+async fn accept(listener: TcpListener, tx: mpsc::Sender<Exchange>, connections: Arc<Semaphore>) {
+    loop {
+        // the permit is released when the connection is closed.
+        let Ok(permit) = connections.clone().acquire_owned().await else {
+            return;
+        };
+        match listener.accept().await {
+            Ok((stream, remote)) => {
+                let tx = tx.clone();
+                tokio::spawn(async move {
+                    connection(stream, remote, tx).await;
+                    drop(permit);
+                });
+            }
+            Err(e) => {
+                error!("accept error: {}", e);
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        }
+    }
+}
+
+async fn connection(mut stream: TcpStream, remote: SocketAddr, tx: mpsc::Sender<Exchange>) {
+    let read = tokio::time::timeout(
+        READ_TIMEOUT,
+        http_server_driver::read_request(&mut stream, remote),
+    );
+    let response = match read.await {
+        Ok(Ok(request)) => {
+            let (reply, response) = oneshot::channel();
+            if tx.send((request, reply)).await.is_err() {
+                return;
+            }
+            response
+                .await
+                .unwrap_or_else(|_| Response::text(503, http_server_driver::reason(503)))
+        }
+        Ok(Err(ReadError::Closed)) => return,
+        Ok(Err(ReadError::Status(status))) => {
+            Response::text(status, http_server_driver::reason(status))
+        }
+        Err(_) => Response::text(408, http_server_driver::reason(408)),
+    };
+    if let Err(e) = http_server_driver::write_response(&mut stream, &response).await {
+        debug!("write response to {} error: {}", remote, e);
+    }
+}
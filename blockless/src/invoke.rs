@@ -0,0 +1,108 @@
+use anyhow::{bail, Context};
+use wasmtime::component::{self, wasm_wave};
+use wasmtime::{Instance, Store, Val, ValType};
+
+use crate::context::BlocklessContext;
+use crate::AnyResult;
+
+/// call the exported function of the core module with the arguments, the arguments are
+/// parsed by the types of the params, only the i32, i64, f32 and f64 are supported.
+pub(crate) async fn invoke_core(
+    store: &mut Store<BlocklessContext>,
+    instance: &Instance,
+    name: &str,
+    args: &[String],
+) -> AnyResult<Vec<String>> {
+    let func = instance
+        .get_func(&mut *store, name)
+        .with_context(|| format!("the function `{name}` is not exported."))?;
+    let ty = func.ty(&*store);
+    if ty.params().len() != args.len() {
+        bail!(
+            "the function `{name}` expects {} arguments, but {} are given.",
+            ty.params().len(),
+            args.len()
+        );
+    }
+    let params = ty
+        .params()
+        .zip(args)
+        .map(|(ty, arg)| core_val(&ty, arg))
+        .collect::<AnyResult<Vec<_>>>()?;
+    // the results are overwritten by the call.
+    let mut results = vec![Val::I32(0); ty.results().len()];
+    func.call_async(&mut *store, &params, &mut results).await?;
+    results.iter().map(core_result).collect()
+}
+
+fn core_val(ty: &ValType, arg: &str) -> AnyResult<Val> {
+    let val = match ty {
+        ValType::I32 => Val::I32(arg.parse().with_context(|| format!("invalid i32: {arg}"))?),
+        ValType::I64 => Val::I64(arg.parse().with_context(|| format!("invalid i64: {arg}"))?),
+        ValType::F32 => {
+            let f: f32 = arg.parse().with_context(|| format!("invalid f32: {arg}"))?;
+            Val::F32(f.to_bits())
+        }
+        ValType::F64 => {
+            let f: f64 = arg.parse().with_context(|| format!("invalid f64: {arg}"))?;
+            Val::F64(f.to_bits())
+        }
+        ty => bail!("the param type {:?} is not supported.", ty),
+    };
+    Ok(val)
+}
+
+fn core_result(val: &Val) -> AnyResult<String> {
+    let result = match val {
+        Val::I32(i) => i.to_string(),
+        Val::I64(i) => i.to_string(),
+        Val::F32(f) => f32::from_bits(*f).to_string(),
+        Val::F64(f) => f64::from_bits(*f).to_string(),
+        val => bail!("the result type {:?} is not supported.", val),
+    };
+    Ok(result)
+}
+
+/// call the exported function of the component with the WAVE encoded arguments, e.g.
+/// `"hello"`, `[1, 2]` or `{a: 1}`, and the results are WAVE encoded. The function of the
+/// exported interface is named `<interface>#<function>`, e.g. `my:app/api#add`.
+pub(crate) async fn invoke_component(
+    store: &mut Store<BlocklessContext>,
+    instance: &component::Instance,
+    name: &str,
+    args: &[String],
+) -> AnyResult<Vec<String>> {
+    let export = match name.split_once('#') {
+        Some((interface, func)) => instance
+            .get_export(&mut *store, None, interface)
+            .and_then(|idx| instance.get_export(&mut *store, Some(&idx), func)),
+        None => instance.get_export(&mut *store, None, name),
+    };
+    let func = export
+        .and_then(|idx| instance.get_func(&mut *store, idx))
+        .with_context(|| format!("the function `{name}` is not exported."))?;
+    let param_tys = func.params(&*store);
+    if param_tys.len() != args.len() {
+        bail!(
+            "the function `{name}` expects {} arguments, but {} are given.",
+            param_tys.len(),
+            args.len()
+        );
+    }
+    let params = param_tys
+        .iter()
+        .zip(args)
+        .map(|(ty, arg)| {
+            wasm_wave::from_str::<component::Val>(ty, arg)
+                .with_context(|| format!("invalid argument: {arg}"))
+        })
+        .collect::<AnyResult<Vec<_>>>()?;
+    // the results are overwritten by the call.
+    let mut results = vec![component::Val::Bool(false); func.results(&*store).len()];
+    func.call_async(&mut *store, &params, &mut results).await?;
+    func.post_return_async(&mut *store).await?;
+    results
+        .iter()
+        .map(|val| wasm_wave::to_string(val).context("failed to encode the result"))
+        .collect()
+}
@@ -1,26 +1,42 @@
+mod cache;
 mod context;
 pub mod error;
+mod http_server;
+mod invoke;
 mod modules;
+mod runtime;
+mod stdio;
+mod wasi_http;
 
 pub use anyhow::Result as AnyResult;
 use anyhow::{bail, Context};
 use blockless_drivers::{CdylibDriver, DriverConetxt};
 use blockless_env;
 pub use blockless_multiaddr::MultiAddr;
+pub use cache::ModuleCache;
 use cap_std::ambient_authority;
-use context::BlocklessContext;
+use context::{BlocklessContext, BlocklessLimits};
 pub use error::*;
 use log::{debug, error};
 use modules::ModuleLinker;
+pub use runtime::{BlocklessRuntime, JobHandle};
+use runtime::{EpochTicker, RuntimeShared};
 use std::fs::File;
-use std::sync::Mutex;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, RwLock};
+use std::task::{Context as TaskContext, Poll};
+use std::time::{Duration, Instant};
 use std::{env, path::Path, sync::Arc};
-use wasi_common::sync::WasiCtxBuilder;
+pub use stdio::stdin_stream;
+use stdio::{SharedOutput, StdioCapture};
 use wasi_common::sync::{Dir, TcpListener};
+use wasi_common::tokio::WasiCtxBuilder;
 pub use wasi_common::*;
 use wasmtime::{
     component::Component, Config, Engine, Linker, Module, Precompiled, Store, StoreLimits,
-    StoreLimitsBuilder, Trap,
+    StoreLimitsBuilder, Trap, UpdateDeadline, WasmBacktrace,
 };
 use wasmtime_wasi::WasiView;
 use wasmtime_wasi::{DirPerms, FilePerms};
@@ -30,11 +46,41 @@ use wasmtime_wasi_threads::WasiThreadsCtx;
 // the default wasm entry name.
 const ENTRY: &str = "_start";
 
+// the exit code of the app timeout.
+const APP_TIMEOUT_CODE: i32 = 15;
+
+/// The status of the run, it's also the data of the execution report.
 pub struct ExitStatus {
+    /// the remaining fuel, it's present when the fuel is limited.
     pub fuel: Option<u64>,
     pub code: i32,
+    /// the guest exited with `proc_exit`, the code is the exit code of the guest.
+    pub exited: bool,
+    /// the kind of the trap, e.g. `OutOfFuel`.
+    pub trap: Option<String>,
+    /// the wasm backtrace of the trap.
+    pub backtrace: Option<String>,
+    pub fuel_used: Option<u64>,
+    pub limited_fuel: Option<u64>,
+    /// the peak size of the linear memory in bytes.
+    pub peak_memory: u64,
+    pub wall_time: Duration,
+    /// the cpu time of the job, it's the sum of the thread cpu time of each poll of the
+    /// job, the threads of the `wasi-threads` are not included. It's `None` if the
+    /// platform has no thread cpu clock.
+    pub cpu_time: Option<Duration>,
+    /// the usage of the host extensions.
+    pub extensions: Vec<(Extension, ExtensionUsage)>,
+    /// the captured stdout, it's present when the stdout is `Stdout::Capture`.
+    pub stdout: Option<Vec<u8>>,
+    /// the captured stderr, it's present when the stderr is `Stderr::Capture`.
+    pub stderr: Option<Vec<u8>>,
+    /// the results of the invoked function, the core values are the numbers and the
+    /// component values are WAVE encoded.
+    pub results: Vec<String>,
 }
 
+#[derive(Clone)]
 pub enum BlsRunTarget {
     Module(Module),
     Component(Component),
@@ -54,12 +100,26 @@ impl BlsRunTarget {
             BlsRunTarget::Component(component) => component,
         }
     }
+
+    /// serialize the compiled module, it's loaded by the engine with the same configure.
+    pub fn serialize(&self) -> AnyResult<Vec<u8>> {
+        match self {
+            BlsRunTarget::Module(module) => module.serialize(),
+            BlsRunTarget::Component(component) => component.serialize(),
+        }
+    }
 }
 
 trait BlocklessConfig2Preview1WasiBuilder {
-    fn preview1_builder(&self) -> anyhow::Result<WasiCtxBuilder>;
+    fn preview1_builder(&self, capture: &StdioCapture) -> anyhow::Result<WasiCtxBuilder>;
     fn preview2_builder(&self) -> anyhow::Result<wasmtime_wasi::WasiCtxBuilder>;
-    fn preview1_set_stdio(&self, builder: &mut WasiCtxBuilder);
+    fn preopen_tcp_listens(&self, builder: &mut WasiCtxBuilder, max_fd: u32) -> anyhow::Result<()>;
+    fn preview1_set_stdio(&self, builder: &mut WasiCtxBuilder, capture: &StdioCapture);
+    fn preview2_set_stdio(
+        &self,
+        builder: &mut wasmtime_wasi::WasiCtxBuilder,
+        capture: &StdioCapture,
+    );
     fn preview1_engine_config(&self) -> Config;
     fn store_limits(&self) -> StoreLimits;
 }
@@ -92,11 +152,11 @@ impl BlocklessConfig2Preview1WasiBuilder for BlocklessConfig {
         builder.build()
     }
     /// set the stdout and stderr for the wasm.
-    /// the stdout adn stderr can be setting to file or inherit the stdout and stderr.
-    fn preview1_set_stdio(&self, builder: &mut WasiCtxBuilder) {
+    /// the stdout adn stderr can be setting to file, captured in memory or inherit the stdout and stderr.
+    fn preview1_set_stdio(&self, builder: &mut WasiCtxBuilder, capture: &StdioCapture) {
         let b_conf = self;
         macro_rules! process_output {
-            ($out_ref: expr, $out_expr: ident, $stdout: ident, $inherit_stdout: ident) => {
+            ($out_ref: expr, $out_expr: ident, $stdout: ident, $inherit_stdout: ident, $capture: expr) => {
                 //$out_ref is b_conf.stdout_ref() or b_conf.stderr_ref()
                 match $out_ref {
                     &$out_expr::FileName(ref file_name) => {
@@ -111,7 +171,7 @@ impl BlocklessConfig2Preview1WasiBuilder for BlocklessConfig {
                                 file_opts.write(true);
                                 file_opts.open(file_name).ok().map(|file| {
                                     let file = cap_std::fs::File::from_std(file);
-                                    let f = wasi_common::sync::file::File::from_cap_std(file);
+                                    let f = wasi_common::tokio::File::from_cap_std(file);
                                     Box::new(f)
                                 })
                             } {
@@ -128,27 +188,113 @@ impl BlocklessConfig2Preview1WasiBuilder for BlocklessConfig {
                     &$out_expr::Inherit => {
                         builder.$inherit_stdout();
                     }
+                    &$out_expr::Capture(_) => {
+                        if let Some(buf) = $capture {
+                            builder.$stdout(Box::new(wasi_common::pipe::WritePipe::from_shared(
+                                buf.clone(),
+                            )));
+                        }
+                    }
                     &$out_expr::Null => {}
                 }
             };
         }
-        process_output!(b_conf.stdout_ref(), Stdout, stdout, inherit_stdout);
-        process_output!(b_conf.stderr_ref(), Stderr, stderr, inherit_stderr);
+        process_output!(
+            b_conf.stdout_ref(),
+            Stdout,
+            stdout,
+            inherit_stdout,
+            &capture.stdout
+        );
+        process_output!(
+            b_conf.stderr_ref(),
+            Stderr,
+            stderr,
+            inherit_stderr,
+            &capture.stderr
+        );
 
         if let Stdin::Inherit = b_conf.stdio.stdin {
             builder.inherit_stdin();
         }
     }
 
+    /// set the stdout and stderr for the component, it's same as the preview1.
+    fn preview2_set_stdio(
+        &self,
+        builder: &mut wasmtime_wasi::WasiCtxBuilder,
+        capture: &StdioCapture,
+    ) {
+        let b_conf = self;
+        macro_rules! process_output {
+            ($out_ref: expr, $out_expr: ident, $stdout: ident, $inherit_stdout: ident, $capture: expr) => {
+                match $out_ref {
+                    &$out_expr::FileName(ref file_name) => {
+                        let file = b_conf.fs_root_path_ref().and_then(|r| {
+                            std::fs::File::options()
+                                .create(true)
+                                .append(true)
+                                .open(Path::new(r).join(file_name))
+                                .ok()
+                        });
+                        match file {
+                            Some(f) => {
+                                builder.$stdout(SharedOutput(Arc::new(RwLock::new(f))));
+                            }
+                            None => {
+                                builder.$inherit_stdout();
+                            }
+                        }
+                    }
+                    &$out_expr::Inherit => {
+                        builder.$inherit_stdout();
+                    }
+                    &$out_expr::Capture(_) => {
+                        if let Some(buf) = $capture {
+                            builder.$stdout(SharedOutput(buf.clone()));
+                        }
+                    }
+                    &$out_expr::Null => {}
+                }
+            };
+        }
+        process_output!(
+            b_conf.stdout_ref(),
+            Stdout,
+            stdout,
+            inherit_stdout,
+            &capture.stdout
+        );
+        process_output!(
+            b_conf.stderr_ref(),
+            Stderr,
+            stderr,
+            inherit_stderr,
+            &capture.stderr
+        );
+
+        match b_conf.stdio.stdin {
+            Stdin::Inherit => {
+                builder.inherit_stdin();
+            }
+            Stdin::Fixed(ref s) => {
+                let input = s.clone().into_bytes();
+                builder.stdin(wasmtime_wasi::pipe::MemoryInputPipe::new(input));
+            }
+            // the streaming stdin is read by `memory-read` of the extensions.
+            Stdin::Stream(_) => {}
+        }
+    }
+
     /// create the preview1_builder by the configure.
-    fn preview1_builder(&self) -> anyhow::Result<WasiCtxBuilder> {
+    fn preview1_builder(&self, capture: &StdioCapture) -> anyhow::Result<WasiCtxBuilder> {
         let b_conf = self;
         let root_dir = b_conf.fs_root_path_ref().and_then(|path| {
             wasi_common::sync::Dir::open_ambient_dir(path, ambient_authority()).ok()
         });
         let mut builder = WasiCtxBuilder::new();
         //stdout file process for setting.
-        b_conf.preview1_set_stdio(&mut builder);
+        b_conf.preview1_set_stdio(&mut builder, capture);
         // configure to storeLimit
         let entry_module = b_conf
             .entry_module()
@@ -164,13 +310,33 @@ impl BlocklessConfig2Preview1WasiBuilder for BlocklessConfig {
             builder.preopened_dir(host, guest)?;
             max_fd += 1;
         }
+        for (host, guest) in b_conf.readonly_dirs.iter() {
+            let host = Dir::open_ambient_dir(host, ambient_authority())?;
+            builder.preopened_readonly_dir(host, guest)?;
+            max_fd += 1;
+        }
         // map root fs
         if let Some(d) = root_dir {
             builder.preopened_dir(d, "/")?;
             max_fd += 1;
         }
-        //set the tcp listener.
-        for (l, fd) in b_conf.tcp_listens.iter() {
+        b_conf.preopen_tcp_listens(&mut builder, max_fd)?;
+        anyhow::Ok(builder)
+    }
+
+    /// set the tcp listener from the `max_fd`, the listeners are served by the runtime
+    /// if the http handler is set.
+    fn preopen_tcp_listens(
+        &self,
+        builder: &mut WasiCtxBuilder,
+        mut max_fd: u32,
+    ) -> anyhow::Result<()> {
+        let b_conf = self;
+        let tcp_listens = match b_conf.http_handler_ref() {
+            Some(_) => &[][..],
+            None => &b_conf.tcp_listens[..],
+        };
+        for (l, fd) in tcp_listens.iter() {
             let fd = if let Some(fd) = fd {
                 if *fd < max_fd {
                     bail!("the invalid fd{fd} for listenfd.");
@@ -185,7 +351,7 @@ impl BlocklessConfig2Preview1WasiBuilder for BlocklessConfig {
             let l = TcpListener::from_std(l);
             builder.preopened_socket(fd, l)?;
         }
-        anyhow::Ok(builder)
+        Ok(())
     }
 
     /// convert the blockless configure  to wasmtime configure.
@@ -281,6 +447,12 @@ impl BlocklessConfig2Preview1WasiBuilder for BlocklessConfig {
             conf.allocation_strategy(wasmtime::InstanceAllocationStrategy::Pooling(cfg));
         }
         conf.debug_info(self.get_debug_info());
+        if self.coredump.is_some() {
+            conf.coredump_on_trap(true);
+        }
+        if self.time_limit().is_some() && !self.feature_thread() {
+            conf.epoch_interruption(true);
+        }
 
         if let Some(_) = self.get_limited_fuel() {
             //fuel is enable.
@@ -299,13 +471,53 @@ impl BlocklessConfig2Preview1WasiBuilder for BlocklessConfig {
         for (host_path, guest_path) in self.dirs.iter() {
             builder.preopened_dir(host_path, guest_path, DirPerms::all(), FilePerms::all())?;
         }
+        for (host_path, guest_path) in self.readonly_dirs.iter() {
+            builder.preopened_dir(host_path, guest_path, DirPerms::READ, FilePerms::READ)?;
+        }
         if let Some(root_dir) = self.fs_root_path_ref() {
             builder.preopened_dir(root_dir, "/", DirPerms::all(), FilePerms::all())?;
         }
+        // the sockets of `wasi:sockets` are checked with the tcp and udp permissions.
+        // the name lookup of wasmtime-wasi can't be checked for each host like the `resolve`
+        // of preview1, it's allowed when a tcp or udp permission has the host name, and the
+        // resolved addresses are still checked by the permissions when they're used.
+        let b_conf = Arc::new(self.clone());
+        let resolve = self.permisions_ref().iter().any(|p| {
+            !p.deny
+                && matches!(p.schema.to_ascii_lowercase().as_str(), "tcp" | "udp")
+                && p.pattern().host.is_name()
+        });
+        builder.allow_ip_name_lookup(resolve);
+        builder.socket_addr_check(move |addr, addr_use| {
+            let allowed = socket_addr_permission(&b_conf, addr, addr_use);
+            Box::pin(async move { allowed })
+        });
         Ok(builder)
     }
 }
 
+/// check the socket address of the component with the schema permissions,
+/// e.g. `tcp://127.0.0.1:8080` for connecting or binding the tcp socket.
+fn socket_addr_permission(
+    b_conf: &BlocklessConfig,
+    addr: std::net::SocketAddr,
+    addr_use: wasmtime_wasi::SocketAddrUse,
+) -> bool {
+    use wasmtime_wasi::SocketAddrUse;
+    let allowed = match addr_use {
+        SocketAddrUse::TcpBind | SocketAddrUse::TcpConnect => {
+            b_conf.schema_permission("tcp", &format!("tcp://{addr}"))
+        }
+        SocketAddrUse::UdpBind | SocketAddrUse::UdpConnect | SocketAddrUse::UdpOutgoingDatagram => {
+            b_conf.schema_permission("udp", &format!("udp://{addr}"))
+        }
+    };
+    if !allowed {
+        error!("Permission Deny: {addr}");
+    }
+    allowed
+}
+
 enum BlsLinker {
     Core(wasmtime::Linker<BlocklessContext>),
     Component(wasmtime::component::Linker<BlocklessContext>),
@@ -330,33 +542,64 @@ impl BlsLinker {
 
 struct BlocklessRunner(BlocklessConfig);
 
+/// get the drivers root path of the configure, if not setting use exe file path.
+fn drivers_root_path(b_conf: &BlocklessConfig) -> String {
+    b_conf
+        .drivers_root_path_ref()
+        .map(|p| p.into())
+        .unwrap_or_else(|| {
+            let mut current_exe_path = env::current_exe().unwrap();
+            current_exe_path.pop();
+            String::from(current_exe_path.to_str().unwrap())
+        })
+}
+
 impl BlocklessRunner {
     /// blockless run method, it execute the wasm program with configure file.
     async fn run(self) -> AnyResult<ExitStatus> {
+        self.run_in(None).await
+    }
+
+    /// execute the wasm program, if the runtime is present, the engine, the compiled
+    /// modules and the drivers are shared with the other jobs of the runtime.
+    async fn run_in(self, runtime: Option<&RuntimeShared>) -> AnyResult<ExitStatus> {
         let b_conf = &self.0;
+        let started = Instant::now();
         let max_fuel = b_conf.get_limited_fuel();
-        // set the drivers root path, if not setting use exe file path.
-        let drivers_root_path = b_conf
-            .drivers_root_path_ref()
-            .map(|p| p.into())
-            .unwrap_or_else(|| {
-                let mut current_exe_path = env::current_exe().unwrap();
-                current_exe_path.pop();
-                String::from(current_exe_path.to_str().unwrap())
-            });
-        DriverConetxt::init_built_in_drivers(drivers_root_path);
-        let conf = b_conf.preview1_engine_config();
-        let engine = Engine::new(&conf)?;
         let support_thread = b_conf.feature_thread();
+        let (engine, drivers) = match runtime {
+            Some(runtime) => {
+                // the thread stores are created by wasi-threads without the fuel and deadline.
+                if support_thread {
+                    bail!("the thread feature is not supported by the runtime.");
+                }
+                runtime.load_drivers(b_conf)?;
+                (runtime.engine.clone(), runtime.drivers.clone())
+            }
+            None => {
+                let mut drivers = DriverConetxt::new();
+                drivers.init_built_in_drivers(drivers_root_path(b_conf));
+                Self::load_driver(&mut drivers, b_conf.drivers_ref());
+                let conf = b_conf.preview1_engine_config();
+                (Engine::new(&conf)?, Arc::new(RwLock::new(drivers)))
+            }
+        };
+        // the thread stores are created without the deadline, so the time limit
+        // is not enforced by the epoch when the thread feature is enabled.
+        let time_limit = b_conf.time_limit().filter(|_| !support_thread);
+        let _ticker = match (runtime, time_limit) {
+            (None, Some(_)) => Some(EpochTicker::start(&engine)?),
+            _ => None,
+        };
 
-        let drivers = b_conf.drivers_ref();
-        Self::load_driver(drivers);
         let entry: String = b_conf.entry_ref().into();
         let store_limits = b_conf.store_limits();
         let fule = b_conf.get_limited_fuel();
 
         let mut ctx = BlocklessContext::default();
-        ctx.store_limits = store_limits;
+        ctx.store_limits = BlocklessLimits::new(store_limits);
+        ctx.stdio_capture = StdioCapture::new(&b_conf.stdio);
+        ctx.drivers = drivers;
 
         let mut store: Store<BlocklessContext> = Store::new(&engine, ctx);
         store.limiter(|ctx| &mut ctx.store_limits);
@@ -364,8 +607,27 @@ impl BlocklessRunner {
         if let Some(f) = fule {
             store.set_fuel(f).unwrap();
         }
-        let (mut linker, mut run_target, entry) =
-            self.module_linker(entry, &engine, &mut store).await?;
+        // the engine of runtime always consumes fuel, the job without limited runs unlimited.
+        if runtime.is_some() && fule.is_none() {
+            store.set_fuel(u64::MAX)?;
+        }
+        let timeout = Arc::new(AtomicBool::new(false));
+        let deadline = time_limit.map(|t| Instant::now() + Duration::from_millis(t));
+        if runtime.is_some() || time_limit.is_some() {
+            let timeout = timeout.clone();
+            // yield to the executor at every epoch tick, so the job can be cancelled,
+            // and trap the guest when the time limit is reached.
+            store.epoch_deadline_callback(move |_| {
+                if deadline.map_or(false, |d| Instant::now() >= d) {
+                    timeout.store(true, Ordering::Relaxed);
+                    return Err(Trap::Interrupt.into());
+                }
+                Ok(UpdateDeadline::Yield(1))
+            });
+        }
+        let (mut linker, mut run_target, entry) = self
+            .module_linker(entry, &engine, &mut store, runtime)
+            .await?;
         let mut is_component = false;
         if b_conf.nn {
             self.nn_setup(&mut linker, &mut store)?;
@@ -377,8 +639,16 @@ impl BlocklessRunner {
             }
             BlsLinker::Component(ref mut linker) => {
                 is_component = true;
-                wasmtime_wasi::add_to_linker_async(linker)?;
+                let mut opts = wasmtime_wasi::bindings::LinkOptions::default();
+                opts.cli_exit_with_code(b_conf.cli_exit_with_code);
+                opts.network_error_code(b_conf.network_error_code);
+                wasmtime_wasi::add_to_linker_with_options_async(linker, &opts)?;
+                blockless_env::add_extensions_to_component_linker(linker, |s| {
+                    s.preview1_ctx.as_mut().unwrap()
+                })?;
+                wasmtime_wasi_http::add_only_http_to_linker_async(linker)?;
                 self.preview2_setup(store.data_mut())?;
+                self.extensions_setup(store.data_mut())?;
             }
         }
         // support thread.
@@ -390,34 +660,132 @@ impl BlocklessRunner {
             );
         }
 
-        let result =
-            Self::load_main_module(&b_conf, &mut store, &mut linker, &mut run_target, &entry).await;
+        let mut cpu_time = thread_cpu_time().map(|_| Duration::ZERO);
+        let main = CpuTimed {
+            inner: Box::pin(Self::load_main_module(
+                &b_conf,
+                &mut store,
+                &mut linker,
+                &mut run_target,
+                &entry,
+            )),
+            cpu_time: &mut cpu_time,
+        };
+        // the epoch only interrupts the running wasm, the guest blocked in the host call,
+        // e.g. sleeping in `poll_oneoff`, is interrupted by the timer of the deadline.
+        let result = match deadline {
+            Some(deadline) => match tokio::time::timeout_at(deadline.into(), main).await {
+                Ok(result) => result,
+                Err(_) => {
+                    timeout.store(true, Ordering::Relaxed);
+                    Err(Trap::Interrupt.into())
+                }
+            },
+            None => main.await,
+        };
+        let exited = match result {
+            Err(ref t) => Self::exit_code(is_component, t),
+            Ok(_) => None,
+        };
+        // the serving of the http handler is interrupted by the time limit without the epoch.
+        let timed_out = timeout.load(Ordering::Relaxed)
+            || (b_conf.http_handler_ref().is_some()
+                && time_limit.map_or(false, |t| started.elapsed() >= Duration::from_millis(t)));
         let exit_code = match result {
-            Err(ref t) => {
-                Self::error_process(is_component, t, || store.get_fuel().unwrap(), max_fuel)
+            Err(ref t) if timed_out => {
+                error!(
+                    "The app timeout, the time limit is {}ms: {}",
+                    time_limit.unwrap(),
+                    t
+                );
+                APP_TIMEOUT_CODE
             }
+            Err(ref t) => match exited {
+                // the guest exit doesn't exit the process, so the report can be emitted
+                // and the jobs sharing the runtime keep running.
+                Some(code) => code,
+                None => {
+                    Self::error_process(is_component, t, || store.get_fuel().unwrap(), max_fuel)
+                }
+            },
             Ok(_) => {
                 debug!("program exit normal.");
                 0
             }
         };
+        let fuel = max_fuel.and(store.get_fuel().ok());
+        let (trap, backtrace) = match result {
+            Err(ref e) => (
+                e.downcast_ref::<Trap>().map(|t| format!("{t:?}")),
+                e.downcast_ref::<WasmBacktrace>().map(|b| b.to_string()),
+            ),
+            Ok(_) => (None, None),
+        };
+        let extensions = Extension::ALL
+            .iter()
+            .map(|&ext| {
+                let usage = store
+                    .data()
+                    .preview1_ctx
+                    .as_ref()
+                    .map(|ctx| ctx.usage.usage(ext))
+                    .unwrap_or_default();
+                (ext, usage)
+            })
+            .collect();
+        let (stdout, stderr) = store.data().stdio_capture.take();
+        let results = result.unwrap_or_default();
         Ok(ExitStatus {
-            fuel: store.get_fuel().ok(),
+            fuel,
             code: exit_code,
+            exited: exited.is_some(),
+            trap,
+            backtrace,
+            fuel_used: max_fuel.zip(fuel).map(|(m, f)| m.saturating_sub(f)),
+            limited_fuel: max_fuel,
+            peak_memory: store.data().store_limits.peak_memory as u64,
+            wall_time: started.elapsed(),
+            cpu_time,
+            extensions,
+            stdout,
+            stderr,
+            results,
         })
     }
 
     fn preview1_setup(&self, ctx: &mut BlocklessContext) -> AnyResult<()> {
-        let mut builder = self.0.preview1_builder()?;
+        let mut builder = self.0.preview1_builder(&ctx.stdio_capture)?;
         let mut preview1_ctx = builder.build();
         preview1_ctx.set_blockless_config(Some(self.0.clone()));
+        preview1_ctx.set_drivers(ctx.drivers.clone());
         ctx.preview1_ctx = Some(preview1_ctx);
         Ok(())
     }
 
+    /// the extensions and the outgoing `wasi:http` of the component hold the handles,
+    /// the permissions and the usage in the preview1 context, it has no preopened directories.
+    /// the tcp listeners are preopened from the fd 3 and accepted by `socket-accept`.
+    fn extensions_setup(&self, ctx: &mut BlocklessContext) -> AnyResult<()> {
+        let mut builder = WasiCtxBuilder::new();
+        self.0.preopen_tcp_listens(&mut builder, 3)?;
+        let mut extensions_ctx = builder.build();
+        extensions_ctx.set_blockless_config(Some(self.0.clone()));
+        extensions_ctx.set_drivers(ctx.drivers.clone());
+        ctx.preview1_ctx = Some(extensions_ctx);
+        ctx.wasi_http = Some(Arc::new(wasmtime_wasi_http::WasiHttpCtx::new()));
+        Ok(())
+    }
+
     fn preview2_setup(&self, ctx: &mut BlocklessContext) -> AnyResult<()> {
         let mut builder = self.0.preview2_builder()?;
-        builder.inherit_stdio().args(&self.0.stdin_args);
+        self.0.preview2_set_stdio(&mut builder, &ctx.stdio_capture);
+        let entry_module = self
+            .0
+            .entry_module()
+            .context("not found the entry module.")?;
+        let mut args = vec![entry_module];
+        args.extend_from_slice(&self.0.stdin_args_ref()[..]);
+        builder.args(&args);
         builder.envs(&self.0.envs);
         let preview2_ctx = builder.build_p1();
         ctx.preview2_ctx = Some(Arc::new(Mutex::new(preview2_ctx)));
@@ -445,9 +813,9 @@ impl BlocklessRunner {
         use std::fs::File;
         use std::io::Write;
 
-        let core_dump = err
-            .downcast_ref::<wasmtime::WasmCoreDump>()
-            .expect("should have been configured to capture core dumps");
+        let core_dump = err.downcast_ref::<wasmtime::WasmCoreDump>().context(
+            "the core dump is not captured, the engine should enable `coredump_on_trap`",
+        )?;
 
         let core_dump = core_dump.serialize(store, name);
 
@@ -465,7 +833,10 @@ impl BlocklessRunner {
         linker: &mut BlsLinker,
         module: &BlsRunTarget,
         entry: &str,
-    ) -> AnyResult<()> {
+    ) -> AnyResult<Vec<String>> {
+        if cfg.invoke_ref().is_some() && cfg.http_handler_ref().is_some() {
+            bail!("the invoke and the http handler can't be used together.");
+        }
         // The main module might be allowed to have unknown imports, which
         // should be defined as traps:
         if cfg.unknown_imports_trap == true {
@@ -492,6 +863,25 @@ impl BlocklessRunner {
                     let init = func.typed::<(), ()>(&store)?;
                     init.call_async(&mut *store, ()).await?;
                 }
+                // the http handler is called for each request instead of the entry.
+                if let Some(handler) = cfg.http_handler_ref() {
+                    return http_server::serve(
+                        &mut *store,
+                        &instance,
+                        handler,
+                        &cfg.tcp_listens,
+                        cfg.time_limit(),
+                    )
+                    .await
+                    .map(|_| Vec::new())
+                    .map_err(|e| Self::handle_core_dump(cfg, &mut *store, e));
+                }
+                // the invoked function is called with the app arguments instead of the entry.
+                if let Some(name) = cfg.invoke_ref() {
+                    return invoke::invoke_core(&mut *store, &instance, name, &cfg.stdin_args)
+                        .await
+                        .map_err(|e| Self::handle_core_dump(cfg, &mut *store, e));
+                }
                 // Look for the specific function provided or otherwise look for
                 // "" or "_start" exports to run as a "main" function.
                 let func = match cfg.version {
@@ -505,10 +895,22 @@ impl BlocklessRunner {
                 };
                 // if thread multi thread use sync model.
                 // The multi-thread model is used for the cpu intensive program.
-                func.call_async(&mut *store, ()).await
+                func.call_async(&mut *store, ())
+                    .await
+                    .map(|_| Vec::new())
+                    .map_err(|e| Self::handle_core_dump(cfg, &mut *store, e))
             }
             BlsLinker::Component(linker) => {
+                if cfg.http_handler_ref().is_some() {
+                    bail!("the http handler is not supported by the component.");
+                }
                 let component = module.unwrap_component();
+                if let Some(name) = cfg.invoke_ref() {
+                    let instance = linker.instantiate_async(&mut *store, component).await?;
+                    return invoke::invoke_component(&mut *store, &instance, name, &cfg.stdin_args)
+                        .await
+                        .map_err(|e| Self::handle_core_dump(cfg, &mut *store, e));
+                }
                 let command = wasmtime_wasi::bindings::Command::instantiate_async(
                     &mut *store,
                     component,
@@ -524,7 +926,7 @@ impl BlocklessRunner {
                 // Translate the `Result<(),()>` produced by wasm into a feigned
                 // explicit exit here with status 1 if `Err(())` is returned.
                 result.and_then(|wasm_result| match wasm_result {
-                    Ok(()) => Ok(()),
+                    Ok(()) => Ok(Vec::new()),
                     Err(()) => Err(wasmtime_wasi::I32Exit(1).into()),
                 })
             }
@@ -579,7 +981,7 @@ impl BlocklessRunner {
         if !err.is::<wasmtime::Trap>() {
             return err;
         }
-        let source_name = cfg.modules[0].file.as_str();
+        let source_name = cfg.entry_module().unwrap_or_default();
 
         if let Err(coredump_err) = Self::write_core_dump(store, &err, &source_name, coredump_path) {
             eprintln!("warning: coredump failed to generate: {coredump_err}");
@@ -657,7 +1059,10 @@ impl BlocklessRunner {
         add_to_linker!(blockless_env::add_memory_to_linker);
         add_to_linker!(blockless_env::add_cgi_to_linker);
         add_to_linker!(blockless_env::add_socket_to_linker);
-        wasi_common::sync::add_to_linker(linker, |host| host.preview1_ctx.as_mut().unwrap())
+        add_to_linker!(blockless_env::add_car_to_linker);
+        add_to_linker!(blockless_env::add_kv_to_linker);
+        add_to_linker!(blockless_env::add_websocket_to_linker);
+        wasi_common::tokio::add_to_linker(linker, |host| host.preview1_ctx.as_mut().unwrap())
             .unwrap();
     }
 
@@ -681,12 +1086,16 @@ impl BlocklessRunner {
         mut entry: String,
         engine: &Engine,
         store: &'a mut Store<BlocklessContext>,
+        runtime: Option<&RuntimeShared>,
     ) -> anyhow::Result<(BlsLinker, BlsRunTarget, String)> {
         let version = self.0.version();
         match version {
             // this is older configure for bls-runtime, this only run single wasm.
             BlocklessConfigVersion::Version0 => {
-                let module = Self::load_module(engine, &entry)?;
+                let module = match runtime {
+                    Some(runtime) => runtime.load_module(&entry, self.0.module_cache_ref())?,
+                    None => cache::load_module(engine, &entry, self.0.module_cache_ref())?,
+                };
                 let linker = match module {
                     BlsRunTarget::Module(_) => {
                         self.preview1_setup(store.data_mut())?;
@@ -712,13 +1121,23 @@ impl BlocklessRunner {
         }
     }
 
-    fn load_driver(cfs: &[DriverConfig]) {
+    fn load_driver(drivers: &mut DriverConetxt, cfs: &[DriverConfig]) {
         cfs.iter().for_each(|cfg| {
             let drv = CdylibDriver::load(cfg.path(), cfg.schema()).unwrap();
-            DriverConetxt::insert_driver(drv);
+            drivers.insert_driver(drv);
         });
     }
 
+    /// the exit code of the guest, if the guest is exited by `proc_exit`.
+    fn exit_code(is_component: bool, e: &anyhow::Error) -> Option<i32> {
+        if is_component {
+            e.downcast_ref::<wasmtime_wasi::I32Exit>()
+                .map(|exit| exit.0)
+        } else {
+            e.downcast_ref::<wasi_common::I32Exit>().map(|exit| exit.0)
+        }
+    }
+
     /// the error code process.
     fn error_process<F>(
         is_component: bool,
@@ -729,14 +1148,8 @@ impl BlocklessRunner {
     where
         F: FnOnce() -> u64,
     {
-        if is_component {
-            if let Some(exit) = e.downcast_ref::<wasmtime_wasi::I32Exit>() {
-                std::process::exit(exit.0);
-            }
-        } else {
-            if let Some(exit) = e.downcast_ref::<wasi_common::I32Exit>() {
-                std::process::exit(exit.0);
-            }
+        if let Some(code) = Self::exit_code(is_component, e) {
+            return code;
         }
         let trap_code_2_exit_code = |trap_code: &Trap| -> Option<i32> {
             match *trap_code {
@@ -776,10 +1189,57 @@ impl BlocklessRunner {
     }
 }
 
+/// the future sums the thread cpu time of its polls, the jobs sharing the runtime
+/// are polled on the same threads, so the process cpu time can't be used.
+struct CpuTimed<'a, F> {
+    inner: Pin<Box<F>>,
+    cpu_time: &'a mut Option<Duration>,
+}
+
+impl<F: Future> Future for CpuTimed<'_, F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Self::Output> {
+        let start = thread_cpu_time();
+        let rs = self.inner.as_mut().poll(cx);
+        if let (Some(start), Some(end)) = (start, thread_cpu_time()) {
+            if let Some(cpu_time) = self.cpu_time.as_mut() {
+                *cpu_time += end.saturating_sub(start);
+            }
+        }
+        rs
+    }
+}
+
+/// the cpu time consumed by the current thread.
+#[cfg(unix)]
+fn thread_cpu_time() -> Option<Duration> {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    let rs = unsafe { libc::clock_gettime(libc::CLOCK_THREAD_CPUTIME_ID, &mut ts) };
+    (rs == 0).then(|| Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32))
+}
+
+#[cfg(not(unix))]
+fn thread_cpu_time() -> Option<Duration> {
+    None
+}
+
+/// run the guest with the configure, it must run in the multi-thread tokio runtime.
 pub async fn blockless_run(b_conf: BlocklessConfig) -> anyhow::Result<ExitStatus> {
     BlocklessRunner(b_conf).run().await
 }
 
+/// compile the module ahead of time with the engine of the configure, the compiled module
+/// is loaded by `blockless_run` with the same configure without compiling. The compiled
+/// module is also stored in the module cache if the cache is configured.
+pub fn blockless_compile(b_conf: &BlocklessConfig, file: &str) -> anyhow::Result<Vec<u8>> {
+    let engine = Engine::new(&b_conf.preview1_engine_config())?;
+    cache::load_module(&engine, file, b_conf.module_cache_ref())?.serialize()
+}
+
 #[cfg(test)]
 mod test {
     #[allow(unused_imports)]
@@ -792,4 +1252,33 @@ mod test {
         let rs = BlocklessRunner::error_process(false, &err, || 20u64, Some(30));
         assert_eq!(rs, 1);
     }
+
+    #[test]
+    fn test_socket_addr_permission() {
+        use wasmtime_wasi::SocketAddrUse;
+        let mut b_conf = BlocklessConfig::new("test");
+        b_conf.set_permisions(vec![Permission::parse("tcp://127.0.0.1:8080").unwrap()]);
+        let addr = "127.0.0.1:8080".parse().unwrap();
+        assert!(socket_addr_permission(
+            &b_conf,
+            addr,
+            SocketAddrUse::TcpBind
+        ));
+        assert!(socket_addr_permission(
+            &b_conf,
+            addr,
+            SocketAddrUse::TcpConnect
+        ));
+        assert!(!socket_addr_permission(
+            &b_conf,
+            addr,
+            SocketAddrUse::UdpBind
+        ));
+        let addr = "127.0.0.1:8081".parse().unwrap();
+        assert!(!socket_addr_permission(
+            &b_conf,
+            addr,
+            SocketAddrUse::TcpConnect
+        ));
+    }
 }
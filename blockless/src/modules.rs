@@ -1,6 +1,5 @@
 use anyhow::{anyhow, Context};
 use json::JsonValue;
-use lazy_static::lazy_static;
 use std::future::Future;
 use std::sync::Arc;
 use std::{cmp::min, collections::HashMap};
@@ -13,12 +12,11 @@ use wasmtime::{
 
 use crate::context::BlocklessContext as BSContext;
 use crate::error::McallError;
+use crate::{cache, BlsRunTarget};
 
-lazy_static! {
-    static ref INS_CTX: Mutex<InstanceCtx> = Mutex::new(InstanceCtx::new());
-}
-
-struct InstanceCtx {
+/// The registered modules of the guest, it's stored in the store context,
+/// so the modules of the different guests are isolated.
+pub(crate) struct InstanceCtx {
     //key is mem, value is the register module name,
     modules: HashMap<usize, String>,
     //key is module::method.
@@ -28,7 +26,7 @@ struct InstanceCtx {
 }
 
 impl InstanceCtx {
-    fn new() -> Self {
+    pub(crate) fn new() -> Self {
         Self {
             modules: HashMap::new(),
             module_caller: HashMap::new(),
@@ -308,7 +306,8 @@ impl<'a> ModuleLinker<'a> {
                         responseError!(&emsg);
                     }
                 };
-                let ctx = INS_CTX.lock().await;
+                let ins_ctx = caller.data().instance_ctx.clone();
+                let ctx = ins_ctx.lock().await;
                 let mcaller = ctx.module_caller.get(&mcall_name);
                 let mcaller = if mcaller.is_none() {
                     responseError!("no mcall register.");
@@ -353,7 +352,8 @@ impl<'a> ModuleLinker<'a> {
                     };
                 }
                 let mem_ptr = mem_slice.as_ptr() as usize;
-                let module = INS_CTX.lock().await.modules.get(&mem_ptr).map(String::from);
+                let ins_ctx = caller.data().instance_ctx.clone();
+                let module = ins_ctx.lock().await.modules.get(&mem_ptr).map(String::from);
                 let module = match module {
                     Some(m) => m,
                     None => return McallError::MCallMemoryNotFound.into(),
@@ -364,7 +364,7 @@ impl<'a> ModuleLinker<'a> {
                         responseError!("error parse json");
                     }
                 };
-                let mut ctx = INS_CTX.lock().await;
+                let mut ctx = ins_ctx.lock().await;
                 for method in req.methods.iter() {
                     let module = ctx.instance_infos.get_mut(&req.module);
                     if module.is_none() {
@@ -393,7 +393,7 @@ impl<'a> ModuleLinker<'a> {
     /// export the ```blockless.mcall``` and ```blockless.register``` in the runtime.
     /// The modules can be use the register to register the moudle's function for mcall.
     pub(crate) async fn link_modules(&mut self) -> anyhow::Result<Module> {
-        let mut modules: Vec<BlocklessModule> = {
+        let (mut modules, cache_dir): (Vec<BlocklessModule>, Option<String>) = {
             let preview1 = self
                 .store
                 .data()
@@ -402,7 +402,8 @@ impl<'a> ModuleLinker<'a> {
                 .ok_or(anyhow!("get preview1_ctx fail"))?;
             let lock = preview1.blockless_config.lock().unwrap();
             let cfg = lock.as_ref().ok_or(anyhow!("get the lock fail"))?;
-            cfg.modules_ref().iter().map(|m| (*m).clone()).collect()
+            let modules = cfg.modules_ref().iter().map(|m| (*m).clone()).collect();
+            (modules, cfg.module_cache_ref().map(String::from))
         };
         modules.sort_by(|a, b| a.module_type.partial_cmp(&b.module_type).unwrap());
         let mut entry = None;
@@ -427,7 +428,13 @@ impl<'a> ModuleLinker<'a> {
                 ModuleType::Module => (m.name.as_str(), false),
                 ModuleType::Entry => ("", true),
             };
-            let module = Module::from_file(self.store.engine(), &m.file)?;
+            let module =
+                match cache::load_module(self.store.engine(), &m.file, cache_dir.as_deref())? {
+                    BlsRunTarget::Module(module) => module,
+                    BlsRunTarget::Component(_) => {
+                        return Err(anyhow!("the module {} is a component.", m.file))
+                    }
+                };
             if is_entry {
                 entry = Some(module);
             } else {
@@ -475,7 +482,9 @@ impl<'a> ModuleLinker<'a> {
 
         let mem_ptr = mem.map(|m| m.data_ptr(self.store.as_context_mut()) as usize);
         if let Some(mem_ptr) = mem_ptr {
-            INS_CTX
+            self.store
+                .data()
+                .instance_ctx
                 .lock()
                 .await
                 .modules
@@ -510,7 +519,9 @@ impl<'a> ModuleLinker<'a> {
             mem: mem,
         };
         //must release the lock, the initial method will access the modules.
-        INS_CTX
+        self.store
+            .data()
+            .instance_ctx
             .lock()
             .await
             .instance_infos
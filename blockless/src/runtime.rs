@@ -0,0 +1,290 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::Context;
+use blockless_drivers::{CdylibDriver, DriverConetxt};
+use tokio::task::JoinHandle;
+use wasi_common::BlocklessConfig;
+use wasmtime::{Config, Engine};
+
+use crate::{
+    cache, drivers_root_path, error::JobCancelled, AnyResult, BlocklessRunner, BlsRunTarget,
+    ExitStatus,
+};
+
+/// the interval of the engine epoch, the running jobs yield at every tick.
+const EPOCH_TICK: Duration = Duration::from_millis(10);
+
+/// the default number of the compiled modules kept by the runtime.
+const MODULE_CAPACITY: usize = 64;
+
+/// The compiled modules of the runtime, only the module of the latest modified time
+/// is kept for a file, and the least recently used module is evicted at the capacity.
+struct CompiledModules {
+    capacity: usize,
+    // the counter of the uses, the module of the smallest use is the least recently used.
+    uses: u64,
+    modules: HashMap<PathBuf, (Option<SystemTime>, u64, BlsRunTarget)>,
+}
+
+impl CompiledModules {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            uses: 0,
+            modules: HashMap::new(),
+        }
+    }
+
+    fn get(&mut self, path: &Path, modified: Option<SystemTime>) -> Option<BlsRunTarget> {
+        self.uses += 1;
+        let (m, used, target) = self.modules.get_mut(path)?;
+        if *m != modified {
+            return None;
+        }
+        *used = self.uses;
+        Some(target.clone())
+    }
+
+    fn insert(&mut self, path: PathBuf, modified: Option<SystemTime>, target: BlsRunTarget) {
+        self.uses += 1;
+        self.modules.insert(path, (modified, self.uses, target));
+        self.evict();
+    }
+
+    fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.evict();
+    }
+
+    fn evict(&mut self) {
+        while self.modules.len() > self.capacity {
+            let lru = self
+                .modules
+                .iter()
+                .min_by_key(|(_, (_, used, _))| *used)
+                .map(|(path, _)| path.clone());
+            match lru {
+                Some(path) => self.modules.remove(&path),
+                None => break,
+            };
+        }
+    }
+}
+
+/// the state shared by all the jobs of the runtime.
+pub(crate) struct RuntimeShared {
+    pub(crate) engine: Engine,
+    modules: Mutex<CompiledModules>,
+    /// the driver registry of the runtime, the drivers are resolved through it by the jobs.
+    pub(crate) drivers: Arc<RwLock<DriverConetxt>>,
+    // the loaded drivers root paths and the driver libraries.
+    loaded_drivers: Mutex<HashSet<String>>,
+    next_job_id: AtomicU64,
+    _ticker: EpochTicker,
+}
+
+impl RuntimeShared {
+    /// load the compiled module from the cache, compile it if not in the cache, the compiled
+    /// module is also stored in the cache directory if it's set.
+    pub(crate) fn load_module(
+        &self,
+        path: &str,
+        cache_dir: Option<&str>,
+    ) -> AnyResult<BlsRunTarget> {
+        let path = PathBuf::from(path);
+        let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+        if let Some(target) = self.modules.lock().unwrap().get(&path, modified) {
+            return Ok(target);
+        }
+        let target = cache::load_module(&self.engine, &path, cache_dir)?;
+        self.modules
+            .lock()
+            .unwrap()
+            .insert(path, modified, target.clone());
+        Ok(target)
+    }
+
+    /// register the drivers of the configure into the registry of the runtime,
+    /// every driver is only loaded once.
+    pub(crate) fn load_drivers(&self, b_conf: &BlocklessConfig) -> AnyResult<()> {
+        let mut loaded = self.loaded_drivers.lock().unwrap();
+        let root_path = drivers_root_path(b_conf);
+        if loaded.insert(root_path.clone()) {
+            self.drivers
+                .write()
+                .unwrap()
+                .init_built_in_drivers(root_path);
+        }
+        for cfg in b_conf.drivers_ref() {
+            if loaded.contains(cfg.path()) {
+                continue;
+            }
+            let drv = CdylibDriver::load(cfg.path(), cfg.schema())
+                .with_context(|| format!("failed to load the driver: {}", cfg.path()))?;
+            self.drivers.write().unwrap().insert_driver(drv);
+            loaded.insert(cfg.path().to_string());
+        }
+        Ok(())
+    }
+}
+
+/// The ticker increases the epoch of the engine at every tick, it's stopped when dropped.
+pub(crate) struct EpochTicker(Arc<AtomicBool>);
+
+impl EpochTicker {
+    pub(crate) fn start(engine: &Engine) -> AnyResult<Self> {
+        let stop = Arc::new(AtomicBool::new(false));
+        let ticker_stop = stop.clone();
+        let engine = engine.clone();
+        thread::Builder::new()
+            .name("blockless-epoch".into())
+            .spawn(move || {
+                while !ticker_stop.load(Ordering::Relaxed) {
+                    thread::sleep(EPOCH_TICK);
+                    engine.increment_epoch();
+                }
+            })?;
+        Ok(Self(stop))
+    }
+}
+
+impl Drop for EpochTicker {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+/// The runtime to run many guests concurrently in one process, the engine, the compiled
+/// modules and the drivers are shared by the jobs, every job has its own store.
+///
+/// The jobs must be spawned in the multi-thread tokio runtime.
+#[derive(Clone)]
+pub struct BlocklessRuntime(Arc<RuntimeShared>);
+
+impl BlocklessRuntime {
+    /// create the runtime with the default engine configure.
+    pub fn new() -> AnyResult<Self> {
+        Self::with_config(Config::new())
+    }
+
+    /// create the runtime with the engine configure, the async support, fuel consuming
+    /// and epoch interruption are always enabled, they are required by the jobs.
+    pub fn with_config(mut conf: Config) -> AnyResult<Self> {
+        conf.async_support(true)
+            .consume_fuel(true)
+            .epoch_interruption(true);
+        let engine = Engine::new(&conf)?;
+        let ticker = EpochTicker::start(&engine)?;
+        Ok(Self(Arc::new(RuntimeShared {
+            engine,
+            modules: Mutex::new(CompiledModules::new(MODULE_CAPACITY)),
+            drivers: Arc::new(RwLock::new(DriverConetxt::new())),
+            loaded_drivers: Mutex::new(HashSet::new()),
+            next_job_id: AtomicU64::new(1),
+            _ticker: ticker,
+        })))
+    }
+
+    #[inline(always)]
+    pub fn engine(&self) -> &Engine {
+        &self.0.engine
+    }
+
+    /// set the number of the compiled modules kept by the runtime, the default is 64,
+    /// the least recently used modules are evicted over the capacity.
+    pub fn set_module_capacity(&self, capacity: usize) {
+        self.0.modules.lock().unwrap().set_capacity(capacity);
+    }
+
+    /// spawn the job with the configure, the job is running in the tokio runtime.
+    pub fn spawn(&self, b_conf: BlocklessConfig) -> JobHandle {
+        let id = self.0.next_job_id.fetch_add(1, Ordering::Relaxed);
+        let shared = self.0.clone();
+        let handle =
+            tokio::spawn(async move { BlocklessRunner(b_conf).run_in(Some(&shared)).await });
+        JobHandle { id, handle }
+    }
+
+    /// run the job with the configure and wait the result.
+    pub async fn run(&self, b_conf: BlocklessConfig) -> AnyResult<ExitStatus> {
+        self.spawn(b_conf).result().await
+    }
+}
+
+/// The handle of the spawned job.
+pub struct JobHandle {
+    id: u64,
+    handle: JoinHandle<AnyResult<ExitStatus>>,
+}
+
+impl JobHandle {
+    #[inline(always)]
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// cancel the job, the result of the cancelled job is the `JobCancelled` error.
+    pub fn cancel(&self) {
+        self.handle.abort();
+    }
+
+    #[inline(always)]
+    pub fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+
+    /// wait the job finished and get the exit status.
+    pub async fn result(self) -> AnyResult<ExitStatus> {
+        match self.handle.await {
+            Ok(rs) => rs,
+            Err(e) if e.is_cancelled() => Err(JobCancelled(self.id).into()),
+            Err(e) => Err(anyhow::anyhow!("the job {} failed: {e}", self.id)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use wasmtime::Module;
+
+    fn target(engine: &Engine) -> BlsRunTarget {
+        BlsRunTarget::Module(Module::new(engine, "(module)").unwrap())
+    }
+
+    #[test]
+    fn test_compiled_modules() {
+        let engine = Engine::default();
+        let t1 = Some(SystemTime::UNIX_EPOCH);
+        let t2 = Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1));
+        let mut modules = CompiledModules::new(2);
+        modules.insert("a.wasm".into(), t1, target(&engine));
+        assert!(modules.get(Path::new("a.wasm"), t1).is_some());
+        assert!(modules.get(Path::new("a.wasm"), t2).is_none());
+        // the module of the new modified time replaces the old one.
+        modules.insert("a.wasm".into(), t2, target(&engine));
+        assert_eq!(modules.modules.len(), 1);
+        assert!(modules.get(Path::new("a.wasm"), t1).is_none());
+
+        modules.insert("b.wasm".into(), t1, target(&engine));
+        assert!(modules.get(Path::new("a.wasm"), t2).is_some());
+        // `b.wasm` is the least recently used.
+        modules.insert("c.wasm".into(), t1, target(&engine));
+        assert_eq!(modules.modules.len(), 2);
+        assert!(modules.get(Path::new("b.wasm"), t1).is_none());
+        assert!(modules.get(Path::new("a.wasm"), t2).is_some());
+
+        modules.set_capacity(1);
+        assert_eq!(modules.modules.len(), 1);
+        assert!(modules.get(Path::new("a.wasm"), t2).is_some());
+    }
+}
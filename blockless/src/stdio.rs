@@ -0,0 +1,160 @@
+use std::io::{self, Write};
+use std::sync::{Arc, RwLock};
+
+use bytes::Bytes;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use wasi_common::{CaptureLimit, Stderr, Stdin, StdinSource, StdinStream, Stdio, Stdout};
+use wasmtime_wasi::{HostOutputStream, StdoutStream, StreamError, StreamResult, Subscribe};
+
+/// The in-memory buffer of the captured output.
+pub(crate) struct CaptureBuffer {
+    buf: Vec<u8>,
+    limit: Option<CaptureLimit>,
+}
+
+impl CaptureBuffer {
+    fn new(limit: Option<CaptureLimit>) -> Self {
+        Self {
+            buf: Vec::new(),
+            limit,
+        }
+    }
+}
+
+impl Write for CaptureBuffer {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let remain = match self.limit {
+            Some(CaptureLimit::Truncate(max)) | Some(CaptureLimit::Trap(max)) => {
+                max.saturating_sub(self.buf.len())
+            }
+            None => data.len(),
+        };
+        if let Some(CaptureLimit::Trap(max)) = self.limit.filter(|_| data.len() > remain) {
+            // the error without the os error code traps the guest.
+            return Err(io::Error::other(format!(
+                "the output exceeds the capture limit {max}"
+            )));
+        }
+        self.buf.extend_from_slice(&data[..data.len().min(remain)]);
+        // the truncated bytes are reported as written, so the guest doesn't retry.
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+pub(crate) type SharedCapture = Arc<RwLock<CaptureBuffer>>;
+
+/// The captured stdout and stderr of the guest, they are shared by the threads.
+#[derive(Clone, Default)]
+pub(crate) struct StdioCapture {
+    pub(crate) stdout: Option<SharedCapture>,
+    pub(crate) stderr: Option<SharedCapture>,
+}
+
+impl StdioCapture {
+    pub(crate) fn new(stdio: &Stdio) -> Self {
+        let capture = |limit| Arc::new(RwLock::new(CaptureBuffer::new(limit)));
+        Self {
+            stdout: match stdio.stdout {
+                Stdout::Capture(limit) => Some(capture(limit)),
+                _ => None,
+            },
+            stderr: match stdio.stderr {
+                Stderr::Capture(limit) => Some(capture(limit)),
+                _ => None,
+            },
+        }
+    }
+
+    /// take the captured bytes of the stdout and stderr.
+    pub(crate) fn take(&self) -> (Option<Vec<u8>>, Option<Vec<u8>>) {
+        let take = |c: &Option<SharedCapture>| {
+            c.as_ref()
+                .map(|c| std::mem::take(&mut c.write().unwrap().buf))
+        };
+        (take(&self.stdout), take(&self.stderr))
+    }
+}
+
+/// The output stream of the component guest, it writes to the shared writer,
+/// e.g. the captured buffer or the stdout file.
+pub(crate) struct SharedOutput<W>(pub(crate) Arc<RwLock<W>>);
+
+impl<W: Write + Send + Sync + 'static> StdoutStream for SharedOutput<W> {
+    fn stream(&self) -> Box<dyn HostOutputStream> {
+        Box::new(SharedOutput(self.0.clone()))
+    }
+
+    fn isatty(&self) -> bool {
+        false
+    }
+}
+
+impl<W: Write + Send + Sync + 'static> HostOutputStream for SharedOutput<W> {
+    fn write(&mut self, bytes: Bytes) -> StreamResult<()> {
+        self.0
+            .write()
+            .unwrap()
+            .write_all(&bytes)
+            .map_err(|e| StreamError::Trap(e.into()))
+    }
+
+    fn flush(&mut self) -> StreamResult<()> {
+        self.0
+            .write()
+            .unwrap()
+            .flush()
+            .map_err(|e| StreamError::LastOperationFailed(e.into()))
+    }
+
+    fn check_write(&mut self) -> StreamResult<usize> {
+        Ok(usize::MAX)
+    }
+}
+
+#[async_trait::async_trait]
+impl<W: Write + Send + Sync + 'static> Subscribe for SharedOutput<W> {
+    async fn ready(&mut self) {}
+}
+
+/// The stdin source of the async reader.
+struct AsyncReadSource<R>(tokio::sync::Mutex<R>);
+
+#[async_trait::async_trait]
+impl<R: AsyncRead + Unpin + Send + 'static> StdinSource for AsyncReadSource<R> {
+    async fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.lock().await.read(buf).await
+    }
+}
+
+/// create the streaming stdin from the async reader, e.g. `tokio::io::stdin()`.
+pub fn stdin_stream<R: AsyncRead + Unpin + Send + 'static>(reader: R) -> Stdin {
+    Stdin::Stream(StdinStream::new(AsyncReadSource(tokio::sync::Mutex::new(
+        reader,
+    ))))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_capture_limit() {
+        let mut buf = CaptureBuffer::new(None);
+        buf.write_all(b"hello").unwrap();
+        assert_eq!(buf.buf, b"hello");
+
+        let mut buf = CaptureBuffer::new(Some(CaptureLimit::Truncate(3)));
+        assert_eq!(buf.write(b"hello").unwrap(), 5);
+        assert_eq!(buf.write(b"world").unwrap(), 5);
+        assert_eq!(buf.buf, b"hel");
+
+        let mut buf = CaptureBuffer::new(Some(CaptureLimit::Trap(3)));
+        buf.write_all(b"he").unwrap();
+        assert!(buf.write(b"llo").is_err());
+        assert_eq!(buf.buf, b"he");
+    }
+}
@@ -0,0 +1,277 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use http_body_util::{BodyExt, Limited};
+use log::error;
+use wasi_common::{Extension, UsageCounter, WasiCtx};
+use wasmtime::component::ResourceTable;
+use wasmtime_wasi_http::bindings::http::types::ErrorCode;
+use wasmtime_wasi_http::body::HyperOutgoingBody;
+use wasmtime_wasi_http::types::{
+    default_send_request_handler, HostFutureIncomingResponse, IncomingResponse,
+    OutgoingRequestConfig,
+};
+use wasmtime_wasi_http::{HttpResult, WasiHttpCtx, WasiHttpView};
+
+use crate::context::BlocklessContext;
+
+impl WasiHttpView for BlocklessContext {
+    fn ctx(&mut self) -> &mut WasiHttpCtx {
+        let ctx = self.wasi_http.as_mut().unwrap();
+        Arc::get_mut(ctx).expect("wasi http was not compatiable threads")
+    }
+
+    fn table(&mut self) -> &mut ResourceTable {
+        self.preview2_ctx().table()
+    }
+
+    fn send_request(
+        &mut self,
+        request: http::Request<HyperOutgoingBody>,
+        config: OutgoingRequestConfig,
+    ) -> HttpResult<HostFutureIncomingResponse> {
+        send_request(self.preview1_ctx.as_ref().unwrap(), request, config)
+    }
+}
+
+fn content_length(headers: &http::HeaderMap) -> Option<u64> {
+    headers
+        .get(http::header::CONTENT_LENGTH)?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()
+}
+
+/// the body fails with the error when it's larger than the max bytes.
+fn limited(body: HyperOutgoingBody, max: Option<u64>, exceeded: ErrorCode) -> HyperOutgoingBody {
+    match max {
+        Some(max) => Limited::new(body, max as _)
+            .map_err(move |e| match e.downcast::<ErrorCode>() {
+                Ok(e) => *e,
+                Err(_) => exceeded.clone(),
+            })
+            .boxed(),
+        None => body,
+    }
+}
+
+/// count the bytes of the body in the http usage.
+fn counted(
+    body: HyperOutgoingBody,
+    ctx: WasiCtx,
+    count: fn(&UsageCounter, Extension, u64),
+) -> HyperOutgoingBody {
+    body.map_frame(move |frame| {
+        if let Some(data) = frame.data_ref() {
+            count(&ctx.usage, Extension::Http, data.len() as _);
+        }
+        frame
+    })
+    .boxed()
+}
+
+fn limit_response(
+    ctx: WasiCtx,
+    mut resp: IncomingResponse,
+    max: Option<u64>,
+) -> Result<IncomingResponse, ErrorCode> {
+    if let (Some(max), Some(len)) = (max, content_length(resp.resp.headers())) {
+        if len > max {
+            return Err(ErrorCode::HttpResponseBodySize(Some(len)));
+        }
+    }
+    resp.resp = resp.resp.map(|body| {
+        let body = limited(body, max, ErrorCode::HttpResponseBodySize(None));
+        counted(body, ctx, UsageCounter::bytes_in)
+    });
+    Ok(resp)
+}
+
+/// the outgoing request of the component is checked with the method permissions,
+/// then sent with the timeouts and the body limits of the `http` configure.
+pub(crate) fn send_request(
+    ctx: &WasiCtx,
+    request: http::Request<HyperOutgoingBody>,
+    mut config: OutgoingRequestConfig,
+) -> HttpResult<HostFutureIncomingResponse> {
+    let url = request.uri().to_string();
+    if !ctx.method_permission(&url, Some(request.method().as_str())) {
+        error!("Permission Deny: {}", url);
+        return Err(ErrorCode::HttpRequestDenied.into());
+    }
+    let limits = ctx.http_config();
+    if let Some(ms) = limits.connect_timeout {
+        config.connect_timeout = Duration::from_millis(ms);
+    }
+    if let Some(ms) = limits.first_byte_timeout {
+        config.first_byte_timeout = Duration::from_millis(ms);
+    }
+    if let Some(ms) = limits.between_bytes_timeout {
+        config.between_bytes_timeout = Duration::from_millis(ms);
+    }
+    if let (Some(max), Some(len)) = (limits.max_request_body, content_length(request.headers())) {
+        if len > max {
+            return Err(ErrorCode::HttpRequestBodySize(Some(len)).into());
+        }
+    }
+    ctx.usage.call(Extension::Http);
+    let request = request.map(|body| {
+        let body = limited(
+            body,
+            limits.max_request_body,
+            ErrorCode::HttpRequestBodySize(None),
+        );
+        counted(body, ctx.clone(), UsageCounter::bytes_out)
+    });
+    let ctx = ctx.clone();
+    let handle = wasmtime_wasi::runtime::spawn(async move {
+        let resp = default_send_request_handler(request, config).await;
+        Ok(resp.and_then(|resp| limit_response(ctx, resp, limits.max_response_body)))
+    });
+    Ok(HostFutureIncomingResponse::pending(handle))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bytes::Bytes;
+    use http_body_util::Empty;
+    use std::net::SocketAddr;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use wasi_common::{BlocklessConfig, HttpConfig, Permission};
+
+    /// serve the requests with the `hello` body.
+    async fn hello_server() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = Vec::new();
+                let mut chunk = [0; 1024];
+                while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+                    match stream.read(&mut chunk).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                    }
+                }
+                let resp = "HTTP/1.1 200 OK\r\ncontent-length: 5\r\nconnection: close\r\n\r\nhello";
+                let _ = stream.write_all(resp.as_bytes()).await;
+            }
+        });
+        addr
+    }
+
+    fn wasi_ctx(perms: &[String], http: HttpConfig) -> WasiCtx {
+        let mut config = BlocklessConfig::new("test");
+        config.set_permisions(
+            perms
+                .iter()
+                .map(|p| Permission::parse(p).unwrap())
+                .collect(),
+        );
+        config.set_http(http);
+        let mut ctx = wasi_common::tokio::WasiCtxBuilder::new().build();
+        ctx.set_blockless_config(Some(config));
+        ctx
+    }
+
+    fn request(addr: SocketAddr) -> http::Request<HyperOutgoingBody> {
+        let body = Empty::<Bytes>::new().map_err(|e| match e {}).boxed();
+        http::Request::builder()
+            .method("GET")
+            .uri(format!("http://{addr}/hello"))
+            .body(body)
+            .unwrap()
+    }
+
+    fn request_config() -> OutgoingRequestConfig {
+        OutgoingRequestConfig {
+            use_tls: false,
+            connect_timeout: Duration::from_secs(10),
+            first_byte_timeout: Duration::from_secs(10),
+            between_bytes_timeout: Duration::from_secs(10),
+        }
+    }
+
+    async fn response(fut: HostFutureIncomingResponse) -> Result<IncomingResponse, ErrorCode> {
+        match fut {
+            HostFutureIncomingResponse::Pending(handle) => handle.await.unwrap(),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_send_request() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let addr = hello_server().await;
+            let ctx = wasi_ctx(&[format!("http://{addr}")], HttpConfig::default());
+            let fut = send_request(&ctx, request(addr), request_config()).unwrap();
+            let IncomingResponse { resp, worker, .. } = response(fut).await.unwrap();
+            let _worker = worker;
+            assert_eq!(resp.status(), 200);
+            let body = resp.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(&body[..], b"hello");
+            let usage = ctx.usage.usage(Extension::Http);
+            assert_eq!(usage.calls, 1);
+            assert_eq!(usage.bytes_in, 5);
+        });
+    }
+
+    #[test]
+    fn test_send_request_denied() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let addr = hello_server().await;
+            let ctx = wasi_ctx(&[], HttpConfig::default());
+            let err = send_request(&ctx, request(addr), request_config()).unwrap_err();
+            assert!(matches!(err.downcast(), Ok(ErrorCode::HttpRequestDenied)));
+            assert_eq!(ctx.usage.usage(Extension::Http).calls, 0);
+        });
+    }
+
+    #[test]
+    fn test_send_request_body_limit() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let addr = hello_server().await;
+            let http = HttpConfig {
+                max_response_body: Some(3),
+                ..Default::default()
+            };
+            let ctx = wasi_ctx(&[format!("http://{addr}")], http);
+            let fut = send_request(&ctx, request(addr), request_config()).unwrap();
+            assert!(matches!(
+                response(fut).await,
+                Err(ErrorCode::HttpResponseBodySize(Some(5)))
+            ));
+
+            let http = HttpConfig {
+                max_request_body: Some(3),
+                ..Default::default()
+            };
+            let ctx = wasi_ctx(&[format!("http://{addr}")], http);
+            let mut req = request(addr);
+            req.headers_mut().insert(
+                http::header::CONTENT_LENGTH,
+                http::HeaderValue::from_static("5"),
+            );
+            let err = send_request(&ctx, req, request_config()).unwrap_err();
+            assert!(matches!(
+                err.downcast(),
+                Ok(ErrorCode::HttpRequestBodySize(Some(5)))
+            ));
+        });
+    }
+}
@@ -1,10 +1,12 @@
 mod common;
 
 use std::fs;
+use std::time::Duration;
 
+use blockless::stdin_stream;
 use common::run_blockless;
 use tempdir::TempDir;
-use wasi_common::{BlocklessConfig, BlocklessConfigVersion};
+use wasi_common::{BlocklessConfig, BlocklessConfigVersion, CaptureLimit, Stderr, Stdin, Stdout};
 
 #[test]
 fn test_outof_fuel() {
@@ -42,3 +44,310 @@ fn test_blockless_normal() {
     let code = run_blockless(config).unwrap();
     assert_eq!(code.code, 0);
 }
+
+#[test]
+fn test_blockless_run_timeout() {
+    let temp_dir = TempDir::new("blockless_run").unwrap();
+    let file_path = temp_dir.path().join("test_blockless_run.wasm");
+    let code = r#"
+    (module
+        (func (export "_start") (loop $l (br $l)))
+        (memory (export "memory") 1)
+    )
+    "#;
+    fs::write(&file_path, code).unwrap();
+    let path = file_path.to_str().unwrap();
+    let mut config = BlocklessConfig::new(path);
+    config.set_run_time(Some(100));
+    config.set_version(BlocklessConfigVersion::Version0);
+    let code = run_blockless(config).unwrap();
+    assert_eq!(code.code, 15);
+    assert!(code.cpu_time.map_or(true, |t| t > Duration::ZERO));
+
+    let mut config = BlocklessConfig::new(path);
+    config.limited_time(Some(100));
+    config.set_version(BlocklessConfigVersion::Version0);
+    let code = run_blockless(config).unwrap();
+    assert_eq!(code.code, 15);
+}
+
+#[test]
+fn test_blockless_run_timeout_in_host_call() {
+    let temp_dir = TempDir::new("blockless_run").unwrap();
+    let file_path = temp_dir.path().join("test_blockless_run.wasm");
+    // the guest sleeps 10 seconds in the monotonic clock subscription of `poll_oneoff`.
+    let code = r#"
+    (module
+        (import "wasi_snapshot_preview1" "poll_oneoff"
+            (func $poll_oneoff (param i32 i32 i32 i32) (result i32)))
+        (func (export "_start")
+            (i32.store (i32.const 16) (i32.const 1))
+            (i64.store (i32.const 24) (i64.const 10000000000))
+            (drop (call $poll_oneoff (i32.const 0) (i32.const 64) (i32.const 1) (i32.const 128)))
+        )
+        (memory (export "memory") 1)
+    )
+    "#;
+    fs::write(&file_path, code).unwrap();
+    let path = file_path.to_str().unwrap();
+    let mut config = BlocklessConfig::new(path);
+    config.set_run_time(Some(100));
+    config.set_version(BlocklessConfigVersion::Version0);
+    let status = run_blockless(config).unwrap();
+    assert_eq!(status.code, 15);
+    assert!(status.wall_time < Duration::from_secs(5));
+    // the sleeping guest doesn't consume the cpu time.
+    if let Some(cpu_time) = status.cpu_time {
+        assert!(cpu_time < status.wall_time);
+    }
+}
+
+#[test]
+fn test_blockless_exit_status() {
+    let temp_dir = TempDir::new("blockless_run").unwrap();
+    let file_path = temp_dir.path().join("test_blockless_run.wasm");
+    let code = r#"
+    (module
+        (func (export "_start") (loop $l (br $l)))
+        (memory (export "memory") 2)
+    )
+    "#;
+    fs::write(&file_path, code).unwrap();
+    let path = file_path.to_str().unwrap();
+    let mut config = BlocklessConfig::new(path);
+    config.limited_fuel(Some(100));
+    config.set_version(BlocklessConfigVersion::Version0);
+    let status = run_blockless(config).unwrap();
+    assert_eq!(status.code, 1);
+    assert!(!status.exited);
+    assert_eq!(status.trap.as_deref(), Some("OutOfFuel"));
+    assert_eq!(status.limited_fuel, Some(100));
+    assert_eq!(status.fuel_used, Some(100));
+    assert_eq!(status.peak_memory, 2 * 65536);
+    assert_eq!(status.extensions.len(), 5);
+    assert!(status.extensions.iter().all(|(_, u)| u.calls == 0));
+}
+
+#[test]
+fn test_blockless_guest_exit() {
+    let temp_dir = TempDir::new("blockless_run").unwrap();
+    let file_path = temp_dir.path().join("test_blockless_run.wasm");
+    let code = r#"
+    (module
+        (import "wasi_snapshot_preview1" "proc_exit" (func $exit (param i32)))
+        (func (export "_start") (call $exit (i32.const 42)))
+        (memory (export "memory") 1)
+    )
+    "#;
+    fs::write(&file_path, code).unwrap();
+    let path = file_path.to_str().unwrap();
+    let mut config = BlocklessConfig::new(path);
+    config.set_version(BlocklessConfigVersion::Version0);
+    let status = run_blockless(config).unwrap();
+    assert_eq!(status.code, 42);
+    assert!(status.exited);
+    assert!(status.trap.is_none());
+}
+
+#[test]
+fn test_blockless_capture_stdio() {
+    let temp_dir = TempDir::new("blockless_run").unwrap();
+    let file_path = temp_dir.path().join("test_blockless_run.wasm");
+    let code = r#"
+    (module
+        (import "wasi_snapshot_preview1" "fd_write"
+            (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (memory (export "memory") 1)
+        (data (i32.const 16) "hello")
+        (func (export "_start")
+            (i32.store (i32.const 0) (i32.const 16))
+            (i32.store (i32.const 4) (i32.const 5))
+            (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+            (drop (call $fd_write (i32.const 2) (i32.const 0) (i32.const 1) (i32.const 8))))
+    )
+    "#;
+    fs::write(&file_path, code).unwrap();
+    let path = file_path.to_str().unwrap();
+    let mut config = BlocklessConfig::new(path);
+    config.set_version(BlocklessConfigVersion::Version0);
+    config.stdout(Stdout::Capture(None));
+    config.stderr(Stderr::Capture(Some(CaptureLimit::Truncate(3))));
+    let status = run_blockless(config).unwrap();
+    assert_eq!(status.code, 0);
+    assert_eq!(status.stdout.as_deref(), Some(&b"hello"[..]));
+    assert_eq!(status.stderr.as_deref(), Some(&b"hel"[..]));
+
+    let mut config = BlocklessConfig::new(path);
+    config.set_version(BlocklessConfigVersion::Version0);
+    config.stdout(Stdout::Capture(None));
+    config.stderr(Stderr::Capture(Some(CaptureLimit::Trap(3))));
+    let status = run_blockless(config).unwrap();
+    assert_ne!(status.code, 0);
+    assert_eq!(status.stdout.as_deref(), Some(&b"hello"[..]));
+    assert_eq!(status.stderr.as_deref(), Some(&b""[..]));
+
+    let mut config = BlocklessConfig::new(path);
+    config.set_version(BlocklessConfigVersion::Version0);
+    config.stdout(Stdout::Null);
+    let status = run_blockless(config).unwrap();
+    assert!(status.stdout.is_none());
+}
+
+#[test]
+fn test_blockless_stdin_read_in_chunks() {
+    let temp_dir = TempDir::new("blockless_run").unwrap();
+    let file_path = temp_dir.path().join("test_blockless_run.wasm");
+    // read the stdin by 4 bytes and write the chunks to the stdout.
+    let code = r#"
+    (module
+        (import "blockless_memory" "memory_read"
+            (func $memory_read (param i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_write"
+            (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (memory (export "memory") 1)
+        (func (export "_start")
+            (block $done
+                (loop $l
+                    (br_if $done (call $memory_read (i32.const 100) (i32.const 4) (i32.const 8)))
+                    (br_if $done (i32.eqz (i32.load (i32.const 8))))
+                    (i32.store (i32.const 0) (i32.const 100))
+                    (i32.store (i32.const 4) (i32.load (i32.const 8)))
+                    (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 12)))
+                    (br $l))))
+    )
+    "#;
+    fs::write(&file_path, code).unwrap();
+    let path = file_path.to_str().unwrap();
+    let stdins = [
+        Stdin::Fixed("hello world".into()),
+        stdin_stream(&b"hello world"[..]),
+    ];
+    for stdin in stdins {
+        let mut config = BlocklessConfig::new(path);
+        config.set_version(BlocklessConfigVersion::Version0);
+        config.stdout(Stdout::Capture(None));
+        config.stdio.stdin = stdin;
+        let status = run_blockless(config).unwrap();
+        assert_eq!(status.code, 0);
+        assert_eq!(status.stdout.as_deref(), Some(&b"hello world"[..]));
+    }
+}
+
+#[test]
+fn test_blockless_invoke() {
+    let temp_dir = TempDir::new("blockless_run").unwrap();
+    let file_path = temp_dir.path().join("test_blockless_invoke.wasm");
+    let code = r#"
+    (module
+        (func (export "add") (param i32 i32) (result i32)
+            (i32.add (local.get 0) (local.get 1)))
+        (func (export "half") (param f64) (result f64)
+            (f64.div (local.get 0) (f64.const 2)))
+        (memory (export "memory") 1)
+    )
+    "#;
+    fs::write(&file_path, code).unwrap();
+    let path = file_path.to_str().unwrap();
+    let mut config = BlocklessConfig::new(path);
+    config.set_version(BlocklessConfigVersion::Version0);
+    config.set_invoke(Some("add".into()));
+    config.set_stdin_args(vec!["1".into(), "-3".into()]);
+    let status = run_blockless(config.clone()).unwrap();
+    assert_eq!(status.code, 0);
+    assert_eq!(status.results, vec!["-2".to_string()]);
+
+    config.set_invoke(Some("half".into()));
+    config.set_stdin_args(vec!["5".into()]);
+    let status = run_blockless(config.clone()).unwrap();
+    assert_eq!(status.results, vec!["2.5".to_string()]);
+
+    // the arguments don't match the params.
+    config.set_invoke(Some("add".into()));
+    config.set_stdin_args(vec!["1".into()]);
+    let status = run_blockless(config).unwrap();
+    assert_ne!(status.code, 0);
+    assert!(status.results.is_empty());
+}
+
+#[test]
+fn test_blockless_invoke_component() {
+    let temp_dir = TempDir::new("blockless_run").unwrap();
+    let file_path = temp_dir.path().join("test_blockless_invoke_component.wasm");
+    let code = r#"
+    (component
+        (core module $m
+            (func (export "add") (param i32 i32) (result i32)
+                (i32.add (local.get 0) (local.get 1))))
+        (core instance $i (instantiate $m))
+        (func (export "add") (param "a" u32) (param "b" u32) (result u32)
+            (canon lift (core func $i "add")))
+    )
+    "#;
+    fs::write(&file_path, code).unwrap();
+    let path = file_path.to_str().unwrap();
+    let mut config = BlocklessConfig::new(path);
+    config.set_version(BlocklessConfigVersion::Version0);
+    config.set_invoke(Some("add".into()));
+    config.set_stdin_args(vec!["1".into(), "2".into()]);
+    let status = run_blockless(config).unwrap();
+    assert_eq!(status.code, 0);
+    assert_eq!(status.results, vec!["3".to_string()]);
+}
+
+#[test]
+fn test_blockless_component_extensions() {
+    let temp_dir = TempDir::new("blockless_run").unwrap();
+    let file_path = temp_dir
+        .path()
+        .join("test_blockless_component_extensions.wasm");
+    // the component reads the env json with `env-var-read` and returns the length.
+    let code = r#"
+    (component
+        (import "blockless:extensions/memory@0.1.0" (instance $ext
+            (type $error (enum "invalid-handle" "runtime-error" "invalid-parameter"))
+            (export "memory-error" (type $memory-error (eq $error)))
+            (export "env-var-read"
+                (func (param "len" u32) (result (result (list u8) (error $memory-error)))))
+        ))
+        (core module $libc
+            (memory (export "memory") 1)
+            (global $heap (mut i32) (i32.const 1024))
+            (func (export "realloc") (param i32 i32 i32 i32) (result i32)
+                (local $ptr i32)
+                (local.set $ptr (global.get $heap))
+                (global.set $heap (i32.add (global.get $heap) (local.get 3)))
+                (local.get $ptr))
+        )
+        (core instance $libc (instantiate $libc))
+        (alias export $ext "env-var-read" (func $env-var-read))
+        (core func $env-var-read
+            (canon lower (func $env-var-read)
+                (memory $libc "memory") (realloc (func $libc "realloc"))))
+        (core module $m
+            (import "libc" "memory" (memory 1))
+            (import "ext" "env-var-read" (func $env-var-read (param i32 i32)))
+            (func (export "env-len") (result i32)
+                (call $env-var-read (i32.const 1024) (i32.const 16))
+                (if (result i32) (i32.load8_u (i32.const 16))
+                    (then (i32.const -1))
+                    (else (i32.load (i32.const 24)))))
+        )
+        (core instance $i (instantiate $m
+            (with "libc" (instance $libc))
+            (with "ext" (instance (export "env-var-read" (func $env-var-read))))
+        ))
+        (func (export "env-len") (result s32)
+            (canon lift (core func $i "env-len")))
+    )
+    "#;
+    fs::write(&file_path, code).unwrap();
+    let path = file_path.to_str().unwrap();
+    let mut config = BlocklessConfig::new(path);
+    config.set_version(BlocklessConfigVersion::Version0);
+    config.set_envs(vec![("FOO".into(), "bar".into())]);
+    config.set_invoke(Some("env-len".into()));
+    let status = run_blockless(config).unwrap();
+    assert_eq!(status.code, 0);
+    // the env json is `{"FOO":"bar"}`.
+    assert_eq!(status.results, vec!["13".to_string()]);
+}
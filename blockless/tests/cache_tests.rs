@@ -0,0 +1,86 @@
+mod common;
+
+use std::fs;
+
+use blockless::{blockless_compile, ModuleCache};
+use common::run_blockless;
+use tempdir::TempDir;
+use wasi_common::{BlocklessConfig, BlocklessConfigVersion};
+use wasmtime::{Config, Engine, OptLevel};
+
+const EXIT_GUEST: &str = r#"
+(module
+    (import "wasi_snapshot_preview1" "proc_exit" (func $exit (param i32)))
+    (func (export "_start") (call $exit (i32.const 3)))
+    (memory (export "memory") 1)
+)
+"#;
+
+fn cached_files(dir: &std::path::Path) -> usize {
+    fs::read_dir(dir)
+        .map(|entries| entries.filter(|e| e.is_ok()).count())
+        .unwrap_or(0)
+}
+
+#[test]
+fn test_module_cache() {
+    let temp_dir = TempDir::new("blockless_cache").unwrap();
+    let file_path = temp_dir.path().join("exit.wat");
+    fs::write(&file_path, EXIT_GUEST).unwrap();
+    let cache_dir = temp_dir.path().join("cache");
+    let cache = ModuleCache::new(&cache_dir);
+
+    let engine = Engine::new(&Config::new()).unwrap();
+    cache.load(&engine, &file_path).unwrap();
+    assert_eq!(cached_files(&cache_dir), 1);
+    let cached = cache.path(&engine, EXIT_GUEST.as_bytes());
+    assert!(cached.exists());
+    // the second load is from the cache.
+    cache.load(&engine, &file_path).unwrap();
+    assert_eq!(cached_files(&cache_dir), 1);
+
+    // the engine with the other optimize options doesn't share the compiled module.
+    let mut config = Config::new();
+    config.cranelift_opt_level(OptLevel::None);
+    let engine2 = Engine::new(&config).unwrap();
+    assert_ne!(
+        ModuleCache::engine_fingerprint(&engine),
+        ModuleCache::engine_fingerprint(&engine2)
+    );
+    cache.load(&engine2, &file_path).unwrap();
+    assert_eq!(cached_files(&cache_dir), 2);
+
+    // the broken cached module is compiled again.
+    fs::write(&cached, b"broken").unwrap();
+    cache.load(&engine, &file_path).unwrap();
+    assert_ne!(fs::read(&cached).unwrap(), b"broken");
+}
+
+#[test]
+fn test_blockless_compile() {
+    let temp_dir = TempDir::new("blockless_cache").unwrap();
+    let file_path = temp_dir.path().join("exit.wat");
+    fs::write(&file_path, EXIT_GUEST).unwrap();
+    let mut config = BlocklessConfig::new(file_path.to_str().unwrap());
+    config.set_version(BlocklessConfigVersion::Version0);
+    let cache_dir = temp_dir.path().join("cache");
+    config.set_module_cache(Some(cache_dir.to_str().unwrap().into()));
+    let compiled = blockless_compile(&config, file_path.to_str().unwrap()).unwrap();
+    assert_eq!(cached_files(&cache_dir), 1);
+
+    // the precompiled module runs without compiling.
+    let compiled_path = temp_dir.path().join("exit.cwasm");
+    fs::write(&compiled_path, compiled).unwrap();
+    let mut config = BlocklessConfig::new(compiled_path.to_str().unwrap());
+    config.set_version(BlocklessConfigVersion::Version0);
+    let status = run_blockless(config).unwrap();
+    assert_eq!(status.code, 3);
+
+    // the module is loaded from the cache.
+    let mut config = BlocklessConfig::new(file_path.to_str().unwrap());
+    config.set_version(BlocklessConfigVersion::Version0);
+    config.set_module_cache(Some(cache_dir.to_str().unwrap().into()));
+    let status = run_blockless(config).unwrap();
+    assert_eq!(status.code, 3);
+    assert_eq!(cached_files(&cache_dir), 1);
+}
@@ -2,10 +2,12 @@ use blockless::{blockless_run, ExitStatus};
 use tokio::runtime::Builder;
 use wasi_common::BlocklessConfig;
 
-/// runing environment for test.
+/// runing environment for test, the guest requires the multi-thread runtime, it runs
+/// with one worker.
 #[allow(dead_code)]
 pub fn run_blockless(config: BlocklessConfig) -> anyhow::Result<ExitStatus> {
-    let rt = Builder::new_current_thread()
+    let rt = Builder::new_multi_thread()
+        .worker_threads(1)
         .enable_io()
         .enable_time()
         .build()
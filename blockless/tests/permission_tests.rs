@@ -0,0 +1,252 @@
+mod common;
+use std::fs;
+
+use common::run_blockless;
+use tempdir::TempDir;
+use wasi_common::{
+    BlocklessConfig, BlocklessConfigVersion, CredentialKind, CredentialProfile, Permission,
+    SecretSource,
+};
+
+/// build the guest which call the extension function, the datas is put into the memory
+/// begin with offset 1024 and step 1024. if the result of call is not equal with `expect`,
+/// the guest will be trapped with `unreachable`.
+fn guest_code(
+    module: &str,
+    func: &str,
+    params: usize,
+    args: &str,
+    datas: &[&str],
+    expect: u32,
+) -> String {
+    let params = vec!["i32"; params].join(" ");
+    let datas = datas
+        .iter()
+        .enumerate()
+        .map(|(i, d)| {
+            let d = d.replace('"', "\\\"");
+            format!("(data (i32.const {}) \"{d}\")", (i + 1) * 1024)
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        r#"
+    (module
+        (import "{module}" "{func}" (func $call (param {params}) (result i32)))
+        (memory (export "memory") 1)
+        (func (export "_start")
+            (if (i32.ne (call $call {args}) (i32.const {expect}))
+                (then unreachable)
+            )
+        )
+        {datas}
+    )
+    "#
+    )
+}
+
+fn run_guest(code: &str, permissions: Vec<Permission>) -> i32 {
+    run_guest_with(code, permissions, |_| {})
+}
+
+fn run_guest_with(
+    code: &str,
+    permissions: Vec<Permission>,
+    f: impl FnOnce(&mut BlocklessConfig),
+) -> i32 {
+    let temp_dir = TempDir::new("blockless_run").unwrap();
+    let guest_path = temp_dir.path().join("test_permission.wasm");
+    fs::write(&guest_path, code).unwrap();
+    let mut config = BlocklessConfig::new(guest_path.to_str().unwrap());
+    config.set_version(BlocklessConfigVersion::Version0);
+    config.set_permisions(permissions);
+    f(&mut config);
+    run_blockless(config).unwrap().code
+}
+
+fn permission(perm: &str) -> Permission {
+    Permission::parse(perm).unwrap()
+}
+
+#[test]
+fn test_ipfs_command_permission_deny() {
+    let params = r#"{"api":"files/ls"}"#;
+    let args = format!(
+        "(i32.const 1024) (i32.const {}) (i32.const 0) (i32.const 8)",
+        params.len()
+    );
+    // ipfs_error::permission_deny
+    let code = guest_code("blockless_ipfs", "ipfs_command", 4, &args, &[params], 10);
+    let perms = vec![permission("ipfs://files/read")];
+    assert_eq!(run_guest(&code, perms), 0);
+}
+
+#[test]
+fn test_s3_permission_deny() {
+    let params = r#"{"endpoint":"http://127.0.0.1:9000","bucket_name":"test","access_key":"a","secret_key":"b","prefix":""}"#;
+    let perms = || vec![permission("s3://127.0.0.1:9000/other")];
+    // s3_error::permission_deny
+    let args = format!(
+        "(i32.const 2) (i32.const 1024) (i32.const {}) (i32.const 0)",
+        params.len()
+    );
+    let code = guest_code("blockless_s3", "bucket_command", 4, &args, &[params], 12);
+    assert_eq!(run_guest(&code, perms()), 0);
+
+    let args = format!(
+        "(i32.const 1024) (i32.const {}) (i32.const 2048) (i32.const 4)",
+        params.len()
+    );
+    let code = guest_code(
+        "blockless_s3",
+        "bucket_put_object",
+        4,
+        &args,
+        &[params, "test"],
+        12,
+    );
+    assert_eq!(run_guest(&code, perms()), 0);
+}
+
+#[test]
+fn test_s3_credential_profile_permission_deny() {
+    let params = r#"{"endpoint":"http://127.0.0.1:9000","bucket_name":"test","profile":"s3-prod","prefix":""}"#;
+    let args = format!(
+        "(i32.const 2) (i32.const 1024) (i32.const {}) (i32.const 0)",
+        params.len()
+    );
+    // s3_error::permission_deny, the bucket is permitted but the profile is not.
+    let code = guest_code("blockless_s3", "bucket_command", 4, &args, &[params], 12);
+    let perms = vec![
+        permission("s3://127.0.0.1:9000/test"),
+        permission("cred://other"),
+    ];
+    let profile = |url: &str| CredentialProfile {
+        kind: CredentialKind::S3 {
+            access_key: SecretSource::Env("BLS_TEST_S3_KEY".into()),
+            secret_key: SecretSource::Env("BLS_TEST_S3_SECRET".into()),
+            session_token: None,
+        },
+        urls: vec![permission(url)],
+    };
+    let code = run_guest_with(&code, perms, |config| {
+        let profile = profile("http://127.0.0.1:9000/test");
+        config.set_credentials([("s3-prod".to_string(), profile)].into());
+    });
+    assert_eq!(code, 0);
+
+    // the profile is permitted, but the bucket is not the url of the profile.
+    let perms = || {
+        vec![
+            permission("s3://127.0.0.1:9000/test"),
+            permission("cred://s3-prod"),
+        ]
+    };
+    let code = guest_code("blockless_s3", "bucket_command", 4, &args, &[params], 12);
+    let code = run_guest_with(&code, perms(), |config| {
+        let profile = profile("https://s3.amazonaws.com/test");
+        config.set_credentials([("s3-prod".to_string(), profile)].into());
+    });
+    assert_eq!(code, 0);
+
+    // the presigned url can't be signed with the profile.
+    let args = format!(
+        "(i32.const 7) (i32.const 1024) (i32.const {}) (i32.const 0)",
+        params.len()
+    );
+    let code = guest_code("blockless_s3", "bucket_command", 4, &args, &[params], 12);
+    let code = run_guest_with(&code, perms(), |config| {
+        let profile = profile("http://127.0.0.1:9000/test");
+        config.set_credentials([("s3-prod".to_string(), profile)].into());
+    });
+    assert_eq!(code, 0);
+}
+
+#[test]
+fn test_cgi_open_permission_deny() {
+    let params = r#"{"command":"test"}"#;
+    let args = format!(
+        "(i32.const 1024) (i32.const {}) (i32.const 0)",
+        params.len()
+    );
+    // cgi_error::permission_deny
+    let code = guest_code("blockless_cgi", "cgi_open", 3, &args, &[params], 5);
+    let perms = vec![permission("cgi://other")];
+    assert_eq!(run_guest(&code, perms), 0);
+}
+
+#[test]
+fn test_socket_permission_deny() {
+    let addr = "127.0.0.1:1";
+    let args = format!("(i32.const 1024) (i32.const {}) (i32.const 0)", addr.len());
+    // socket_error::permission_deny
+    let code = guest_code("blockless_socket", "tcp_connect", 3, &args, &[addr], 5);
+    assert_eq!(run_guest(&code, vec![]), 0);
+    let code = guest_code(
+        "blockless_socket",
+        "create_tcp_bind_socket",
+        3,
+        &args,
+        &[addr],
+        5,
+    );
+    assert_eq!(run_guest(&code, vec![]), 0);
+
+    // socket_error::connection_refused, the permission is granted.
+    let code = guest_code("blockless_socket", "tcp_connect", 3, &args, &[addr], 2);
+    let perms = vec![permission("tcp://127.0.0.1:1")];
+    assert_eq!(run_guest(&code, perms), 0);
+}
+
+#[test]
+fn test_http_method_permission_deny() {
+    let url = "http://127.0.0.1:1/test";
+    let opts = r#"{"method":"POST"}"#;
+    let args = format!(
+        "(i32.const 1024) (i32.const {}) (i32.const 2048) (i32.const {}) (i32.const 0) (i32.const 8)",
+        url.len(),
+        opts.len()
+    );
+    // http_error::permission_deny
+    let code = guest_code("blockless_http", "http_req", 6, &args, &[url, opts], 14);
+    let perms = vec![permission("GET,HEAD http://127.0.0.1:1")];
+    assert_eq!(run_guest(&code, perms), 0);
+    let perms = || {
+        vec![
+            permission("http://127.0.0.1:1"),
+            permission("!POST http://127.0.0.1:1/test"),
+        ]
+    };
+    assert_eq!(run_guest(&code, perms()), 0);
+
+    let args = format!(
+        "(i32.const 1024) (i32.const {}) (i32.const 2048) (i32.const {}) (i32.const 0)",
+        url.len(),
+        opts.len()
+    );
+    let code = guest_code("blockless_http", "http_open", 5, &args, &[url, opts], 14);
+    assert_eq!(run_guest(&code, perms()), 0);
+}
+
+#[test]
+fn test_blockless_open_permission_deny() {
+    let url = "tcp://127.0.0.1:1";
+    let opts = "{}";
+    let args = format!(
+        "(i32.const 1024) (i32.const {}) (i32.const 2048) (i32.const {}) (i32.const 0)",
+        url.len(),
+        opts.len()
+    );
+    // errno::permission_deny
+    let code = guest_code(
+        "blockless_drivers",
+        "blockless_open",
+        5,
+        &args,
+        &[url, opts],
+        14,
+    );
+    // the deny rule overrides the allow rule.
+    let perms = vec![permission("tcp://*"), permission("!tcp://127.0.0.1:1")];
+    assert_eq!(run_guest(&code, perms), 0);
+}
@@ -0,0 +1,194 @@
+use std::{fs, path::Path, time::Duration};
+
+use blockless::{error::JobCancelled, BlocklessRuntime};
+use tempdir::TempDir;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+    runtime::Builder,
+};
+use wasi_common::{BlocklessConfig, BlocklessConfigVersion};
+
+fn write_guest(dir: &Path, name: &str, code: &str) -> BlocklessConfig {
+    let file_path = dir.join(name);
+    fs::write(&file_path, code).unwrap();
+    let mut config = BlocklessConfig::new(file_path.to_str().unwrap());
+    config.set_version(BlocklessConfigVersion::Version0);
+    config
+}
+
+fn multi_threads_run<F: std::future::Future>(f: F) -> F::Output {
+    let rt = Builder::new_multi_thread()
+        .enable_io()
+        .enable_time()
+        .build()
+        .unwrap();
+    rt.block_on(f)
+}
+
+#[test]
+fn test_runtime_concurrent_jobs() {
+    let temp_dir = TempDir::new("blockless_runtime").unwrap();
+    let normal = r#"
+    (module
+        (func (export "_start"))
+        (memory (export "memory") 1)
+    )
+    "#;
+    let exit = r#"
+    (module
+        (import "wasi_snapshot_preview1" "proc_exit" (func $exit (param i32)))
+        (func (export "_start") (call $exit (i32.const 3)))
+        (memory (export "memory") 1)
+    )
+    "#;
+    let trap = r#"
+    (module
+        (func (export "_start") unreachable)
+        (memory (export "memory") 1)
+    )
+    "#;
+    let dir = temp_dir.path();
+    let guests = [
+        (write_guest(dir, "normal.wat", normal), 0),
+        (write_guest(dir, "exit.wat", exit), 3),
+        (write_guest(dir, "trap.wat", trap), 11),
+        (
+            {
+                let mut cfg = write_guest(dir, "fuel.wat", normal);
+                cfg.limited_fuel(Some(1));
+                cfg
+            },
+            1,
+        ),
+    ];
+    let runtime = BlocklessRuntime::new().unwrap();
+    multi_threads_run(async move {
+        let jobs = (0..48)
+            .map(|i| {
+                let (cfg, code) = &guests[i % guests.len()];
+                (runtime.spawn(cfg.clone()), *code)
+            })
+            .collect::<Vec<_>>();
+        for (job, code) in jobs {
+            let status = job.result().await.unwrap();
+            assert_eq!(status.code, code);
+        }
+    });
+}
+
+#[test]
+fn test_runtime_cancel_job() {
+    let temp_dir = TempDir::new("blockless_runtime").unwrap();
+    let endless = r#"
+    (module
+        (func (export "_start") (loop $l (br $l)))
+        (memory (export "memory") 1)
+    )
+    "#;
+    let cfg = write_guest(temp_dir.path(), "endless.wat", endless);
+    let runtime = BlocklessRuntime::new().unwrap();
+    multi_threads_run(async move {
+        let job = runtime.spawn(cfg);
+        let id = job.id();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(!job.is_finished());
+        job.cancel();
+        let err = job.result().await.err().unwrap();
+        let cancelled = err.downcast_ref::<JobCancelled>().unwrap();
+        assert_eq!(cancelled.0, id);
+    });
+}
+
+#[test]
+fn test_runtime_job_timeout() {
+    let temp_dir = TempDir::new("blockless_runtime").unwrap();
+    let endless = r#"
+    (module
+        (func (export "_start") (loop $l (br $l)))
+        (memory (export "memory") 1)
+    )
+    "#;
+    let mut cfg = write_guest(temp_dir.path(), "endless.wat", endless);
+    cfg.set_run_time(Some(100));
+    let runtime = BlocklessRuntime::new().unwrap();
+    multi_threads_run(async move {
+        let status = runtime.run(cfg).await.unwrap();
+        assert_eq!(status.code, 15);
+    });
+}
+
+#[test]
+fn test_runtime_http_handler() {
+    let temp_dir = TempDir::new("blockless_runtime").unwrap();
+    let server = r#"
+    (module
+        (import "blockless_http_server" "response_write"
+            (func $write (param i32 i32 i32) (result i32)))
+        (func (export "_start") unreachable)
+        (func (export "http_handle") (param $handle i32)
+            (drop (call $write (local.get $handle) (i32.const 0) (i32.const 5))))
+        (memory (export "memory") 1)
+        (data (i32.const 0) "hello")
+    )
+    "#;
+    let addr = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let mut cfg = write_guest(temp_dir.path(), "server.wat", server);
+    cfg.tcp_listens = vec![(addr, None)];
+    cfg.set_http_handler(Some("http_handle".into()));
+    let runtime = BlocklessRuntime::new().unwrap();
+    multi_threads_run(async move {
+        let job = runtime.spawn(cfg);
+        let mut stream = None;
+        for _ in 0..50 {
+            match TcpStream::connect(addr).await {
+                Ok(s) => {
+                    stream = Some(s);
+                    break;
+                }
+                Err(_) => tokio::time::sleep(Duration::from_millis(100)).await,
+            }
+        }
+        let mut stream = stream.unwrap();
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nhost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with("\r\n\r\nhello"));
+        assert!(!job.is_finished());
+        job.cancel();
+        assert!(job.result().await.is_err());
+    });
+}
+
+#[test]
+fn test_runtime_http_handler_timeout() {
+    let temp_dir = TempDir::new("blockless_runtime").unwrap();
+    let server = r#"
+    (module
+        (func (export "_start") unreachable)
+        (func (export "http_handle") (param $handle i32))
+        (memory (export "memory") 1)
+    )
+    "#;
+    let addr = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let mut cfg = write_guest(temp_dir.path(), "server.wat", server);
+    cfg.tcp_listens = vec![(addr, None)];
+    cfg.set_http_handler(Some("http_handle".into()));
+    cfg.set_run_time(Some(200));
+    let runtime = BlocklessRuntime::new().unwrap();
+    multi_threads_run(async move {
+        // the serving waits for the requests and ends at the time limit.
+        let status = runtime.run(cfg).await.unwrap();
+        assert_eq!(status.code, 15);
+    });
+}
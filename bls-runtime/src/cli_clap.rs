@@ -6,16 +6,14 @@ use blockless::{
 };
 use clap::{
     builder::{TypedValueParser, ValueParser},
-    Arg, ArgMatches, Command, Parser,
+    Arg, ArgMatches, Args, Command, Parser, Subcommand,
 };
 use std::{
     collections::HashMap,
     net::{IpAddr, SocketAddr, TcpListener, ToSocketAddrs},
     option,
     path::{Path, PathBuf},
-    str::FromStr,
 };
-use url::Url;
 
 use crate::config::CliConfig;
 
@@ -46,10 +44,17 @@ const ENV_FILE_HELP: &str = "Path to an environment file (.env) to load variable
 
 const OPTS_HELP: &str = "Optimization and tuning related options for wasm performance";
 
-const PERMISSION_HELP: &str = "The permissions for app";
+const PERMISSION_HELP: &str =
+    "The permissions for app, the form is `[!][METHODS ]scheme://host[:port]/path`, e.g. `GET,POST https://*.example.com/api/**`, the leading `!` denies the resource";
 
 const MODULES_HELP: &str = "The modules used by app";
 
+const IPFS_API_HELP: &str =
+    "The ipfs api endpoint used by the ipfs extension, the default is `http://127.0.0.1:5001`.";
+
+const IPFS_API_AUTH_HELP: &str =
+    "The `Authorization` header sent to the ipfs api endpoint, e.g. `Basic dXNlcjpwYXNz`.";
+
 const STDOUT_HELP: &str =
     "The app's stdout setting, which can be configured to one of the following values: inherit, null, or a specific file name.";
 
@@ -62,6 +67,9 @@ const STDIN_HELP: &str =
 const MAP_DIR_HELP: &str =
     "Grant access to a host directory for a guest. If specified as HOST_DIR, the corresponding directory on the host will be made available within the guest.";
 
+const MAP_DIR_READONLY_HELP: &str =
+    "Grant read-only access to a host directory for a guest, the guest can't create, change or remove the files in it.";
+
 const V86_HELP: &str =
     "V86 model flag when the v86 flag the car file must be v86 configure and image.";
 
@@ -70,6 +78,19 @@ const THREAD_SUPPORT_HELP: &str =
 
 const TCP_LISTEN_HELP: &str = "Grant access to the given TCP listen socket. ";
 
+const HTTP_HANDLER_HELP: &str =
+    "Serve HTTP on the TCP listen sockets, each request is handled by the given function exported by the app.";
+
+const MODULE_CACHE_HELP: &str =
+    "The directory of the compiled modules cache, the modules compiled once are loaded from the cache.";
+
+const COMPILE_OUTPUT_HELP: &str =
+    "The output directory of the compiled modules, default is the directory of each module.";
+
+const INVOKE_HELP: &str =
+    "Call the exported function with the app arguments instead of the entry and print the results, \
+the arguments of the component function are WAVE encoded, e.g. `\"hello\"`.";
+
 const UNKNOW_IMPORTS_TRAP_HELP: &str = "Allow the main module to import unknown functions.";
 
 const CLI_EXIT_WITH_CODE_HELP: &str =
@@ -78,6 +99,9 @@ const CLI_EXIT_WITH_CODE_HELP: &str =
 const NETWORK_ERROR_CODE_HELP: &str =
     "Enable WASI APIs marked as: @unstable(feature = network-error-code).";
 
+const REPORT_HELP: &str =
+    "Write the JSON execution report to the file after the run, the report is written to stdout if the value is -.";
+
 const MAX_MEMORY_SIZE_HELP: &str = "The max memory size limited.";
 
 const NN_HELP: &str = "Enable support for WASI neural network imports .";
@@ -142,11 +166,7 @@ fn parse_opts(opt: &str) -> Result<OptimizeOpts> {
 }
 
 fn parse_permission(permsion: &str) -> Result<Permission> {
-    let url = Url::from_str(permsion)?;
-    Ok(Permission {
-        schema: url.scheme().into(),
-        url: permsion.into(),
-    })
+    Permission::parse(permsion)
 }
 
 fn parse_module(module: &str) -> Result<BlocklessModule> {
@@ -216,12 +236,16 @@ pub(crate) const SHORT_VERSION: &str = concat!("v", env!("CARGO_PKG_VERSION"));
 
 #[derive(Parser, Debug)]
 #[command(author, version = SHORT_VERSION, long_version = SHORT_VERSION, about = "Blockless WebAssembly Runtime", long_about = None)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub(crate) struct CliCommandOpts {
+    #[command(subcommand)]
+    command: Option<CliCommand>,
+
     #[clap(long = "v86", value_name = "V86", required = false, help = V86_HELP )]
     v86: bool,
 
     #[clap(value_name = "INPUT", required = true, help = INPUT_HELP )]
-    input: String,
+    input: Option<String>,
 
     #[clap(long = "debug-info", value_name = "DEBUG-INFO", help = DEBUG_INFO_HELP)]
     debug_info: bool,
@@ -238,6 +262,9 @@ pub(crate) struct CliCommandOpts {
     #[arg(long = "dir", value_name = "HOST_DIR[::GUEST_DIR]", help = MAP_DIR_HELP,value_parser = parse_dirs)]
     dirs: Vec<(String, String)>,
 
+    #[arg(long = "dir-readonly", value_name = "HOST_DIR[::GUEST_DIR]", help = MAP_DIR_READONLY_HELP, value_parser = parse_dirs)]
+    readonly_dirs: Vec<(String, String)>,
+
     #[clap(long = "drivers-root-path", value_name = "DRIVERS-ROOT-PATH", help = DRIVERS_ROOT_PATH_HELP)]
     drivers_root_path: Option<String>,
 
@@ -283,6 +310,15 @@ pub(crate) struct CliCommandOpts {
     #[clap(long = "tcplisten", value_name = "TCPLISTEN[::LISTENFD]", help = TCP_LISTEN_HELP, value_parser = parse_listen)]
     tcp_listens: Vec<(SocketAddr, Option<u32>)>,
 
+    #[clap(long = "http-handler", value_name = "HTTP_HANDLER", help = HTTP_HANDLER_HELP)]
+    http_handler: Option<String>,
+
+    #[clap(long = "module-cache", value_name = "MODULE_CACHE", help = MODULE_CACHE_HELP)]
+    module_cache: Option<String>,
+
+    #[clap(long = "invoke", value_name = "INVOKE", help = INVOKE_HELP)]
+    invoke: Option<String>,
+
     #[clap(value_name = "ARGS", help = APP_ARGS_HELP)]
     args: Vec<String>,
 
@@ -303,6 +339,15 @@ pub(crate) struct CliCommandOpts {
 
     #[clap(long = "nn-graph", value_name = "NN_GRAPH", value_parser = parse_nn_graph, help = NN_GRAPH_HELP)]
     nn_graph: Vec<BlsNnGraph>,
+
+    #[clap(long = "ipfs-api", value_name = "IPFS-API", help = IPFS_API_HELP)]
+    ipfs_api: Option<String>,
+
+    #[clap(long = "ipfs-api-auth", value_name = "IPFS-API-AUTH", help = IPFS_API_AUTH_HELP)]
+    ipfs_api_auth: Option<String>,
+
+    #[clap(long = "report", value_name = "REPORT", help = REPORT_HELP)]
+    report: Option<String>,
 }
 
 impl CliCommandOpts {
@@ -322,7 +367,18 @@ impl CliCommandOpts {
 
     #[inline(always)]
     pub fn input_ref(&self) -> &str {
-        &self.input
+        self.input.as_deref().unwrap_or_default()
+    }
+
+    /// the subcommand, the input is not set when the subcommand is present.
+    #[inline(always)]
+    pub fn take_command(&mut self) -> Option<CliCommand> {
+        self.command.take()
+    }
+
+    #[inline(always)]
+    pub fn report_ref(&self) -> Option<&str> {
+        self.report.as_deref()
     }
 
     pub fn into_config(self, conf: &mut CliConfig) -> Result<()> {
@@ -336,6 +392,7 @@ impl CliCommandOpts {
         conf.0.set_run_time(self.run_time);
         conf.0.set_stdin_args(self.args);
         conf.0.set_map_dirs(self.dirs);
+        conf.0.set_readonly_dirs(self.readonly_dirs);
         conf.0.set_feature_thread(self.feature_thread);
         conf.0.limited_memory(self.max_memory_size);
 
@@ -353,8 +410,12 @@ impl CliCommandOpts {
             conf.0.set_permisions(self.permissions);
         }
 
-        // Handle environment variables
-        conf.0.set_envs(envs);
+        // Handle environment variables, the command line overrides the configure.
+        let mut all_envs = conf.0.envs_ref().clone();
+        all_envs.retain(|(k, _)| !envs.iter().any(|(key, _)| key == k));
+        all_envs.extend(envs);
+        all_envs.sort_by(|(a_key, _), (b_key, _)| a_key.cmp(b_key));
+        conf.0.set_envs(all_envs);
 
         conf.0.set_drivers_root_path(self.drivers_root_path);
         let mut modules = self.modules;
@@ -367,7 +428,7 @@ impl CliCommandOpts {
             modules.push(BlocklessModule {
                 module_type: ModuleType::Entry,
                 name: String::new(),
-                file: self.input,
+                file: self.input.unwrap_or_default(),
                 md5: String::new(),
             });
             conf.0.set_modules(modules);
@@ -379,9 +440,25 @@ impl CliCommandOpts {
         }
         conf.0.nn = self.nn;
         conf.0.tcp_listens = self.tcp_listens;
+        if self.http_handler.is_some() {
+            conf.0.set_http_handler(self.http_handler);
+        }
+        if self.module_cache.is_some() {
+            conf.0.set_module_cache(self.module_cache);
+        }
+        if self.invoke.is_some() {
+            conf.0.set_invoke(self.invoke);
+        }
+        conf.0.cli_exit_with_code = self.cli_exit_with_code;
         conf.0.network_error_code = self.network_error_code;
         conf.0.unknown_imports_trap = self.unknown_imports_trap;
         conf.0.nn_graph = self.nn_graph;
+        if let Some(url) = self.ipfs_api {
+            conf.0.ipfs_api.url = url;
+        }
+        if self.ipfs_api_auth.is_some() {
+            conf.0.ipfs_api.auth = self.ipfs_api_auth;
+        }
         Ok(())
     }
 
@@ -420,6 +497,63 @@ impl CliCommandOpts {
     }
 }
 
+/// The subcommands of the runtime, the input is run without the subcommand.
+#[derive(Subcommand, Debug)]
+pub(crate) enum CliCommand {
+    /// Compile the WebAssembly modules ahead of time
+    Compile(CompileCommandOpts),
+}
+
+/// The options of `bls-runtime compile`, the modules are compiled ahead of time with the
+/// engine options which must be the same as the run.
+#[derive(Args, Debug)]
+pub(crate) struct CompileCommandOpts {
+    #[clap(value_name = "INPUT", required = true, help = INPUT_HELP)]
+    input: String,
+
+    #[clap(long = "output", short = 'o', value_name = "OUTPUT", help = COMPILE_OUTPUT_HELP)]
+    output: Option<PathBuf>,
+
+    #[clap(long = "debug-info", value_name = "DEBUG-INFO", help = DEBUG_INFO_HELP)]
+    debug_info: bool,
+
+    #[clap(long = "feature-thread", value_name = "SUPPORT-THREAD", help = THREAD_SUPPORT_HELP)]
+    feature_thread: bool,
+
+    #[clap(long = "run-time", value_name = "RUN-TIME", help = RUN_TIME_HELP)]
+    run_time: Option<u64>,
+
+    #[clap(long = "limited-fuel", value_name = "LIMITED-FUEL", help = LIMITED_FUEL_HELP)]
+    limited_fuel: Option<u64>,
+
+    #[clap(long = "module-cache", value_name = "MODULE_CACHE", help = MODULE_CACHE_HELP)]
+    module_cache: Option<String>,
+}
+
+impl CompileCommandOpts {
+    #[inline(always)]
+    pub fn input_ref(&self) -> &str {
+        &self.input
+    }
+
+    #[inline(always)]
+    pub fn output_ref(&self) -> Option<&Path> {
+        self.output.as_deref()
+    }
+
+    /// the options change the engine configure, they're set as the run does.
+    pub fn into_config(self, conf: &mut CliConfig) {
+        let run_time = conf.0.run_time();
+        conf.0.set_debug_info(self.debug_info);
+        conf.0.limited_fuel(self.limited_fuel);
+        conf.0.set_run_time(self.run_time.or(run_time));
+        conf.0.set_feature_thread(self.feature_thread);
+        if self.module_cache.is_some() {
+            conf.0.set_module_cache(self.module_cache);
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     #[allow(unused)]
@@ -437,7 +571,7 @@ mod test {
     #[test]
     fn test_cli_command() {
         let cli = CliCommandOpts::try_parse_from(["cli", "test", "--", "--test=10"]).unwrap();
-        assert_eq!(cli.input.as_str(), "test");
+        assert_eq!(cli.input_ref(), "test");
         assert_eq!(cli.args.len(), 1);
         assert_eq!(cli.args[0], "--test=10");
     }
@@ -446,12 +580,31 @@ mod test {
     fn test_cli_command_env() {
         let cli = CliCommandOpts::try_parse_from(["cli", "test", "--env", "a=1", "--env", "b=2"])
             .unwrap();
-        assert_eq!(cli.input.as_str(), "test");
+        assert_eq!(cli.input_ref(), "test");
         assert_eq!(cli.envs.len(), 2);
         assert_eq!(cli.envs[0], ("a".to_string(), "1".to_string()));
         assert_eq!(cli.envs[1], ("b".to_string(), "2".to_string()));
     }
 
+    #[test]
+    fn test_cli_command_env_override_config() {
+        let cli = CliCommandOpts::try_parse_from(["cli", "test", "--env", "a=1", "--env", "c=3"])
+            .unwrap();
+        let mut config = CliConfig(BlocklessConfig::new("test"));
+        config
+            .0
+            .set_envs(vec![("a".into(), "0".into()), ("b".into(), "2".into())]);
+        cli.into_config(&mut config).unwrap();
+        assert_eq!(
+            config.0.envs_ref(),
+            &vec![
+                ("a".to_string(), "1".to_string()),
+                ("b".to_string(), "2".to_string()),
+                ("c".to_string(), "3".to_string()),
+            ]
+        );
+    }
+
     #[test]
     fn test_cli_command_env_file_loading_and_sorting() -> Result<()> {
         // Create temp env file with variables including interpolation
@@ -507,13 +660,134 @@ SERVICE_URL=${{BASE_URL}}/v1"#
             "http://www.google.com",
         ])
         .unwrap();
-        assert_eq!(cli.input.as_str(), "test");
+        assert_eq!(cli.input_ref(), "test");
         assert_eq!(cli.permissions.len(), 1);
-        let perm = Permission {
-            schema: "http".to_string(),
-            url: "http://www.google.com".to_string(),
-        };
+        let perm = Permission::new("http://www.google.com").unwrap();
         assert_eq!(cli.permissions[0], perm);
+        assert_eq!(cli.permissions[0].schema, "http");
+    }
+
+    #[test]
+    fn test_cli_command_permisson_rules() {
+        let cli = CliCommandOpts::try_parse_from([
+            "cli",
+            "test",
+            "--permission",
+            "GET,POST https://*.example.com:443/api/**",
+            "--permission",
+            "!https://admin.example.com",
+        ])
+        .unwrap();
+        assert_eq!(cli.permissions.len(), 2);
+        assert_eq!(cli.permissions[0].methods, vec!["GET", "POST"]);
+        assert!(!cli.permissions[0].deny);
+        assert!(cli.permissions[1].deny);
+        assert!(CliCommandOpts::try_parse_from(["cli", "test", "--permission", "google"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_command_ipfs_api() {
+        let cli = CliCommandOpts::try_parse_from([
+            "cli",
+            "test",
+            "--ipfs-api",
+            "http://10.0.0.1:5001",
+            "--ipfs-api-auth",
+            "Basic dGVzdA==",
+        ])
+        .unwrap();
+        let mut config = CliConfig(BlocklessConfig::new("test"));
+        config.0.ipfs_api.auth = Some("Bearer old".into());
+        cli.into_config(&mut config).unwrap();
+        assert_eq!(config.0.ipfs_api_ref().url, "http://10.0.0.1:5001");
+        assert_eq!(
+            config.0.ipfs_api_ref().auth.as_deref(),
+            Some("Basic dGVzdA==")
+        );
+    }
+
+    #[test]
+    fn test_cli_command_http_handler() {
+        let cli = CliCommandOpts::try_parse_from([
+            "cli",
+            "test",
+            "--tcplisten",
+            "127.0.0.1:8080",
+            "--http-handler",
+            "http_handle",
+        ])
+        .unwrap();
+        let mut config = CliConfig(BlocklessConfig::new("test"));
+        cli.into_config(&mut config).unwrap();
+        assert_eq!(config.0.http_handler_ref(), Some("http_handle"));
+        assert_eq!(config.0.tcp_listens.len(), 1);
+    }
+
+    #[test]
+    fn test_cli_command_readonly_dirs() {
+        let cli = CliCommandOpts::try_parse_from([
+            "cli",
+            "test",
+            "--dir",
+            "/tmp/data::/data",
+            "--dir-readonly",
+            "/tmp/assets::/assets",
+            "--cli_exit_with_code",
+        ])
+        .unwrap();
+        let mut config = CliConfig(BlocklessConfig::new("test"));
+        cli.into_config(&mut config).unwrap();
+        assert_eq!(config.0.dirs, vec![("/tmp/data".into(), "/data".into())]);
+        assert_eq!(
+            config.0.readonly_dirs,
+            vec![("/tmp/assets".into(), "/assets".into())]
+        );
+        assert!(config.0.cli_exit_with_code);
+    }
+
+    #[test]
+    fn test_compile_command() {
+        let mut cli = CliCommandOpts::try_parse_from([
+            "cli",
+            "compile",
+            "app.wasm",
+            "-o",
+            "out",
+            "--limited-fuel",
+            "100",
+            "--module-cache",
+            "/tmp/bls-cache",
+        ])
+        .unwrap();
+        let Some(CliCommand::Compile(opts)) = cli.take_command() else {
+            panic!("the compile command is not parsed");
+        };
+        assert_eq!(opts.input_ref(), "app.wasm");
+        assert_eq!(opts.output_ref(), Some(Path::new("out")));
+        let mut config = CliConfig(BlocklessConfig::new("app.wasm"));
+        opts.into_config(&mut config);
+        assert_eq!(config.0.get_limited_fuel(), Some(100));
+        assert_eq!(config.0.module_cache_ref(), Some("/tmp/bls-cache"));
+
+        // the `compile` after the input is the argument of the app.
+        let mut cli = CliCommandOpts::try_parse_from(["cli", "app.wasm", "compile"]).unwrap();
+        assert!(cli.take_command().is_none());
+        assert_eq!(cli.input_ref(), "app.wasm");
+        assert_eq!(cli.args, vec!["compile".to_string()]);
+        assert!(CliCommandOpts::try_parse_from(["cli", "compile"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_command_invoke() {
+        let cli = CliCommandOpts::try_parse_from(["cli", "test.wasm", "--invoke", "add", "1", "2"])
+            .unwrap();
+        let mut config = CliConfig(BlocklessConfig::new("test.wasm"));
+        cli.into_config(&mut config).unwrap();
+        assert_eq!(config.0.invoke_ref(), Some("add"));
+        assert_eq!(
+            config.0.stdin_args_ref(),
+            &vec!["1".to_string(), "2".to_string()]
+        );
     }
 
     #[test]
@@ -524,7 +798,7 @@ SERVICE_URL=${{BASE_URL}}/v1"#
             .map(str::to_string)
             .collect::<Vec<String>>();
         let cli_opts = CliCommandOpts::try_parse_from(command_line).unwrap();
-        let pat = cli_opts.input.as_str();
+        let pat = cli_opts.input_ref();
         assert_eq!(pat, "test.wasm");
     }
 
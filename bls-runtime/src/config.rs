@@ -2,10 +2,14 @@ use anyhow::{bail, Context, Result};
 use blockless::{
     self, BlocklessModule, LoggerLevel, ModuleType, OptimizeOpts, Stderr, Stdin, Stdio, Stdout,
 };
-use blockless::{BlocklessConfig, DriverConfig, MultiAddr, Permission};
+use blockless::{
+    BlocklessConfig, CredentialKind, CredentialProfile, DriverConfig, HttpConfig, IpfsApi,
+    KvConfig, Permission, SecretSource,
+};
 use json::{self, JsonValue};
 use rust_car::reader::{self, CarReader};
 use rust_car::utils::{extract_ipld, ipld_write};
+use std::collections::HashMap;
 use std::env::VarError;
 use std::ffi::OsStr;
 use std::fs::{self, File};
@@ -69,8 +73,13 @@ impl CliConfig {
         Ok(opts)
     }
 
-    fn map_dirs(map_dir: &JsonValue) -> anyhow::Result<Vec<(String, String)>> {
+    /// the map dirs, e.g. `[{"host": "/data", "guest": "/data", "readonly": true}]`,
+    /// the read-only dirs are returned as the second.
+    fn map_dirs(
+        map_dir: &JsonValue,
+    ) -> anyhow::Result<(Vec<(String, String)>, Vec<(String, String)>)> {
         let mut ret = Vec::new();
+        let mut readonly = Vec::new();
         if !map_dir.is_null() {
             if !map_dir.is_array() {
                 bail!("the map dir item should be array.");
@@ -84,46 +93,200 @@ impl CliConfig {
                     .as_str()
                     .context("guest item is not define.")?
                     .to_string();
-                ret.push((host, guest));
+                if obj["readonly"].as_bool().unwrap_or(false) {
+                    readonly.push((host, guest));
+                } else {
+                    ret.push((host, guest));
+                }
+            }
+        }
+        Ok((ret, readonly))
+    }
+
+    /// the envs is the object of the env name and value, e.g. `{"MODE": "prod"}`.
+    fn envs(envs_json: &JsonValue) -> anyhow::Result<Vec<(String, String)>> {
+        let mut ret = Vec::new();
+        if !envs_json.is_null() {
+            if !envs_json.is_object() {
+                bail!("the envs item should be object.");
+            }
+            for (name, value) in envs_json.entries() {
+                let value = value
+                    .as_str()
+                    .with_context(|| format!("the env {name} value should be string."))?;
+                ret.push((name.to_string(), value.to_string()));
             }
         }
+        ret.sort_by(|(a_key, _), (b_key, _)| a_key.cmp(b_key));
         Ok(ret)
     }
 
+    /// the host env names passed through to the guest, e.g. `["HOME", "LANG"]`.
+    fn env_passthrough(names_json: &JsonValue) -> anyhow::Result<Vec<String>> {
+        if names_json.is_null() {
+            return Ok(Vec::new());
+        }
+        if !names_json.is_array() {
+            bail!("the env_passthrough item should be array.");
+        }
+        names_json
+            .members()
+            .map(|n| {
+                n.as_str()
+                    .map(String::from)
+                    .context("the env_passthrough item should be string.")
+            })
+            .collect()
+    }
+
+    /// the permission is the string, e.g. `GET https://*.example.com/api/**`, or the object,
+    /// e.g. `{"url": "https://*.example.com/api/**", "methods": ["GET"], "deny": false}`.
+    fn permission(permission_json: &JsonValue) -> Option<Permission> {
+        if let Some(p) = permission_json.as_str() {
+            return Permission::parse(p).ok();
+        }
+        let url = permission_json["url"].as_str()?;
+        let mut perm = Permission::new(url).ok()?;
+        if let JsonValue::Array(ref methods) = permission_json["methods"] {
+            perm = perm.with_methods(methods.iter().filter_map(|m| m.as_str()));
+        }
+        Some(perm.with_deny(permission_json["deny"].as_bool().unwrap_or(false)))
+    }
+
     fn permissions(permission_json: &JsonValue) -> Vec<Permission> {
         match *permission_json {
-            JsonValue::Array(ref perms) => perms
-                .iter()
-                .filter_map(|p| {
-                    let p = p.as_str();
-                    match p {
-                        Some(p) => {
-                            let bs = p.as_bytes();
-                            let addr = MultiAddr::parse(bs);
-                            let addr = if addr.is_ok() {
-                                addr.unwrap()
-                            } else {
-                                return None;
-                            };
-                            let schema = addr.schema();
-                            let schema = if schema.is_ok() {
-                                schema.unwrap()
-                            } else {
-                                return None;
-                            };
-                            Some(Permission {
-                                schema: schema.into(),
-                                url: p.into(),
-                            })
-                        }
-                        None => None,
-                    }
-                })
-                .collect(),
+            JsonValue::Array(ref perms) => perms.iter().filter_map(Self::permission).collect(),
             _ => Vec::new(),
         }
     }
 
+    /// the ipfs api is the endpoint string, e.g. `http://127.0.0.1:5001`, or the object,
+    /// e.g. `{"url": "https://ipfs.example.com", "auth": "Basic dXNlcjpwYXNz"}`.
+    fn ipfs_api(ipfs_api_json: &JsonValue) -> Option<IpfsApi> {
+        if let Some(url) = ipfs_api_json.as_str() {
+            return Some(IpfsApi {
+                url: url.into(),
+                auth: None,
+            });
+        }
+        let url = ipfs_api_json["url"].as_str()?;
+        Some(IpfsApi {
+            url: url.into(),
+            auth: ipfs_api_json["auth"].as_str().map(String::from),
+        })
+    }
+
+    /// the secret is read from the host env, e.g. `{"env": "S3_SECRET"}`,
+    /// or the host file, e.g. `{"file": "/run/secrets/s3"}`.
+    fn secret(secret_json: &JsonValue, field: &str) -> Result<SecretSource> {
+        if let Some(env) = secret_json["env"].as_str() {
+            return Ok(SecretSource::Env(env.into()));
+        }
+        if let Some(file) = secret_json["file"].as_str() {
+            return Ok(SecretSource::File(file.into()));
+        }
+        bail!("the {field} should be the env or file source.")
+    }
+
+    /// the named credential profiles, e.g.
+    /// `{"s3-prod": {"type": "s3", "access_key": {"env": "S3_KEY"}, "secret_key": {"file": "/run/s3"}}}`,
+    /// the types are `s3`, `bearer` with the `token` and `basic` with the `username` and `password`,
+    /// the `urls` are the urls the credential is sent to.
+    fn credentials(credentials_json: &JsonValue) -> Result<HashMap<String, CredentialProfile>> {
+        let mut ret = HashMap::new();
+        if credentials_json.is_null() {
+            return Ok(ret);
+        }
+        if !credentials_json.is_object() {
+            bail!("the credentials item should be object.");
+        }
+        for (name, profile) in credentials_json.entries() {
+            let kind = match profile["type"].as_str() {
+                Some("s3") => CredentialKind::S3 {
+                    access_key: Self::secret(&profile["access_key"], "access_key")?,
+                    secret_key: Self::secret(&profile["secret_key"], "secret_key")?,
+                    session_token: match profile["session_token"] {
+                        JsonValue::Null => None,
+                        ref token => Some(Self::secret(token, "session_token")?),
+                    },
+                },
+                Some("bearer") => CredentialKind::Bearer {
+                    token: Self::secret(&profile["token"], "token")?,
+                },
+                Some("basic") => CredentialKind::Basic {
+                    username: profile["username"]
+                        .as_str()
+                        .context("username item is not define.")?
+                        .to_string(),
+                    password: Self::secret(&profile["password"], "password")?,
+                },
+                _ => bail!("the type of the credential profile {name} is unknown."),
+            };
+            // the credential is only sent to the urls of the profile.
+            let urls = match profile["urls"] {
+                JsonValue::Array(ref urls) if !urls.is_empty() => urls
+                    .iter()
+                    .map(|url| {
+                        let url = url.as_str().context("the url of the profile is string.")?;
+                        Permission::parse(url)
+                    })
+                    .collect::<Result<Vec<_>>>()?,
+                _ => bail!("the urls of the credential profile {name} are not define."),
+            };
+            ret.insert(name.to_string(), CredentialProfile { kind, urls });
+        }
+        Ok(ret)
+    }
+
+    /// the kv store, e.g. `{"path": "/var/lib/bls/kv.db", "app": "my-app", "quota": 1048576}`,
+    /// the store is in memory without the path.
+    fn kv(kv_json: &JsonValue) -> Result<KvConfig> {
+        if kv_json.is_null() {
+            return Ok(KvConfig::default());
+        }
+        if !kv_json.is_object() {
+            bail!("the kv item should be object.");
+        }
+        let number = |field: &str| match kv_json[field] {
+            JsonValue::Null => Ok(None),
+            ref n => n
+                .as_u64()
+                .map(Some)
+                .with_context(|| format!("the kv {field} should be number.")),
+        };
+        Ok(KvConfig {
+            path: kv_json["path"].as_str().map(String::from),
+            app: kv_json["app"].as_str().map(String::from),
+            quota: number("quota")?,
+            max_value_size: number("max_value_size")?,
+        })
+    }
+
+    /// the limits of the outgoing http requests of the components,
+    /// e.g. `{"connect_timeout": 5000, "max_response_body": 1048576}`.
+    fn http(http_json: &JsonValue) -> Result<HttpConfig> {
+        if http_json.is_null() {
+            return Ok(HttpConfig::default());
+        }
+        if !http_json.is_object() {
+            bail!("the http item should be object.");
+        }
+        let number = |field: &str| match http_json[field] {
+            JsonValue::Null => Ok(None),
+            ref n => n
+                .as_u64()
+                .map(Some)
+                .with_context(|| format!("the http {field} should be number.")),
+        };
+        Ok(HttpConfig {
+            connect_timeout: number("connect_timeout")?,
+            first_byte_timeout: number("first_byte_timeout")?,
+            between_bytes_timeout: number("between_bytes_timeout")?,
+            max_request_body: number("max_request_body")?,
+            max_response_body: number("max_response_body")?,
+        })
+    }
+
     fn drivers(driver_json: &JsonValue) -> Vec<DriverConfig> {
         match *driver_json {
             JsonValue::Array(ref drvs_cfg) => {
@@ -180,6 +343,10 @@ impl CliConfig {
         let limited_memory: Option<u64> = json_obj["limited_memory"].as_u64();
         let extensions_path: Option<String> =
             json_obj["extensions_path"].as_str().map(String::from);
+        let tls_ca_file: Option<String> = json_obj["tls_ca_file"].as_str().map(String::from);
+        let http_handler: Option<String> = json_obj["http_handler"].as_str().map(String::from);
+        let module_cache: Option<String> = json_obj["module_cache"].as_str().map(String::from);
+        let invoke: Option<String> = json_obj["invoke"].as_str().map(String::from);
         let stdin: Option<&str> = json_obj["stdin"].as_str();
         let stdout: Option<&str> = json_obj["stdout"].as_str();
         let stderr: Option<&str> = json_obj["stderr"].as_str();
@@ -191,13 +358,19 @@ impl CliConfig {
         let perms: Vec<Permission> = Self::permissions(&json_obj["permissions"]);
         let entry: &str = json_obj["entry"].as_str().unwrap();
         let version = json_obj["version"].as_usize();
-        let dirs = Self::map_dirs(&json_obj["map_dirs"])?;
+        let (dirs, readonly_dirs) = Self::map_dirs(&json_obj["map_dirs"])?;
+        let credentials = Self::credentials(&json_obj["credentials"])?;
+        let envs = Self::envs(&json_obj["envs"])?;
+        let kv = Self::kv(&json_obj["kv"])?;
+        let http = Self::http(&json_obj["http"])?;
+        let env_passthrough = Self::env_passthrough(&json_obj["env_passthrough"])?;
         let mut bc = BlocklessConfig::new(entry);
         //if has the optimize item.
         if json_obj["optimize"].is_object() {
             bc.opts = Self::optimize_options(&json_obj["optimize"])?;
         }
         bc.set_map_dirs(dirs);
+        bc.set_readonly_dirs(readonly_dirs);
         bc.set_modules(modules);
         bc.extensions_path(extensions_path);
         bc.set_fs_root_path(fs_root_path);
@@ -211,6 +384,16 @@ impl CliConfig {
         bc.limited_fuel(limited_fuel);
         bc.limited_memory(limited_memory);
         bc.set_run_time(run_time);
+        Self::ipfs_api(&json_obj["ipfs_api"]).map(|api| bc.set_ipfs_api(api));
+        bc.set_credentials(credentials);
+        bc.set_kv(kv);
+        bc.set_http(http);
+        bc.set_tls_ca_file(tls_ca_file);
+        bc.set_http_handler(http_handler);
+        bc.set_module_cache(module_cache);
+        bc.set_invoke(invoke);
+        bc.set_envs(envs);
+        bc.set_env_passthrough(env_passthrough);
         version.map(|v| bc.set_version(v.into()));
         let stdin = match stdin {
             Some(s) => {
@@ -439,6 +622,246 @@ mod test {
         assert_eq!(config.0.get_limited_fuel(), Some(200000000));
     }
 
+    #[test]
+    fn test_from_json_permissions() {
+        let data = r#"{
+            "entry": "lib.wasm",
+            "permissions": [
+                "http://httpbin.org/anything",
+                "GET,POST https://*.example.com/api/**",
+                {"url": "https://admin.example.com", "deny": true},
+                {"url": "http://httpbin.org/post", "methods": ["post"]},
+                "invalid"
+            ]
+        }"#
+        .to_string();
+        let config = CliConfig::from_json_string(data).unwrap();
+        let perms = config.0.permisions_ref();
+        assert_eq!(perms.len(), 4);
+        assert_eq!(perms[1].methods, vec!["GET", "POST"]);
+        assert!(perms[2].deny);
+        assert_eq!(perms[3].methods, vec!["POST"]);
+        assert!(config
+            .0
+            .method_permission("https://api.example.com/api/v1", Some("GET")));
+        assert!(!config
+            .0
+            .method_permission("https://api.example.com/api/v1", Some("PUT")));
+    }
+
+    #[test]
+    fn test_from_json_ipfs_api() {
+        let config = CliConfig::from_json_string(r#"{"entry": "lib.wasm"}"#.into()).unwrap();
+        assert_eq!(config.0.ipfs_api_ref(), &IpfsApi::default());
+
+        let data = r#"{"entry": "lib.wasm", "ipfs_api": "http://10.0.0.1:5001"}"#;
+        let config = CliConfig::from_json_string(data.into()).unwrap();
+        assert_eq!(config.0.ipfs_api_ref().url, "http://10.0.0.1:5001");
+        assert_eq!(config.0.ipfs_api_ref().auth, None);
+
+        let data = r#"{
+            "entry": "lib.wasm",
+            "ipfs_api": {"url": "https://ipfs.example.com", "auth": "Basic dGVzdA=="}
+        }"#;
+        let config = CliConfig::from_json_string(data.into()).unwrap();
+        assert_eq!(config.0.ipfs_api_ref().url, "https://ipfs.example.com");
+        assert_eq!(
+            config.0.ipfs_api_ref().auth.as_deref(),
+            Some("Basic dGVzdA==")
+        );
+    }
+
+    #[test]
+    fn test_from_json_credentials() {
+        let data = r#"{
+            "entry": "lib.wasm",
+            "credentials": {
+                "s3-prod": {
+                    "type": "s3",
+                    "access_key": {"env": "S3_KEY"},
+                    "secret_key": {"file": "/run/secrets/s3"},
+                    "urls": ["https://s3.amazonaws.com/prod"]
+                },
+                "api-token": {
+                    "type": "bearer",
+                    "token": {"env": "API_TOKEN"},
+                    "urls": ["https://api.example.com/**"]
+                },
+                "basic": {
+                    "type": "basic",
+                    "username": "user",
+                    "password": {"env": "PASS"},
+                    "urls": ["https://example.com"]
+                }
+            },
+            "permissions": ["cred://s3-prod"]
+        }"#;
+        let config = CliConfig::from_json_string(data.into()).unwrap();
+        let credentials = config.0.credentials_ref();
+        assert_eq!(credentials.len(), 3);
+        assert_eq!(
+            credentials["s3-prod"].kind,
+            CredentialKind::S3 {
+                access_key: SecretSource::Env("S3_KEY".into()),
+                secret_key: SecretSource::File("/run/secrets/s3".into()),
+                session_token: None,
+            }
+        );
+        assert_eq!(
+            credentials["api-token"].kind,
+            CredentialKind::Bearer {
+                token: SecretSource::Env("API_TOKEN".into()),
+            }
+        );
+        assert!(credentials["api-token"].allows("https://api.example.com/v1"));
+        assert!(!credentials["api-token"].allows("https://example.com/v1"));
+        assert!(config.0.schema_permission("cred", "cred://s3-prod"));
+        assert!(!config.0.schema_permission("cred", "cred://api-token"));
+
+        // the secret can't be written in the manifest.
+        let data = r#"{
+            "entry": "lib.wasm",
+            "credentials": {"api-token": {"type": "bearer", "token": "secret"}}
+        }"#;
+        assert!(CliConfig::from_json_string(data.into()).is_err());
+        let data = r#"{
+            "entry": "lib.wasm",
+            "credentials": {"api-token": {"type": "oauth", "token": {"env": "TOKEN"}}}
+        }"#;
+        assert!(CliConfig::from_json_string(data.into()).is_err());
+        // the profile must have the urls.
+        let data = r#"{
+            "entry": "lib.wasm",
+            "credentials": {"api-token": {"type": "bearer", "token": {"env": "TOKEN"}}}
+        }"#;
+        assert!(CliConfig::from_json_string(data.into()).is_err());
+    }
+
+    #[test]
+    fn test_from_json_envs() {
+        let data = r#"{
+            "entry": "lib.wasm",
+            "envs": {"MODE": "prod", "A": "1"},
+            "env_passthrough": ["HOME"]
+        }"#;
+        let config = CliConfig::from_json_string(data.into()).unwrap();
+        assert_eq!(
+            config.0.envs_ref(),
+            &vec![("A".into(), "1".into()), ("MODE".into(), "prod".into())]
+        );
+        assert_eq!(config.0.env_passthrough_ref(), &["HOME".to_string()]);
+
+        let data = r#"{"entry": "lib.wasm", "envs": {"MODE": 1}}"#;
+        assert!(CliConfig::from_json_string(data.into()).is_err());
+        let data = r#"{"entry": "lib.wasm", "env_passthrough": "HOME"}"#;
+        assert!(CliConfig::from_json_string(data.into()).is_err());
+    }
+
+    #[test]
+    fn test_from_json_map_dirs() {
+        let data = r#"{
+            "entry": "lib.wasm",
+            "map_dirs": [
+                {"host": "/tmp/data", "guest": "/data"},
+                {"host": "/tmp/assets", "guest": "/assets", "readonly": true}
+            ]
+        }"#;
+        let config = CliConfig::from_json_string(data.into()).unwrap();
+        assert_eq!(config.0.dirs, vec![("/tmp/data".into(), "/data".into())]);
+        assert_eq!(
+            config.0.readonly_dirs,
+            vec![("/tmp/assets".into(), "/assets".into())]
+        );
+    }
+
+    #[test]
+    fn test_from_json_kv() {
+        let config = CliConfig::from_json_string(r#"{"entry": "lib.wasm"}"#.into()).unwrap();
+        assert_eq!(config.0.kv_ref(), &KvConfig::default());
+        assert_eq!(config.0.kv_app(), None);
+
+        let data = r#"{
+            "entry": "lib.wasm",
+            "kv": {"path": "/tmp/kv.db", "app": "my-app", "quota": 1024},
+            "permissions": ["kv://cache"]
+        }"#;
+        let config = CliConfig::from_json_string(data.into()).unwrap();
+        assert_eq!(
+            config.0.kv_ref(),
+            &KvConfig {
+                path: Some("/tmp/kv.db".into()),
+                app: Some("my-app".into()),
+                quota: Some(1024),
+                max_value_size: None,
+            }
+        );
+        assert_eq!(config.0.kv_app(), Some("my-app"));
+        assert!(config.0.schema_permission("kv", "kv://cache"));
+        assert!(!config.0.schema_permission("kv", "kv://other"));
+
+        let data = r#"{"entry": "lib.wasm", "kv": {"quota": "1k"}}"#;
+        assert!(CliConfig::from_json_string(data.into()).is_err());
+    }
+
+    #[test]
+    fn test_from_json_http() {
+        let config = CliConfig::from_json_string(r#"{"entry": "lib.wasm"}"#.into()).unwrap();
+        assert_eq!(config.0.http_ref(), &HttpConfig::default());
+
+        let data = r#"{
+            "entry": "lib.wasm",
+            "http": {"connect_timeout": 5000, "max_response_body": 1024}
+        }"#;
+        let config = CliConfig::from_json_string(data.into()).unwrap();
+        assert_eq!(
+            config.0.http_ref(),
+            &HttpConfig {
+                connect_timeout: Some(5000),
+                max_response_body: Some(1024),
+                ..Default::default()
+            }
+        );
+
+        let data = r#"{"entry": "lib.wasm", "http": {"max_request_body": "1k"}}"#;
+        assert!(CliConfig::from_json_string(data.into()).is_err());
+    }
+
+    #[test]
+    fn test_from_json_tls_ca_file() {
+        let config = CliConfig::from_json_string(r#"{"entry": "lib.wasm"}"#.into()).unwrap();
+        assert_eq!(config.0.tls_ca_file_ref(), None);
+        let data = r#"{"entry": "lib.wasm", "tls_ca_file": "/etc/bls/ca.pem"}"#;
+        let config = CliConfig::from_json_string(data.into()).unwrap();
+        assert_eq!(config.0.tls_ca_file_ref(), Some("/etc/bls/ca.pem"));
+    }
+
+    #[test]
+    fn test_from_json_http_handler() {
+        let config = CliConfig::from_json_string(r#"{"entry": "lib.wasm"}"#.into()).unwrap();
+        assert_eq!(config.0.http_handler_ref(), None);
+        let data = r#"{"entry": "lib.wasm", "http_handler": "http_handle"}"#;
+        let config = CliConfig::from_json_string(data.into()).unwrap();
+        assert_eq!(config.0.http_handler_ref(), Some("http_handle"));
+    }
+
+    #[test]
+    fn test_from_json_module_cache() {
+        let config = CliConfig::from_json_string(r#"{"entry": "lib.wasm"}"#.into()).unwrap();
+        assert_eq!(config.0.module_cache_ref(), None);
+        let data = r#"{"entry": "lib.wasm", "module_cache": "/var/cache/bls"}"#;
+        let config = CliConfig::from_json_string(data.into()).unwrap();
+        assert_eq!(config.0.module_cache_ref(), Some("/var/cache/bls"));
+    }
+
+    #[test]
+    fn test_from_json_invoke() {
+        let config = CliConfig::from_json_string(r#"{"entry": "lib.wasm"}"#.into()).unwrap();
+        assert_eq!(config.0.invoke_ref(), None);
+        let data = r#"{"entry": "lib.wasm", "invoke": "add"}"#;
+        let config = CliConfig::from_json_string(data.into()).unwrap();
+        assert_eq!(config.0.invoke_ref(), Some("add"));
+    }
+
     #[test]
     fn test_stdin_from_json() {
         let bls_config = CliConfig::from_json_string(
@@ -24,6 +24,31 @@ pub enum CliExitCode {
     UnknownError(String),
 }
 
+impl CliExitCode {
+    /// the name of the exit code, it's used in the execution report.
+    pub fn name(&self) -> &'static str {
+        match self {
+            CliExitCode::Success => "Success",
+            CliExitCode::FlueUsedOut => "FlueUsedOut",
+            CliExitCode::CallStackExhausted => "CallStackExhausted",
+            CliExitCode::OutOfBoundsMemoryAccess => "OutOfBoundsMemoryAccess",
+            CliExitCode::MisalignedMemoryAccess => "MisalignedMemoryAccess",
+            CliExitCode::UndefinedElement => "UndefinedElement",
+            CliExitCode::UninitializedElement => "UninitializedElement",
+            CliExitCode::IndirectCallTypeMismatch => "IndirectCallTypeMismatch",
+            CliExitCode::IntegerOverflow => "IntegerOverflow",
+            CliExitCode::IntegerDivideByZero => "IntegerDivideByZero",
+            CliExitCode::InvalidConversionToInteger => "InvalidConversionToInteger",
+            CliExitCode::UnreachableInstructionExecuted => "UnreachableInstructionExecuted",
+            CliExitCode::Interrupt => "Interrupt",
+            CliExitCode::DegenerateComponentAdapterCalled => "DegenerateComponentAdapterCalled",
+            CliExitCode::AppTimeout => "AppTimeout",
+            CliExitCode::ConfigureError => "ConfigureError",
+            CliExitCode::UnknownError(_) => "UnknownError",
+        }
+    }
+}
+
 impl fmt::Display for CliExitCode {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
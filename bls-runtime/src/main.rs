@@ -4,11 +4,12 @@ mod cli_clap;
 mod config;
 mod error;
 mod plog;
+mod report;
 mod v86;
 mod v86config;
-use blockless::{blockless_run, LoggerLevel, Stdin};
+use blockless::{blockless_compile, blockless_run, stdin_stream, LoggerLevel};
 use clap::Parser;
-use cli_clap::{CliCommandOpts, RuntimeType};
+use cli_clap::{CliCommand, CliCommandOpts, CompileCommandOpts, RuntimeType};
 use config::load_cli_config_extract_from_car;
 #[allow(unused_imports)]
 use config::CliConfig;
@@ -16,8 +17,9 @@ use env_logger::Target;
 use error::CliExitCode;
 use log::{error, info, LevelFilter};
 use std::fs;
+use std::io::{IsTerminal, Read};
 use std::path::Path;
-use std::{io::Read, path::PathBuf, time::Duration};
+use std::{path::PathBuf, time::Duration};
 use v86::V86Lib;
 use v86config::load_v86conf_extract_from_car;
 
@@ -124,7 +126,7 @@ fn load_cli_config(file_path: &str) -> Result<CliConfig, CliExitCode> {
                 })?;
             Some(load_cli_config_extract_from_car(file))
         }
-        Some(ext) if ext == "wasm" || ext == "wasi" || ext == "wat" => {
+        Some(ext) if ext == "wasm" || ext == "wasi" || ext == "wat" || ext == "cwasm" => {
             Some(Ok(CliConfig::new_with_wasm(file_path)))
         }
         _ => None,
@@ -160,20 +162,25 @@ async fn wasm_runtime(mut cfg: CliConfig, cli_command_opts: CliCommandOpts) -> C
     }
 
     let run_time = cfg.0.run_time();
+    let report_path = cli_command_opts.report_ref().map(String::from);
     cli_command_opts.into_config(&mut cfg).unwrap();
-    if cfg.0.is_fixed_stdin() {
-        if let Some(stdin_buffer) = non_blocking_read(std::io::stdin()).await {
-            cfg.0.stdio.stdin = Stdin::Fixed(stdin_buffer);
-        }
+    // the piped stdin is streamed to the guest, the fixed stdin of the configure is kept.
+    if cfg.0.fix_stdin_ref().is_some_and(str::is_empty) && !std::io::stdin().is_terminal() {
+        cfg.0.stdio.stdin = stdin_stream(tokio::io::stdin());
     }
 
-    if let Some(time) = run_time {
-        let _ = tokio::spawn(async move {
+    // keep the run time of the configure file if it's not set in the command line.
+    if cfg.0.run_time().is_none() {
+        cfg.0.set_run_time(run_time);
+    }
+    // the run time limit is enforced by the epoch interruption in the runner, but the
+    // threads can't be interrupted, so exit the process when the time is up.
+    if let Some(time) = cfg.0.time_limit().filter(|_| cfg.0.feature_thread()) {
+        tokio::spawn(async move {
             tokio::time::sleep(Duration::from_millis(time)).await;
             info!("The wasm execute finish, the exit code: 15");
             std::process::exit(CliExitCode::AppTimeout.into());
-        })
-        .await;
+        });
     }
 
     info!("The wasm app started.");
@@ -182,39 +189,100 @@ async fn wasm_runtime(mut cfg: CliConfig, cli_command_opts: CliCommandOpts) -> C
         perror!("WASM app crashed, please check the runtime.log file");
     }));
 
+    let modules = cfg.0.modules_ref().into_iter().cloned().collect::<Vec<_>>();
     let exit_status = blockless_run(cfg.0).await.unwrap();
     info!(
         "The wasm execute finish, the exit code: {}",
         exit_status.code
     );
+    // the results of the invoked function.
+    for result in exit_status.results.iter() {
+        println!("{result}");
+    }
+    if let Some(path) = report_path {
+        let report = report::build_report(&exit_status, &modules);
+        if let Err(e) = report::write_report(&path, &report) {
+            error!("failed to write the report {}: {}", path, e);
+        }
+    }
+    // the guest exit code may be out of the cli exit codes.
+    if exit_status.exited {
+        std::process::exit(exit_status.code);
+    }
     exit_status.code.into()
 }
 
-fn set_root_path_env_var(cli_command_opts: &CliCommandOpts) {
-    cli_command_opts
-        .fs_root_path()
-        .map(|s| std::env::set_var(ENV_ROOT_PATH_NAME, s.as_str()));
+/// the path of the compiled module, it's the module file with the `cwasm` extension
+/// in the output directory or the directory of the module.
+fn compiled_path(file: &str, output: Option<&Path>) -> PathBuf {
+    let path = Path::new(file).with_extension("cwasm");
+    match (output, path.file_name()) {
+        (Some(dir), Some(name)) => dir.join(name),
+        _ => path,
+    }
 }
 
-async fn non_blocking_read<R: Read + Send + 'static>(mut reader: R) -> Option<String> {
-    let (tx, rx) = std::sync::mpsc::channel();
-
-    // spawn thread to read from the reader asynchronously
-    std::thread::spawn(move || {
-        let mut buffer = String::new();
-        if reader.read_to_string(&mut buffer).is_ok() && !buffer.is_empty() {
-            // blocks
-            let _ = tx.send(buffer);
+/// compile every module of the input ahead of time, the compiled modules are written
+/// beside the modules or in the output directory.
+fn compile(opts: CompileCommandOpts) -> CliExitCode {
+    let mut cfg = match load_cli_config(opts.input_ref()) {
+        Ok(cfg) => cfg,
+        Err(e) => {
+            perror!("failed to load CLI config: {}", e);
+            return e;
+        }
+    };
+    let output = opts.output_ref().map(PathBuf::from);
+    opts.into_config(&mut cfg);
+    let files = match cfg.0.modules_ref() {
+        modules if modules.is_empty() => vec![cfg.0.entry_ref().to_string()],
+        modules => modules.iter().map(|m| m.file.clone()).collect(),
+    };
+    if let Some(dir) = output.as_ref() {
+        if let Err(e) = fs::create_dir_all(dir) {
+            perror!(
+                "failed to create the output directory {}: {}",
+                dir.display(),
+                e
+            );
+            return CliExitCode::ConfigureError;
+        }
+    }
+    for file in files {
+        let compiled = match blockless_compile(&cfg.0, &file) {
+            Ok(compiled) => compiled,
+            Err(e) => {
+                perror!("failed to compile the module {}: {:?}", file, e);
+                return CliExitCode::UnknownError(e.to_string());
+            }
+        };
+        let path = compiled_path(&file, output.as_deref());
+        if let Err(e) = fs::write(&path, compiled) {
+            perror!(
+                "failed to write the compiled module {}: {}",
+                path.display(),
+                e
+            );
+            return CliExitCode::UnknownError(e.to_string());
         }
-    });
+        println!("{}", path.display());
+    }
+    CliExitCode::Success
+}
 
-    // wait for either a message from the thread or timeout
-    rx.recv_timeout(std::time::Duration::from_millis(1000)).ok()
+fn set_root_path_env_var(cli_command_opts: &CliCommandOpts) {
+    cli_command_opts
+        .fs_root_path()
+        .map(|s| std::env::set_var(ENV_ROOT_PATH_NAME, s.as_str()));
 }
 
 #[tokio::main]
 async fn main() -> CliExitCode {
-    let cli_command_opts = CliCommandOpts::parse();
+    let mut cli_command_opts = CliCommandOpts::parse();
+    // `bls-runtime compile <INPUT>` compiles the modules instead of running them.
+    if let Some(CliCommand::Compile(opts)) = cli_command_opts.take_command() {
+        return compile(opts);
+    }
     set_root_path_env_var(&cli_command_opts);
     let path = cli_command_opts.input_ref();
 
@@ -270,6 +338,18 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_compiled_path() {
+        assert_eq!(
+            compiled_path("build/app.wasm", None),
+            PathBuf::from("build/app.cwasm")
+        );
+        assert_eq!(
+            compiled_path("build/app.wasm", Some(Path::new("out"))),
+            PathBuf::from("out/app.cwasm")
+        );
+    }
+
     #[test]
     fn test_load_cli_wasm_config() {
         let wasm_conf = load_cli_config("test.wasm");
@@ -334,24 +414,4 @@ mod test {
         assert_eq!(cfg.0.fs_root_path_ref(), Some("target"));
         assert_eq!(cfg.0.drivers_root_path_ref(), Some("target/drivers"));
     }
-
-    #[tokio::test]
-    async fn test_no_input_non_blocking_read() {
-        let result = non_blocking_read(std::io::stdin()).await;
-        assert_eq!(result, None);
-    }
-
-    #[tokio::test]
-    async fn test_empty_input_non_blocking_read() {
-        let cursor = std::io::Cursor::new("");
-        let result = non_blocking_read(cursor).await;
-        assert_eq!(result, None);
-    }
-
-    #[tokio::test]
-    async fn test_input_non_blocking_read() {
-        let cursor = std::io::Cursor::new("test input");
-        let result = non_blocking_read(cursor).await;
-        assert_eq!(result, Some("test input".to_string()));
-    }
 }
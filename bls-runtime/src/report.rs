@@ -0,0 +1,143 @@
+use crate::error::CliExitCode;
+use blockless::{BlocklessModule, ExitStatus};
+use json::JsonValue;
+use std::io::Write;
+
+/// build the execution report of the run.
+pub(crate) fn build_report(status: &ExitStatus, modules: &[BlocklessModule]) -> JsonValue {
+    let mut report = JsonValue::new_object();
+    report["code"] = status.code.into();
+    // the code of the guest exit is the guest's own code, not a runtime error code.
+    if status.exited {
+        report["name"] = JsonValue::Null;
+        report["message"] = format!("The guest exited with code {}", status.code).into();
+    } else {
+        let exit_code: CliExitCode = status.code.into();
+        report["name"] = exit_code.name().into();
+        report["message"] = exit_code.to_string().into();
+    }
+    report["exited"] = status.exited.into();
+    report["trap"] = status.trap.clone().into();
+    report["backtrace"] = status.backtrace.clone().into();
+    report["fuel_used"] = status.fuel_used.into();
+    report["limited_fuel"] = status.limited_fuel.into();
+    report["peak_memory"] = status.peak_memory.into();
+    report["wall_time_ms"] = (status.wall_time.as_micros() as f64 / 1000.0).into();
+    report["cpu_time_ms"] = status
+        .cpu_time
+        .map(|t| t.as_micros() as f64 / 1000.0)
+        .into();
+    let mut extensions = JsonValue::new_object();
+    for (ext, usage) in status.extensions.iter() {
+        let mut obj = JsonValue::new_object();
+        obj["calls"] = usage.calls.into();
+        obj["bytes_in"] = usage.bytes_in.into();
+        obj["bytes_out"] = usage.bytes_out.into();
+        extensions[ext.name()] = obj;
+    }
+    report["extensions"] = extensions;
+    report["results"] = status.results.clone().into();
+    let modules = modules
+        .iter()
+        .map(|m| {
+            let mut obj = JsonValue::new_object();
+            obj["name"] = m.name.as_str().into();
+            obj["file"] = m.file.as_str().into();
+            obj["md5"] = m.md5.as_str().into();
+            obj
+        })
+        .collect::<Vec<_>>();
+    report["modules"] = JsonValue::Array(modules);
+    report
+}
+
+/// write the report to the file, or to stdout if the path is `-`.
+pub(crate) fn write_report(path: &str, report: &JsonValue) -> std::io::Result<()> {
+    let report = report.pretty(2);
+    if path == "-" {
+        let mut stdout = std::io::stdout().lock();
+        writeln!(stdout, "{report}")?;
+        stdout.flush()
+    } else {
+        std::fs::write(path, report)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use blockless::{Extension, ExtensionUsage, ModuleType};
+    use std::time::Duration;
+
+    #[test]
+    fn test_build_report() {
+        let status = ExitStatus {
+            fuel: Some(0),
+            code: 1,
+            exited: false,
+            trap: Some("OutOfFuel".into()),
+            backtrace: None,
+            fuel_used: Some(100),
+            limited_fuel: Some(100),
+            peak_memory: 65536,
+            wall_time: Duration::from_millis(12),
+            cpu_time: Some(Duration::from_millis(5)),
+            extensions: vec![(
+                Extension::Http,
+                ExtensionUsage {
+                    calls: 2,
+                    bytes_in: 10,
+                    bytes_out: 3,
+                },
+            )],
+            stdout: None,
+            stderr: None,
+            results: vec!["3".into()],
+        };
+        let modules = vec![BlocklessModule {
+            module_type: ModuleType::Entry,
+            name: "main".into(),
+            file: "main.wasm".into(),
+            md5: "d41d8cd98f00b204e9800998ecf8427e".into(),
+        }];
+        let report = build_report(&status, &modules);
+        assert_eq!(report["code"], 1);
+        assert_eq!(report["name"], "FlueUsedOut");
+        assert_eq!(report["trap"], "OutOfFuel");
+        assert!(report["backtrace"].is_null());
+        assert_eq!(report["fuel_used"], 100);
+        assert_eq!(report["peak_memory"], 65536);
+        assert_eq!(report["wall_time_ms"], 12.0);
+        assert_eq!(report["cpu_time_ms"], 5.0);
+        assert_eq!(report["extensions"]["http"]["calls"], 2);
+        assert_eq!(report["extensions"]["http"]["bytes_out"], 3);
+        assert_eq!(report["modules"][0]["md5"], modules[0].md5.as_str());
+        assert_eq!(report["results"][0], "3");
+    }
+
+    #[test]
+    fn test_build_report_guest_exit() {
+        let status = ExitStatus {
+            fuel: None,
+            code: 1,
+            exited: true,
+            trap: None,
+            backtrace: None,
+            fuel_used: None,
+            limited_fuel: None,
+            peak_memory: 65536,
+            wall_time: Duration::from_millis(1),
+            cpu_time: None,
+            extensions: Vec::new(),
+            stdout: None,
+            stderr: None,
+            results: Vec::new(),
+        };
+        let report = build_report(&status, &[]);
+        assert_eq!(report["code"], 1);
+        assert!(report["name"].is_null());
+        assert_eq!(report["message"], "The guest exited with code 1");
+        assert_eq!(report["exited"], true);
+        assert!(report["cpu_time_ms"].is_null());
+    }
+}
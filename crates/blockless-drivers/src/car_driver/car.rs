@@ -0,0 +1,434 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{ErrorKind, Read, Seek, SeekFrom},
+};
+
+use super::{
+    cid::{Cid, DAG_PB, RAW},
+    unixfs::{FileType, Node},
+};
+use crate::CarErrorKind;
+
+/// the max length of the section prefix which contains the cid.
+const CID_PREFIX_LEN: u64 = 128;
+
+/// the max length of the car header.
+const MAX_HEADER_LEN: u64 = 1024 * 1024;
+
+/// the max length of the block data, the blocks of ipfs are less than 2MiB.
+const MAX_BLOCK_LEN: u64 = 4 * 1024 * 1024;
+
+/// the max depth of the nested cbor items of the header.
+const MAX_CBOR_DEPTH: usize = 32;
+
+/// the max depth of the hamt shards of the directory.
+const MAX_SHARD_DEPTH: usize = 64;
+
+/// The car v1 file, the sections are indexed when opened and the blocks are read on demand,
+/// every block is verified with the hash of the cid when it's read.
+pub struct CarFile<R = File> {
+    reader: R,
+    roots: Vec<Cid>,
+    // the offset and the length of the block data in the file.
+    index: HashMap<Cid, (u64, u64)>,
+}
+
+/// read the varint from the reader, return `None` if the reader is at the end.
+fn read_varint<R: Read>(reader: &mut R) -> Result<Option<u64>, CarErrorKind> {
+    let mut buf = [0u8; 10];
+    for i in 0..buf.len() {
+        match reader.read_exact(&mut buf[i..i + 1]) {
+            Ok(_) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof && i == 0 => return Ok(None),
+            Err(_) => return Err(CarErrorKind::InvalidCar),
+        }
+        if buf[i] & 0x80 == 0 {
+            let (v, _) =
+                unsigned_varint::decode::u64(&buf[..=i]).map_err(|_| CarErrorKind::InvalidCar)?;
+            return Ok(Some(v));
+        }
+    }
+    Err(CarErrorKind::InvalidCar)
+}
+
+/// the minimal dag-cbor reader, it's only used to read the roots of the car header.
+struct Cbor<'a>(&'a [u8]);
+
+impl<'a> Cbor<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], CarErrorKind> {
+        if self.0.len() < n {
+            return Err(CarErrorKind::InvalidCar);
+        }
+        let (v, remain) = self.0.split_at(n);
+        self.0 = remain;
+        Ok(v)
+    }
+
+    /// read the major type and the argument of the item.
+    fn head(&mut self) -> Result<(u8, u64), CarErrorKind> {
+        let b = self.take(1)?[0];
+        let arg = match b & 0x1f {
+            n @ 0..=23 => n as u64,
+            n @ 24..=27 => {
+                let bytes = self.take(1 << (n - 24))?;
+                bytes.iter().fold(0u64, |acc, b| acc << 8 | *b as u64)
+            }
+            _ => return Err(CarErrorKind::InvalidCar),
+        };
+        Ok((b >> 5, arg))
+    }
+
+    fn bytes(&mut self, major: u8) -> Result<&'a [u8], CarErrorKind> {
+        match self.head()? {
+            (m, len) if m == major => {
+                self.take(usize::try_from(len).map_err(|_| CarErrorKind::InvalidCar)?)
+            }
+            _ => Err(CarErrorKind::InvalidCar),
+        }
+    }
+
+    /// skip the item, the nested items deeper than `MAX_CBOR_DEPTH` are rejected.
+    fn skip(&mut self, depth: usize) -> Result<(), CarErrorKind> {
+        if depth > MAX_CBOR_DEPTH {
+            return Err(CarErrorKind::InvalidCar);
+        }
+        match self.head()? {
+            (0 | 1 | 7, _) => {}
+            (2 | 3, len) => {
+                let len = usize::try_from(len).map_err(|_| CarErrorKind::InvalidCar)?;
+                self.take(len)?;
+            }
+            (4, len) => (0..len).try_for_each(|_| self.skip(depth + 1))?,
+            (5, len) => {
+                let len = len.checked_mul(2).ok_or(CarErrorKind::InvalidCar)?;
+                (0..len).try_for_each(|_| self.skip(depth + 1))?
+            }
+            (6, _) => self.skip(depth + 1)?,
+            _ => return Err(CarErrorKind::InvalidCar),
+        }
+        Ok(())
+    }
+
+    /// read the cid link, the tag 42 with the bytes prefixed with the identity multibase.
+    fn cid(&mut self) -> Result<Cid, CarErrorKind> {
+        match (self.head()?, self.bytes(2)?) {
+            ((6, 42), [0, cid @ ..]) => Ok(Cid::read_bytes(cid)?.0),
+            _ => Err(CarErrorKind::InvalidCar),
+        }
+    }
+}
+
+/// read the roots of the car v1 header.
+fn header_roots(buf: &[u8]) -> Result<Vec<Cid>, CarErrorKind> {
+    let mut cbor = Cbor(buf);
+    let (5, len) = cbor.head()? else {
+        return Err(CarErrorKind::InvalidCar);
+    };
+    let mut roots = None;
+    let mut version = None;
+    for _ in 0..len {
+        match cbor.bytes(3)? {
+            b"roots" => {
+                let (4, len) = cbor.head()? else {
+                    return Err(CarErrorKind::InvalidCar);
+                };
+                roots = Some((0..len).map(|_| cbor.cid()).collect::<Result<_, _>>()?);
+            }
+            b"version" => version = Some(cbor.head()?),
+            _ => cbor.skip(0)?,
+        }
+    }
+    match (roots, version) {
+        (Some(roots), Some((0, 1))) => Ok(roots),
+        // the car v2 is not supported.
+        _ => Err(CarErrorKind::InvalidCar),
+    }
+}
+
+impl CarFile {
+    pub fn open(path: impl AsRef<std::path::Path>) -> Result<Self, CarErrorKind> {
+        let file = File::open(path).map_err(|_| CarErrorKind::NotFound)?;
+        Self::new(file)
+    }
+}
+
+impl<R: Read + Seek> CarFile<R> {
+    /// index the sections of the car, the lengths are checked with the size of the car
+    /// before the buffers are allocated.
+    pub fn new(mut reader: R) -> Result<Self, CarErrorKind> {
+        let size = reader
+            .seek(SeekFrom::End(0))
+            .and_then(|size| reader.seek(SeekFrom::Start(0)).map(|_| size))
+            .map_err(|_| CarErrorKind::InvalidCar)?;
+        let header_len = read_varint(&mut reader)?.ok_or(CarErrorKind::InvalidCar)?;
+        if header_len > MAX_HEADER_LEN || header_len > size {
+            return Err(CarErrorKind::InvalidCar);
+        }
+        let mut header = vec![0; header_len as usize];
+        reader
+            .read_exact(&mut header)
+            .map_err(|_| CarErrorKind::InvalidCar)?;
+        let roots = header_roots(&header)?;
+        let mut index = HashMap::new();
+        let mut prefix = Vec::with_capacity(CID_PREFIX_LEN as usize);
+        while let Some(len) = read_varint(&mut reader)? {
+            let offset = reader
+                .stream_position()
+                .map_err(|_| CarErrorKind::InvalidCar)?;
+            let end = offset.checked_add(len).ok_or(CarErrorKind::InvalidCar)?;
+            if end > size {
+                return Err(CarErrorKind::InvalidCar);
+            }
+            prefix.clear();
+            (&mut reader)
+                .take(len.min(CID_PREFIX_LEN))
+                .read_to_end(&mut prefix)
+                .map_err(|_| CarErrorKind::InvalidCar)?;
+            let (cid, remain) = Cid::read_bytes(&prefix).map_err(|_| CarErrorKind::InvalidCar)?;
+            let cid_len = (prefix.len() - remain.len()) as u64;
+            let data_len = len.checked_sub(cid_len).ok_or(CarErrorKind::InvalidCar)?;
+            if data_len > MAX_BLOCK_LEN {
+                return Err(CarErrorKind::InvalidCar);
+            }
+            index.insert(cid, (offset + cid_len, data_len));
+            reader
+                .seek(SeekFrom::Start(end))
+                .map_err(|_| CarErrorKind::InvalidCar)?;
+        }
+        Ok(Self {
+            reader,
+            roots,
+            index,
+        })
+    }
+
+    #[inline(always)]
+    pub fn roots(&self) -> &[Cid] {
+        &self.roots
+    }
+
+    /// read the block data and verify it with the cid.
+    pub fn block(&mut self, cid: &Cid) -> Result<Vec<u8>, CarErrorKind> {
+        if let Some(data) = cid.identity_data() {
+            return Ok(data.to_vec());
+        }
+        let (offset, len) = *self.index.get(cid).ok_or(CarErrorKind::NotFound)?;
+        let mut data = vec![0; len as usize];
+        self.reader
+            .seek(SeekFrom::Start(offset))
+            .and_then(|_| self.reader.read_exact(&mut data))
+            .map_err(|_| CarErrorKind::InvalidCar)?;
+        cid.verify(&data)?;
+        Ok(data)
+    }
+
+    fn node(&mut self, cid: &Cid) -> Result<Node, CarErrorKind> {
+        if cid.codec() != DAG_PB {
+            return Err(CarErrorKind::NotFound);
+        }
+        Node::decode(&self.block(cid)?)
+    }
+
+    /// find the child of the directory, the hamt shards are searched recursively.
+    fn child(&mut self, dir: &Cid, name: &str, depth: usize) -> Result<Option<Cid>, CarErrorKind> {
+        if depth > MAX_SHARD_DEPTH {
+            return Err(CarErrorKind::InvalidNode);
+        }
+        let node = self.node(dir)?;
+        if !matches!(node.file_type, FileType::Directory | FileType::HamtShard) {
+            return Err(CarErrorKind::NotFound);
+        }
+        if let Some(link) = node.find_link(name) {
+            return Ok(Some(link.cid.clone()));
+        }
+        for shard in node.sub_shards() {
+            if let Some(cid) = self.child(&shard.cid, name, depth + 1)? {
+                return Ok(Some(cid));
+            }
+        }
+        Ok(None)
+    }
+
+    /// resolve the path to the cid, the path is `<cid>/a/b`, `/ipfs/<cid>/a/b`, or `/a/b`
+    /// which is resolved from the first root.
+    pub fn resolve(&mut self, path: &str) -> Result<Cid, CarErrorKind> {
+        let path = path.strip_prefix("/ipfs/").unwrap_or(path);
+        let mut segments = path.split('/').filter(|s| !s.is_empty());
+        let mut cid = if path.starts_with('/') || path.is_empty() {
+            self.roots.first().cloned().ok_or(CarErrorKind::NotFound)?
+        } else {
+            Cid::parse(segments.next().ok_or(CarErrorKind::NotFound)?)?
+        };
+        for name in segments {
+            cid = self.child(&cid, name, 0)?.ok_or(CarErrorKind::NotFound)?;
+        }
+        Ok(cid)
+    }
+
+    /// read the next data of the file, the pending is the stack of the blocks to read.
+    pub fn next_file_data(
+        &mut self,
+        pending: &mut Vec<Cid>,
+    ) -> Result<Option<Vec<u8>>, CarErrorKind> {
+        while let Some(cid) = pending.pop() {
+            let data = match cid.codec() {
+                RAW => self.block(&cid)?,
+                DAG_PB => {
+                    let node = self.node(&cid)?;
+                    if !matches!(node.file_type, FileType::File | FileType::Raw) {
+                        return Err(CarErrorKind::NotFile);
+                    }
+                    // the children are read in order after the data of the node.
+                    pending.extend(node.links.into_iter().rev().map(|l| l.cid));
+                    node.data
+                }
+                _ => return Err(CarErrorKind::NotFile),
+            };
+            if !data.is_empty() {
+                return Ok(Some(data));
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::super::unixfs::test::encode_node;
+    use super::*;
+    use std::io::Cursor;
+
+    fn varint(out: &mut Vec<u8>, v: u64) {
+        let mut buf = unsigned_varint::encode::u64_buffer();
+        out.extend_from_slice(unsigned_varint::encode::u64(v, &mut buf));
+    }
+
+    /// build the car v1 with the root and the blocks.
+    pub(crate) fn build_car(root: &Cid, blocks: &[(&Cid, &[u8])]) -> Vec<u8> {
+        let cid = root.to_bytes();
+        let mut header = vec![0xa2, 0x65];
+        header.extend_from_slice(b"roots");
+        header.extend_from_slice(&[0x81, 0xd8, 0x2a, 0x58, cid.len() as u8 + 1, 0]);
+        header.extend_from_slice(&cid);
+        header.push(0x67);
+        header.extend_from_slice(b"version");
+        header.push(0x01);
+        let mut car = Vec::new();
+        varint(&mut car, header.len() as _);
+        car.extend_from_slice(&header);
+        for (cid, data) in blocks {
+            let cid = cid.to_bytes();
+            varint(&mut car, (cid.len() + data.len()) as _);
+            car.extend_from_slice(&cid);
+            car.extend_from_slice(data);
+        }
+        car
+    }
+
+    /// the test dag, the root directory contains `hello.txt` with 2 chunks and the `sub`
+    /// directory which is the hamt shard contains `raw.bin`.
+    pub(crate) fn test_car() -> (Cid, Vec<u8>) {
+        let chunk1 = Cid::new_v1(RAW, b"hello ");
+        let chunk2 = Cid::new_v1(RAW, b"world");
+        let file = encode_node(2, b"", &[(&chunk1, ""), (&chunk2, "")]);
+        let file_cid = Cid::new_v0(&file);
+        let raw = Cid::new_v1(RAW, b"raw data");
+        let shard = encode_node(5, b"", &[(&raw, "A1raw.bin")]);
+        let shard_cid = Cid::new_v1(DAG_PB, &shard);
+        let sub = encode_node(5, b"", &[(&shard_cid, "0F")]);
+        let sub_cid = Cid::new_v1(DAG_PB, &sub);
+        let root = encode_node(1, b"", &[(&file_cid, "hello.txt"), (&sub_cid, "sub")]);
+        let root_cid = Cid::new_v1(DAG_PB, &root);
+        let car = build_car(
+            &root_cid,
+            &[
+                (&root_cid, &root),
+                (&file_cid, &file),
+                (&chunk1, b"hello "),
+                (&chunk2, b"world"),
+                (&sub_cid, &sub),
+                (&shard_cid, &shard),
+                (&raw, b"raw data"),
+            ],
+        );
+        (root_cid, car)
+    }
+
+    fn read_file(car: &mut CarFile<Cursor<Vec<u8>>>, path: &str) -> Result<Vec<u8>, CarErrorKind> {
+        let mut pending = vec![car.resolve(path)?];
+        let mut out = Vec::new();
+        while let Some(data) = car.next_file_data(&mut pending)? {
+            out.extend(data);
+        }
+        Ok(out)
+    }
+
+    #[test]
+    fn test_car_resolve_and_read() {
+        let (root, data) = test_car();
+        let mut car = CarFile::new(Cursor::new(data)).unwrap();
+        assert_eq!(car.roots()[0], root);
+        assert_eq!(car.resolve("/").unwrap(), root);
+        assert_eq!(read_file(&mut car, "/hello.txt").unwrap(), b"hello world");
+        let path = format!("/ipfs/{root}/sub/raw.bin");
+        assert_eq!(read_file(&mut car, &path).unwrap(), b"raw data");
+        assert!(matches!(car.resolve("/none"), Err(CarErrorKind::NotFound)));
+        assert!(matches!(
+            read_file(&mut car, "/sub"),
+            Err(CarErrorKind::NotFile)
+        ));
+        let raw = car.resolve(&format!("{root}/sub/raw.bin")).unwrap();
+        assert_eq!(car.block(&raw).unwrap(), b"raw data");
+    }
+
+    #[test]
+    fn test_car_hash_mismatch() {
+        let (_, mut data) = test_car();
+        // the last block is `raw data`, corrupt it.
+        let len = data.len();
+        data[len - 1] = b'A';
+        let mut car = CarFile::new(Cursor::new(data)).unwrap();
+        assert!(matches!(
+            read_file(&mut car, "/sub/raw.bin"),
+            Err(CarErrorKind::HashMismatch)
+        ));
+        assert!(CarFile::new(Cursor::new(b"invalid".to_vec())).is_err());
+    }
+
+    #[test]
+    fn test_car_invalid_lengths() {
+        // the header length is larger than the car.
+        let mut data = Vec::new();
+        varint(&mut data, u64::MAX >> 1);
+        assert!(matches!(
+            CarFile::new(Cursor::new(data)),
+            Err(CarErrorKind::InvalidCar)
+        ));
+
+        // the section length is larger than the car.
+        let (_, mut data) = test_car();
+        varint(&mut data, u64::MAX);
+        assert!(matches!(
+            CarFile::new(Cursor::new(data)),
+            Err(CarErrorKind::InvalidCar)
+        ));
+
+        // the deeply nested cbor in the header.
+        let mut header = vec![0xa1, 0x61, b'x'];
+        header.extend(vec![0x81; MAX_CBOR_DEPTH + 1]);
+        header.push(0x00);
+        assert!(matches!(
+            header_roots(&header),
+            Err(CarErrorKind::InvalidCar)
+        ));
+        // the map with the length which overflows.
+        let header = [
+            0xa1, 0x61, b'x', 0xbb, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        ];
+        assert!(matches!(
+            header_roots(&header),
+            Err(CarErrorKind::InvalidCar)
+        ));
+    }
+}
@@ -0,0 +1,186 @@
+use std::fmt;
+
+use multibase::Base;
+use sha2::{Digest, Sha256, Sha512};
+
+use crate::CarErrorKind;
+
+/// the codec of the raw block.
+pub const RAW: u64 = 0x55;
+/// the codec of the dag-pb block.
+pub const DAG_PB: u64 = 0x70;
+
+const IDENTITY: u64 = 0x00;
+const SHA2_256: u64 = 0x12;
+const SHA2_512: u64 = 0x13;
+
+/// The content identifier, only the cid v0 and v1 are supported.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Cid {
+    version: u64,
+    codec: u64,
+    hash_code: u64,
+    digest: Vec<u8>,
+}
+
+fn read_varint(buf: &[u8]) -> Result<(u64, &[u8]), CarErrorKind> {
+    unsigned_varint::decode::u64(buf).map_err(|_| CarErrorKind::InvalidCid)
+}
+
+impl Cid {
+    /// read the cid from the bytes, return the cid and the remain bytes.
+    pub fn read_bytes(buf: &[u8]) -> Result<(Cid, &[u8]), CarErrorKind> {
+        // the cid v0 is the sha2-256 multihash.
+        if buf.len() >= 34 && buf[0] == SHA2_256 as u8 && buf[1] == 32 {
+            let cid = Cid {
+                version: 0,
+                codec: DAG_PB,
+                hash_code: SHA2_256,
+                digest: buf[2..34].to_vec(),
+            };
+            return Ok((cid, &buf[34..]));
+        }
+        let (version, remain) = read_varint(buf)?;
+        if version != 1 {
+            return Err(CarErrorKind::InvalidCid);
+        }
+        let (codec, remain) = read_varint(remain)?;
+        let (hash_code, remain) = read_varint(remain)?;
+        let (len, remain) = read_varint(remain)?;
+        let len = len as usize;
+        if remain.len() < len {
+            return Err(CarErrorKind::InvalidCid);
+        }
+        let cid = Cid {
+            version,
+            codec,
+            hash_code,
+            digest: remain[..len].to_vec(),
+        };
+        Ok((cid, &remain[len..]))
+    }
+
+    /// parse the cid string, the cid v0 is base58btc, the cid v1 is multibase.
+    pub fn parse(s: &str) -> Result<Cid, CarErrorKind> {
+        let bytes = if s.len() == 46 && s.starts_with("Qm") {
+            Base::Base58Btc
+                .decode(s)
+                .map_err(|_| CarErrorKind::InvalidCid)?
+        } else {
+            multibase::decode(s)
+                .map_err(|_| CarErrorKind::InvalidCid)?
+                .1
+        };
+        match Cid::read_bytes(&bytes)? {
+            (cid, []) => Ok(cid),
+            _ => Err(CarErrorKind::InvalidCid),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.digest.len() + 8);
+        if self.version == 0 {
+            bytes.extend_from_slice(&[SHA2_256 as u8, 32]);
+        } else {
+            let mut buf = unsigned_varint::encode::u64_buffer();
+            for v in [self.version, self.codec, self.hash_code] {
+                bytes.extend_from_slice(unsigned_varint::encode::u64(v, &mut buf));
+            }
+            let len = unsigned_varint::encode::u64(self.digest.len() as _, &mut buf);
+            bytes.extend_from_slice(len);
+        }
+        bytes.extend_from_slice(&self.digest);
+        bytes
+    }
+
+    #[inline(always)]
+    pub fn codec(&self) -> u64 {
+        self.codec
+    }
+
+    /// the data of the identity cid is inlined in the digest.
+    pub fn identity_data(&self) -> Option<&[u8]> {
+        (self.hash_code == IDENTITY).then_some(&self.digest[..])
+    }
+
+    /// verify the block data with the hash of the cid.
+    pub fn verify(&self, data: &[u8]) -> Result<(), CarErrorKind> {
+        let matched = match self.hash_code {
+            IDENTITY => self.digest == data,
+            SHA2_256 => self.digest[..] == Sha256::digest(data)[..],
+            SHA2_512 => self.digest[..] == Sha512::digest(data)[..],
+            _ => return Err(CarErrorKind::UnsupportedCid),
+        };
+        if !matched {
+            return Err(CarErrorKind::HashMismatch);
+        }
+        Ok(())
+    }
+
+    /// the cid v1 with the sha2-256 hash of the data.
+    #[cfg(test)]
+    pub fn new_v1(codec: u64, data: &[u8]) -> Cid {
+        Cid {
+            version: 1,
+            codec,
+            hash_code: SHA2_256,
+            digest: Sha256::digest(data).to_vec(),
+        }
+    }
+
+    /// the cid v0 of the dag-pb data.
+    #[cfg(test)]
+    pub fn new_v0(data: &[u8]) -> Cid {
+        Cid {
+            version: 0,
+            ..Cid::new_v1(DAG_PB, data)
+        }
+    }
+}
+
+impl fmt::Display for Cid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bytes = self.to_bytes();
+        if self.version == 0 {
+            write!(f, "{}", Base::Base58Btc.encode(bytes))
+        } else {
+            write!(f, "{}", multibase::encode(Base::Base32Lower, bytes))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_cid_string() {
+        let v0 = "QmdfTbBqBPQ7VNxZEYEj14VmRuZBkqFbiwReogJgS1zR1n";
+        let cid = Cid::parse(v0).unwrap();
+        assert_eq!(cid.codec(), DAG_PB);
+        assert_eq!(cid.to_string(), v0);
+
+        let v1 = "bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi";
+        let cid = Cid::parse(v1).unwrap();
+        assert_eq!(cid.codec(), DAG_PB);
+        assert_eq!(cid.to_string(), v1);
+        let bytes = cid.to_bytes();
+        let (read, remain) = Cid::read_bytes(&bytes).unwrap();
+        assert_eq!(read, cid);
+        assert!(remain.is_empty());
+
+        assert!(matches!(Cid::parse("Qm1"), Err(CarErrorKind::InvalidCid)));
+    }
+
+    #[test]
+    fn test_cid_verify() {
+        let cid = Cid::new_v1(RAW, b"hello");
+        assert!(cid.verify(b"hello").is_ok());
+        assert!(matches!(
+            cid.verify(b"hellp"),
+            Err(CarErrorKind::HashMismatch)
+        ));
+        let v0 = Cid::new_v0(b"hello");
+        assert_eq!(Cid::parse(&v0.to_string()).unwrap(), v0);
+    }
+}
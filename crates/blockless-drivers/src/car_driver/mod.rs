@@ -0,0 +1,211 @@
+mod car;
+mod cid;
+mod unixfs;
+pub use car::CarFile;
+pub use cid::Cid;
+
+use std::path::{Component, Path, PathBuf};
+use wasi_common::Table;
+
+use crate::{
+    resource::{self, Resource},
+    CarErrorKind,
+};
+
+/// The stream of the block bytes or the unixfs file bytes.
+struct CarStream {
+    car: Resource<CarFile>,
+    // the blocks of the file to read, the last is read first.
+    pending: Vec<Cid>,
+    buffer: Vec<u8>,
+    pos: usize,
+}
+
+/// get the host path of the car file, the path must be inside the fs root path.
+fn host_path(root: &Path, path: &str) -> Result<PathBuf, CarErrorKind> {
+    let mut host = root.to_path_buf();
+    for comp in Path::new(path).components() {
+        match comp {
+            Component::Normal(c) => host.push(c),
+            Component::RootDir | Component::CurDir => {}
+            Component::ParentDir | Component::Prefix(_) => {
+                return Err(CarErrorKind::PermissionDeny)
+            }
+        }
+    }
+    // the symbol link can't point to the outside of the root.
+    let root = root.canonicalize().map_err(|_| CarErrorKind::NotFound)?;
+    let host = host.canonicalize().map_err(|_| CarErrorKind::NotFound)?;
+    if !host.starts_with(&root) {
+        return Err(CarErrorKind::PermissionDeny);
+    }
+    Ok(host)
+}
+
+/// open the car file in the fs root path, the sections of the car file are indexed.
+pub async fn open(table: &Table, root: Option<&str>, path: &str) -> Result<u32, CarErrorKind> {
+    let root = root.ok_or(CarErrorKind::PermissionDeny)?;
+    let path = host_path(Path::new(root), path)?;
+    let car = tokio::task::spawn_blocking(move || CarFile::open(path))
+        .await
+        .map_err(|_| CarErrorKind::RuntimeError)??;
+    resource::push(table, car).ok_or(CarErrorKind::RuntimeError)
+}
+
+fn get_car(table: &Table, fd: u32) -> Result<Resource<CarFile>, CarErrorKind> {
+    resource::get::<CarFile>(table, fd).ok_or(CarErrorKind::InvalidHandle)
+}
+
+/// the blocks are read from the file, so the call is run in the blocking pool.
+async fn with_car<T, F>(car: &Resource<CarFile>, call: F) -> Result<T, CarErrorKind>
+where
+    T: Send + 'static,
+    F: FnOnce(&mut CarFile) -> Result<T, CarErrorKind> + Send + 'static,
+{
+    let mut car = car.clone().lock_owned().await;
+    tokio::task::spawn_blocking(move || call(&mut car))
+        .await
+        .map_err(|_| CarErrorKind::RuntimeError)?
+}
+
+/// the roots of the car file as the json array.
+pub async fn roots(table: &Table, fd: u32) -> Result<String, CarErrorKind> {
+    let car = get_car(table, fd)?;
+    let car = car.lock().await;
+    let roots = car
+        .roots()
+        .iter()
+        .map(|c| json::JsonValue::from(c.to_string()))
+        .collect::<Vec<_>>();
+    Ok(json::JsonValue::Array(roots).dump())
+}
+
+pub async fn resolve(table: &Table, fd: u32, path: &str) -> Result<String, CarErrorKind> {
+    let car = get_car(table, fd)?;
+    let path = path.to_string();
+    let cid = with_car(&car, move |car| car.resolve(&path)).await?;
+    Ok(cid.to_string())
+}
+
+/// open the stream of the block, the block is verified when opened.
+pub async fn open_block(table: &Table, fd: u32, cid: &str) -> Result<u32, CarErrorKind> {
+    let car = get_car(table, fd)?;
+    let cid = Cid::parse(cid)?;
+    let buffer = with_car(&car, move |car| car.block(&cid)).await?;
+    let stream = CarStream {
+        car,
+        pending: Vec::new(),
+        buffer,
+        pos: 0,
+    };
+    resource::push(table, stream).ok_or(CarErrorKind::RuntimeError)
+}
+
+/// open the stream of the file, the blocks of the file are read and verified when the
+/// stream is read.
+pub async fn open_file(table: &Table, fd: u32, path: &str) -> Result<u32, CarErrorKind> {
+    let car = get_car(table, fd)?;
+    let path = path.to_string();
+    let cid = with_car(&car, move |car| car.resolve(&path)).await?;
+    let stream = CarStream {
+        car,
+        pending: vec![cid],
+        buffer: Vec::new(),
+        pos: 0,
+    };
+    resource::push(table, stream).ok_or(CarErrorKind::RuntimeError)
+}
+
+pub async fn read(table: &Table, fd: u32, buf: &mut [u8]) -> Result<u32, CarErrorKind> {
+    let stream = resource::get::<CarStream>(table, fd).ok_or(CarErrorKind::InvalidHandle)?;
+    let mut stream = stream.lock().await;
+    let stream = &mut *stream;
+    if stream.pos == stream.buffer.len() {
+        // the pending blocks are kept in the stream even if the read fails.
+        let mut pending = std::mem::take(&mut stream.pending);
+        let (data, pending) = with_car(&stream.car, move |car| {
+            let data = car.next_file_data(&mut pending);
+            Ok((data, pending))
+        })
+        .await?;
+        stream.pending = pending;
+        match data? {
+            Some(data) => {
+                stream.buffer = data;
+                stream.pos = 0;
+            }
+            None => return Ok(0),
+        }
+    }
+    let n = buf.len().min(stream.buffer.len() - stream.pos);
+    buf[..n].copy_from_slice(&stream.buffer[stream.pos..stream.pos + n]);
+    stream.pos += n;
+    Ok(n as u32)
+}
+
+/// close the car file or the stream, the car file is closed when all the streams are closed.
+pub async fn close(table: &Table, fd: u32) -> Result<(), CarErrorKind> {
+    if resource::remove::<CarFile>(table, fd).is_some()
+        || resource::remove::<CarStream>(table, fd).is_some()
+    {
+        return Ok(());
+    }
+    Err(CarErrorKind::InvalidHandle)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tempdir::TempDir;
+    use tokio::runtime::Builder;
+
+    #[test]
+    fn test_car_driver() {
+        let rt = Builder::new_current_thread().enable_all().build().unwrap();
+        rt.block_on(async move {
+            let temp_dir = TempDir::new("blockless_car").unwrap();
+            let root = temp_dir.path().to_str().unwrap();
+            let (root_cid, data) = car::test::test_car();
+            std::fs::write(temp_dir.path().join("test.car"), data).unwrap();
+            let table = Table::new();
+            assert!(matches!(
+                open(&table, Some(root), "/../test.car").await,
+                Err(CarErrorKind::PermissionDeny)
+            ));
+            assert!(matches!(
+                open(&table, None, "/test.car").await,
+                Err(CarErrorKind::PermissionDeny)
+            ));
+            let fd = open(&table, Some(root), "/test.car").await.unwrap();
+            let roots = roots(&table, fd).await.unwrap();
+            assert_eq!(roots, format!("[\"{root_cid}\"]"));
+
+            let file = open_file(&table, fd, "/hello.txt").await.unwrap();
+            let mut buf = [0u8; 4];
+            let mut out = Vec::new();
+            loop {
+                let n = read(&table, file, &mut buf).await.unwrap() as usize;
+                if n == 0 {
+                    break;
+                }
+                out.extend_from_slice(&buf[..n]);
+            }
+            assert_eq!(out, b"hello world");
+
+            let cid = resolve(&table, fd, "/sub/raw.bin").await.unwrap();
+            let block = open_block(&table, fd, &cid).await.unwrap();
+            let mut buf = [0u8; 16];
+            let n = read(&table, block, &mut buf).await.unwrap() as usize;
+            assert_eq!(&buf[..n], b"raw data");
+            assert_eq!(read(&table, block, &mut buf).await.unwrap(), 0);
+
+            close(&table, block).await.unwrap();
+            close(&table, file).await.unwrap();
+            close(&table, fd).await.unwrap();
+            assert!(matches!(
+                close(&table, fd).await,
+                Err(CarErrorKind::InvalidHandle)
+            ));
+        });
+    }
+}
@@ -0,0 +1,200 @@
+use super::cid::Cid;
+use crate::CarErrorKind;
+
+/// the type of the unixfs node.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileType {
+    Raw,
+    Directory,
+    File,
+    Metadata,
+    Symlink,
+    HamtShard,
+}
+
+pub struct Link {
+    pub cid: Cid,
+    pub name: String,
+}
+
+/// The dag-pb node with the unixfs data.
+pub struct Node {
+    pub links: Vec<Link>,
+    pub file_type: FileType,
+    pub data: Vec<u8>,
+}
+
+/// the reader of the protobuf fields, only the wire types of the dag-pb are supported.
+struct Fields<'a>(&'a [u8]);
+
+enum Value<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+}
+
+impl<'a> Fields<'a> {
+    fn varint(&mut self) -> Result<u64, CarErrorKind> {
+        let (v, remain) =
+            unsigned_varint::decode::u64(self.0).map_err(|_| CarErrorKind::InvalidNode)?;
+        self.0 = remain;
+        Ok(v)
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], CarErrorKind> {
+        if self.0.len() < n {
+            return Err(CarErrorKind::InvalidNode);
+        }
+        let (v, remain) = self.0.split_at(n);
+        self.0 = remain;
+        Ok(v)
+    }
+
+    fn next_field(&mut self) -> Result<Option<(u64, Value<'a>)>, CarErrorKind> {
+        if self.0.is_empty() {
+            return Ok(None);
+        }
+        let key = self.varint()?;
+        let value = match key & 0x7 {
+            0 => Value::Varint(self.varint()?),
+            1 => Value::Bytes(self.take(8)?),
+            2 => {
+                let len = self.varint()? as usize;
+                Value::Bytes(self.take(len)?)
+            }
+            5 => Value::Bytes(self.take(4)?),
+            _ => return Err(CarErrorKind::InvalidNode),
+        };
+        Ok(Some((key >> 3, value)))
+    }
+}
+
+fn decode_link(buf: &[u8]) -> Result<Link, CarErrorKind> {
+    let mut fields = Fields(buf);
+    let mut cid = None;
+    let mut name = String::new();
+    while let Some((tag, value)) = fields.next_field()? {
+        match (tag, value) {
+            (1, Value::Bytes(b)) => cid = Some(Cid::read_bytes(b)?.0),
+            (2, Value::Bytes(b)) => {
+                name = String::from_utf8(b.to_vec()).map_err(|_| CarErrorKind::InvalidNode)?
+            }
+            _ => {}
+        }
+    }
+    let cid = cid.ok_or(CarErrorKind::InvalidNode)?;
+    Ok(Link { cid, name })
+}
+
+fn decode_data(buf: &[u8]) -> Result<(FileType, Vec<u8>), CarErrorKind> {
+    let mut fields = Fields(buf);
+    let mut file_type = FileType::Raw;
+    let mut data = Vec::new();
+    while let Some((tag, value)) = fields.next_field()? {
+        match (tag, value) {
+            (1, Value::Varint(t)) => {
+                file_type = match t {
+                    0 => FileType::Raw,
+                    1 => FileType::Directory,
+                    2 => FileType::File,
+                    3 => FileType::Metadata,
+                    4 => FileType::Symlink,
+                    5 => FileType::HamtShard,
+                    _ => return Err(CarErrorKind::InvalidNode),
+                }
+            }
+            (2, Value::Bytes(b)) => data = b.to_vec(),
+            _ => {}
+        }
+    }
+    Ok((file_type, data))
+}
+
+impl Node {
+    /// decode the dag-pb block, the links are ordered as the block.
+    pub fn decode(buf: &[u8]) -> Result<Node, CarErrorKind> {
+        let mut fields = Fields(buf);
+        let mut links = Vec::new();
+        let mut unixfs = None;
+        while let Some((tag, value)) = fields.next_field()? {
+            match (tag, value) {
+                (2, Value::Bytes(b)) => links.push(decode_link(b)?),
+                (1, Value::Bytes(b)) => unixfs = Some(decode_data(b)?),
+                _ => {}
+            }
+        }
+        let (file_type, data) = unixfs.ok_or(CarErrorKind::InvalidNode)?;
+        Ok(Node {
+            links,
+            file_type,
+            data,
+        })
+    }
+
+    /// find the child by the name, the children of the hamt shard are prefixed with
+    /// the 2 hex chars of the bucket, the link with only the prefix is the sub shard.
+    pub fn find_link(&self, name: &str) -> Option<&Link> {
+        self.links.iter().find(|l| match self.file_type {
+            FileType::HamtShard => l.name.len() > 2 && &l.name[2..] == name,
+            _ => l.name == name,
+        })
+    }
+
+    /// the sub shards of the hamt shard.
+    pub fn sub_shards(&self) -> impl Iterator<Item = &Link> {
+        let is_shard = self.file_type == FileType::HamtShard;
+        self.links
+            .iter()
+            .filter(move |l| is_shard && l.name.len() == 2)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+
+    fn field(out: &mut Vec<u8>, tag: u64, bytes: &[u8]) {
+        let mut buf = unsigned_varint::encode::u64_buffer();
+        out.extend_from_slice(unsigned_varint::encode::u64(tag << 3 | 2, &mut buf));
+        out.extend_from_slice(unsigned_varint::encode::u64(bytes.len() as _, &mut buf));
+        out.extend_from_slice(bytes);
+    }
+
+    /// encode the dag-pb node with the unixfs data, it's used to build the test car.
+    pub(crate) fn encode_node(file_type: u64, data: &[u8], links: &[(&Cid, &str)]) -> Vec<u8> {
+        let mut out = Vec::new();
+        for (cid, name) in links {
+            let mut link = Vec::new();
+            field(&mut link, 1, &cid.to_bytes());
+            field(&mut link, 2, name.as_bytes());
+            field(&mut out, 2, &link);
+        }
+        let mut unixfs = vec![0x08, file_type as u8];
+        if !data.is_empty() {
+            field(&mut unixfs, 2, data);
+        }
+        field(&mut out, 1, &unixfs);
+        out
+    }
+
+    #[test]
+    fn test_decode_node() {
+        let file = Cid::new_v1(super::super::cid::RAW, b"hello");
+        let buf = encode_node(1, b"", &[(&file, "a.txt")]);
+        let node = Node::decode(&buf).unwrap();
+        assert_eq!(node.file_type, FileType::Directory);
+        assert_eq!(node.find_link("a.txt").unwrap().cid, file);
+        assert!(node.find_link("b.txt").is_none());
+
+        let buf = encode_node(5, b"", &[(&file, "0Fa.txt"), (&file, "A1")]);
+        let node = Node::decode(&buf).unwrap();
+        assert_eq!(node.file_type, FileType::HamtShard);
+        assert!(node.find_link("a.txt").is_some());
+        assert_eq!(node.sub_shards().count(), 1);
+
+        let buf = encode_node(2, b"data", &[]);
+        let node = Node::decode(&buf).unwrap();
+        assert_eq!(node.file_type, FileType::File);
+        assert_eq!(node.data, b"data");
+        assert!(Node::decode(&[0x12, 0x05]).is_err());
+    }
+}
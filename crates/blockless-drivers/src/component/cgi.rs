@@ -0,0 +1,77 @@
+use super::blockless::extensions::cgi::{CgiError, Host};
+use super::read_buf;
+use crate::cgi_driver::{
+    self, cgi_directory_list_exec, cgi_directory_list_read, child_stderr_read, child_stdin_write,
+    child_stdout_read, command_alias, command_and_exec,
+};
+use crate::CgiErrorKind;
+use log::error;
+use wasi_common::{Extension, WasiCtx};
+
+impl From<CgiErrorKind> for CgiError {
+    fn from(e: CgiErrorKind) -> CgiError {
+        match e {
+            CgiErrorKind::InvalidHandle => CgiError::InvalidHandle,
+            CgiErrorKind::InvalidParameter => CgiError::InvalidParameter,
+            CgiErrorKind::RuntimeError => CgiError::RuntimeError,
+            CgiErrorKind::InvalidExtension => CgiError::InvalidExtension,
+            CgiErrorKind::PermissionDeny => CgiError::PermissionDeny,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Host for WasiCtx {
+    async fn cgi_list_exec(&mut self) -> Result<u32, CgiError> {
+        let root_path = self.config_drivers_root_path_ref().unwrap();
+        let rs = cgi_directory_list_exec(self.resource_table(), &root_path).await?;
+        Ok(rs)
+    }
+
+    async fn cgi_list_read(&mut self, handle: u32, len: u32) -> Result<Vec<u8>, CgiError> {
+        let mut buf = read_buf(len);
+        let rs = cgi_directory_list_read(self.resource_table(), handle, &mut buf).await?;
+        buf.truncate(rs as _);
+        self.usage.bytes_in(Extension::Cgi, rs as _);
+        Ok(buf)
+    }
+
+    async fn cgi_open(&mut self, command: String) -> Result<u32, CgiError> {
+        let alias = command_alias(&command)?;
+        if !self.schema_permission("cgi", &format!("cgi://{alias}")) {
+            error!("Permission Deny");
+            return Err(CgiError::PermissionDeny);
+        }
+        let root_path = self.config_drivers_root_path_ref().unwrap();
+        self.usage.call(Extension::Cgi);
+        let rs = command_and_exec(self.resource_table(), &root_path, &command).await?;
+        Ok(rs)
+    }
+
+    async fn cgi_stdout_read(&mut self, handle: u32, len: u32) -> Result<Vec<u8>, CgiError> {
+        let mut buf = read_buf(len);
+        let rs = child_stdout_read(self.resource_table(), handle, &mut buf).await?;
+        buf.truncate(rs as _);
+        self.usage.bytes_in(Extension::Cgi, rs as _);
+        Ok(buf)
+    }
+
+    async fn cgi_stderr_read(&mut self, handle: u32, len: u32) -> Result<Vec<u8>, CgiError> {
+        let mut buf = read_buf(len);
+        let rs = child_stderr_read(self.resource_table(), handle, &mut buf).await?;
+        buf.truncate(rs as _);
+        self.usage.bytes_in(Extension::Cgi, rs as _);
+        Ok(buf)
+    }
+
+    async fn cgi_stdin_write(&mut self, handle: u32, buf: Vec<u8>) -> Result<u32, CgiError> {
+        let rs = child_stdin_write(self.resource_table(), handle, &buf).await?;
+        self.usage.bytes_out(Extension::Cgi, rs as _);
+        Ok(rs)
+    }
+
+    async fn cgi_close(&mut self, handle: u32) -> Result<(), CgiError> {
+        cgi_driver::close(self.resource_table(), handle)?;
+        Ok(())
+    }
+}
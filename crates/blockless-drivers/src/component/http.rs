@@ -0,0 +1,106 @@
+use super::blockless::extensions::http::{Host, HttpError};
+use super::read_buf;
+use crate::wasi::http::{http_opts, request_url};
+use crate::{http_driver, HttpErrorKind};
+use log::error;
+use wasi_common::{Extension, WasiCtx};
+
+impl From<HttpErrorKind> for HttpError {
+    fn from(e: HttpErrorKind) -> HttpError {
+        match e {
+            HttpErrorKind::InvalidHandle => HttpError::InvalidHandle,
+            HttpErrorKind::MemoryAccessError => HttpError::MemoryAccessError,
+            HttpErrorKind::BufferTooSmall => HttpError::BufferTooSmall,
+            HttpErrorKind::HeaderNotFound => HttpError::HeaderNotFound,
+            HttpErrorKind::Utf8Error => HttpError::Utf8Error,
+            HttpErrorKind::DestinationNotAllowed => HttpError::DestinationNotAllowed,
+            HttpErrorKind::InvalidMethod => HttpError::InvalidMethod,
+            HttpErrorKind::InvalidEncoding => HttpError::InvalidEncoding,
+            HttpErrorKind::InvalidUrl => HttpError::InvalidUrl,
+            HttpErrorKind::RequestError => HttpError::RequestError,
+            HttpErrorKind::RuntimeError => HttpError::RuntimeError,
+            HttpErrorKind::TooManySessions => HttpError::TooManySessions,
+            HttpErrorKind::InvalidDriver => HttpError::InvalidDriver,
+            HttpErrorKind::PermissionDeny => HttpError::PermissionDeny,
+            HttpErrorKind::HeadersValidationError => HttpError::HeadersValidationError,
+        }
+    }
+}
+
+/// parse the url and check the method permission of the request, then fill the credential
+/// of the options, return the parsed url and the options.
+fn request_opts(ctx: &WasiCtx, url: &str, opts: &str) -> Result<(String, String), HttpErrorKind> {
+    let url = request_url(url)?;
+    let method = http_driver::request_method(opts);
+    if !ctx.method_permission(&url, method.as_deref()) {
+        error!("Permission Deny");
+        return Err(HttpErrorKind::PermissionDeny);
+    }
+    let opts = http_opts(ctx, &url, opts)?;
+    Ok((url, opts))
+}
+
+#[async_trait::async_trait]
+impl Host for WasiCtx {
+    async fn http_req(&mut self, url: String, opts: String) -> Result<(u32, i32), HttpError> {
+        let (url, opts) = request_opts(self, &url, &opts)?;
+        self.usage.call(Extension::Http);
+        self.usage
+            .bytes_out(Extension::Http, http_driver::request_body_len(&opts));
+        let (fd, code) = http_driver::http_req(self.resource_table(), &url, &opts).await?;
+        Ok((fd, code))
+    }
+
+    async fn http_open(&mut self, url: String, opts: String) -> Result<u32, HttpError> {
+        let (url, opts) = request_opts(self, &url, &opts)?;
+        self.usage.call(Extension::Http);
+        let fd = http_driver::http_open(self.resource_table(), &url, &opts).await?;
+        Ok(fd)
+    }
+
+    async fn http_write_body(&mut self, handle: u32, body: Vec<u8>) -> Result<u32, HttpError> {
+        let n = http_driver::http_write_body(self.resource_table(), handle, &body).await?;
+        self.usage.bytes_out(Extension::Http, n as _);
+        Ok(n)
+    }
+
+    async fn http_send(&mut self, handle: u32) -> Result<i32, HttpError> {
+        let code = http_driver::http_send(self.resource_table(), handle).await?;
+        Ok(code)
+    }
+
+    async fn http_close(&mut self, handle: u32) -> Result<(), HttpError> {
+        http_driver::http_close(self.resource_table(), handle).await?;
+        Ok(())
+    }
+
+    async fn http_read_header(
+        &mut self,
+        handle: u32,
+        name: String,
+        len: u32,
+    ) -> Result<Vec<u8>, HttpError> {
+        let mut buf = read_buf(len);
+        let rs =
+            http_driver::http_read_head(self.resource_table(), handle, &name, &mut buf).await?;
+        buf.truncate(rs as _);
+        self.usage.bytes_in(Extension::Http, rs as _);
+        Ok(buf)
+    }
+
+    async fn http_read_headers(&mut self, handle: u32, len: u32) -> Result<Vec<u8>, HttpError> {
+        let mut buf = read_buf(len);
+        let rs = http_driver::http_read_headers(self.resource_table(), handle, &mut buf).await?;
+        buf.truncate(rs as _);
+        self.usage.bytes_in(Extension::Http, rs as _);
+        Ok(buf)
+    }
+
+    async fn http_read_body(&mut self, handle: u32, len: u32) -> Result<Vec<u8>, HttpError> {
+        let mut buf = read_buf(len);
+        let rs = http_driver::http_read_body(self.resource_table(), handle, &mut buf).await?;
+        buf.truncate(rs as _);
+        self.usage.bytes_in(Extension::Http, rs as _);
+        Ok(buf)
+    }
+}
@@ -0,0 +1,55 @@
+use super::blockless::extensions::ipfs::{Host, IpfsError};
+use super::read_buf;
+use crate::{ipfs_driver, IpfsErrorKind};
+use log::error;
+use wasi_common::{Extension, WasiCtx};
+
+impl From<IpfsErrorKind> for IpfsError {
+    fn from(e: IpfsErrorKind) -> IpfsError {
+        match e {
+            IpfsErrorKind::InvalidHandle => IpfsError::InvalidHandle,
+            IpfsErrorKind::Utf8Error => IpfsError::Utf8Error,
+            IpfsErrorKind::InvalidParameter => IpfsError::InvalidParameter,
+            IpfsErrorKind::InvalidMethod => IpfsError::InvalidMethod,
+            IpfsErrorKind::InvalidEncoding => IpfsError::InvalidEncoding,
+            IpfsErrorKind::RequestError => IpfsError::RequestError,
+            IpfsErrorKind::RuntimeError => IpfsError::RuntimeError,
+            IpfsErrorKind::TooManySessions => IpfsError::TooManySessions,
+            IpfsErrorKind::PermissionDeny => IpfsError::PermissionDeny,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Host for WasiCtx {
+    async fn ipfs_command(&mut self, params: String) -> Result<(u32, u32), IpfsError> {
+        let api = ipfs_driver::command_api(&params)?;
+        if !self.schema_permission("ipfs", &format!("ipfs://{api}")) {
+            error!("Permission Deny");
+            return Err(IpfsError::PermissionDeny);
+        }
+        let ipfs_api = self.ipfs_api();
+        self.usage.call(Extension::Ipfs);
+        let (status, fd) = ipfs_driver::command(self.resource_table(), &ipfs_api, &params).await?;
+        Ok((fd, status))
+    }
+
+    async fn ipfs_read(&mut self, handle: u32, len: u32) -> Result<Vec<u8>, IpfsError> {
+        let mut buf = read_buf(len);
+        let rs = ipfs_driver::read_body(self.resource_table(), handle, &mut buf).await?;
+        buf.truncate(rs as _);
+        self.usage.bytes_in(Extension::Ipfs, rs as _);
+        Ok(buf)
+    }
+
+    async fn ipfs_write(&mut self, handle: u32, body: Vec<u8>) -> Result<u32, IpfsError> {
+        let rs = ipfs_driver::write_body(self.resource_table(), handle, &body).await?;
+        self.usage.bytes_out(Extension::Ipfs, rs as _);
+        Ok(rs)
+    }
+
+    async fn ipfs_close(&mut self, handle: u32) -> Result<(), IpfsError> {
+        ipfs_driver::close(self.resource_table(), handle).await?;
+        Ok(())
+    }
+}
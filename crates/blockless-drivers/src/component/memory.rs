@@ -0,0 +1,39 @@
+use super::blockless::extensions::memory::{Host, MemoryError};
+use super::read_buf;
+use crate::{memory_driver, BlocklessMemoryErrorKind};
+use log::error;
+use wasi_common::WasiCtx;
+
+impl From<BlocklessMemoryErrorKind> for MemoryError {
+    fn from(e: BlocklessMemoryErrorKind) -> MemoryError {
+        match e {
+            BlocklessMemoryErrorKind::InvalidHandle => MemoryError::InvalidHandle,
+            BlocklessMemoryErrorKind::RuntimeError => MemoryError::RuntimeError,
+            BlocklessMemoryErrorKind::InvalidParameter => MemoryError::InvalidParameter,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Host for WasiCtx {
+    async fn memory_read(&mut self, len: u32) -> Result<Vec<u8>, MemoryError> {
+        if len == 0 {
+            return Err(MemoryError::InvalidParameter);
+        }
+        let mut buf = read_buf(len);
+        let rs = self.config_stdin_read(&mut buf).await.map_err(|e| {
+            error!("read the stdin error: {}", e);
+            MemoryError::RuntimeError
+        })?;
+        buf.truncate(rs);
+        Ok(buf)
+    }
+
+    async fn env_var_read(&mut self, len: u32) -> Result<Vec<u8>, MemoryError> {
+        let json = memory_driver::env_json(&self.config_env_vars());
+        let mut buf = read_buf(len);
+        let rs = memory_driver::read_at(&mut buf, json.as_bytes(), &self.env_offset)?;
+        buf.truncate(rs as _);
+        Ok(buf)
+    }
+}
@@ -0,0 +1,22 @@
+//! the component bindings of the extensions, the wit is derived from the witx,
+//! the host functions share the drivers and the permission checks with the wasi modules.
+mod cgi;
+mod http;
+mod ipfs;
+mod memory;
+mod s3;
+mod socket;
+
+wasmtime::component::bindgen!({
+    path: "wit",
+    world: "extensions",
+    async: true,
+});
+
+/// the max bytes of a read of the guest, the rest is read by the next calls.
+const MAX_READ_SIZE: u32 = 1024 * 1024;
+
+/// the buffer of the read, the length of the guest is clamped to `MAX_READ_SIZE`.
+fn read_buf(len: u32) -> Vec<u8> {
+    vec![0; len.min(MAX_READ_SIZE) as usize]
+}
@@ -0,0 +1,72 @@
+use super::blockless::extensions::s3::{Host, S3Error};
+use super::read_buf;
+use crate::wasi::s3::{bucket_params, s3_params};
+use crate::{s3_driver, S3ErrorKind};
+use wasi_common::{Extension, WasiCtx};
+
+impl From<S3ErrorKind> for S3Error {
+    fn from(e: S3ErrorKind) -> S3Error {
+        match e {
+            S3ErrorKind::InvalidHandle => S3Error::InvalidHandle,
+            S3ErrorKind::Utf8Error => S3Error::Utf8Error,
+            S3ErrorKind::InvalidParameter => S3Error::InvalidParameter,
+            S3ErrorKind::InvalidMethod => S3Error::InvalidMethod,
+            S3ErrorKind::InvalidEncoding => S3Error::InvalidEncoding,
+            S3ErrorKind::CredentialsError => S3Error::CredentialsError,
+            S3ErrorKind::RegionError => S3Error::RegionError,
+            S3ErrorKind::RequestError => S3Error::RequestError,
+            S3ErrorKind::RuntimeError => S3Error::RuntimeError,
+            S3ErrorKind::TooManySessions => S3Error::TooManySessions,
+            S3ErrorKind::PermissionDeny => S3Error::PermissionDeny,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Host for WasiCtx {
+    async fn bucket_command(&mut self, cmd: u16, params: String) -> Result<u32, S3Error> {
+        let params = bucket_params(self, cmd, &params)?;
+        self.usage.call(Extension::S3);
+        let rs = s3_driver::bucket_command(self.resource_table(), cmd, &params).await?;
+        Ok(rs)
+    }
+
+    async fn bucket_put_object(&mut self, params: String, body: Vec<u8>) -> Result<(), S3Error> {
+        let params = s3_params(self, &params)?;
+        self.usage.call(Extension::S3);
+        s3_driver::bucket_put_object(&params, &body).await?;
+        self.usage.bytes_out(Extension::S3, body.len() as _);
+        Ok(())
+    }
+
+    async fn s3_multipart_open(&mut self, params: String) -> Result<u32, S3Error> {
+        let params = s3_params(self, &params)?;
+        self.usage.call(Extension::S3);
+        let rs = s3_driver::multipart_open(self.resource_table(), &params).await?;
+        Ok(rs)
+    }
+
+    async fn s3_multipart_write(&mut self, handle: u32, body: Vec<u8>) -> Result<u32, S3Error> {
+        let rs = s3_driver::multipart_write(self.resource_table(), handle, &body).await?;
+        self.usage.bytes_out(Extension::S3, rs as _);
+        Ok(rs)
+    }
+
+    async fn s3_multipart_complete(&mut self, handle: u32) -> Result<(), S3Error> {
+        s3_driver::multipart_complete(self.resource_table(), handle).await?;
+        Ok(())
+    }
+
+    async fn s3_read(&mut self, handle: u32, len: u32) -> Result<Vec<u8>, S3Error> {
+        let mut buf = read_buf(len);
+        let rs = s3_driver::read(self.resource_table(), handle, &mut buf).await?;
+        buf.truncate(rs as _);
+        self.usage.bytes_in(Extension::S3, rs as _);
+        Ok(buf)
+    }
+
+    async fn s3_close(&mut self, handle: u32) -> Result<(), S3Error> {
+        s3_driver::close(self.resource_table(), handle).await?;
+        Ok(())
+    }
+}
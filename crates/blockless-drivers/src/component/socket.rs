@@ -0,0 +1,210 @@
+use super::blockless::extensions::socket::{Host, SocketError, UdpRecv};
+use super::read_buf;
+use crate::tcp_driver::{
+    self,
+    tls::{self, TlsOptions},
+};
+use crate::udp_driver::{UdpSocket, MAX_DATAGRAM_SIZE};
+use crate::BlocklessSocketErrorKind;
+use log::error;
+use std::io::{IoSlice, IoSliceMut};
+use std::sync::Arc;
+use std::time::Duration;
+use wasi_common::file::{FdFlags, FileAccessMode, FileEntry};
+use wasi_common::snapshots::preview_1::error::Errno;
+use wasi_common::{WasiCtx, WasiFile};
+
+impl From<BlocklessSocketErrorKind> for SocketError {
+    fn from(e: BlocklessSocketErrorKind) -> SocketError {
+        match e {
+            BlocklessSocketErrorKind::AddressInUse => SocketError::AddressInUse,
+            BlocklessSocketErrorKind::ConnectRefused => SocketError::ConnectionRefused,
+            BlocklessSocketErrorKind::ConnectionReset => SocketError::ConnectionReset,
+            BlocklessSocketErrorKind::ParameterError => SocketError::ParameterError,
+            BlocklessSocketErrorKind::PermissionDeny => SocketError::PermissionDeny,
+            BlocklessSocketErrorKind::TlsError => SocketError::TlsError,
+            BlocklessSocketErrorKind::Timeout => SocketError::Timeout,
+            BlocklessSocketErrorKind::HostUnreachable => SocketError::HostUnreachable,
+            BlocklessSocketErrorKind::WouldBlock => SocketError::WouldBlock,
+            BlocklessSocketErrorKind::NameNotResolved => SocketError::NameNotResolved,
+            BlocklessSocketErrorKind::InvalidHandle => SocketError::InvalidHandle,
+            BlocklessSocketErrorKind::BufferTooSmall => SocketError::BufferTooSmall,
+            BlocklessSocketErrorKind::RuntimeError => SocketError::RuntimeError,
+        }
+    }
+}
+
+/// the errors of the socket files are the wasi errno.
+fn file_error(e: wasi_common::Error) -> SocketError {
+    match e.downcast() {
+        Ok(Errno::Again) => SocketError::WouldBlock,
+        Ok(Errno::Connreset) | Ok(Errno::Pipe) => SocketError::ConnectionReset,
+        Ok(Errno::Badf) | Ok(Errno::Notsup) => SocketError::InvalidHandle,
+        Ok(Errno::Timedout) => SocketError::Timeout,
+        _ => SocketError::RuntimeError,
+    }
+}
+
+fn tcp_permission(ctx: &WasiCtx, addr: &str) -> Result<(), SocketError> {
+    if !ctx.schema_permission("tcp", &format!("tcp://{addr}")) {
+        error!("Permission Deny");
+        return Err(SocketError::PermissionDeny);
+    }
+    Ok(())
+}
+
+fn udp_permission(ctx: &WasiCtx, addr: &str) -> Result<(), SocketError> {
+    if !ctx.schema_permission("udp", &format!("udp://{addr}")) {
+        error!("Permission Deny");
+        return Err(SocketError::PermissionDeny);
+    }
+    Ok(())
+}
+
+fn push_file(ctx: &WasiCtx, file: Box<dyn WasiFile>) -> Result<u32, SocketError> {
+    let mode = FileAccessMode::READ | FileAccessMode::WRITE;
+    ctx.table()
+        .push(Arc::new(FileEntry::new(file, mode)))
+        .map_err(|_| SocketError::RuntimeError)
+}
+
+fn socket(ctx: &WasiCtx, fd: u32) -> Result<Arc<FileEntry>, SocketError> {
+    ctx.table()
+        .get::<FileEntry>(fd)
+        .map_err(|_| SocketError::InvalidHandle)
+}
+
+/// get the file entry of the udp socket, the entry holds the socket across the await.
+fn udp_socket(ctx: &WasiCtx, fd: u32) -> Result<Arc<FileEntry>, SocketError> {
+    let entry = socket(ctx, fd)?;
+    if !entry.file.as_any().is::<UdpSocket>() {
+        return Err(SocketError::InvalidHandle);
+    }
+    Ok(entry)
+}
+
+fn as_udp(entry: &FileEntry) -> &UdpSocket {
+    entry.file.as_any().downcast_ref::<UdpSocket>().unwrap()
+}
+
+#[async_trait::async_trait]
+impl Host for WasiCtx {
+    async fn create_tcp_bind_socket(&mut self, target: String) -> Result<u32, SocketError> {
+        tcp_permission(self, &target)?;
+        let f = tcp_driver::bind(&target).await?;
+        push_file(self, f)
+    }
+
+    async fn tcp_connect(&mut self, target: String) -> Result<u32, SocketError> {
+        tcp_permission(self, &target)?;
+        let f = tcp_driver::connect(&target, tcp_driver::CONNECT_TIMEOUT)
+            .await
+            .and_then(tcp_driver::stream_file)?;
+        push_file(self, f)
+    }
+
+    async fn tls_connect(&mut self, target: String, opts: String) -> Result<u32, SocketError> {
+        tcp_permission(self, &target)?;
+        let opts = match opts.as_str() {
+            "" => json::JsonValue::Null,
+            opts => json::parse(opts).map_err(|_| SocketError::ParameterError)?,
+        };
+        let mut opts = TlsOptions::from_json(&opts, &target)?;
+        opts.ca_file = self.config_tls_ca_file();
+        let stream = tls::tls_connect(&target, &opts).await?;
+        push_file(self, Box::new(stream))
+    }
+
+    async fn udp_bind(&mut self, target: String) -> Result<u32, SocketError> {
+        udp_permission(self, &target)?;
+        let socket = UdpSocket::bind(&target).await?;
+        push_file(self, Box::new(socket))
+    }
+
+    async fn udp_connect(&mut self, handle: u32, target: String) -> Result<(), SocketError> {
+        udp_permission(self, &target)?;
+        let entry = udp_socket(self, handle)?;
+        as_udp(&entry).connect(&target).await?;
+        Ok(())
+    }
+
+    async fn udp_send_to(
+        &mut self,
+        handle: u32,
+        target: String,
+        buf: Vec<u8>,
+    ) -> Result<u32, SocketError> {
+        udp_permission(self, &target)?;
+        let entry = udp_socket(self, handle)?;
+        let n = as_udp(&entry).send_to(&buf, &target).await?;
+        Ok(n as u32)
+    }
+
+    async fn udp_recv_from(
+        &mut self,
+        handle: u32,
+        timeout_ms: u32,
+        len: u32,
+    ) -> Result<UdpRecv, SocketError> {
+        let entry = udp_socket(self, handle)?;
+        let timeout = match timeout_ms {
+            0 => None,
+            ms => Some(Duration::from_millis(ms as _)),
+        };
+        let mut data = vec![0; (len as usize).min(MAX_DATAGRAM_SIZE)];
+        let (n, from) = as_udp(&entry).recv_from(&mut data, timeout).await?;
+        data.truncate(n);
+        Ok(UdpRecv {
+            data,
+            addr: from.to_string(),
+        })
+    }
+
+    async fn resolve(&mut self, host: String) -> Result<Vec<String>, SocketError> {
+        if !self.host_permission(&["tcp", "udp"], &host) {
+            error!("Permission Deny");
+            return Err(SocketError::PermissionDeny);
+        }
+        let ips = tcp_driver::resolve(&host).await?;
+        Ok(ips.iter().map(|ip| ip.to_string()).collect())
+    }
+
+    async fn socket_accept(&mut self, handle: u32) -> Result<u32, SocketError> {
+        let entry = socket(self, handle)?;
+        let f = entry
+            .file
+            .sock_accept(FdFlags::empty())
+            .await
+            .map_err(file_error)?;
+        push_file(self, f)
+    }
+
+    async fn socket_read(&mut self, handle: u32, len: u32) -> Result<Vec<u8>, SocketError> {
+        let entry = socket(self, handle)?;
+        let mut buf = read_buf(len);
+        let n = entry
+            .file
+            .read_vectored(&mut [IoSliceMut::new(&mut buf)])
+            .await
+            .map_err(file_error)?;
+        buf.truncate(n as _);
+        Ok(buf)
+    }
+
+    async fn socket_write(&mut self, handle: u32, buf: Vec<u8>) -> Result<u32, SocketError> {
+        let entry = socket(self, handle)?;
+        let n = entry
+            .file
+            .write_vectored(&[IoSlice::new(&buf)])
+            .await
+            .map_err(file_error)?;
+        Ok(n as u32)
+    }
+
+    async fn socket_close(&mut self, handle: u32) -> Result<(), SocketError> {
+        match self.table().delete::<FileEntry>(handle) {
+            Some(_) => Ok(()),
+            None => Err(SocketError::InvalidHandle),
+        }
+    }
+}
@@ -168,6 +168,7 @@ pub enum CgiErrorKind {
     RuntimeError,
     InvalidParameter,
     InvalidExtension,
+    PermissionDeny,
 }
 
 impl std::error::Error for CgiErrorKind {}
@@ -179,6 +180,7 @@ impl std::fmt::Display for CgiErrorKind {
             &Self::InvalidHandle => write!(f, "Invalid Error"),
             &Self::InvalidParameter => write!(f, "Invalid parameter"),
             &Self::InvalidExtension => write!(f, "Invalid extension"),
+            &Self::PermissionDeny => write!(f, "Permision deny."),
         }
     }
 }
@@ -189,6 +191,15 @@ pub enum BlocklessSocketErrorKind {
     ParameterError,
     ConnectionReset,
     AddressInUse,
+    PermissionDeny,
+    TlsError,
+    Timeout,
+    HostUnreachable,
+    WouldBlock,
+    NameNotResolved,
+    InvalidHandle,
+    BufferTooSmall,
+    RuntimeError,
 }
 
 impl std::error::Error for BlocklessSocketErrorKind {}
@@ -200,6 +211,159 @@ impl std::fmt::Display for BlocklessSocketErrorKind {
             Self::ConnectionReset => write!(f, "Connection Reset Error"),
             Self::AddressInUse => write!(f, "Address In Use"),
             Self::ParameterError => write!(f, "Parameter Error"),
+            Self::PermissionDeny => write!(f, "Permision deny."),
+            Self::TlsError => write!(f, "Tls Error"),
+            Self::Timeout => write!(f, "Timeout"),
+            Self::HostUnreachable => write!(f, "Host Unreachable"),
+            Self::WouldBlock => write!(f, "Would Block"),
+            Self::NameNotResolved => write!(f, "Name Not Resolved"),
+            Self::InvalidHandle => write!(f, "Invalid Handle"),
+            Self::BufferTooSmall => write!(f, "Buffer Too Small"),
+            Self::RuntimeError => write!(f, "Runtime Error"),
+        }
+    }
+}
+
+impl From<std::io::Error> for BlocklessSocketErrorKind {
+    fn from(e: std::io::Error) -> Self {
+        use std::io::ErrorKind;
+        match e.kind() {
+            ErrorKind::ConnectionRefused => Self::ConnectRefused,
+            ErrorKind::ConnectionReset | ErrorKind::ConnectionAborted | ErrorKind::BrokenPipe => {
+                Self::ConnectionReset
+            }
+            ErrorKind::AddrInUse => Self::AddressInUse,
+            ErrorKind::PermissionDenied => Self::PermissionDeny,
+            ErrorKind::TimedOut => Self::Timeout,
+            ErrorKind::HostUnreachable | ErrorKind::NetworkUnreachable => Self::HostUnreachable,
+            ErrorKind::WouldBlock => Self::WouldBlock,
+            ErrorKind::InvalidInput | ErrorKind::AddrNotAvailable => Self::ParameterError,
+            _ => Self::RuntimeError,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum CarErrorKind {
+    InvalidHandle,
+    InvalidParameter,
+    InvalidCar,
+    InvalidCid,
+    InvalidNode,
+    UnsupportedCid,
+    HashMismatch,
+    NotFound,
+    NotFile,
+    BufferTooSmall,
+    PermissionDeny,
+    RuntimeError,
+}
+
+impl std::error::Error for CarErrorKind {}
+
+impl std::fmt::Display for CarErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Self::InvalidHandle => write!(f, "Invalid handle"),
+            Self::InvalidParameter => write!(f, "Invalid parameter"),
+            Self::InvalidCar => write!(f, "Invalid car file"),
+            Self::InvalidCid => write!(f, "Invalid cid"),
+            Self::InvalidNode => write!(f, "Invalid dag-pb node"),
+            Self::UnsupportedCid => write!(f, "Unsupported cid hash"),
+            Self::HashMismatch => write!(f, "The block hash is mismatched with the cid"),
+            Self::NotFound => write!(f, "Not found"),
+            Self::NotFile => write!(f, "Not a file"),
+            Self::BufferTooSmall => write!(f, "Buffer too small"),
+            Self::PermissionDeny => write!(f, "Permision deny."),
+            Self::RuntimeError => write!(f, "Runtime error"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum KvErrorKind {
+    InvalidHandle,
+    InvalidParameter,
+    NotFound,
+    BufferTooSmall,
+    ValueTooLarge,
+    QuotaExceeded,
+    PermissionDeny,
+    RuntimeError,
+}
+
+impl std::error::Error for KvErrorKind {}
+
+impl std::fmt::Display for KvErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Self::InvalidHandle => write!(f, "Invalid handle"),
+            Self::InvalidParameter => write!(f, "Invalid parameter"),
+            Self::NotFound => write!(f, "Not found"),
+            Self::BufferTooSmall => write!(f, "Buffer too small"),
+            Self::ValueTooLarge => write!(f, "The value is too large"),
+            Self::QuotaExceeded => write!(f, "The quota of the namespace is exceeded"),
+            Self::PermissionDeny => write!(f, "Permision deny."),
+            Self::RuntimeError => write!(f, "Runtime error"),
+        }
+    }
+}
+
+impl From<rusqlite::Error> for KvErrorKind {
+    fn from(e: rusqlite::Error) -> Self {
+        log::error!("kv store error: {e}");
+        KvErrorKind::RuntimeError
+    }
+}
+
+#[derive(Debug)]
+pub enum WsErrorKind {
+    InvalidHandle,
+    InvalidParameter,
+    InvalidUrl,
+    ConnectError,
+    Timeout,
+    Closed,
+    ProtocolError,
+    PermissionDeny,
+    RuntimeError,
+}
+
+impl std::error::Error for WsErrorKind {}
+
+impl std::fmt::Display for WsErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Self::InvalidHandle => write!(f, "Invalid handle"),
+            Self::InvalidParameter => write!(f, "Invalid parameter"),
+            Self::InvalidUrl => write!(f, "Invalid url"),
+            Self::ConnectError => write!(f, "Connect error"),
+            Self::Timeout => write!(f, "Timeout"),
+            Self::Closed => write!(f, "The connection is closed"),
+            Self::ProtocolError => write!(f, "The websocket protocol error"),
+            Self::PermissionDeny => write!(f, "Permision deny."),
+            Self::RuntimeError => write!(f, "Runtime error"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum HttpServerErrorKind {
+    InvalidHandle,
+    InvalidParameter,
+    BufferTooSmall,
+    RuntimeError,
+}
+
+impl std::error::Error for HttpServerErrorKind {}
+
+impl std::fmt::Display for HttpServerErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Self::InvalidHandle => write!(f, "Invalid handle"),
+            Self::InvalidParameter => write!(f, "Invalid parameter"),
+            Self::BufferTooSmall => write!(f, "Buffer too small"),
+            Self::RuntimeError => write!(f, "Runtime error"),
         }
     }
 }
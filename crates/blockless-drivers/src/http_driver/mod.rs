@@ -8,52 +8,192 @@ use cdylib_driver::get_http_driver;
 pub(crate) use cdylib_driver::init_http_driver;
 
 use crate::HttpErrorKind;
+use wasi_common::Table;
 
+/// get the request method from the options json, it's used for the permission check.
+pub fn request_method(opts: &str) -> Option<String> {
+    let json = json::parse(opts).ok()?;
+    json["method"].as_str().map(str::to_ascii_uppercase)
+}
+
+/// get the length of the string body from the options json, it's counted in the usage.
+pub fn request_body_len(opts: &str) -> u64 {
+    json::parse(opts)
+        .ok()
+        .and_then(|json| json["body"].as_str().map(|b| b.len() as u64))
+        .unwrap_or(0)
+}
+
+/// get the credential profile name from the options json, e.g. `{"profile": "api-token"}`.
+pub fn profile_name(opts: &str) -> Option<String> {
+    let json = json::parse(opts).ok()?;
+    json["profile"].as_str().map(String::from)
+}
+
+/// replace the profile of the options with the `Authorization` header,
+/// the `Authorization` header of the options is overridden.
+pub fn with_authorization(opts: &str, auth: &str) -> Result<String, HttpErrorKind> {
+    let mut json = json::parse(opts).map_err(|_| HttpErrorKind::HeadersValidationError)?;
+    json.remove("profile");
+    let mut headers = match json.remove("headers") {
+        json::JsonValue::Null => json::JsonValue::new_object(),
+        h if h.is_object() => h,
+        h => match h.as_str().map(json::parse) {
+            Some(Ok(o)) if o.is_object() => o,
+            _ => return Err(HttpErrorKind::HeadersValidationError),
+        },
+    };
+    let keys = headers
+        .entries()
+        .map(|(k, _)| k.to_string())
+        .filter(|k| k.eq_ignore_ascii_case("authorization"))
+        .collect::<Vec<_>>();
+    for key in keys {
+        headers.remove(&key);
+    }
+    headers["Authorization"] = auth.into();
+    json["headers"] = headers;
+    Ok(json::stringify(json))
+}
+
+/// the handles of the cdylib driver are managed by the driver library, so the table is unused.
 #[cfg(not(feature = "builtin_http"))]
-pub async fn http_req(url: &str, opts: &str) -> Result<(u32, i32), HttpErrorKind> {
+pub async fn http_req(_table: &Table, url: &str, opts: &str) -> Result<(u32, i32), HttpErrorKind> {
     let driver = get_http_driver().ok_or(HttpErrorKind::InvalidDriver)?;
     driver.http_req(url, opts)
 }
 
 #[cfg(feature = "builtin_http")]
-pub async fn http_req(url: &str, opts: &str) -> Result<(u32, i32), HttpErrorKind> {
-    reqwest_driver::http_req(url, opts).await
+pub async fn http_req(table: &Table, url: &str, opts: &str) -> Result<(u32, i32), HttpErrorKind> {
+    reqwest_driver::http_req(table, url, opts).await
 }
 
+/// the cdylib driver only supports the request with the string body.
 #[cfg(not(feature = "builtin_http"))]
-pub async fn http_close(fd: u32) -> Result<(), HttpErrorKind> {
+pub async fn http_open(_table: &Table, _url: &str, _opts: &str) -> Result<u32, HttpErrorKind> {
+    Err(HttpErrorKind::InvalidDriver)
+}
+
+#[cfg(feature = "builtin_http")]
+pub async fn http_open(table: &Table, url: &str, opts: &str) -> Result<u32, HttpErrorKind> {
+    reqwest_driver::http_open(table, url, opts)
+}
+
+#[cfg(not(feature = "builtin_http"))]
+pub async fn http_write_body(_table: &Table, _fd: u32, _buf: &[u8]) -> Result<u32, HttpErrorKind> {
+    Err(HttpErrorKind::InvalidDriver)
+}
+
+#[cfg(feature = "builtin_http")]
+pub async fn http_write_body(table: &Table, fd: u32, buf: &[u8]) -> Result<u32, HttpErrorKind> {
+    reqwest_driver::http_write_body(table, fd, buf).await
+}
+
+#[cfg(not(feature = "builtin_http"))]
+pub async fn http_send(_table: &Table, _fd: u32) -> Result<i32, HttpErrorKind> {
+    Err(HttpErrorKind::InvalidDriver)
+}
+
+#[cfg(feature = "builtin_http")]
+pub async fn http_send(table: &Table, fd: u32) -> Result<i32, HttpErrorKind> {
+    reqwest_driver::http_send(table, fd).await
+}
+
+#[cfg(not(feature = "builtin_http"))]
+pub async fn http_close(_table: &Table, fd: u32) -> Result<(), HttpErrorKind> {
     let driver = get_http_driver().ok_or(HttpErrorKind::InvalidDriver)?;
     driver.http_close(fd)?;
     Ok(())
 }
 
 #[cfg(feature = "builtin_http")]
-pub async fn http_close(fd: u32) -> Result<(), HttpErrorKind> {
-    reqwest_driver::http_close(fd)
+pub async fn http_close(table: &Table, fd: u32) -> Result<(), HttpErrorKind> {
+    reqwest_driver::http_close(table, fd)
 }
 
 #[cfg(not(feature = "builtin_http"))]
-pub async fn http_read_head(fd: u32, head: &str, buf: &mut [u8]) -> Result<u32, HttpErrorKind> {
+pub async fn http_read_head(
+    _table: &Table,
+    fd: u32,
+    head: &str,
+    buf: &mut [u8],
+) -> Result<u32, HttpErrorKind> {
     let driver = get_http_driver().ok_or(HttpErrorKind::InvalidDriver)?;
     driver.http_read_head(fd, head.as_bytes(), buf)
 }
 
 #[cfg(feature = "builtin_http")]
-pub async fn http_read_head(fd: u32, head: &str, buf: &mut [u8]) -> Result<u32, HttpErrorKind> {
-    let h = reqwest_driver::http_read_head(fd, head)?;
+pub async fn http_read_head(
+    table: &Table,
+    fd: u32,
+    head: &str,
+    buf: &mut [u8],
+) -> Result<u32, HttpErrorKind> {
+    let h = reqwest_driver::http_read_head(table, fd, head).await?;
     let sbuf = h.as_bytes();
     let copyn = buf.len().min(sbuf.len());
     buf[..copyn].copy_from_slice(&sbuf);
     Ok(copyn as u32)
 }
 
+#[cfg(not(feature = "builtin_http"))]
+pub async fn http_read_headers(
+    _table: &Table,
+    _fd: u32,
+    _buf: &mut [u8],
+) -> Result<u32, HttpErrorKind> {
+    Err(HttpErrorKind::InvalidDriver)
+}
+
+/// the headers json is not truncated, the error is returned if the buffer is too small.
+#[cfg(feature = "builtin_http")]
+pub async fn http_read_headers(
+    table: &Table,
+    fd: u32,
+    buf: &mut [u8],
+) -> Result<u32, HttpErrorKind> {
+    let h = reqwest_driver::http_read_headers(table, fd).await?;
+    let sbuf = h.as_bytes();
+    if sbuf.len() > buf.len() {
+        return Err(HttpErrorKind::BufferTooSmall);
+    }
+    buf[..sbuf.len()].copy_from_slice(sbuf);
+    Ok(sbuf.len() as u32)
+}
+
 #[cfg(feature = "builtin_http")]
-pub async fn http_read_body(fd: u32, buf: &mut [u8]) -> Result<u32, HttpErrorKind> {
-    reqwest_driver::http_read_body(fd, buf).await
+pub async fn http_read_body(table: &Table, fd: u32, buf: &mut [u8]) -> Result<u32, HttpErrorKind> {
+    reqwest_driver::http_read_body(table, fd, buf).await
 }
 
 #[cfg(not(feature = "builtin_http"))]
-pub async fn http_read_body(fd: u32, buf: &mut [u8]) -> Result<u32, HttpErrorKind> {
+pub async fn http_read_body(_table: &Table, fd: u32, buf: &mut [u8]) -> Result<u32, HttpErrorKind> {
     let driver = get_http_driver().ok_or(HttpErrorKind::InvalidDriver)?;
     driver.http_read_body(fd, buf)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_with_authorization() {
+        let opts = r#"{"method": "GET", "profile": "api-token",
+            "headers": "{\"authorization\": \"guest\", \"Accept\": \"*/*\"}"}"#;
+        assert_eq!(profile_name(opts).as_deref(), Some("api-token"));
+        let json = json::parse(&with_authorization(opts, "Bearer token").unwrap()).unwrap();
+        assert!(json["profile"].is_null());
+        assert_eq!(json["method"], "GET");
+        assert_eq!(json["headers"]["Authorization"], "Bearer token");
+        assert_eq!(json["headers"]["Accept"], "*/*");
+        assert!(json["headers"]["authorization"].is_null());
+
+        let json = json::parse(&with_authorization("{}", "Bearer token").unwrap()).unwrap();
+        assert_eq!(json["headers"]["Authorization"], "Bearer token");
+        assert_eq!(profile_name("{}"), None);
+        assert_eq!(
+            with_authorization(r#"{"headers": 1}"#, "Bearer token"),
+            Err(HttpErrorKind::HeadersValidationError)
+        );
+    }
+}
@@ -1,13 +1,23 @@
-use std::{collections::HashMap, pin::Pin, sync::Once, time::Duration};
+use std::{pin::Pin, time::Duration};
 
 use bytes::{Buf, Bytes};
 use futures_util::StreamExt;
+use json::JsonValue;
 use log::{debug, error};
-use reqwest::Response;
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    Body, Method, RequestBuilder, Response,
+};
 
-use crate::HttpErrorKind;
+use crate::{resource, HttpErrorKind};
 use futures_core;
 use futures_core::Stream;
+use tokio::{sync::mpsc, task::JoinHandle};
+use wasi_common::Table;
+
+/// the chunks of the request body buffered before they are sent, the write of the body
+/// waits when the buffer is full.
+const BODY_CHANNEL_SIZE: usize = 16;
 
 type StreamInBox = Pin<Box<dyn Stream<Item = reqwest::Result<Bytes>> + Send>>;
 
@@ -16,78 +26,137 @@ struct StreamState {
     buffer: Option<Bytes>,
 }
 
+/// the request opened by `http_open`, the body is written by the guest in chunks.
+/// The request is started by the first write of the body and the chunks are streamed
+/// to the server, the request without the written body is sent by `http_send`.
+struct RequestState {
+    builder: Option<(RequestBuilder, String)>,
+    sender: Option<mpsc::Sender<Bytes>>,
+    response: Option<JoinHandle<Result<Response, HttpErrorKind>>>,
+}
+
+impl RequestState {
+    /// start the request with the streaming body, return the sender of the body chunks.
+    async fn body_sender(&mut self) -> Result<&mpsc::Sender<Bytes>, HttpErrorKind> {
+        if self.sender.is_none() {
+            let (builder, body) = self.builder.take().ok_or(HttpErrorKind::RuntimeError)?;
+            let (tx, rx) = mpsc::channel::<Bytes>(BODY_CHANNEL_SIZE);
+            let stream = futures_util::stream::unfold(rx, |mut rx| async move {
+                let chunk = rx.recv().await?;
+                Some((Ok::<_, std::io::Error>(chunk), rx))
+            });
+            self.response = Some(tokio::spawn(send(builder, Body::wrap_stream(stream))));
+            if !body.is_empty() {
+                tx.send(body.into())
+                    .await
+                    .map_err(|_| HttpErrorKind::RuntimeError)?;
+            }
+            self.sender = Some(tx);
+        }
+        self.sender.as_ref().ok_or(HttpErrorKind::RuntimeError)
+    }
+}
+
+impl Drop for RequestState {
+    fn drop(&mut self) {
+        // the request closed before sent is cancelled.
+        if let Some(response) = self.response.take() {
+            response.abort();
+        }
+    }
+}
+
 enum HttpCtx {
+    Request(RequestState),
     Response(Response),
     StreamState(StreamState),
 }
 
-/// get the http context
-fn get_ctx() -> Option<&'static mut HashMap<u32, HttpCtx>> {
-    static mut CTX: Option<HashMap<u32, HttpCtx>> = None;
-    static CTX_ONCE: Once = Once::new();
-    CTX_ONCE.call_once(|| unsafe {
-        CTX = Some(HashMap::new());
-    });
-    unsafe { CTX.as_mut() }
+impl HttpCtx {
+    /// the response is converted to the stream when the body is read at the first time.
+    fn stream_state(&mut self) -> Option<&mut StreamState> {
+        if let HttpCtx::Response(_) = self {
+            let empty = HttpCtx::StreamState(StreamState {
+                stream: Box::pin(futures_util::stream::empty()),
+                buffer: None,
+            });
+            if let HttpCtx::Response(resp) = std::mem::replace(self, empty) {
+                *self = HttpCtx::StreamState(StreamState {
+                    stream: Box::pin(resp.bytes_stream()),
+                    buffer: None,
+                });
+            }
+        }
+        match self {
+            HttpCtx::StreamState(state) => Some(state),
+            _ => None,
+        }
+    }
+}
+
+/// build the headers from the options json, the value of the header can be the string or
+/// the array of strings, the headers object nested in the json string is also supported.
+fn build_headers(value: &JsonValue) -> Result<HeaderMap, HttpErrorKind> {
+    let nested;
+    let value = match value {
+        JsonValue::Null => return Ok(HeaderMap::new()),
+        JsonValue::Object(_) => value,
+        v => match v.as_str().map(json::parse) {
+            Some(Ok(o)) if o.is_object() => {
+                nested = o;
+                &nested
+            }
+            _ => return Err(HttpErrorKind::HeadersValidationError),
+        },
+    };
+    let mut headers = HeaderMap::new();
+    for (key, value) in value.entries() {
+        let name = HeaderName::from_bytes(key.as_bytes())
+            .map_err(|_| HttpErrorKind::HeadersValidationError)?;
+        let values = match value {
+            JsonValue::Array(values) => values.iter().collect(),
+            value => vec![value],
+        };
+        for value in values {
+            let value = value
+                .as_str()
+                .and_then(|v| HeaderValue::from_str(v).ok())
+                .ok_or(HttpErrorKind::HeadersValidationError)?;
+            headers.append(name.clone(), value);
+        }
+    }
+    Ok(headers)
 }
 
-fn increase_fd() -> Option<u32> {
-    static mut MAX_HANDLE: u32 = 0;
-    unsafe {
-        MAX_HANDLE += 1;
-        Some(MAX_HANDLE)
+/// get the method from the options json.
+fn request_method(json: &JsonValue) -> Result<Method, HttpErrorKind> {
+    let method = json["method"].as_str().ok_or(HttpErrorKind::RequestError)?;
+    match method.to_ascii_uppercase().as_str() {
+        "GET" => Ok(Method::GET),
+        "POST" => Ok(Method::POST),
+        "PUT" => Ok(Method::PUT),
+        "PATCH" => Ok(Method::PATCH),
+        "DELETE" => Ok(Method::DELETE),
+        "HEAD" => Ok(Method::HEAD),
+        "OPTIONS" => Ok(Method::OPTIONS),
+        _ => Err(HttpErrorKind::InvalidMethod),
     }
 }
 
-/// request the url and the return the fd handle.
-pub(crate) async fn http_req(url: &str, opts: &str) -> Result<(u32, i32), HttpErrorKind> {
+/// build the request from the options json, return the request builder and the body.
+fn request_builder(url: &str, opts: &str) -> Result<(RequestBuilder, String), HttpErrorKind> {
     let json = match json::parse(opts) {
         Ok(o) => o,
         Err(_) => return Err(HttpErrorKind::RequestError),
     };
-    let method = match json["method"].as_str() {
-        Some(s) => String::from(s),
-        None => return Err(HttpErrorKind::RequestError),
-    };
-
-    let mut body = None;
-    if let Some(b) = json["body"].as_str() {
-        body = Some(b.to_string());
-    }
+    let method = request_method(&json)?;
+    let body = json["body"].as_str().unwrap_or_default().to_string();
 
     let connect_timeout = json["connectTimeout"]
         .as_u64()
         .map(|s| Duration::from_secs(s));
     let read_timeout = json["readTimeout"].as_u64().map(|s| Duration::from_secs(s));
-
-    // build the headers from the options json
-    let mut headers = reqwest::header::HeaderMap::new();
-    let header_value = &json["headers"];
-
-    // Check if header_value is a valid string
-    let header_obj = match json::parse(header_value.as_str().unwrap_or_default()) {
-        Ok(o) => o,
-        Err(_) => return Err(HttpErrorKind::HeadersValidationError),
-    };
-
-    if header_obj.is_object() {
-        for (key, value) in header_obj.entries() {
-            // Handle possible errors from from_bytes
-            let header_name = match reqwest::header::HeaderName::from_bytes(key.as_bytes()) {
-                Ok(name) => name,
-                Err(_) => return Err(HttpErrorKind::HeadersValidationError),
-            };
-
-            // Handle possible errors from from_str
-            let header_value =
-                match reqwest::header::HeaderValue::from_str(value.as_str().unwrap_or_default()) {
-                    Ok(value) => value,
-                    Err(_) => return Err(HttpErrorKind::HeadersValidationError),
-                };
-
-            headers.insert(header_name, header_value);
-        }
-    }
+    let headers = build_headers(&json["headers"])?;
 
     let mut client_builder = reqwest::ClientBuilder::new();
     if connect_timeout.is_some() {
@@ -96,36 +165,103 @@ pub(crate) async fn http_req(url: &str, opts: &str) -> Result<(u32, i32), HttpEr
     if read_timeout.is_some() {
         client_builder = client_builder.timeout(read_timeout.unwrap());
     }
-    let client = client_builder.build().unwrap();
-    let req_method = method.to_lowercase();
-    let req_builder = match req_method.as_str() {
-        "get" => client.get(url),
-        "post" => client.post(url),
-        _ => return Err(HttpErrorKind::RequestError),
-    };
-    let resp = req_builder
-        .headers(headers)
-        .body(body.unwrap_or_default())
-        .send()
-        .await
-        .map_err(|e| {
-            error!("request send error, {}", e);
-            HttpErrorKind::RuntimeError
-        })?;
+    let client = client_builder.build().map_err(|e| {
+        error!("build client error, {}", e);
+        HttpErrorKind::RuntimeError
+    })?;
+    let builder = client.request(method, url).headers(headers);
+    Ok((builder, body))
+}
+
+async fn send(builder: RequestBuilder, body: impl Into<Body>) -> Result<Response, HttpErrorKind> {
+    builder.body(body).send().await.map_err(|e| {
+        error!("request send error, {}", e);
+        HttpErrorKind::RuntimeError
+    })
+}
+
+/// request the url and the return the fd handle.
+pub(crate) async fn http_req(
+    table: &Table,
+    url: &str,
+    opts: &str,
+) -> Result<(u32, i32), HttpErrorKind> {
+    let (builder, body) = request_builder(url, opts)?;
+    let resp = send(builder, body).await?;
     let status = resp.status().as_u16() as i32;
-    let fd = increase_fd().unwrap();
-    let ctx = get_ctx().unwrap();
-    ctx.insert(fd, HttpCtx::Response(resp));
+    let fd = resource::push(table, HttpCtx::Response(resp)).ok_or(HttpErrorKind::RuntimeError)?;
     Ok((fd, status))
 }
 
+/// open the request and return the fd handle, the request is sent by `http_send`.
+pub(crate) fn http_open(table: &Table, url: &str, opts: &str) -> Result<u32, HttpErrorKind> {
+    let (builder, body) = request_builder(url, opts)?;
+    let state = RequestState {
+        builder: Some((builder, body)),
+        sender: None,
+        response: None,
+    };
+    resource::push(table, HttpCtx::Request(state)).ok_or(HttpErrorKind::RuntimeError)
+}
+
+/// stream the chunk of the body of the opened request to the server.
+pub(crate) async fn http_write_body(
+    table: &Table,
+    fd: u32,
+    buf: &[u8],
+) -> Result<u32, HttpErrorKind> {
+    let ctx = resource::get::<HttpCtx>(table, fd).ok_or(HttpErrorKind::InvalidHandle)?;
+    let mut ctx = ctx.lock().await;
+    match *ctx {
+        HttpCtx::Request(ref mut state) => {
+            let sender = state.body_sender().await?;
+            // the body is closed when the request is failed, the error is returned by the send.
+            sender
+                .send(Bytes::copy_from_slice(buf))
+                .await
+                .map_err(|_| HttpErrorKind::RuntimeError)?;
+            Ok(buf.len() as u32)
+        }
+        _ => Err(HttpErrorKind::RuntimeError),
+    }
+}
+
+/// send the opened request, the handle is the response handle after sent.
+pub(crate) async fn http_send(table: &Table, fd: u32) -> Result<i32, HttpErrorKind> {
+    let ctx = resource::get::<HttpCtx>(table, fd).ok_or(HttpErrorKind::InvalidHandle)?;
+    let mut ctx = ctx.lock().await;
+    let state = match *ctx {
+        HttpCtx::Request(ref mut state) => state,
+        _ => return Err(HttpErrorKind::RuntimeError),
+    };
+    let resp = match (state.builder.take(), state.response.take()) {
+        (Some((builder, body)), _) => send(builder, body).await?,
+        (None, Some(response)) => {
+            // end the streaming body, then wait the response.
+            state.sender.take();
+            response.await.map_err(|e| {
+                error!("request task error, {}", e);
+                HttpErrorKind::RuntimeError
+            })??
+        }
+        (None, None) => return Err(HttpErrorKind::RuntimeError),
+    };
+    let status = resp.status().as_u16() as i32;
+    *ctx = HttpCtx::Response(resp);
+    Ok(status)
+}
+
 /// read from handle
-pub(crate) fn http_read_head(fd: u32, head: &str) -> Result<String, HttpErrorKind> {
-    let ctx = get_ctx().unwrap();
-    let respone = match ctx.get_mut(&fd) {
-        Some(HttpCtx::Response(ref h)) => h,
-        Some(HttpCtx::StreamState(_)) => return Err(HttpErrorKind::RuntimeError),
-        None => return Err(HttpErrorKind::InvalidHandle),
+pub(crate) async fn http_read_head(
+    table: &Table,
+    fd: u32,
+    head: &str,
+) -> Result<String, HttpErrorKind> {
+    let ctx = resource::get::<HttpCtx>(table, fd).ok_or(HttpErrorKind::InvalidHandle)?;
+    let ctx = ctx.lock().await;
+    let respone = match *ctx {
+        HttpCtx::Response(ref h) => h,
+        _ => return Err(HttpErrorKind::RuntimeError),
     };
     let headers = respone.headers();
     match headers.get(head) {
@@ -137,6 +273,33 @@ pub(crate) fn http_read_head(fd: u32, head: &str) -> Result<String, HttpErrorKin
     }
 }
 
+/// read all the headers as the json object, the value of the header with multiple
+/// values is the array.
+pub(crate) async fn http_read_headers(table: &Table, fd: u32) -> Result<String, HttpErrorKind> {
+    let ctx = resource::get::<HttpCtx>(table, fd).ok_or(HttpErrorKind::InvalidHandle)?;
+    let ctx = ctx.lock().await;
+    let respone = match *ctx {
+        HttpCtx::Response(ref h) => h,
+        _ => return Err(HttpErrorKind::RuntimeError),
+    };
+    let headers = respone.headers();
+    let mut obj = JsonValue::new_object();
+    for name in headers.keys() {
+        let values = headers
+            .get_all(name)
+            .iter()
+            .map(|v| v.to_str().map(JsonValue::from))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| HttpErrorKind::InvalidEncoding)?;
+        let value = match values.len() {
+            1 => values.into_iter().next().unwrap(),
+            _ => JsonValue::Array(values),
+        };
+        obj[name.as_str()] = value;
+    }
+    Ok(obj.dump())
+}
+
 async fn stream_read(state: &mut StreamState, dest: &mut [u8]) -> usize {
     let read_call = |buffer: &mut Bytes, dest: &mut [u8]| -> usize {
         let remaining = buffer.remaining();
@@ -191,32 +354,17 @@ async fn stream_read(state: &mut StreamState, dest: &mut [u8]) -> usize {
     }
 }
 
-pub async fn http_read_body(fd: u32, buf: &mut [u8]) -> Result<u32, HttpErrorKind> {
-    let ctx = get_ctx().unwrap();
-    match ctx.remove(&fd) {
-        Some(HttpCtx::Response(resp)) => {
-            let stream = Box::pin(resp.bytes_stream());
-            let mut stream_state = StreamState {
-                stream,
-                buffer: None,
-            };
-            let readn = stream_read(&mut stream_state, buf).await;
-            ctx.insert(fd, HttpCtx::StreamState(stream_state));
-            Ok(readn as u32)
-        }
-        Some(HttpCtx::StreamState(mut stream_state)) => {
-            let readn = stream_read(&mut stream_state, buf).await;
-            ctx.insert(fd, HttpCtx::StreamState(stream_state));
-            Ok(readn as u32)
-        }
-        None => return Err(HttpErrorKind::InvalidHandle),
-    }
+pub async fn http_read_body(table: &Table, fd: u32, buf: &mut [u8]) -> Result<u32, HttpErrorKind> {
+    let ctx = resource::get::<HttpCtx>(table, fd).ok_or(HttpErrorKind::InvalidHandle)?;
+    let mut ctx = ctx.lock().await;
+    let state = ctx.stream_state().ok_or(HttpErrorKind::RuntimeError)?;
+    let readn = stream_read(state, buf).await;
+    Ok(readn as u32)
 }
 
 /// close the handle, destroy the memory.
-pub(crate) fn http_close(fd: u32) -> Result<(), HttpErrorKind> {
-    let ctx = get_ctx().unwrap();
-    match ctx.remove(&fd) {
+pub(crate) fn http_close(table: &Table, fd: u32) -> Result<(), HttpErrorKind> {
+    match resource::remove::<HttpCtx>(table, fd) {
         Some(_) => Ok(()),
         None => Err(HttpErrorKind::InvalidHandle),
     }
@@ -226,11 +374,12 @@ pub(crate) fn http_close(fd: u32) -> Result<(), HttpErrorKind> {
 mod test {
     use super::*;
     use crate::error::HttpErrorKind;
-    use bytes::BytesMut;
-    use json::JsonValue;
-    use reqwest::header::{HeaderMap, HeaderValue};
     use std::task::Poll;
-    use tokio::runtime::{Builder, Runtime};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+        runtime::{Builder, Runtime},
+    };
 
     struct TestStream(Vec<Bytes>);
 
@@ -247,35 +396,8 @@ mod test {
     }
 
     fn build_headers(json_str: &str) -> Result<HeaderMap, HttpErrorKind> {
-        let parsed_json = match json::parse(json_str) {
-            Ok(json) => json,
-            Err(_) => return Err(HttpErrorKind::HeadersValidationError),
-        };
-
-        let headers_value = match &parsed_json["headers"] {
-            JsonValue::Object(obj) => obj,
-            _ => return Err(HttpErrorKind::HeadersValidationError),
-        };
-
-        let mut headers = HeaderMap::new();
-        for (key, value) in headers_value.iter() {
-            let header_name = match reqwest::header::HeaderName::from_bytes(key.as_bytes()) {
-                Ok(name) => name,
-                Err(_) => return Err(HttpErrorKind::HeadersValidationError),
-            };
-
-            let header_value = match value.as_str() {
-                Some(val) => match HeaderValue::from_str(val) {
-                    Ok(value) => value,
-                    Err(_) => return Err(HttpErrorKind::HeadersValidationError),
-                },
-                None => return Err(HttpErrorKind::HeadersValidationError),
-            };
-
-            headers.insert(header_name, header_value);
-        }
-
-        Ok(headers)
+        let json = json::parse(json_str).map_err(|_| HttpErrorKind::HeadersValidationError)?;
+        super::build_headers(&json["headers"])
     }
 
     fn get_runtime() -> Runtime {
@@ -348,6 +470,176 @@ mod test {
         assert_eq!(result.err().unwrap(), HttpErrorKind::HeadersValidationError);
     }
 
+    #[test]
+    fn test_headers_compatible() {
+        // the headers object nested in the json string.
+        let json_str = r#"{"headers": "{\"Content-Type\": \"text/plain\"}"}"#;
+        let headers = build_headers(json_str).unwrap();
+        assert_eq!(headers.get("Content-Type").unwrap(), "text/plain");
+
+        let headers = build_headers("{}").unwrap();
+        assert!(headers.is_empty());
+
+        let json_str = r#"{"headers": {"Accept": ["text/plain", "text/html"]}}"#;
+        let headers = build_headers(json_str).unwrap();
+        assert_eq!(headers.get_all("Accept").iter().count(), 2);
+    }
+
+    #[test]
+    fn test_request_method() {
+        for m in ["get", "POST", "put", "patch", "delete", "head", "options"] {
+            let json = json::object! {"method": m};
+            assert_eq!(
+                request_method(&json).unwrap().as_str(),
+                m.to_ascii_uppercase()
+            );
+        }
+        let json = json::object! {"method": "TRACE"};
+        assert_eq!(request_method(&json), Err(HttpErrorKind::InvalidMethod));
+        let json = json::object! {};
+        assert_eq!(request_method(&json), Err(HttpErrorKind::RequestError));
+    }
+
+    /// decode the chunked body, `None` is returned if the body is incomplete.
+    fn decode_chunked(mut data: &[u8]) -> Option<Vec<u8>> {
+        let mut body = Vec::new();
+        loop {
+            let pos = data.windows(2).position(|w| w == b"\r\n")?;
+            let size = std::str::from_utf8(&data[..pos]).ok()?;
+            let size = usize::from_str_radix(size.trim(), 16).ok()?;
+            data = &data[pos + 2..];
+            if data.len() < size + 2 {
+                return None;
+            }
+            if size == 0 {
+                return Some(body);
+            }
+            body.extend_from_slice(&data[..size]);
+            data = &data[size + 2..];
+        }
+    }
+
+    /// the server echoes the method and the request body, the body is sent with the
+    /// content length or chunked.
+    async fn echo_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut req = Vec::new();
+            let mut buf = vec![0u8; 64 * 1024];
+            let (method, body) = loop {
+                let n = stream.read(&mut buf).await.unwrap();
+                req.extend_from_slice(&buf[..n]);
+                let mut headers = [httparse::EMPTY_HEADER; 16];
+                let mut parsed = httparse::Request::new(&mut headers);
+                if let httparse::Status::Complete(len) = parsed.parse(&req).unwrap() {
+                    let header = |name: &str| {
+                        parsed
+                            .headers
+                            .iter()
+                            .find(|h| h.name.eq_ignore_ascii_case(name))
+                            .map(|h| std::str::from_utf8(h.value).unwrap().to_string())
+                    };
+                    let method = parsed.method.unwrap().to_string();
+                    if header("transfer-encoding").as_deref() == Some("chunked") {
+                        if let Some(body) = decode_chunked(&req[len..]) {
+                            break (method, body);
+                        }
+                        continue;
+                    }
+                    let content_len = header("content-length")
+                        .map(|v| v.parse().unwrap())
+                        .unwrap_or(0);
+                    if req.len() >= len + content_len {
+                        break (method, req[len..len + content_len].to_vec());
+                    }
+                }
+            };
+            let head = format!(
+                "HTTP/1.1 200 OK\r\nx-method: {method}\r\nx-echo: a\r\nx-echo: b\r\ncontent-length: {}\r\n\r\n",
+                body.len()
+            );
+            stream.write_all(head.as_bytes()).await.unwrap();
+            stream.write_all(&body).await.unwrap();
+        });
+        format!("http://{addr}/echo")
+    }
+
+    #[test]
+    fn test_http_stream_binary_body() {
+        let rt = get_runtime();
+        rt.block_on(async move {
+            let url = echo_server().await;
+            let table = Table::new();
+            let opts =
+                r#"{"method": "PUT", "headers": {"Content-Type": "application/octet-stream"}}"#;
+            let fd = http_open(&table, &url, opts).unwrap();
+            let chunks: [&[u8]; 2] = [&[0, 159, 146, 150], &[255, 0, 1]];
+            for chunk in chunks {
+                let n = http_write_body(&table, fd, chunk).await.unwrap();
+                assert_eq!(n as usize, chunk.len());
+            }
+            assert_eq!(http_send(&table, fd).await.unwrap(), 200);
+            // the request is sent, the body can't be written anymore.
+            assert_eq!(
+                http_write_body(&table, fd, &[1]).await,
+                Err(HttpErrorKind::RuntimeError)
+            );
+
+            let headers = json::parse(&http_read_headers(&table, fd).await.unwrap()).unwrap();
+            assert_eq!(headers["x-method"], "PUT");
+            assert_eq!(headers["x-echo"], json::array!["a", "b"]);
+
+            let mut body = [0u8; 16];
+            let n = http_read_body(&table, fd, &mut body).await.unwrap() as usize;
+            assert_eq!(&body[..n], chunks.concat().as_slice());
+            http_close(&table, fd).unwrap();
+        });
+    }
+
+    #[test]
+    fn test_http_write_body_stream() {
+        let rt = get_runtime();
+        rt.block_on(async move {
+            let url = echo_server().await;
+            let table = Table::new();
+            let opts = r#"{"method": "POST", "body": "head"}"#;
+            let fd = http_open(&table, &url, opts).unwrap();
+            // the body is larger than the buffered chunks, it's streamed to the server.
+            let chunk = vec![7u8; 16 * 1024];
+            for _ in 0..BODY_CHANNEL_SIZE * 4 {
+                http_write_body(&table, fd, &chunk).await.unwrap();
+            }
+            assert_eq!(http_send(&table, fd).await.unwrap(), 200);
+            let mut body = Vec::new();
+            let mut buf = vec![0u8; 64 * 1024];
+            loop {
+                let n = http_read_body(&table, fd, &mut buf).await.unwrap() as usize;
+                if n == 0 {
+                    break;
+                }
+                body.extend_from_slice(&buf[..n]);
+            }
+            assert_eq!(body.len(), 4 + chunk.len() * BODY_CHANNEL_SIZE * 4);
+            assert_eq!(&body[..4], b"head");
+            assert!(body[4..].iter().all(|b| *b == 7));
+            http_close(&table, fd).unwrap();
+
+            // the error of the streaming request is returned by the send.
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!("http://{}/", listener.local_addr().unwrap());
+            drop(listener);
+            let fd = http_open(&table, &url, r#"{"method": "PUT"}"#).unwrap();
+            let _ = http_write_body(&table, fd, &[1]).await;
+            assert_eq!(
+                http_send(&table, fd).await,
+                Err(HttpErrorKind::RuntimeError)
+            );
+            http_close(&table, fd).unwrap();
+        });
+    }
+
     #[test]
     fn test_stream_read_full() {
         let rt = get_runtime();
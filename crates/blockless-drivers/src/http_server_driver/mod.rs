@@ -0,0 +1,462 @@
+use std::io;
+use std::net::SocketAddr;
+
+use json::JsonValue;
+use log::error;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use wasi_common::Table;
+
+use crate::{resource, HttpServerErrorKind};
+
+/// the max bytes of the request line and the headers.
+pub const MAX_HEAD_SIZE: usize = 64 * 1024;
+
+/// the max bytes of the request body.
+pub const MAX_BODY_SIZE: usize = 16 * 1024 * 1024;
+
+const MAX_HEADERS: usize = 64;
+
+/// The incoming request, the body is read before it's dispatched to the guest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    /// the names of the headers are lowercase.
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    pub remote: SocketAddr,
+}
+
+impl Request {
+    /// the value of the header, the values of the same name are joined by `, `.
+    pub fn header(&self, name: &str) -> Option<String> {
+        let values = self
+            .headers
+            .iter()
+            .filter(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+            .collect::<Vec<_>>();
+        if values.is_empty() {
+            return None;
+        }
+        Some(values.join(", "))
+    }
+
+    /// the head of the request as the json, e.g.
+    /// `{"method": "GET", "path": "/a?b=1", "headers": {"host": "example.com"}, "remote": "127.0.0.1:5678"}`.
+    pub fn head_json(&self) -> String {
+        let mut headers = JsonValue::new_object();
+        for (name, _) in self.headers.iter() {
+            if headers[name.as_str()].is_null() {
+                headers[name.as_str()] = self.header(name).into();
+            }
+        }
+        json::object! {
+            "method": self.method.as_str(),
+            "path": self.path.as_str(),
+            "headers": headers,
+            "remote": self.remote.to_string(),
+        }
+        .dump()
+    }
+}
+
+/// The response written by the guest, the status is 200 if it's not set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Default for Response {
+    fn default() -> Self {
+        Self::text(200, "")
+    }
+}
+
+impl Response {
+    /// the response with the plain text body, e.g. the error answered by the runtime.
+    pub fn text(status: u16, body: &str) -> Self {
+        Self {
+            status,
+            headers: vec![("content-type".into(), "text/plain".into())],
+            body: body.as_bytes().to_vec(),
+        }
+    }
+}
+
+/// The error of reading the request.
+#[derive(Debug, PartialEq)]
+pub enum ReadError {
+    /// the connection is closed or broken before the request is read.
+    Closed,
+    /// the request is invalid, it's answered with the status.
+    Status(u16),
+}
+
+/// read and parse the request from the connection, the `100 Continue` is answered if the
+/// client expects it before sending the body.
+pub async fn read_request<S>(stream: &mut S, remote: SocketAddr) -> Result<Request, ReadError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut buf = Vec::with_capacity(4096);
+    let mut chunk = [0u8; 4096];
+    let (mut request, len) = loop {
+        let n = stream
+            .read(&mut chunk)
+            .await
+            .map_err(|_| ReadError::Closed)?;
+        if n == 0 {
+            return Err(ReadError::Closed);
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+        let mut parsed = httparse::Request::new(&mut headers);
+        match parsed.parse(&buf) {
+            Ok(httparse::Status::Complete(len)) => {
+                let headers = parsed
+                    .headers
+                    .iter()
+                    .map(|h| {
+                        let value = String::from_utf8(h.value.to_vec())
+                            .map_err(|_| ReadError::Status(400))?;
+                        Ok((h.name.to_ascii_lowercase(), value))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                let request = Request {
+                    method: parsed.method.unwrap_or_default().to_string(),
+                    path: parsed.path.unwrap_or_default().to_string(),
+                    headers,
+                    body: Vec::new(),
+                    remote,
+                };
+                break (request, len);
+            }
+            Ok(httparse::Status::Partial) if buf.len() < MAX_HEAD_SIZE => continue,
+            Ok(httparse::Status::Partial) | Err(httparse::Error::TooManyHeaders) => {
+                return Err(ReadError::Status(431))
+            }
+            Err(_) => return Err(ReadError::Status(400)),
+        }
+    };
+    if request.header("transfer-encoding").is_some() {
+        return Err(ReadError::Status(501));
+    }
+    let body_len = match request.header("content-length") {
+        Some(len) => len
+            .trim()
+            .parse::<usize>()
+            .map_err(|_| ReadError::Status(400))?,
+        None => 0,
+    };
+    if body_len > MAX_BODY_SIZE {
+        return Err(ReadError::Status(413));
+    }
+    let mut body = buf.split_off(len);
+    // the bytes of the pipelined requests are dropped, the connection serves one request.
+    body.truncate(body_len);
+    if body.len() < body_len {
+        let expect = request.header("expect");
+        if expect.is_some_and(|e| e.eq_ignore_ascii_case("100-continue")) {
+            stream
+                .write_all(b"HTTP/1.1 100 Continue\r\n\r\n")
+                .await
+                .map_err(|_| ReadError::Closed)?;
+        }
+        let start = body.len();
+        body.resize(body_len, 0);
+        stream
+            .read_exact(&mut body[start..])
+            .await
+            .map_err(|_| ReadError::Closed)?;
+    }
+    request.body = body;
+    Ok(request)
+}
+
+/// the reason phrase of the status.
+pub fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        301 => "Moved Permanently",
+        302 => "Found",
+        304 => "Not Modified",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        413 => "Content Too Large",
+        431 => "Request Header Fields Too Large",
+        500 => "Internal Server Error",
+        501 => "Not Implemented",
+        503 => "Service Unavailable",
+        _ => "",
+    }
+}
+
+/// write the response and close the connection, the `content-length` and `connection`
+/// headers are set by the runtime.
+pub async fn write_response<S>(stream: &mut S, response: &Response) -> io::Result<()>
+where
+    S: AsyncWrite + Unpin,
+{
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\n",
+        response.status,
+        reason(response.status)
+    );
+    for (name, value) in response.headers.iter() {
+        match name.to_ascii_lowercase().as_str() {
+            "content-length" | "connection" | "transfer-encoding" => continue,
+            _ => head.push_str(&format!("{name}: {value}\r\n")),
+        }
+    }
+    head.push_str(&format!(
+        "content-length: {}\r\nconnection: close\r\n\r\n",
+        response.body.len()
+    ));
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(&response.body).await?;
+    stream.flush().await?;
+    stream.shutdown().await
+}
+
+/// The request dispatched to the guest and the response written by the guest.
+struct Exchange {
+    request: Request,
+    // the bytes of the body read by the guest.
+    body_pos: usize,
+    response: Response,
+}
+
+/// push the request into the table, the guest handles the request with the handle.
+pub fn push_request(table: &Table, request: Request) -> Option<u32> {
+    let exchange = Exchange {
+        request,
+        body_pos: 0,
+        response: Response::default(),
+    };
+    resource::push(table, exchange)
+}
+
+/// remove the request from the table and take the response written by the guest.
+pub async fn take_response(table: &Table, handle: u32) -> Option<Response> {
+    let exchange = resource::remove::<Exchange>(table, handle)?;
+    let mut exchange = exchange.lock().await;
+    Some(std::mem::take(&mut exchange.response))
+}
+
+fn exchange(
+    table: &Table,
+    handle: u32,
+) -> Result<resource::Resource<Exchange>, HttpServerErrorKind> {
+    resource::get::<Exchange>(table, handle).ok_or(HttpServerErrorKind::InvalidHandle)
+}
+
+/// the head json of the request.
+pub async fn request_head(table: &Table, handle: u32) -> Result<String, HttpServerErrorKind> {
+    let exchange = exchange(table, handle)?;
+    let exchange = exchange.lock().await;
+    Ok(exchange.request.head_json())
+}
+
+/// read the body of the request into the buffer, 0 is returned at the end of the body.
+pub async fn request_body_read(
+    table: &Table,
+    handle: u32,
+    buf: &mut [u8],
+) -> Result<u32, HttpServerErrorKind> {
+    let exchange = exchange(table, handle)?;
+    let mut exchange = exchange.lock().await;
+    let pos = exchange.body_pos;
+    let remaining = &exchange.request.body[pos..];
+    let n = remaining.len().min(buf.len());
+    buf[..n].copy_from_slice(&remaining[..n]);
+    exchange.body_pos += n;
+    Ok(n as u32)
+}
+
+/// the header name is the token and the value has no line break, so the guest can't
+/// inject the headers or the body.
+fn valid_header(name: &str, value: &str) -> bool {
+    let is_tchar = |c: u8| c.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&c);
+    !name.is_empty()
+        && name.bytes().all(is_tchar)
+        && !value.bytes().any(|c| c == b'\r' || c == b'\n')
+}
+
+/// set the status and the headers of the response, the headers is the json object,
+/// e.g. `{"content-type": "text/plain"}`.
+pub async fn response_head(
+    table: &Table,
+    handle: u32,
+    status: u16,
+    headers: &str,
+) -> Result<(), HttpServerErrorKind> {
+    if !(100..=999).contains(&status) {
+        return Err(HttpServerErrorKind::InvalidParameter);
+    }
+    let headers = match headers {
+        "" => JsonValue::new_object(),
+        headers => json::parse(headers).map_err(|_| HttpServerErrorKind::InvalidParameter)?,
+    };
+    if !headers.is_object() {
+        return Err(HttpServerErrorKind::InvalidParameter);
+    }
+    let headers = headers
+        .entries()
+        .map(|(name, value)| match value.as_str() {
+            Some(value) if valid_header(name, value) => Ok((name.to_string(), value.to_string())),
+            _ => {
+                error!("invalid response header: {}", name);
+                Err(HttpServerErrorKind::InvalidParameter)
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let exchange = exchange(table, handle)?;
+    let mut exchange = exchange.lock().await;
+    exchange.response.status = status;
+    exchange.response.headers = headers;
+    Ok(())
+}
+
+/// append the bytes to the body of the response.
+pub async fn response_write(
+    table: &Table,
+    handle: u32,
+    data: &[u8],
+) -> Result<(), HttpServerErrorKind> {
+    let exchange = exchange(table, handle)?;
+    let mut exchange = exchange.lock().await;
+    exchange.response.body.extend_from_slice(data);
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokio::runtime::Builder;
+
+    fn remote() -> SocketAddr {
+        "127.0.0.1:5678".parse().unwrap()
+    }
+
+    async fn read(raw: &[u8]) -> (Result<Request, ReadError>, Vec<u8>) {
+        let (mut client, mut server) = tokio::io::duplex(MAX_HEAD_SIZE * 2);
+        client.write_all(raw).await.unwrap();
+        client.shutdown().await.unwrap();
+        let rs = read_request(&mut server, remote()).await;
+        drop(server);
+        let mut answered = Vec::new();
+        client.read_to_end(&mut answered).await.unwrap();
+        (rs, answered)
+    }
+
+    #[test]
+    fn test_read_request() {
+        let rt = Builder::new_current_thread().enable_all().build().unwrap();
+        rt.block_on(async move {
+            let raw = b"POST /echo?a=1 HTTP/1.1\r\nHost: localhost\r\nX-A: 1\r\nx-a: 2\r\n\
+                Content-Length: 5\r\nExpect: 100-continue\r\n\r\nhello";
+            let (rs, answered) = read(raw).await;
+            let request = rs.unwrap();
+            assert_eq!(request.method, "POST");
+            assert_eq!(request.path, "/echo?a=1");
+            assert_eq!(request.body, b"hello");
+            assert_eq!(request.header("x-a").as_deref(), Some("1, 2"));
+            // the body is sent with the head, the client doesn't wait for the continue.
+            assert!(answered.is_empty());
+            let head = json::parse(&request.head_json()).unwrap();
+            assert_eq!(head["method"], "POST");
+            assert_eq!(head["headers"]["host"], "localhost");
+            assert_eq!(head["headers"]["x-a"], "1, 2");
+            assert_eq!(head["remote"], "127.0.0.1:5678");
+
+            let raw = b"PUT / HTTP/1.1\r\nContent-Length: 5\r\nExpect: 100-continue\r\n\r\nhi";
+            let (rs, answered) = read(raw).await;
+            assert_eq!(rs, Err(ReadError::Closed));
+            assert_eq!(answered, b"HTTP/1.1 100 Continue\r\n\r\n");
+
+            assert_eq!(read(b"").await.0, Err(ReadError::Closed));
+            assert_eq!(read(b"GET / HTTP/1.1\r\n").await.0, Err(ReadError::Closed));
+            assert_eq!(read(b"GET\r\n\r\n").await.0, Err(ReadError::Status(400)));
+            let raw = b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n\r\n";
+            assert_eq!(read(raw).await.0, Err(ReadError::Status(501)));
+            let raw = format!(
+                "POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+                MAX_BODY_SIZE + 1
+            );
+            assert_eq!(read(raw.as_bytes()).await.0, Err(ReadError::Status(413)));
+            let raw = format!("GET /{} HTTP/1.1\r\n", "a".repeat(MAX_HEAD_SIZE));
+            assert_eq!(read(raw.as_bytes()).await.0, Err(ReadError::Status(431)));
+        });
+    }
+
+    #[test]
+    fn test_exchange() {
+        let rt = Builder::new_current_thread().enable_all().build().unwrap();
+        rt.block_on(async move {
+            let table = Table::new();
+            let request = Request {
+                method: "POST".into(),
+                path: "/".into(),
+                headers: Vec::new(),
+                body: b"hello".to_vec(),
+                remote: remote(),
+            };
+            let handle = push_request(&table, request).unwrap();
+            let mut buf = [0u8; 3];
+            assert_eq!(
+                request_body_read(&table, handle, &mut buf).await.unwrap(),
+                3
+            );
+            assert_eq!(&buf, b"hel");
+            assert_eq!(
+                request_body_read(&table, handle, &mut buf).await.unwrap(),
+                2
+            );
+            assert_eq!(
+                request_body_read(&table, handle, &mut buf).await.unwrap(),
+                0
+            );
+
+            let headers = r#"{"x-b": "1"}"#;
+            response_head(&table, handle, 201, headers).await.unwrap();
+            response_write(&table, handle, b"ok").await.unwrap();
+            for headers in [r#"{"x-b": "1\r\nx-c: 2"}"#, r#"{"x b": "1"}"#, r#"["x"]"#] {
+                assert!(matches!(
+                    response_head(&table, handle, 200, headers).await,
+                    Err(HttpServerErrorKind::InvalidParameter)
+                ));
+            }
+            assert!(matches!(
+                response_head(&table, handle, 99, "").await,
+                Err(HttpServerErrorKind::InvalidParameter)
+            ));
+
+            let response = take_response(&table, handle).await.unwrap();
+            assert_eq!(response.status, 201);
+            assert_eq!(response.body, b"ok");
+            assert!(take_response(&table, handle).await.is_none());
+            assert!(matches!(
+                response_write(&table, handle, b"ok").await,
+                Err(HttpServerErrorKind::InvalidHandle)
+            ));
+
+            let (mut client, mut server) = tokio::io::duplex(1024);
+            write_response(&mut server, &response).await.unwrap();
+            let mut raw = String::new();
+            client.read_to_string(&mut raw).await.unwrap();
+            assert_eq!(
+                raw,
+                "HTTP/1.1 201 Created\r\nx-b: 1\r\ncontent-length: 2\r\nconnection: close\r\n\r\nok"
+            );
+        });
+    }
+}
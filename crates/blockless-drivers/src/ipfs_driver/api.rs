@@ -1,11 +1,13 @@
 use crate::IpfsErrorKind;
 
-use super::{file::FileApi, gen_boundary, HttpRaw};
+use super::{block::BlockApi, dag::DagApi, file::FileApi, gen_boundary, pin::PinApi, Multipart};
 use crate::read_ext::ReadRemain;
+use bytes::{Buf, Bytes};
+use wasi_common::IpfsApi;
 
 pub struct Api {
-    host: String,
-    port: u16,
+    url: String,
+    auth: Option<String>,
 }
 
 pub struct Response {
@@ -15,7 +17,7 @@ pub struct Response {
 }
 
 impl Response {
-    fn new(status: u16, body: Option<Vec<u8>>) -> Self {
+    pub(crate) fn new(status: u16, body: Option<Vec<u8>>) -> Self {
         Self {
             status,
             body,
@@ -42,11 +44,34 @@ impl ReadRemain for Response {
     }
 }
 
+/// The response is read from the connection when the guest reads the body.
+pub struct StreamResponse {
+    pub(crate) status: u16,
+    resp: reqwest::Response,
+    buffer: Bytes,
+}
+
+impl StreamResponse {
+    /// read the body into the buffer, return 0 if the body is finished.
+    pub async fn read_body(&mut self, buf: &mut [u8]) -> Result<usize, IpfsErrorKind> {
+        while !self.buffer.has_remaining() {
+            match self.resp.chunk().await {
+                Ok(Some(chunk)) => self.buffer = chunk,
+                Ok(None) => return Ok(0),
+                Err(_) => return Err(IpfsErrorKind::RequestError),
+            }
+        }
+        let n = buf.len().min(self.buffer.remaining());
+        self.buffer.copy_to_slice(&mut buf[..n]);
+        Ok(n)
+    }
+}
+
 impl Api {
-    pub fn new(host: &str, port: u16) -> Self {
+    pub fn new(api: &IpfsApi) -> Self {
         Self {
-            host: host.into(),
-            port,
+            url: api.url.trim_end_matches('/').into(),
+            auth: api.auth.clone(),
         }
     }
 
@@ -54,8 +79,52 @@ impl Api {
         FileApi::new(self)
     }
 
-    pub fn build_url(&self, api: &str) -> String {
-        format!("http://{}:{}/{}", &self.host, self.port, api)
+    pub fn pin_api(self) -> PinApi {
+        PinApi::new(self)
+    }
+
+    pub fn dag_api(self) -> DagApi {
+        DagApi::new(self)
+    }
+
+    pub fn block_api(self) -> BlockApi {
+        BlockApi::new(self)
+    }
+
+    pub async fn add(&self, args: Option<String>) -> Result<Multipart, IpfsErrorKind> {
+        static ADD_API: &str = "api/v0/add";
+        self.multipart(ADD_API, args).await
+    }
+
+    pub async fn cat(&self, args: Option<String>) -> Result<StreamResponse, IpfsErrorKind> {
+        static CAT_API: &str = "api/v0/cat";
+        self.stream_post(CAT_API, args).await
+    }
+
+    pub async fn get(&self, args: Option<String>) -> Result<StreamResponse, IpfsErrorKind> {
+        static GET_API: &str = "api/v0/get";
+        self.stream_post(GET_API, args).await
+    }
+
+    pub fn build_url(&self, api: &str, args: Option<String>) -> String {
+        match args {
+            Some(ar) => format!("{}/{}?{}", &self.url, api, ar),
+            None => format!("{}/{}", &self.url, api),
+        }
+    }
+
+    async fn post(
+        &self,
+        url: &str,
+        args: Option<String>,
+    ) -> Result<reqwest::Response, IpfsErrorKind> {
+        let url = self.build_url(url, args);
+        let client = reqwest::Client::new();
+        let mut req = client.post(url);
+        if let Some(ref auth) = self.auth {
+            req = req.header(reqwest::header::AUTHORIZATION, auth);
+        }
+        req.send().await.map_err(|_| IpfsErrorKind::RequestError)
     }
 
     pub async fn simple_post(
@@ -63,17 +132,7 @@ impl Api {
         url: &str,
         args: Option<String>,
     ) -> Result<Response, IpfsErrorKind> {
-        let url = self.build_url(url);
-        let url = match args {
-            Some(ar) => format!("{}?{}", url, ar),
-            None => url,
-        };
-        let client = reqwest::Client::new();
-        let resp = client
-            .post(url)
-            .send()
-            .await
-            .map_err(|_| IpfsErrorKind::RequestError)?;
+        let resp = self.post(url, args).await?;
         let status = resp.status().as_u16();
         let bytes = resp
             .bytes()
@@ -82,22 +141,29 @@ impl Api {
         return Ok(Response::new(status, Some(bytes.to_vec())));
     }
 
-    pub async fn multipart_raw(
+    /// post the request, the response body is streamed to the guest.
+    pub async fn stream_post(
+        &self,
+        url: &str,
+        args: Option<String>,
+    ) -> Result<StreamResponse, IpfsErrorKind> {
+        let resp = self.post(url, args).await?;
+        Ok(StreamResponse {
+            status: resp.status().as_u16(),
+            resp,
+            buffer: Bytes::new(),
+        })
+    }
+
+    /// the multipart upload, the part is posted when the guest writes the body.
+    pub async fn multipart(
         &self,
         url: &str,
         args: Option<String>,
-    ) -> Result<HttpRaw, IpfsErrorKind> {
-        let url = self.build_url(url);
-        let url = match args {
-            Some(ar) => format!("{}?{}", url, ar),
-            None => url,
-        };
-        let mut http = HttpRaw::from_url(&url)?;
+    ) -> Result<Multipart, IpfsErrorKind> {
+        let url = self.build_url(url, args);
         let boudary = format!("------------------------{}", gen_boundary());
-        http.boundary(Some(boudary));
-        http.method("POST");
-        http.connect().await?;
-        return Ok(http);
+        Ok(Multipart::new(url, self.auth.clone(), boudary))
     }
 }
 
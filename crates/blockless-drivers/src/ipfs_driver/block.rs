@@ -0,0 +1,23 @@
+use super::{
+    api::{Api, StreamResponse},
+    Multipart,
+};
+use crate::IpfsErrorKind;
+
+pub struct BlockApi(Api);
+
+impl BlockApi {
+    pub fn new(api: Api) -> BlockApi {
+        BlockApi(api)
+    }
+
+    pub async fn get(&self, args: Option<String>) -> Result<StreamResponse, IpfsErrorKind> {
+        static GET_API: &str = "api/v0/block/get";
+        self.0.stream_post(GET_API, args).await
+    }
+
+    pub async fn put(&self, args: Option<String>) -> Result<Multipart, IpfsErrorKind> {
+        static PUT_API: &str = "api/v0/block/put";
+        self.0.multipart(PUT_API, args).await
+    }
+}
@@ -1,6 +1,6 @@
 use super::{
     api::{Api, Response},
-    HttpRaw,
+    Multipart,
 };
 use crate::IpfsErrorKind;
 
@@ -36,8 +36,8 @@ impl FileApi {
         self.0.simple_post(READ_API, args).await
     }
 
-    pub async fn write(&self, args: Option<String>) -> Result<HttpRaw, IpfsErrorKind> {
+    pub async fn write(&self, args: Option<String>) -> Result<Multipart, IpfsErrorKind> {
         static WRITE_API: &str = "api/v0/files/write";
-        self.0.multipart_raw(WRITE_API, args).await
+        self.0.multipart(WRITE_API, args).await
     }
 }
@@ -1,19 +1,20 @@
 mod api;
+mod block;
+mod dag;
 mod file;
-mod http_raw;
+mod multipart;
+mod pin;
 mod util;
 use api::*;
-use http_raw::HttpRaw;
-use std::{collections::HashMap, sync::Once};
+use multipart::Multipart;
+use std::sync::Once;
 pub use util::gen_boundary;
+use wasi_common::{IpfsApi, Table};
 
 #[cfg(feature = "runtime")]
 use tokio::runtime::{Builder, Runtime};
 
-use crate::IpfsErrorKind;
-
-const HOST: &str = "127.0.0.1";
-const PORT: u16 = 5001;
+use crate::{resource, IpfsErrorKind};
 
 #[cfg(feature = "runtime")]
 pub fn get_runtime() -> Option<&'static Runtime> {
@@ -35,81 +36,69 @@ pub fn get_runtime() -> Option<&'static Runtime> {
 
 pub enum ApiCtx {
     Response(Response),
-    HttpRaw(HttpRaw),
-}
-
-pub fn get_ctx() -> Option<&'static mut HashMap<u32, ApiCtx>> {
-    static mut CTX: Option<HashMap<u32, ApiCtx>> = None;
-    static CTX_ONCE: Once = Once::new();
-    CTX_ONCE.call_once(|| {
-        unsafe {
-            CTX = Some(HashMap::new());
-        };
-    });
-    unsafe { CTX.as_mut() }
+    Stream(StreamResponse),
+    Multipart(Multipart),
 }
 
-pub fn increase_fd() -> Option<u32> {
-    static mut MAX_HANDLE: u32 = 0;
-    unsafe {
-        MAX_HANDLE += 1;
-        Some(MAX_HANDLE)
-    }
+/// run the command with the ipfs api endpoint and return the status and the handle.
+pub async fn command(
+    table: &Table,
+    ipfs_api: &IpfsApi,
+    cmd: &str,
+) -> Result<(u16, u32), IpfsErrorKind> {
+    let rs = inner_command(ipfs_api, cmd).await?;
+    let status = match rs {
+        ApiCtx::Response(ref rs) => rs.status,
+        ApiCtx::Stream(ref rs) => rs.status,
+        ApiCtx::Multipart(_) => 0,
+    };
+    let fd = resource::push(table, rs).ok_or(IpfsErrorKind::RuntimeError)?;
+    Ok((status, fd))
 }
 
-pub async fn command(cmd: &str) -> Result<(u16, u32), IpfsErrorKind> {
-    let rs = inner_command(cmd).await?;
-    let fd = increase_fd().unwrap();
-    match rs {
-        ApiCtx::Response(rs) => {
-            let status = rs.status;
-            get_ctx().unwrap().insert(fd, ApiCtx::Response(rs));
-            Ok((status, fd))
-        }
-        ApiCtx::HttpRaw(raw) => {
-            get_ctx().unwrap().insert(fd, ApiCtx::HttpRaw(raw));
-            Ok((0, fd))
-        }
+/// get the api name of the command, it's used for the permission check, e.g. `files/ls`.
+pub fn command_api(cmd: &str) -> Result<String, IpfsErrorKind> {
+    let json = json::parse(cmd).map_err(|_| IpfsErrorKind::InvalidParameter)?;
+    match json["api"].as_str() {
+        Some(s) => Ok(String::from(s)),
+        None => Err(IpfsErrorKind::InvalidParameter),
     }
 }
 
-pub async fn close(handle: u32) -> Result<(), IpfsErrorKind> {
-    let ctx = get_ctx().unwrap();
-    ctx.remove(&handle);
+pub async fn close(table: &Table, handle: u32) -> Result<(), IpfsErrorKind> {
+    resource::remove::<ApiCtx>(table, handle);
     Ok(())
 }
 
-pub async fn write_body(handle: u32, buf: &[u8]) -> Result<u32, IpfsErrorKind> {
-    let ctx = get_ctx().unwrap();
+pub async fn write_body(table: &Table, handle: u32, buf: &[u8]) -> Result<u32, IpfsErrorKind> {
     if buf.len() == 0 {
         return Err(IpfsErrorKind::InvalidParameter);
     }
-    let size = match ctx.get_mut(&handle) {
-        Some(ApiCtx::HttpRaw(raw)) if raw.is_connect() => raw.write_boundary(buf).await?,
+    let ctx = resource::get::<ApiCtx>(table, handle).ok_or(IpfsErrorKind::InvalidHandle)?;
+    let mut ctx = ctx.lock().await;
+    let resp = match *ctx {
+        ApiCtx::Multipart(ref upload) => upload.send(buf).await?,
         _ => return Err(IpfsErrorKind::InvalidHandle),
     };
-    Ok(size as _)
+    // the response of the upload is read by the following calls, e.g. the cid of `add`.
+    *ctx = ApiCtx::Response(resp);
+    Ok(buf.len() as _)
 }
 
-pub async fn read_body(handle: u32, buf: &mut [u8]) -> Result<u32, IpfsErrorKind> {
-    let ctx = get_ctx().unwrap();
+pub async fn read_body(table: &Table, handle: u32, buf: &mut [u8]) -> Result<u32, IpfsErrorKind> {
     if buf.len() == 0 {
         return Err(IpfsErrorKind::InvalidParameter);
     }
-    match ctx.get_mut(&handle) {
-        Some(ApiCtx::Response(resp)) => Ok(resp.copy_body_remain(buf) as _),
-        Some(ApiCtx::HttpRaw(raw)) if raw.is_connect() => {
-            let result = raw.read_response().await?;
-            if result.0 != 200 {
-                return Err(IpfsErrorKind::RequestError);
-            }
-            Ok(0)
-        }
+    let ctx = resource::get::<ApiCtx>(table, handle).ok_or(IpfsErrorKind::InvalidHandle)?;
+    let mut ctx = ctx.lock().await;
+    match *ctx {
+        ApiCtx::Response(ref mut resp) => Ok(resp.copy_body_remain(buf) as _),
+        ApiCtx::Stream(ref mut resp) => Ok(resp.read_body(buf).await? as _),
         _ => return Err(IpfsErrorKind::InvalidHandle),
     }
 }
 
-async fn inner_command(cmd: &str) -> Result<ApiCtx, IpfsErrorKind> {
+async fn inner_command(ipfs_api: &IpfsApi, cmd: &str) -> Result<ApiCtx, IpfsErrorKind> {
     let json = match json::parse(cmd) {
         Ok(o) => o,
         Err(_) => return Err(IpfsErrorKind::InvalidParameter),
@@ -144,36 +133,214 @@ async fn inner_command(cmd: &str) -> Result<ApiCtx, IpfsErrorKind> {
         _ => None,
     };
     match api.as_str() {
-        "files/ls" => Api::new(HOST, PORT)
+        "files/ls" => Api::new(ipfs_api)
             .file_api()
             .ls(args)
             .await
             .map(ApiCtx::Response),
-        "files/mkdir" => Api::new(HOST, PORT)
+        "files/mkdir" => Api::new(ipfs_api)
             .file_api()
             .mkdir(args)
             .await
             .map(ApiCtx::Response),
-        "files/rm" => Api::new(HOST, PORT)
+        "files/rm" => Api::new(ipfs_api)
             .file_api()
             .rm(args)
             .await
             .map(ApiCtx::Response),
-        "files/stat" => Api::new(HOST, PORT)
+        "files/stat" => Api::new(ipfs_api)
             .file_api()
             .stat(args)
             .await
             .map(ApiCtx::Response),
-        "files/read" => Api::new(HOST, PORT)
+        "files/read" => Api::new(ipfs_api)
             .file_api()
             .read(args)
             .await
             .map(ApiCtx::Response),
-        "files/write" => Api::new(HOST, PORT)
+        "files/write" => Api::new(ipfs_api)
             .file_api()
             .write(args)
             .await
-            .map(ApiCtx::HttpRaw),
+            .map(ApiCtx::Multipart),
+        "add" => Api::new(ipfs_api).add(args).await.map(ApiCtx::Multipart),
+        "cat" => Api::new(ipfs_api).cat(args).await.map(ApiCtx::Stream),
+        "get" => Api::new(ipfs_api).get(args).await.map(ApiCtx::Stream),
+        "pin/add" => Api::new(ipfs_api)
+            .pin_api()
+            .add(args)
+            .await
+            .map(ApiCtx::Stream),
+        "pin/rm" => Api::new(ipfs_api)
+            .pin_api()
+            .rm(args)
+            .await
+            .map(ApiCtx::Stream),
+        "pin/ls" => Api::new(ipfs_api)
+            .pin_api()
+            .ls(args)
+            .await
+            .map(ApiCtx::Stream),
+        "dag/get" => Api::new(ipfs_api)
+            .dag_api()
+            .get(args)
+            .await
+            .map(ApiCtx::Stream),
+        "dag/put" => Api::new(ipfs_api)
+            .dag_api()
+            .put(args)
+            .await
+            .map(ApiCtx::Multipart),
+        "block/get" => Api::new(ipfs_api)
+            .block_api()
+            .get(args)
+            .await
+            .map(ApiCtx::Stream),
+        "block/put" => Api::new(ipfs_api)
+            .block_api()
+            .put(args)
+            .await
+            .map(ApiCtx::Multipart),
         _ => return Err(IpfsErrorKind::InvalidMethod),
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+        runtime::Builder,
+    };
+
+    const AUTH: &str = "Basic dGVzdA==";
+
+    /// the request received by the mock kubo server, the path, the auth header and the body.
+    type Requests = Arc<Mutex<Vec<(String, Option<String>, Vec<u8>)>>>;
+
+    async fn read_request(stream: &mut TcpStream) -> (String, Option<String>, Vec<u8>) {
+        let mut req = Vec::new();
+        let mut buf = [0u8; 1024];
+        loop {
+            let n = stream.read(&mut buf).await.unwrap();
+            req.extend_from_slice(&buf[..n]);
+            let mut headers = [httparse::EMPTY_HEADER; 16];
+            let mut parsed = httparse::Request::new(&mut headers);
+            if let httparse::Status::Complete(len) = parsed.parse(&req).unwrap() {
+                let header = |name: &str| {
+                    parsed
+                        .headers
+                        .iter()
+                        .find(|h| h.name.eq_ignore_ascii_case(name))
+                        .map(|h| String::from_utf8_lossy(h.value).to_string())
+                };
+                let content_len = header("content-length").map_or(0, |l| l.parse().unwrap());
+                if req.len() >= len + content_len {
+                    let path = parsed.path.unwrap().to_string();
+                    return (path, header("authorization"), req[len..].to_vec());
+                }
+            }
+        }
+    }
+
+    /// the mock kubo server, the response is chunked as kubo does.
+    async fn mock_kubo() -> (IpfsApi, Requests) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let api = IpfsApi {
+            url: format!("http://{}/", listener.local_addr().unwrap()),
+            auth: Some(AUTH.into()),
+        };
+        let requests: Requests = Default::default();
+        let reqs = requests.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let (path, auth, body) = read_request(&mut stream).await;
+                let api = path.split('?').next().unwrap().to_string();
+                reqs.lock().unwrap().push((path, auth.clone(), body));
+                let (status, body) = match api.as_str() {
+                    _ if auth.as_deref() != Some(AUTH) => ("401 Unauthorized", ""),
+                    "/api/v0/cat" => ("200 OK", "hello ipfs"),
+                    "/api/v0/add" => ("200 OK", r#"{"Name":"test","Hash":"QmTest"}"#),
+                    "/api/v0/pin/ls" => ("200 OK", r#"{"Keys":{}}"#),
+                    _ => ("404 Not Found", ""),
+                };
+                let mut resp = format!(
+                    "HTTP/1.1 {status}\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n"
+                );
+                if body.len() > 0 {
+                    resp += &format!("{:x}\r\n{body}\r\n", body.len());
+                }
+                resp += "0\r\n\r\n";
+                stream.write_all(resp.as_bytes()).await.unwrap();
+            }
+        });
+        (api, requests)
+    }
+
+    async fn read_all(table: &Table, fd: u32) -> Vec<u8> {
+        let mut body = Vec::new();
+        let mut buf = [0u8; 4];
+        loop {
+            let n = read_body(table, fd, &mut buf).await.unwrap() as usize;
+            if n == 0 {
+                break;
+            }
+            body.extend_from_slice(&buf[..n]);
+        }
+        body
+    }
+
+    #[test]
+    fn test_ipfs_cat_stream() {
+        let rt = Builder::new_current_thread().enable_all().build().unwrap();
+        rt.block_on(async move {
+            let (api, requests) = mock_kubo().await;
+            let table = Table::new();
+            let cmd = r#"{"api":"cat","args":[{"name":"arg","value":"QmTest"}]}"#;
+            let (status, fd) = command(&table, &api, cmd).await.unwrap();
+            assert_eq!(status, 200);
+            assert_eq!(read_all(&table, fd).await, b"hello ipfs");
+            let requests = requests.lock().unwrap();
+            assert_eq!(requests[0].0, "/api/v0/cat?arg=QmTest");
+            assert_eq!(requests[0].1.as_deref(), Some(AUTH));
+        });
+    }
+
+    #[test]
+    fn test_ipfs_add_upload() {
+        let rt = Builder::new_current_thread().enable_all().build().unwrap();
+        rt.block_on(async move {
+            let (api, requests) = mock_kubo().await;
+            let table = Table::new();
+            let (_, fd) = command(&table, &api, r#"{"api":"add"}"#).await.unwrap();
+            assert_eq!(write_body(&table, fd, b"hello ipfs").await.unwrap(), 10);
+            let resp = read_all(&table, fd).await;
+            let resp = json::parse(std::str::from_utf8(&resp).unwrap()).unwrap();
+            assert_eq!(resp["Hash"], "QmTest");
+            let requests = requests.lock().unwrap();
+            assert_eq!(requests[0].0, "/api/v0/add");
+            assert_eq!(requests[0].1.as_deref(), Some(AUTH));
+            let body = String::from_utf8_lossy(&requests[0].2);
+            assert!(body.contains("hello ipfs"));
+        });
+    }
+
+    #[test]
+    fn test_ipfs_api_auth() {
+        let rt = Builder::new_current_thread().enable_all().build().unwrap();
+        rt.block_on(async move {
+            let (mut api, _) = mock_kubo().await;
+            let table = Table::new();
+            let (status, _) = command(&table, &api, r#"{"api":"pin/ls"}"#).await.unwrap();
+            assert_eq!(status, 200);
+            api.auth = None;
+            let (status, _) = command(&table, &api, r#"{"api":"pin/ls"}"#).await.unwrap();
+            assert_eq!(status, 401);
+            let rs = command(&table, &api, r#"{"api":"repo/gc"}"#).await;
+            assert!(matches!(rs, Err(IpfsErrorKind::InvalidMethod)));
+        });
+    }
+}
@@ -0,0 +1,68 @@
+use std::io::Write;
+
+use super::api::Response;
+use crate::IpfsErrorKind;
+use reqwest::header::{AUTHORIZATION, CONTENT_TYPE};
+
+const EOL: &[u8] = b"\r\n";
+
+/// The upload of the api, e.g. `add`, the part is written by the guest and posted
+/// as the multipart form, the endpoint can be http or https.
+pub struct Multipart {
+    url: String,
+    auth: Option<String>,
+    boundary: String,
+}
+
+impl Multipart {
+    pub fn new(url: String, auth: Option<String>, boundary: String) -> Self {
+        Self {
+            url,
+            auth,
+            boundary,
+        }
+    }
+
+    fn body(&self, val: &[u8]) -> Vec<u8> {
+        let mut buf = Vec::<u8>::with_capacity(val.len() + 256);
+        buf.write_all(b"--").unwrap();
+        buf.write_all(self.boundary.as_bytes()).unwrap();
+        buf.write_all(EOL).unwrap();
+        buf.write_all(b"Content-Disposition: form-data").unwrap();
+        buf.write_all(EOL).unwrap();
+        buf.write_all(b"Content-Type: application/octet-stream")
+            .unwrap();
+        buf.write_all(EOL).unwrap();
+        buf.write_all(EOL).unwrap();
+        buf.write_all(val).unwrap();
+        buf.write_all(EOL).unwrap();
+        buf.write_all(b"--").unwrap();
+        buf.write_all(self.boundary.as_bytes()).unwrap();
+        buf.write_all(b"--").unwrap();
+        buf.write_all(EOL).unwrap();
+        buf
+    }
+
+    /// post the part and return the response of the upload, e.g. the cid of `add`.
+    pub async fn send(&self, val: &[u8]) -> Result<Response, IpfsErrorKind> {
+        let content_type = format!("multipart/form-data; boundary={}", self.boundary);
+        let client = reqwest::Client::new();
+        let mut req = client
+            .post(&self.url)
+            .header(CONTENT_TYPE, content_type)
+            .body(self.body(val));
+        if let Some(ref auth) = self.auth {
+            req = req.header(AUTHORIZATION, auth);
+        }
+        let resp = req.send().await.map_err(|_| IpfsErrorKind::RequestError)?;
+        let status = resp.status().as_u16();
+        if status != 200 {
+            return Err(IpfsErrorKind::RequestError);
+        }
+        let bytes = resp
+            .bytes()
+            .await
+            .map_err(|_| IpfsErrorKind::RequestError)?;
+        Ok(Response::new(status, Some(bytes.to_vec())))
+    }
+}
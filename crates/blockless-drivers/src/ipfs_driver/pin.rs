@@ -0,0 +1,25 @@
+use super::api::{Api, StreamResponse};
+use crate::IpfsErrorKind;
+
+pub struct PinApi(Api);
+
+impl PinApi {
+    pub fn new(api: Api) -> PinApi {
+        PinApi(api)
+    }
+
+    pub async fn add(&self, args: Option<String>) -> Result<StreamResponse, IpfsErrorKind> {
+        static ADD_API: &str = "api/v0/pin/add";
+        self.0.stream_post(ADD_API, args).await
+    }
+
+    pub async fn rm(&self, args: Option<String>) -> Result<StreamResponse, IpfsErrorKind> {
+        static RM_API: &str = "api/v0/pin/rm";
+        self.0.stream_post(RM_API, args).await
+    }
+
+    pub async fn ls(&self, args: Option<String>) -> Result<StreamResponse, IpfsErrorKind> {
+        static LS_API: &str = "api/v0/pin/ls";
+        self.0.stream_post(LS_API, args).await
+    }
+}
@@ -0,0 +1,345 @@
+mod store;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use lazy_static::lazy_static;
+use wasi_common::{KvConfig, Table};
+
+use crate::{resource, KvErrorKind};
+use store::{KvOp, KvStore};
+
+/// the max length of the namespace name.
+const MAX_NAMESPACE_LEN: usize = 256;
+
+/// the max operations of the batch.
+const MAX_BATCH_OPS: usize = 1024;
+
+/// the max bytes of the keys and values of the batch when the namespace has no quota.
+const MAX_BATCH_BYTES: u64 = 64 * 1024 * 1024;
+
+lazy_static! {
+    // the stores are shared by the guests, the key is the database file or empty for the memory store.
+    static ref STORES: Mutex<HashMap<String, Arc<KvStore>>> = Mutex::new(HashMap::new());
+}
+
+fn open_store(path: Option<&str>) -> Result<Arc<KvStore>, KvErrorKind> {
+    let mut stores = STORES.lock().unwrap();
+    let key = path.unwrap_or_default().to_string();
+    if let Some(store) = stores.get(&key) {
+        return Ok(store.clone());
+    }
+    let store = Arc::new(KvStore::open(path)?);
+    stores.insert(key, store.clone());
+    Ok(store)
+}
+
+/// The opened namespace of the guest.
+#[derive(Clone)]
+struct Namespace {
+    store: Arc<KvStore>,
+    app: String,
+    name: String,
+    quota: Option<u64>,
+    max_value_size: Option<u64>,
+}
+
+impl Namespace {
+    /// run the database operation in the blocking thread.
+    async fn run<T, F>(&self, f: F) -> Result<T, KvErrorKind>
+    where
+        T: Send + 'static,
+        F: FnOnce(&KvStore, &str, &str) -> Result<T, KvErrorKind> + Send + 'static,
+    {
+        let ns = self.clone();
+        tokio::task::spawn_blocking(move || f(&ns.store, &ns.app, &ns.name))
+            .await
+            .map_err(|_| KvErrorKind::RuntimeError)?
+    }
+
+    fn check_value(&self, value: &[u8]) -> Result<(), KvErrorKind> {
+        match self.max_value_size {
+            Some(max) if value.len() as u64 > max => Err(KvErrorKind::ValueTooLarge),
+            _ => Ok(()),
+        }
+    }
+
+    async fn apply(&self, ops: Vec<KvOp>) -> Result<(), KvErrorKind> {
+        let quota = self.quota;
+        self.run(move |store, app, ns| store.apply(app, ns, &ops, quota))
+            .await
+    }
+}
+
+/// The batch of the namespace, the operations are applied by the commit.
+struct Batch {
+    ns: Namespace,
+    ops: Vec<KvOp>,
+    /// the bytes of the keys and values of the operations.
+    bytes: u64,
+}
+
+fn check_key(key: &str) -> Result<(), KvErrorKind> {
+    if key.is_empty() {
+        return Err(KvErrorKind::InvalidParameter);
+    }
+    Ok(())
+}
+
+async fn get_ns(table: &Table, fd: u32) -> Result<Namespace, KvErrorKind> {
+    let ns = resource::get::<Namespace>(table, fd).ok_or(KvErrorKind::InvalidHandle)?;
+    let ns = ns.lock().await.clone();
+    Ok(ns)
+}
+
+/// open the namespace of the app, the permission of the namespace is checked by the caller.
+pub async fn open(
+    table: &Table,
+    conf: &KvConfig,
+    app: &str,
+    namespace: &str,
+) -> Result<u32, KvErrorKind> {
+    if namespace.is_empty() || namespace.len() > MAX_NAMESPACE_LEN {
+        return Err(KvErrorKind::InvalidParameter);
+    }
+    let path = conf.path.clone();
+    let store = tokio::task::spawn_blocking(move || open_store(path.as_deref()))
+        .await
+        .map_err(|_| KvErrorKind::RuntimeError)??;
+    let ns = Namespace {
+        store,
+        app: app.to_string(),
+        name: namespace.to_string(),
+        quota: conf.quota,
+        max_value_size: conf.max_value_size,
+    };
+    resource::push(table, ns).ok_or(KvErrorKind::RuntimeError)
+}
+
+/// the size of the value of the key.
+pub async fn size(table: &Table, fd: u32, key: &str) -> Result<u32, KvErrorKind> {
+    check_key(key)?;
+    let ns = get_ns(table, fd).await?;
+    let key = key.to_string();
+    let size = ns
+        .run(move |store, app, ns| store.size(app, ns, &key))
+        .await?;
+    size.map(|n| n as u32).ok_or(KvErrorKind::NotFound)
+}
+
+pub async fn get(table: &Table, fd: u32, key: &str) -> Result<Vec<u8>, KvErrorKind> {
+    check_key(key)?;
+    let ns = get_ns(table, fd).await?;
+    let key = key.to_string();
+    let value = ns
+        .run(move |store, app, ns| store.get(app, ns, &key))
+        .await?;
+    value.ok_or(KvErrorKind::NotFound)
+}
+
+pub async fn put(table: &Table, fd: u32, key: &str, value: Vec<u8>) -> Result<(), KvErrorKind> {
+    check_key(key)?;
+    let ns = get_ns(table, fd).await?;
+    ns.check_value(&value)?;
+    ns.apply(vec![KvOp::Put(key.to_string(), value)]).await
+}
+
+pub async fn delete(table: &Table, fd: u32, key: &str) -> Result<(), KvErrorKind> {
+    check_key(key)?;
+    let ns = get_ns(table, fd).await?;
+    ns.apply(vec![KvOp::Delete(key.to_string())]).await
+}
+
+/// the keys with the prefix after the start key as the json array.
+pub async fn scan(
+    table: &Table,
+    fd: u32,
+    prefix: &str,
+    start: &str,
+    limit: u32,
+) -> Result<String, KvErrorKind> {
+    let ns = get_ns(table, fd).await?;
+    let prefix = prefix.to_string();
+    let start = start.to_string();
+    let keys = ns
+        .run(move |store, app, ns| store.scan(app, ns, &prefix, &start, limit))
+        .await?;
+    let keys = keys.into_iter().map(json::JsonValue::from).collect();
+    Ok(json::JsonValue::Array(keys).dump())
+}
+
+pub async fn batch_open(table: &Table, fd: u32) -> Result<u32, KvErrorKind> {
+    let ns = get_ns(table, fd).await?;
+    let batch = Batch {
+        ns,
+        ops: Vec::new(),
+        bytes: 0,
+    };
+    resource::push(table, batch).ok_or(KvErrorKind::RuntimeError)
+}
+
+async fn batch_push(table: &Table, fd: u32, op: KvOp) -> Result<(), KvErrorKind> {
+    let batch = resource::get::<Batch>(table, fd).ok_or(KvErrorKind::InvalidHandle)?;
+    let mut batch = batch.lock().await;
+    if batch.ops.len() >= MAX_BATCH_OPS {
+        return Err(KvErrorKind::InvalidParameter);
+    }
+    let bytes = match op {
+        KvOp::Put(ref key, ref value) => {
+            batch.ns.check_value(value)?;
+            key.len() + value.len()
+        }
+        KvOp::Delete(ref key) => key.len(),
+    };
+    // the batch can't be larger than the quota of the namespace.
+    let bytes = batch.bytes + bytes as u64;
+    if bytes > batch.ns.quota.unwrap_or(MAX_BATCH_BYTES) {
+        return Err(KvErrorKind::QuotaExceeded);
+    }
+    batch.bytes = bytes;
+    batch.ops.push(op);
+    Ok(())
+}
+
+pub async fn batch_put(
+    table: &Table,
+    fd: u32,
+    key: &str,
+    value: Vec<u8>,
+) -> Result<(), KvErrorKind> {
+    check_key(key)?;
+    batch_push(table, fd, KvOp::Put(key.to_string(), value)).await
+}
+
+pub async fn batch_delete(table: &Table, fd: u32, key: &str) -> Result<(), KvErrorKind> {
+    check_key(key)?;
+    batch_push(table, fd, KvOp::Delete(key.to_string())).await
+}
+
+/// commit the batch atomically, the batch is closed even if the commit fails.
+pub async fn batch_commit(table: &Table, fd: u32) -> Result<(), KvErrorKind> {
+    let batch = resource::remove::<Batch>(table, fd).ok_or(KvErrorKind::InvalidHandle)?;
+    let mut batch = batch.lock().await;
+    let ops = std::mem::take(&mut batch.ops);
+    batch.ns.apply(ops).await
+}
+
+/// close the namespace or the batch, the operations of the closed batch are dropped.
+pub async fn close(table: &Table, fd: u32) -> Result<(), KvErrorKind> {
+    if resource::remove::<Namespace>(table, fd).is_some()
+        || resource::remove::<Batch>(table, fd).is_some()
+    {
+        return Ok(());
+    }
+    Err(KvErrorKind::InvalidHandle)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tempdir::TempDir;
+    use tokio::runtime::Builder;
+
+    #[test]
+    fn test_kv_driver() {
+        let rt = Builder::new_current_thread().enable_all().build().unwrap();
+        rt.block_on(async move {
+            let temp_dir = TempDir::new("blockless_kv").unwrap();
+            let conf = KvConfig {
+                path: Some(temp_dir.path().join("kv.db").to_str().unwrap().into()),
+                app: None,
+                quota: Some(24),
+                max_value_size: Some(8),
+            };
+            let table = Table::new();
+            assert!(matches!(
+                open(&table, &conf, "app", "").await,
+                Err(KvErrorKind::InvalidParameter)
+            ));
+            let fd = open(&table, &conf, "app", "cache").await.unwrap();
+            put(&table, fd, "a/1", b"one".to_vec()).await.unwrap();
+            put(&table, fd, "a/2", b"two".to_vec()).await.unwrap();
+            put(&table, fd, "b/1", b"three".to_vec()).await.unwrap();
+            assert_eq!(get(&table, fd, "a/1").await.unwrap(), b"one");
+            assert_eq!(size(&table, fd, "b/1").await.unwrap(), 5);
+            assert!(matches!(
+                get(&table, fd, "c").await,
+                Err(KvErrorKind::NotFound)
+            ));
+            assert!(matches!(
+                put(&table, fd, "c", b"too large".to_vec()).await,
+                Err(KvErrorKind::ValueTooLarge)
+            ));
+            // the namespace has 20 bytes, the quota is 24.
+            assert!(matches!(
+                put(&table, fd, "c/1", b"123456".to_vec()).await,
+                Err(KvErrorKind::QuotaExceeded)
+            ));
+            assert_eq!(
+                scan(&table, fd, "a/", "", 10).await.unwrap(),
+                r#"["a/1","a/2"]"#
+            );
+            assert_eq!(
+                scan(&table, fd, "", "a/1", 2).await.unwrap(),
+                r#"["a/2","b/1"]"#
+            );
+
+            // the other app can't see the keys.
+            let other = open(&table, &conf, "other", "cache").await.unwrap();
+            assert_eq!(scan(&table, other, "", "", 10).await.unwrap(), "[]");
+
+            // nothing is applied if the batch exceeds the quota.
+            let batch = batch_open(&table, fd).await.unwrap();
+            batch_delete(&table, batch, "a/1").await.unwrap();
+            batch_put(&table, batch, "c/1", b"12345678".to_vec())
+                .await
+                .unwrap();
+            assert!(matches!(
+                batch_commit(&table, batch).await,
+                Err(KvErrorKind::QuotaExceeded)
+            ));
+            assert!(matches!(
+                batch_commit(&table, batch).await,
+                Err(KvErrorKind::InvalidHandle)
+            ));
+            assert_eq!(get(&table, fd, "a/1").await.unwrap(), b"one");
+
+            // the batch can't hold more bytes than the quota.
+            let batch = batch_open(&table, fd).await.unwrap();
+            for i in 0..3 {
+                batch_put(&table, batch, &format!("d/{i}"), b"1234".to_vec())
+                    .await
+                    .unwrap();
+            }
+            assert!(matches!(
+                batch_put(&table, batch, "d/3", b"1234".to_vec()).await,
+                Err(KvErrorKind::QuotaExceeded)
+            ));
+            close(&table, batch).await.unwrap();
+
+            let batch = batch_open(&table, fd).await.unwrap();
+            batch_delete(&table, batch, "a/1").await.unwrap();
+            batch_delete(&table, batch, "a/2").await.unwrap();
+            batch_put(&table, batch, "c/1", b"12345678".to_vec())
+                .await
+                .unwrap();
+            batch_commit(&table, batch).await.unwrap();
+            assert_eq!(
+                scan(&table, fd, "", "", 10).await.unwrap(),
+                r#"["b/1","c/1"]"#
+            );
+
+            delete(&table, fd, "b/1").await.unwrap();
+            delete(&table, fd, "b/1").await.unwrap();
+            close(&table, fd).await.unwrap();
+            assert!(matches!(
+                close(&table, fd).await,
+                Err(KvErrorKind::InvalidHandle)
+            ));
+
+            // the data is persisted in the database file.
+            let fd = open(&table, &conf, "app", "cache").await.unwrap();
+            assert_eq!(get(&table, fd, "c/1").await.unwrap(), b"12345678");
+        });
+    }
+}
@@ -0,0 +1,188 @@
+use std::sync::Mutex;
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::KvErrorKind;
+
+/// The operation of the batch, the put and delete of the guest are the batch with one operation.
+#[derive(Debug, Clone)]
+pub(crate) enum KvOp {
+    Put(String, Vec<u8>),
+    Delete(String),
+}
+
+/// The embedded store of the namespaces, the rows are keyed by the app, namespace and key.
+pub(crate) struct KvStore {
+    connect: Mutex<Connection>,
+}
+
+impl KvStore {
+    /// open the database file, the store is in memory if the path is not set.
+    pub(crate) fn open(path: Option<&str>) -> Result<Self, KvErrorKind> {
+        let connect = match path {
+            Some(path) => Connection::open(path)?,
+            None => Connection::open_in_memory()?,
+        };
+        let schema_sql = r#"
+            create table if not exists kv (
+                app TEXT NOT NULL,
+                ns TEXT NOT NULL,
+                key TEXT NOT NULL,
+                value BLOB NOT NULL,
+                PRIMARY KEY (app, ns, key)
+            ) WITHOUT ROWID;
+            create table if not exists kv_usage (
+                app TEXT NOT NULL,
+                ns TEXT NOT NULL,
+                bytes INTEGER NOT NULL,
+                PRIMARY KEY (app, ns)
+            ) WITHOUT ROWID;
+        "#;
+        connect.execute_batch(schema_sql)?;
+        Ok(Self {
+            connect: Mutex::new(connect),
+        })
+    }
+
+    pub(crate) fn get(
+        &self,
+        app: &str,
+        ns: &str,
+        key: &str,
+    ) -> Result<Option<Vec<u8>>, KvErrorKind> {
+        let connect = self.connect.lock().unwrap();
+        Ok(connect
+            .query_row(
+                "select value from kv where app = ?1 and ns = ?2 and key = ?3",
+                params![app, ns, key],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    pub(crate) fn size(&self, app: &str, ns: &str, key: &str) -> Result<Option<u64>, KvErrorKind> {
+        let connect = self.connect.lock().unwrap();
+        Ok(connect
+            .query_row(
+                "select length(value) from kv where app = ?1 and ns = ?2 and key = ?3",
+                params![app, ns, key],
+                |row| row.get::<_, i64>(0),
+            )
+            .optional()?
+            .map(|n| n as u64))
+    }
+
+    /// the keys with the prefix after the start key in order.
+    pub(crate) fn scan(
+        &self,
+        app: &str,
+        ns: &str,
+        prefix: &str,
+        start: &str,
+        limit: u32,
+    ) -> Result<Vec<String>, KvErrorKind> {
+        let connect = self.connect.lock().unwrap();
+        let mut stmt = connect.prepare(
+            r#"
+            select key from kv
+            where app = ?1 and ns = ?2 and key > ?3 and substr(key, 1, length(?4)) = ?4
+            order by key limit ?5
+            "#,
+        )?;
+        let keys = stmt
+            .query_map(params![app, ns, start, prefix, limit], |row| row.get(0))?
+            .collect::<Result<Vec<String>, _>>()?;
+        Ok(keys)
+    }
+
+    /// the bytes of the keys and values in the namespace, the running total is kept in
+    /// the usage table, the namespace without the total is summed once.
+    fn usage(connect: &Connection, app: &str, ns: &str) -> Result<u64, KvErrorKind> {
+        let usage: Option<i64> = connect
+            .query_row(
+                "select bytes from kv_usage where app = ?1 and ns = ?2",
+                params![app, ns],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if let Some(usage) = usage {
+            return Ok(usage as u64);
+        }
+        let usage: i64 = connect.query_row(
+            r#"
+            select coalesce(sum(length(cast(key as blob)) + length(value)), 0)
+            from kv where app = ?1 and ns = ?2
+            "#,
+            params![app, ns],
+            |row| row.get(0),
+        )?;
+        connect.execute(
+            "insert into kv_usage (app, ns, bytes) values (?1, ?2, ?3)",
+            params![app, ns, usage],
+        )?;
+        Ok(usage as u64)
+    }
+
+    /// the bytes of the key and its value, it's 0 if the key is not present.
+    fn entry_size(
+        connect: &Connection,
+        app: &str,
+        ns: &str,
+        key: &str,
+    ) -> Result<u64, KvErrorKind> {
+        let size: Option<i64> = connect
+            .query_row(
+                "select length(value) from kv where app = ?1 and ns = ?2 and key = ?3",
+                params![app, ns, key],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(size.map_or(0, |n| key.len() as u64 + n as u64))
+    }
+
+    /// apply the operations in a transaction, nothing is applied if the namespace grows
+    /// over the quota. The namespace over the quota can still shrink, e.g. by the delete.
+    pub(crate) fn apply(
+        &self,
+        app: &str,
+        ns: &str,
+        ops: &[KvOp],
+        quota: Option<u64>,
+    ) -> Result<(), KvErrorKind> {
+        let mut connect = self.connect.lock().unwrap();
+        let tx = connect.transaction()?;
+        let before = Self::usage(&tx, app, ns)?;
+        let mut after = before;
+        for op in ops {
+            match op {
+                KvOp::Put(key, value) => {
+                    after = after.saturating_sub(Self::entry_size(&tx, app, ns, key)?);
+                    tx.execute(
+                        "insert or replace into kv (app, ns, key, value) values (?1, ?2, ?3, ?4)",
+                        params![app, ns, key, value],
+                    )?;
+                    after += (key.len() + value.len()) as u64;
+                }
+                KvOp::Delete(key) => {
+                    after = after.saturating_sub(Self::entry_size(&tx, app, ns, key)?);
+                    tx.execute(
+                        "delete from kv where app = ?1 and ns = ?2 and key = ?3",
+                        params![app, ns, key],
+                    )?;
+                }
+            };
+        }
+        if let Some(quota) = quota {
+            if after > quota && after > before {
+                // the transaction is rolled back when it's dropped.
+                return Err(KvErrorKind::QuotaExceeded);
+            }
+        }
+        tx.execute(
+            "update kv_usage set bytes = ?3 where app = ?1 and ns = ?2",
+            params![app, ns, after as i64],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+}
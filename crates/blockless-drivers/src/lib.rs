@@ -1,27 +1,32 @@
+pub mod car_driver;
 mod cdylib_driver;
 pub mod cgi_driver;
+pub mod component;
 pub mod error;
 pub mod http_driver;
+pub mod http_server_driver;
 pub mod ipfs_driver;
+pub mod kv_driver;
 pub mod memory_driver;
 pub mod read_ext;
+mod resource;
 pub mod s3_driver;
 pub mod tcp_driver;
+pub mod udp_driver;
 pub mod wasi;
+pub mod ws_driver;
 use blockless_multiaddr as multiaddr;
 pub use cdylib_driver::CdylibDriver;
 pub use error::*;
 #[cfg(not(feature = "builtin_http"))]
 use http_driver::init_http_driver;
 
-use lazy_static::*;
 use log::error;
 use std::collections::HashMap;
 use std::future::Future;
 use std::path::Path;
 use std::pin::Pin;
 use std::sync::Arc;
-use std::sync::Mutex;
 use tcp_driver::TcpDriver;
 use wasi_common::WasiFile;
 
@@ -35,23 +40,19 @@ pub trait Driver {
     ) -> Pin<Box<dyn Future<Output = Result<Box<dyn WasiFile>, ErrorKind>> + Send>>;
 }
 
-lazy_static! {
-    pub static ref DRIVERS: Mutex<DriverConetxtImpl> = Mutex::new(DriverConetxtImpl::new());
-}
-
-pub struct DriverConetxtImpl {
+/// The registry of the drivers, the driver is found by the schema of the uri.
+/// The registry is owned by the runner, the jobs of the runtime share its registry.
+#[derive(Default)]
+pub struct DriverConetxt {
     drivers: HashMap<String, Arc<dyn Driver + Sync + Send>>,
 }
 
-impl DriverConetxtImpl {
-    fn new() -> Self {
-        let ctx = DriverConetxtImpl {
-            drivers: HashMap::new(),
-        };
-        ctx
+impl DriverConetxt {
+    pub fn new() -> Self {
+        Self::default()
     }
 
-    fn insert_driver<T>(&mut self, driver: T)
+    pub fn insert_driver<T>(&mut self, driver: T)
     where
         T: Driver + Send + Sync + 'static,
     {
@@ -59,7 +60,7 @@ impl DriverConetxtImpl {
         self.drivers.insert(key, Arc::new(driver));
     }
 
-    fn find_driver(&self, uri: &str) -> Option<Arc<dyn Driver + Sync + Send>> {
+    pub fn find_driver(&self, uri: &str) -> Option<Arc<dyn Driver + Sync + Send>> {
         let addr = match multiaddr::parse(uri.as_bytes()) {
             Err(e) => {
                 error!("error parse:{:?}", e);
@@ -76,29 +77,15 @@ impl DriverConetxtImpl {
         };
         self.drivers.get(&schema).map(|d| d.clone())
     }
-}
-
-pub struct DriverConetxt;
-
-impl DriverConetxt {
-    pub fn find_driver(uri: &str) -> Option<Arc<dyn Driver + Sync + Send>> {
-        let drv = DRIVERS.lock().unwrap();
-        drv.find_driver(uri)
-    }
-
-    pub fn insert_driver<T: Driver + Sync + Send + 'static>(driver: T) {
-        let mut drv = DRIVERS.lock().unwrap();
-        drv.insert_driver(driver);
-    }
 
     #[warn(unused_variables)]
-    pub fn init_built_in_drivers(_path: impl AsRef<Path>) {
+    pub fn init_built_in_drivers(&mut self, _path: impl AsRef<Path>) {
         #[cfg(not(feature = "builtin_http"))]
         let tcp_driver_path = _path.as_ref().join("http_driver.so");
         #[cfg(not(feature = "builtin_http"))]
         if tcp_driver_path.exists() {
             init_http_driver(tcp_driver_path.as_os_str()).unwrap();
         }
-        Self::insert_driver(TcpDriver {});
+        self.insert_driver(TcpDriver {});
     }
 }
@@ -1,15 +1,63 @@
 use crate::BlocklessMemoryErrorKind;
+use std::sync::atomic::AtomicUsize;
 
-pub async fn read(buf: &mut [u8], string: String) -> Result<u32, BlocklessMemoryErrorKind> {
-    let bytes = string.as_bytes();
+/// the env vars as the json object, the later value overrides the same name.
+pub fn env_json(vars: &[(String, String)]) -> String {
+    let mut obj = json::JsonValue::new_object();
+    for (name, value) in vars {
+        obj[name.as_str()] = value.as_str().into();
+    }
+    obj.dump()
+}
 
-    if buf.len() == 0 {
+/// read the data from the cursor into the buffer, the cursor is advanced by the read,
+/// 0 is returned at the end of the data.
+pub fn read_at(
+    buf: &mut [u8],
+    data: &[u8],
+    cursor: &AtomicUsize,
+) -> Result<u32, BlocklessMemoryErrorKind> {
+    if buf.is_empty() {
         return Err(BlocklessMemoryErrorKind::InvalidParameter);
     }
+    Ok(wasi_common::read_at_cursor(cursor, data, buf) as u32)
+}
 
-    for n in 0..(bytes.len()) {
-        buf[n] = bytes[n];
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_env_json() {
+        let vars = vec![
+            ("A".to_string(), "quote \" and\nnewline".to_string()),
+            ("B".to_string(), "1".to_string()),
+            ("B".to_string(), "2".to_string()),
+        ];
+        let json = env_json(&vars);
+        let obj = json::parse(&json).unwrap();
+        assert_eq!(obj["A"], "quote \" and\nnewline");
+        assert_eq!(obj["B"], "2");
+        assert_eq!(env_json(&[]), "{}");
     }
 
-    Ok(bytes.len() as u32)
+    #[test]
+    fn test_read_at() {
+        let data = b"hello world";
+        let mut buf = [0u8; 4];
+        let cursor = AtomicUsize::new(0);
+        let mut out = Vec::new();
+        loop {
+            let n = read_at(&mut buf, data, &cursor).unwrap() as usize;
+            if n == 0 {
+                break;
+            }
+            out.extend_from_slice(&buf[..n]);
+        }
+        assert_eq!(out, data);
+        assert!(matches!(
+            read_at(&mut [], data, &cursor),
+            Err(BlocklessMemoryErrorKind::InvalidParameter)
+        ));
+    }
 }
@@ -0,0 +1,45 @@
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use wasi_common::Table;
+
+/// The resource of the drivers is stored in the resource table of the guest context,
+/// the resource is wrapped with the async mutex, because the driver holds it across the await.
+pub(crate) type Resource<T> = Arc<Mutex<T>>;
+
+/// push the resource into the table and return the handle.
+pub(crate) fn push<T: Send + 'static>(table: &Table, res: T) -> Option<u32> {
+    table.push(Arc::new(Mutex::new(res))).ok()
+}
+
+/// get the resource of the handle, return `None` if the handle is not the resource of the type.
+pub(crate) fn get<T: Send + 'static>(table: &Table, handle: u32) -> Option<Resource<T>> {
+    table.get::<Mutex<T>>(handle).ok()
+}
+
+/// remove the resource of the handle from the table.
+pub(crate) fn remove<T: Send + 'static>(table: &Table, handle: u32) -> Option<Resource<T>> {
+    if !table.is::<Mutex<T>>(handle) {
+        return None;
+    }
+    table.delete::<Mutex<T>>(handle)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_resource_scoped_by_table() {
+        let table_a = Table::new();
+        let table_b = Table::new();
+        let handle = push(&table_a, String::from("a")).unwrap();
+        assert!(get::<String>(&table_a, handle).is_some());
+        // the handle is not shared with the other table.
+        assert!(get::<String>(&table_b, handle).is_none());
+        // the handle can't be used as the resource of the other type.
+        assert!(get::<u32>(&table_a, handle).is_none());
+        assert!(remove::<u32>(&table_a, handle).is_none());
+        assert!(remove::<String>(&table_a, handle).is_some());
+        assert!(get::<String>(&table_a, handle).is_none());
+    }
+}
@@ -6,10 +6,27 @@ use crate::S3ErrorKind;
 struct S3Config {
     access_key: String,
     secret_key: String,
+    session_token: Option<String>,
     endpoint: String,
     region: String,
 }
 
+impl S3Config {
+    fn credentials(&self) -> Result<Credentials, S3ErrorKind> {
+        Credentials::new(
+            Some(&self.access_key),
+            Some(&self.secret_key),
+            None,
+            self.session_token.as_deref(),
+            None,
+        )
+        .map_err(|e| {
+            error!("credentials error: {}", e);
+            S3ErrorKind::CredentialsError
+        })
+    }
+}
+
 fn get_aws_config(json: &json::JsonValue) -> Result<S3Config, S3ErrorKind> {
     let access_key = match json["access_key"].as_str() {
         Some(s) => String::from(s),
@@ -27,25 +44,92 @@ fn get_aws_config(json: &json::JsonValue) -> Result<S3Config, S3ErrorKind> {
         Some(s) => String::from(s),
         None => String::from("us-east-1"),
     };
+    let session_token = json["session_token"].as_str().map(String::from);
     Ok(S3Config {
         access_key,
         secret_key,
+        session_token,
         endpoint,
         region,
     })
 }
 
+/// the bucket resource for the permission check, the format is `s3://endpoint/bucket`,
+/// the scheme of the endpoint is stripped, e.g. `s3://127.0.0.1:9000/test`.
+pub(crate) fn bucket_resource(cfg: &str) -> Result<String, S3ErrorKind> {
+    let json = match json::parse(cfg) {
+        Ok(o) => o,
+        Err(_) => return Err(S3ErrorKind::InvalidParameter),
+    };
+    let endpoint = match json["endpoint"].as_str() {
+        Some(s) => s,
+        None => return Err(S3ErrorKind::InvalidParameter),
+    };
+    let bucket_name = match json["bucket_name"].as_str() {
+        Some(s) => s,
+        None => return Err(S3ErrorKind::InvalidParameter),
+    };
+    let endpoint = match endpoint.find("://") {
+        Some(p) => &endpoint[p + 3..],
+        None => endpoint,
+    };
+    let endpoint = endpoint.trim_end_matches('/');
+    Ok(format!("s3://{endpoint}/{bucket_name}"))
+}
+
+/// the url of the bucket for the urls of the credential profile, e.g.
+/// `https://s3.amazonaws.com/test`, the endpoint without the scheme is https.
+pub(crate) fn bucket_url(cfg: &str) -> Result<String, S3ErrorKind> {
+    let json = json::parse(cfg).map_err(|_| S3ErrorKind::InvalidParameter)?;
+    let endpoint = json["endpoint"]
+        .as_str()
+        .ok_or(S3ErrorKind::InvalidParameter)?;
+    let bucket_name = json["bucket_name"]
+        .as_str()
+        .ok_or(S3ErrorKind::InvalidParameter)?;
+    let endpoint = endpoint.trim_end_matches('/');
+    if endpoint.contains("://") {
+        Ok(format!("{endpoint}/{bucket_name}"))
+    } else {
+        Ok(format!("https://{endpoint}/{bucket_name}"))
+    }
+}
+
+/// the credential profile of the parameters, e.g. `{"profile": "s3-prod"}`.
+pub(crate) fn profile_name(cfg: &str) -> Result<Option<String>, S3ErrorKind> {
+    let json = json::parse(cfg).map_err(|_| S3ErrorKind::InvalidParameter)?;
+    Ok(json["profile"].as_str().map(String::from))
+}
+
+/// replace the profile of the parameters with the credentials, the credentials of the
+/// parameters are overridden.
+pub(crate) fn with_credentials(
+    cfg: &str,
+    access_key: &str,
+    secret_key: &str,
+    session_token: Option<&str>,
+) -> Result<String, S3ErrorKind> {
+    let mut json = json::parse(cfg).map_err(|_| S3ErrorKind::InvalidParameter)?;
+    json.remove("profile");
+    json["access_key"] = access_key.into();
+    json["secret_key"] = secret_key.into();
+    json.remove("session_token");
+    if let Some(token) = session_token {
+        json["session_token"] = token.into();
+    }
+    Ok(json::stringify(json))
+}
+
 pub(crate) async fn create(cfg: &str) -> Result<String, S3ErrorKind> {
     let json = match json::parse(cfg) {
         Ok(o) => o,
         Err(_) => return Err(S3ErrorKind::InvalidParameter),
     };
+    let aws_config = get_aws_config(&json)?;
+    let credentials = aws_config.credentials()?;
     let S3Config {
-        access_key,
-        secret_key,
-        endpoint,
-        region,
-    } = get_aws_config(&json)?;
+        endpoint, region, ..
+    } = aws_config;
 
     let bucket_name = match json["bucket_name"].as_str() {
         Some(s) => String::from(s),
@@ -55,8 +139,6 @@ pub(crate) async fn create(cfg: &str) -> Result<String, S3ErrorKind> {
         region: region.into(),
         endpoint: endpoint,
     };
-    let credentials =
-        Credentials::new(Some(&access_key), Some(&secret_key), None, None, None).unwrap();
     let config = BucketConfiguration::default();
     let response = match Bucket::create(&bucket_name, region, credentials, config).await {
         Ok(respone) => respone,
@@ -117,27 +199,29 @@ pub(crate) async fn list(cfg: &str) -> Result<String, S3ErrorKind> {
     Ok(json::stringify(rs))
 }
 
-fn new_bucket(json: &json::JsonValue) -> Result<Box<Bucket>, S3ErrorKind> {
+/// create the bucket of the parameters, the path style is used if `path_style` is true,
+/// it's required by most of the S3 compatible services, e.g. MinIO.
+pub(crate) fn new_bucket(json: &json::JsonValue) -> Result<Box<Bucket>, S3ErrorKind> {
     let bucket_name = match json["bucket_name"].as_str() {
         Some(s) => String::from(s),
         None => return Err(S3ErrorKind::InvalidParameter),
     };
+    let aws_config = get_aws_config(&json)?;
+    let credentials = aws_config.credentials()?;
     let S3Config {
-        access_key,
-        secret_key,
-        endpoint,
-        region,
-    } = get_aws_config(&json)?;
+        endpoint, region, ..
+    } = aws_config;
     let region = Region::Custom {
         region: region.into(),
         endpoint: endpoint,
     };
-    let credentials =
-        Credentials::new(Some(&access_key), Some(&secret_key), None, None, None).unwrap();
     let bucket = Bucket::new(&bucket_name, region, credentials).map_err(|e| {
         error!("new bucket error:{}", e);
         S3ErrorKind::InvalidParameter
     })?;
+    if json["path_style"].as_bool().unwrap_or(false) {
+        return Ok(bucket.with_path_style());
+    }
     Ok(bucket)
 }
 
@@ -162,7 +246,8 @@ pub(crate) async fn put_object(cfg: &str, buf: &[u8]) -> Result<(), S3ErrorKind>
     Ok(())
 }
 
-pub(crate) async fn get_object(cfg: &str) -> Result<Vec<u8>, S3ErrorKind> {
+/// the head of the object as the json, e.g. `{"content_length": 11, "e_tag": "\"...\""}`.
+pub(crate) async fn head_object(cfg: &str) -> Result<String, S3ErrorKind> {
     let json = match json::parse(cfg) {
         Ok(o) => o,
         Err(_) => return Err(S3ErrorKind::InvalidParameter),
@@ -173,14 +258,86 @@ pub(crate) async fn get_object(cfg: &str) -> Result<Vec<u8>, S3ErrorKind> {
         None => return Err(S3ErrorKind::InvalidParameter),
     };
     let bucket = new_bucket(&json)?;
-    let resp = bucket.get_object(path).await.map_err(|e| {
+    let (head, code) = bucket.head_object(path).await.map_err(|e| {
         error!("{}", e);
         S3ErrorKind::RequestError
     })?;
-    if resp.status_code() != 200 {
+    if code != 200 {
+        return Err(S3ErrorKind::RequestError);
+    }
+    let mut rs = json::JsonValue::new_object();
+    rs["content_length"] = head.content_length.into();
+    rs["content_type"] = head.content_type.into();
+    rs["e_tag"] = head.e_tag.into();
+    rs["last_modified"] = head.last_modified.into();
+    let mut metadata = json::JsonValue::new_object();
+    for (k, v) in head.metadata.unwrap_or_default() {
+        metadata[k] = v.into();
+    }
+    rs["metadata"] = metadata;
+    Ok(json::stringify(rs))
+}
+
+/// copy the object `copy_source` to the `path` in the same bucket.
+pub(crate) async fn copy_object(cfg: &str) -> Result<String, S3ErrorKind> {
+    let json = match json::parse(cfg) {
+        Ok(o) => o,
+        Err(_) => return Err(S3ErrorKind::InvalidParameter),
+    };
+
+    let path = match json["path"].as_str() {
+        Some(s) => String::from(s),
+        None => return Err(S3ErrorKind::InvalidParameter),
+    };
+    let source = match json["copy_source"].as_str() {
+        Some(s) => String::from(s),
+        None => return Err(S3ErrorKind::InvalidParameter),
+    };
+    let bucket = new_bucket(&json)?;
+    let code = bucket
+        .copy_object_internal(source, path)
+        .await
+        .map_err(|e| {
+            error!("{}", e);
+            S3ErrorKind::RequestError
+        })?;
+    if code != 200 {
         return Err(S3ErrorKind::RequestError);
     }
-    Ok(resp.bytes().to_vec())
+    let mut rs = json::JsonValue::new_object();
+    rs["code"] = code.into();
+    Ok(json::stringify(rs))
+}
+
+/// the max expiry of the presigned url, 7 days.
+const MAX_PRESIGN_EXPIRY: u32 = 604800;
+
+/// the presigned url of the object, the `method` is `GET` or `PUT`, the default is `GET`,
+/// the `expiry_secs` is the expiry of the url, the default is 1 hour.
+pub(crate) async fn presign(cfg: &str) -> Result<String, S3ErrorKind> {
+    let json = match json::parse(cfg) {
+        Ok(o) => o,
+        Err(_) => return Err(S3ErrorKind::InvalidParameter),
+    };
+
+    let path = match json["path"].as_str() {
+        Some(s) => String::from(s),
+        None => return Err(S3ErrorKind::InvalidParameter),
+    };
+    let expiry = json["expiry_secs"].as_u32().unwrap_or(3600);
+    if expiry == 0 || expiry > MAX_PRESIGN_EXPIRY {
+        return Err(S3ErrorKind::InvalidParameter);
+    }
+    let bucket = new_bucket(&json)?;
+    let url = match json["method"].as_str().unwrap_or("GET") {
+        "GET" => bucket.presign_get(path, expiry, None).await,
+        "PUT" => bucket.presign_put(path, expiry, None, None).await,
+        _ => return Err(S3ErrorKind::InvalidMethod),
+    };
+    url.map_err(|e| {
+        error!("presign error: {}", e);
+        S3ErrorKind::CredentialsError
+    })
 }
 
 pub(crate) async fn delete_object(cfg: &str) -> Result<(), S3ErrorKind> {
@@ -203,3 +360,69 @@ pub(crate) async fn delete_object(cfg: &str) -> Result<(), S3ErrorKind> {
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokio::runtime::Builder;
+
+    #[test]
+    fn test_bucket_resource() {
+        let cfg = r#"{"endpoint": "http://127.0.0.1:9000/", "bucket_name": "test"}"#;
+        assert_eq!(bucket_resource(cfg).unwrap(), "s3://127.0.0.1:9000/test");
+        let cfg = r#"{"endpoint": "s3.amazonaws.com", "bucket_name": "test"}"#;
+        assert_eq!(bucket_resource(cfg).unwrap(), "s3://s3.amazonaws.com/test");
+        let cfg = r#"{"endpoint": "s3.amazonaws.com"}"#;
+        assert!(bucket_resource(cfg).is_err());
+        assert!(bucket_url(cfg).is_err());
+        let cfg = r#"{"endpoint": "http://127.0.0.1:9000/", "bucket_name": "test"}"#;
+        assert_eq!(bucket_url(cfg).unwrap(), "http://127.0.0.1:9000/test");
+        let cfg = r#"{"endpoint": "s3.amazonaws.com", "bucket_name": "test"}"#;
+        assert_eq!(bucket_url(cfg).unwrap(), "https://s3.amazonaws.com/test");
+    }
+
+    #[test]
+    fn test_with_credentials() {
+        let cfg = r#"{"endpoint": "http://127.0.0.1:9000", "bucket_name": "test",
+            "profile": "s3-prod", "access_key": "guest", "session_token": "old"}"#;
+        assert_eq!(profile_name(cfg).unwrap().as_deref(), Some("s3-prod"));
+        let json = json::parse(&with_credentials(cfg, "ak", "sk", None).unwrap()).unwrap();
+        assert!(json["profile"].is_null());
+        assert!(json["session_token"].is_null());
+        assert_eq!(json["access_key"], "ak");
+        assert_eq!(json["secret_key"], "sk");
+        assert_eq!(json["bucket_name"], "test");
+        let json = json::parse(&with_credentials(cfg, "ak", "sk", Some("st")).unwrap()).unwrap();
+        assert_eq!(json["session_token"], "st");
+        assert_eq!(profile_name(r#"{"bucket_name": "test"}"#).unwrap(), None);
+    }
+
+    #[test]
+    fn test_presign() {
+        let rt = Builder::new_current_thread().enable_all().build().unwrap();
+        rt.block_on(async move {
+            let cfg = r#"{"endpoint": "http://127.0.0.1:9000", "bucket_name": "test",
+                "path_style": true, "access_key": "minio", "secret_key": "minio123",
+                "path": "a.txt", "expiry_secs": 60}"#;
+            let url = presign(cfg).await.unwrap();
+            assert!(url.starts_with("http://127.0.0.1:9000/test/a.txt?"));
+            assert!(url.contains("X-Amz-Expires=60"));
+            assert!(url.contains("X-Amz-Signature="));
+
+            let cfg = r#"{"endpoint": "http://127.0.0.1:9000", "bucket_name": "test",
+                "access_key": "minio", "secret_key": "minio123", "path": "a.txt",
+                "method": "POST"}"#;
+            assert!(matches!(
+                presign(cfg).await,
+                Err(S3ErrorKind::InvalidMethod)
+            ));
+            let cfg = r#"{"endpoint": "http://127.0.0.1:9000", "bucket_name": "test",
+                "access_key": "minio", "secret_key": "minio123", "path": "a.txt",
+                "expiry_secs": 0}"#;
+            assert!(matches!(
+                presign(cfg).await,
+                Err(S3ErrorKind::InvalidParameter)
+            ));
+        });
+    }
+}
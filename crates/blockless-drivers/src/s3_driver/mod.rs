@@ -1,7 +1,12 @@
 mod bucket;
-use std::{collections::HashMap, sync::Once};
+mod multipart;
+mod object;
+use wasi_common::Table;
 
-use crate::{read_ext::ReadRemain, S3ErrorKind};
+use multipart::MultipartUpload;
+use object::ObjectStream;
+
+use crate::{read_ext::ReadRemain, resource, S3ErrorKind};
 
 pub struct VecResult {
     content: Vec<u8>,
@@ -33,35 +38,53 @@ impl ReadRemain for VecResult {
 
 pub enum S3Ctx {
     VecResult(VecResult),
+    Object(ObjectStream),
+    Multipart(MultipartUpload),
     None,
 }
 
-pub fn get_ctx() -> Option<&'static mut HashMap<u32, S3Ctx>> {
-    static mut CTX: Option<HashMap<u32, S3Ctx>> = None;
-    static CTX_ONCE: Once = Once::new();
-    CTX_ONCE.call_once(|| {
-        unsafe {
-            CTX = Some(HashMap::new());
-        };
-    });
-    unsafe { CTX.as_mut() }
+/// get the bucket resource of the parameters, it's used for the permission check.
+pub fn bucket_resource(params: &str) -> Result<String, S3ErrorKind> {
+    bucket::bucket_resource(params)
 }
 
-pub fn increase_fd() -> Option<u32> {
-    static mut MAX_HANDLE: u32 = 0;
-    unsafe {
-        MAX_HANDLE += 1;
-        Some(MAX_HANDLE)
-    }
+/// the command of `bucket_command` to presign the url.
+pub const PRESIGN_COMMAND: u16 = 7;
+
+/// get the url of the bucket, it's checked with the urls of the credential profile.
+pub fn bucket_url(params: &str) -> Result<String, S3ErrorKind> {
+    bucket::bucket_url(params)
 }
 
-pub async fn close(handle: u32) -> Result<(), S3ErrorKind> {
-    let ctx = get_ctx().unwrap();
-    ctx.remove(&handle);
+/// get the credential profile name of the parameters.
+pub fn profile_name(params: &str) -> Result<Option<String>, S3ErrorKind> {
+    bucket::profile_name(params)
+}
+
+/// replace the profile of the parameters with the credentials of the profile.
+pub fn with_credentials(
+    params: &str,
+    access_key: &str,
+    secret_key: &str,
+    session_token: Option<&str>,
+) -> Result<String, S3ErrorKind> {
+    bucket::with_credentials(params, access_key, secret_key, session_token)
+}
+
+/// close the handle, the unfinished multipart upload is aborted.
+pub async fn close(table: &Table, handle: u32) -> Result<(), S3ErrorKind> {
+    if let Some(ctx) = resource::remove::<S3Ctx>(table, handle) {
+        if let S3Ctx::Multipart(ref upload) = *ctx.lock().await {
+            upload.abort().await?;
+        }
+    }
     Ok(())
 }
 
-pub async fn bucket_command(cmd: u16, params: &str) -> Result<u32, S3ErrorKind> {
+/// run the bucket command, the result is read with the handle, the commands are:
+/// 1: create the bucket, 2: list the objects, 3: get the object (the range is optional),
+/// 4: delete the object, 5: head the object, 6: copy the object, 7: presign the url.
+pub async fn bucket_command(table: &Table, cmd: u16, params: &str) -> Result<u32, S3ErrorKind> {
     let content = match cmd {
         1 => {
             let json = bucket::create(params).await?;
@@ -71,33 +94,401 @@ pub async fn bucket_command(cmd: u16, params: &str) -> Result<u32, S3ErrorKind>
             let json = bucket::list(params).await?;
             S3Ctx::VecResult(VecResult::new(json.as_bytes().to_vec()))
         }
-        3 => {
-            let rs = bucket::get_object(params).await?;
-            S3Ctx::VecResult(VecResult::new(rs))
-        }
+        3 => S3Ctx::Object(ObjectStream::open(params).await?),
         4 => {
             bucket::delete_object(params).await?;
             S3Ctx::None
         }
+        5 => {
+            let json = bucket::head_object(params).await?;
+            S3Ctx::VecResult(VecResult::new(json.into_bytes()))
+        }
+        6 => {
+            let json = bucket::copy_object(params).await?;
+            S3Ctx::VecResult(VecResult::new(json.into_bytes()))
+        }
+        PRESIGN_COMMAND => {
+            let url = bucket::presign(params).await?;
+            S3Ctx::VecResult(VecResult::new(url.into_bytes()))
+        }
         _ => return Err(S3ErrorKind::InvalidParameter),
     };
 
-    let fd = increase_fd().unwrap();
-    get_ctx().unwrap().insert(fd, content);
-    Ok(fd)
+    resource::push(table, content).ok_or(S3ErrorKind::RuntimeError)
 }
 
 pub async fn bucket_put_object(cfg: &str, buf: &[u8]) -> Result<(), S3ErrorKind> {
     bucket::put_object(cfg, buf).await
 }
 
-pub async fn read(handle: u32, buf: &mut [u8]) -> Result<u32, S3ErrorKind> {
-    let ctx = get_ctx().unwrap();
+/// initiate the multipart upload, the parts are written with `multipart_write`.
+pub async fn multipart_open(table: &Table, params: &str) -> Result<u32, S3ErrorKind> {
+    let upload = MultipartUpload::open(params).await?;
+    resource::push(table, S3Ctx::Multipart(upload)).ok_or(S3ErrorKind::RuntimeError)
+}
+
+pub async fn multipart_write(table: &Table, handle: u32, buf: &[u8]) -> Result<u32, S3ErrorKind> {
+    let ctx = resource::get::<S3Ctx>(table, handle).ok_or(S3ErrorKind::InvalidHandle)?;
+    let mut ctx = ctx.lock().await;
+    match *ctx {
+        S3Ctx::Multipart(ref mut upload) => upload.write(buf).await,
+        _ => Err(S3ErrorKind::InvalidHandle),
+    }
+}
+
+/// complete the multipart upload, then the result json can be read with the handle.
+pub async fn multipart_complete(table: &Table, handle: u32) -> Result<(), S3ErrorKind> {
+    let ctx = resource::get::<S3Ctx>(table, handle).ok_or(S3ErrorKind::InvalidHandle)?;
+    let mut ctx = ctx.lock().await;
+    let rs = match *ctx {
+        S3Ctx::Multipart(ref mut upload) => upload.complete().await?,
+        _ => return Err(S3ErrorKind::InvalidHandle),
+    };
+    *ctx = S3Ctx::VecResult(VecResult::new(rs.into_bytes()));
+    Ok(())
+}
+
+pub async fn read(table: &Table, handle: u32, buf: &mut [u8]) -> Result<u32, S3ErrorKind> {
     if buf.len() == 0 {
         return Err(S3ErrorKind::InvalidParameter);
     }
-    match ctx.get_mut(&handle) {
-        Some(S3Ctx::VecResult(resp)) => Ok(resp.copy_remain(buf) as _),
+    let ctx = resource::get::<S3Ctx>(table, handle).ok_or(S3ErrorKind::InvalidHandle)?;
+    let mut ctx = ctx.lock().await;
+    match *ctx {
+        S3Ctx::VecResult(ref mut resp) => Ok(resp.copy_remain(buf) as _),
+        S3Ctx::Object(ref mut stream) => stream.read(buf).await,
         _ => return Err(S3ErrorKind::InvalidHandle),
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    };
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+        runtime::Builder,
+    };
+
+    /// the state of the mock S3 server.
+    #[derive(Default)]
+    struct MockS3 {
+        objects: HashMap<String, Vec<u8>>,
+        parts: HashMap<u32, Vec<u8>>,
+        aborted: bool,
+    }
+
+    type State = Arc<Mutex<MockS3>>;
+
+    struct MockRequest {
+        method: String,
+        path: String,
+        range: Option<String>,
+        copy_source: Option<String>,
+        body: Vec<u8>,
+    }
+
+    type MockResponse = (&'static str, Vec<(&'static str, String)>, Vec<u8>);
+
+    /// handle the request with the path style, the path is `/bucket/key`.
+    fn handle(state: &State, req: MockRequest) -> MockResponse {
+        let mut state = state.lock().unwrap();
+        let (path, query) = req.path.split_once('?').unwrap_or((&req.path, ""));
+        let key = path.splitn(3, '/').nth(2).unwrap_or("").to_string();
+        let not_found = ("404 Not Found", Vec::new(), b"<Error/>".to_vec());
+        match req.method.as_str() {
+            "POST" if query.starts_with("uploads") => {
+                let xml = format!(
+                    "<InitiateMultipartUploadResult><Bucket>test</Bucket><Key>{key}</Key>\
+                    <UploadId>upload1</UploadId></InitiateMultipartUploadResult>"
+                );
+                state.parts.clear();
+                ("200 OK", Vec::new(), xml.into_bytes())
+            }
+            "PUT" if query.contains("partNumber=") => {
+                let n: u32 = query
+                    .split('&')
+                    .find_map(|q| q.strip_prefix("partNumber="))
+                    .unwrap()
+                    .parse()
+                    .unwrap();
+                state.parts.insert(n, req.body);
+                ("200 OK", vec![("etag", format!("\"part{n}\""))], Vec::new())
+            }
+            "POST" if query.starts_with("uploadId=") => {
+                let mut numbers = state.parts.keys().copied().collect::<Vec<_>>();
+                numbers.sort();
+                let data = numbers
+                    .iter()
+                    .flat_map(|n| state.parts[n].clone())
+                    .collect::<Vec<_>>();
+                state.objects.insert(key, data);
+                let xml = "<CompleteMultipartUploadResult><ETag>\"obj\"</ETag>\
+                    </CompleteMultipartUploadResult>";
+                ("200 OK", Vec::new(), xml.as_bytes().to_vec())
+            }
+            "DELETE" if query.starts_with("uploadId=") => {
+                state.parts.clear();
+                state.aborted = true;
+                ("204 No Content", Vec::new(), Vec::new())
+            }
+            "PUT" => {
+                let data = match req.copy_source {
+                    Some(source) => {
+                        let source = source.trim_start_matches('/');
+                        let source = source.split_once('/').map_or(source, |(_, k)| k);
+                        match state.objects.get(source) {
+                            Some(data) => data.clone(),
+                            None => return not_found,
+                        }
+                    }
+                    None => req.body,
+                };
+                state.objects.insert(key, data);
+                let xml = "<CopyObjectResult><ETag>\"obj\"</ETag></CopyObjectResult>";
+                (
+                    "200 OK",
+                    vec![("etag", "\"obj\"".into())],
+                    xml.as_bytes().to_vec(),
+                )
+            }
+            "GET" | "HEAD" => {
+                let data = match state.objects.get(&key) {
+                    Some(data) => data,
+                    None => return not_found,
+                };
+                let len = data.len();
+                match req.range.as_deref().and_then(|r| r.strip_prefix("bytes=")) {
+                    Some(range) => {
+                        let (start, end) = range.split_once('-').unwrap();
+                        let start: usize = start.parse().unwrap();
+                        if start >= len {
+                            return ("416 Range Not Satisfiable", Vec::new(), Vec::new());
+                        }
+                        let end = end.parse().map_or(len - 1, |e: usize| e.min(len - 1));
+                        let content_range = format!("bytes {start}-{end}/{len}");
+                        let body = data[start..=end].to_vec();
+                        (
+                            "206 Partial Content",
+                            vec![("content-range", content_range)],
+                            body,
+                        )
+                    }
+                    None => {
+                        let headers = vec![
+                            ("etag", "\"obj\"".into()),
+                            ("content-type", "application/octet-stream".into()),
+                            ("last-modified", "Wed, 21 Oct 2015 07:28:00 GMT".into()),
+                        ];
+                        ("200 OK", headers, data.clone())
+                    }
+                }
+            }
+            "DELETE" => {
+                state.objects.remove(&key);
+                ("204 No Content", Vec::new(), Vec::new())
+            }
+            _ => ("405 Method Not Allowed", Vec::new(), Vec::new()),
+        }
+    }
+
+    /// parse the request from the buffer, return the request and the consumed length.
+    fn parse_request(buf: &[u8]) -> Option<(MockRequest, usize)> {
+        let mut headers = [httparse::EMPTY_HEADER; 32];
+        let mut parsed = httparse::Request::new(&mut headers);
+        let len = match parsed.parse(buf).unwrap() {
+            httparse::Status::Complete(len) => len,
+            httparse::Status::Partial => return None,
+        };
+        let header = |name: &str| {
+            parsed
+                .headers
+                .iter()
+                .find(|h| h.name.eq_ignore_ascii_case(name))
+                .map(|h| String::from_utf8_lossy(h.value).to_string())
+        };
+        let content_len: usize = header("content-length").map_or(0, |v| v.parse().unwrap());
+        if buf.len() < len + content_len {
+            return None;
+        }
+        let req = MockRequest {
+            method: parsed.method.unwrap().to_string(),
+            path: parsed.path.unwrap().to_string(),
+            range: header("range"),
+            copy_source: header("x-amz-copy-source"),
+            body: buf[len..len + content_len].to_vec(),
+        };
+        Some((req, len + content_len))
+    }
+
+    async fn serve(mut stream: TcpStream, state: State) {
+        let mut req = Vec::new();
+        let mut buf = [0u8; 8192];
+        loop {
+            let (request, consumed) = match parse_request(&req) {
+                Some(r) => r,
+                None => {
+                    let n = stream.read(&mut buf).await.unwrap_or(0);
+                    if n == 0 {
+                        return;
+                    }
+                    req.extend_from_slice(&buf[..n]);
+                    continue;
+                }
+            };
+            req.drain(..consumed);
+            let is_head = request.method == "HEAD";
+            let (status, headers, body) = handle(&state, request);
+            let mut head = format!("HTTP/1.1 {status}\r\ncontent-length: {}\r\n", body.len());
+            for (k, v) in headers {
+                head.push_str(&format!("{k}: {v}\r\n"));
+            }
+            head.push_str("\r\n");
+            stream.write_all(head.as_bytes()).await.unwrap();
+            if !is_head {
+                stream.write_all(&body).await.unwrap();
+            }
+        }
+    }
+
+    /// the in-process S3 stand-in, it serves the requests of the driver with the path style.
+    async fn mock_s3() -> (String, State) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let state = State::default();
+        let server_state = state.clone();
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                tokio::spawn(serve(stream, server_state.clone()));
+            }
+        });
+        (format!("http://{addr}"), state)
+    }
+
+    fn params(endpoint: &str, extra: &str) -> String {
+        format!(
+            r#"{{"endpoint": "{endpoint}", "bucket_name": "test", "path_style": true,
+            "access_key": "minio", "secret_key": "minio123", {extra}}}"#
+        )
+    }
+
+    async fn read_all(table: &Table, handle: u32) -> Vec<u8> {
+        let mut buf = vec![0u8; 64 * 1024];
+        let mut out = Vec::new();
+        loop {
+            let n = read(table, handle, &mut buf).await.unwrap() as usize;
+            if n == 0 {
+                return out;
+            }
+            out.extend_from_slice(&buf[..n]);
+        }
+    }
+
+    #[test]
+    fn test_s3_multipart_and_range_get() {
+        let rt = Builder::new_current_thread().enable_all().build().unwrap();
+        rt.block_on(async move {
+            let (endpoint, state) = mock_s3().await;
+            let table = Table::new();
+            let data = (0..2 * multipart::MIN_PART_SIZE + 1234)
+                .map(|i| (i % 251) as u8)
+                .collect::<Vec<_>>();
+
+            let fd = multipart_open(&table, &params(&endpoint, r#""path": "a.bin""#))
+                .await
+                .unwrap();
+            for chunk in data.chunks(1024 * 1024 + 7) {
+                let n = multipart_write(&table, fd, chunk).await.unwrap();
+                assert_eq!(n as usize, chunk.len());
+            }
+            multipart_complete(&table, fd).await.unwrap();
+            let rs = json::parse(&String::from_utf8(read_all(&table, fd).await).unwrap()).unwrap();
+            assert_eq!(rs["parts"], 3);
+            close(&table, fd).await.unwrap();
+            assert_eq!(state.lock().unwrap().objects["a.bin"], data);
+
+            // the whole object is streamed with the range requests.
+            let fd = bucket_command(&table, 3, &params(&endpoint, r#""path": "a.bin""#))
+                .await
+                .unwrap();
+            assert_eq!(read_all(&table, fd).await, data);
+            close(&table, fd).await.unwrap();
+
+            let extra = r#""path": "a.bin", "range": {"start": 100, "end": 1048676}"#;
+            let fd = bucket_command(&table, 3, &params(&endpoint, extra))
+                .await
+                .unwrap();
+            assert_eq!(read_all(&table, fd).await, &data[100..=1048676]);
+            close(&table, fd).await.unwrap();
+
+            let extra = r#""path": "a.bin", "range": {"start": 10, "end": 10}"#;
+            let fd = bucket_command(&table, 3, &params(&endpoint, extra))
+                .await
+                .unwrap();
+            assert_eq!(read_all(&table, fd).await, &data[10..=10]);
+            close(&table, fd).await.unwrap();
+
+            let extra = r#""path": "missing.bin""#;
+            assert!(matches!(
+                bucket_command(&table, 3, &params(&endpoint, extra)).await,
+                Err(S3ErrorKind::RequestError)
+            ));
+        });
+    }
+
+    #[test]
+    fn test_s3_head_copy_and_abort() {
+        let rt = Builder::new_current_thread().enable_all().build().unwrap();
+        rt.block_on(async move {
+            let (endpoint, state) = mock_s3().await;
+            let table = Table::new();
+            bucket_put_object(&params(&endpoint, r#""path": "a.txt""#), b"hello world")
+                .await
+                .unwrap();
+
+            let fd = bucket_command(&table, 5, &params(&endpoint, r#""path": "a.txt""#))
+                .await
+                .unwrap();
+            let head =
+                json::parse(&String::from_utf8(read_all(&table, fd).await).unwrap()).unwrap();
+            assert_eq!(head["content_length"], 11);
+            assert_eq!(head["content_type"], "application/octet-stream");
+            close(&table, fd).await.unwrap();
+
+            let extra = r#""path": "b.txt", "copy_source": "a.txt""#;
+            let fd = bucket_command(&table, 6, &params(&endpoint, extra))
+                .await
+                .unwrap();
+            close(&table, fd).await.unwrap();
+            assert_eq!(state.lock().unwrap().objects["b.txt"], b"hello world");
+
+            // the unfinished upload is aborted when the handle is closed.
+            let fd = multipart_open(&table, &params(&endpoint, r#""path": "c.bin""#))
+                .await
+                .unwrap();
+            multipart_write(&table, fd, b"part").await.unwrap();
+            close(&table, fd).await.unwrap();
+            assert!(state.lock().unwrap().aborted);
+            assert!(!state.lock().unwrap().objects.contains_key("c.bin"));
+
+            let extra = r#""path": "c.bin", "part_size": 1024"#;
+            assert!(matches!(
+                multipart_open(&table, &params(&endpoint, extra)).await,
+                Err(S3ErrorKind::InvalidParameter)
+            ));
+            let extra = format!(
+                r#""path": "c.bin", "part_size": {}"#,
+                multipart::MAX_PART_SIZE + 1
+            );
+            assert!(matches!(
+                multipart_open(&table, &params(&endpoint, &extra)).await,
+                Err(S3ErrorKind::InvalidParameter)
+            ));
+        });
+    }
+}
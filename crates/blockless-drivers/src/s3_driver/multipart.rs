@@ -0,0 +1,140 @@
+use log::error;
+use s3::{serde_types::Part, Bucket};
+
+use super::bucket;
+use crate::S3ErrorKind;
+
+/// the minimum size of the part, except the last part.
+pub(crate) const MIN_PART_SIZE: usize = 5 * 1024 * 1024;
+
+/// the maximum size of the part, the part is buffered in the host memory.
+pub(crate) const MAX_PART_SIZE: usize = 64 * 1024 * 1024;
+
+/// The multipart upload driven by the guest writes, the part is uploaded when the buffer
+/// reaches the part size, so the guest never needs the whole object in the memory.
+pub(crate) struct MultipartUpload {
+    bucket: Box<Bucket>,
+    path: String,
+    upload_id: String,
+    content_type: String,
+    part_size: usize,
+    parts: Vec<Part>,
+    buffer: Vec<u8>,
+    // the upload can't continue after a part failed.
+    failed: bool,
+}
+
+impl MultipartUpload {
+    /// initiate the multipart upload, the parameters can have the content type and the part size,
+    /// e.g. `{"path": "a.bin", "content_type": "application/octet-stream", "part_size": 8388608}`.
+    pub(crate) async fn open(cfg: &str) -> Result<Self, S3ErrorKind> {
+        let json = json::parse(cfg).map_err(|_| S3ErrorKind::InvalidParameter)?;
+        let path = match json["path"].as_str() {
+            Some(s) => String::from(s),
+            None => return Err(S3ErrorKind::InvalidParameter),
+        };
+        let content_type = json["content_type"]
+            .as_str()
+            .unwrap_or("application/octet-stream")
+            .to_string();
+        let part_size = json["part_size"].as_usize().unwrap_or(MIN_PART_SIZE);
+        if !(MIN_PART_SIZE..=MAX_PART_SIZE).contains(&part_size) {
+            return Err(S3ErrorKind::InvalidParameter);
+        }
+        let bucket = bucket::new_bucket(&json)?;
+        let resp = bucket
+            .initiate_multipart_upload(&path, &content_type)
+            .await
+            .map_err(|e| {
+                error!("initiate multipart upload error: {}", e);
+                S3ErrorKind::RequestError
+            })?;
+        Ok(MultipartUpload {
+            bucket,
+            path,
+            upload_id: resp.upload_id,
+            content_type,
+            part_size,
+            parts: Vec::new(),
+            buffer: Vec::new(),
+            failed: false,
+        })
+    }
+
+    async fn put_part(&mut self, chunk: Vec<u8>) -> Result<(), S3ErrorKind> {
+        let part_number = self.parts.len() as u32 + 1;
+        let part = self
+            .bucket
+            .put_multipart_chunk(
+                chunk,
+                &self.path,
+                part_number,
+                &self.upload_id,
+                &self.content_type,
+            )
+            .await
+            .map_err(|e| {
+                error!("upload part {} error: {}", part_number, e);
+                self.failed = true;
+                S3ErrorKind::RequestError
+            })?;
+        self.parts.push(part);
+        Ok(())
+    }
+
+    pub(crate) async fn write(&mut self, buf: &[u8]) -> Result<u32, S3ErrorKind> {
+        if self.failed {
+            return Err(S3ErrorKind::RuntimeError);
+        }
+        self.buffer.extend_from_slice(buf);
+        while self.buffer.len() >= self.part_size {
+            let rest = self.buffer.split_off(self.part_size);
+            let chunk = std::mem::replace(&mut self.buffer, rest);
+            self.put_part(chunk).await?;
+        }
+        Ok(buf.len() as u32)
+    }
+
+    /// upload the remaining bytes as the last part and complete the upload,
+    /// the result is the json, e.g. `{"code": 200, "parts": 3}`.
+    pub(crate) async fn complete(&mut self) -> Result<String, S3ErrorKind> {
+        if self.failed {
+            return Err(S3ErrorKind::RuntimeError);
+        }
+        // the empty object is uploaded as one empty part.
+        if !self.buffer.is_empty() || self.parts.is_empty() {
+            let chunk = std::mem::take(&mut self.buffer);
+            self.put_part(chunk).await?;
+        }
+        let parts = std::mem::take(&mut self.parts);
+        let count = parts.len();
+        let resp = self
+            .bucket
+            .complete_multipart_upload(&self.path, &self.upload_id, parts)
+            .await
+            .map_err(|e| {
+                error!("complete multipart upload error: {}", e);
+                self.failed = true;
+                S3ErrorKind::RequestError
+            })?;
+        if resp.status_code() != 200 {
+            self.failed = true;
+            return Err(S3ErrorKind::RequestError);
+        }
+        let mut rs = json::JsonValue::new_object();
+        rs["code"] = resp.status_code().into();
+        rs["parts"] = count.into();
+        Ok(json::stringify(rs))
+    }
+
+    /// abort the upload, the uploaded parts are released by the server.
+    pub(crate) async fn abort(&self) -> Result<(), S3ErrorKind> {
+        self.bucket
+            .abort_upload(&self.path, &self.upload_id)
+            .await
+            .map_err(|e| {
+                error!("abort multipart upload error: {}", e);
+                S3ErrorKind::RequestError
+            })
+    }
+}
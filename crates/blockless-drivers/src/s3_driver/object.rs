@@ -0,0 +1,137 @@
+use log::error;
+use s3::{error::S3Error, Bucket};
+
+use super::{bucket, VecResult};
+use crate::{read_ext::ReadRemain, S3ErrorKind};
+
+/// the size of the range requested from the object each time.
+const CHUNK_SIZE: u64 = 1024 * 1024;
+
+/// The object stream, the object is read by the range requests when the guest reads,
+/// so the whole object is never buffered in the memory.
+pub(crate) struct ObjectStream {
+    bucket: Box<Bucket>,
+    path: String,
+    // the offset of the next range request.
+    offset: u64,
+    // the last byte of the range, inclusive.
+    end: Option<u64>,
+    done: bool,
+    buffer: VecResult,
+}
+
+/// get the total size from the `Content-Range` header, e.g. `bytes 0-99/1234`.
+fn content_range_total(value: &str) -> Option<u64> {
+    value
+        .rsplit_once('/')
+        .and_then(|(_, total)| total.parse().ok())
+}
+
+impl ObjectStream {
+    /// open the object stream, the parameters can have the range,
+    /// e.g. `{"path": "a.txt", "range": {"start": 0, "end": 99}}`, the end is inclusive.
+    pub(crate) async fn open(cfg: &str) -> Result<Self, S3ErrorKind> {
+        let json = json::parse(cfg).map_err(|_| S3ErrorKind::InvalidParameter)?;
+        let path = match json["path"].as_str() {
+            Some(s) => String::from(s),
+            None => return Err(S3ErrorKind::InvalidParameter),
+        };
+        let range = &json["range"];
+        let start = if range.is_null() {
+            0
+        } else {
+            range["start"]
+                .as_u64()
+                .ok_or(S3ErrorKind::InvalidParameter)?
+        };
+        let end = range["end"].as_u64();
+        if end.map_or(false, |end| end < start) {
+            return Err(S3ErrorKind::InvalidParameter);
+        }
+        let bucket = bucket::new_bucket(&json)?;
+        let mut stream = ObjectStream {
+            bucket,
+            path,
+            offset: start,
+            end,
+            done: false,
+            buffer: VecResult::new(Vec::new()),
+        };
+        // the first range is requested when opened, so the missing object is reported early.
+        stream.fetch().await?;
+        Ok(stream)
+    }
+
+    async fn fetch(&mut self) -> Result<(), S3ErrorKind> {
+        let mut last = self.offset.saturating_add(CHUNK_SIZE - 1);
+        if let Some(end) = self.end {
+            last = last.min(end);
+        }
+        let requested = (last - self.offset + 1) as usize;
+        // rust-s3 requires the end of the range is greater than the start,
+        // one more byte is requested and dropped.
+        let req_end = if last == self.offset {
+            last.saturating_add(1)
+        } else {
+            last
+        };
+        let resp = match self
+            .bucket
+            .get_object_range(&self.path, self.offset, Some(req_end))
+            .await
+        {
+            Ok(resp) => resp,
+            // the offset is beyond the end of the object.
+            Err(S3Error::HttpFailWithBody(416, _)) => {
+                self.done = true;
+                self.buffer = VecResult::new(Vec::new());
+                return Ok(());
+            }
+            Err(e) => {
+                error!("get object range error: {}", e);
+                return Err(S3ErrorKind::RequestError);
+            }
+        };
+        let mut bytes = match resp.status_code() {
+            206 => resp.bytes().to_vec(),
+            // the server ignores the range, the whole object is returned.
+            200 => {
+                let bytes = resp.bytes();
+                let start = (self.offset as usize).min(bytes.len());
+                bytes[start..].to_vec()
+            }
+            416 => Vec::new(),
+            _ => return Err(S3ErrorKind::RequestError),
+        };
+        bytes.truncate(requested);
+        let total = resp
+            .headers()
+            .get("content-range")
+            .and_then(|v| content_range_total(v));
+        self.offset += bytes.len() as u64;
+        self.done = bytes.len() < requested
+            || self.end.map_or(false, |end| self.offset > end)
+            || total.map_or(false, |total| self.offset >= total);
+        self.buffer = VecResult::new(bytes);
+        Ok(())
+    }
+
+    pub(crate) async fn read(&mut self, buf: &mut [u8]) -> Result<u32, S3ErrorKind> {
+        if self.buffer.remain() == 0 && !self.done {
+            self.fetch().await?;
+        }
+        Ok(self.buffer.copy_remain(buf) as u32)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_content_range_total() {
+        assert_eq!(content_range_total("bytes 0-99/1234"), Some(1234));
+        assert_eq!(content_range_total("bytes 0-99/*"), None);
+        assert_eq!(content_range_total("bytes */1234"), Some(1234));
+    }
+}
@@ -1,14 +1,183 @@
-use crate::{Driver, ErrorKind};
+pub mod tls;
+
+use crate::{BlocklessSocketErrorKind, Driver, ErrorKind};
 use blockless_multiaddr as multiaddr;
+use json::JsonValue;
 use log::error;
 use std::future::Future;
+use std::io;
+use std::net::IpAddr;
+#[cfg(unix)]
+use std::os::fd::AsRawFd;
 use std::pin::Pin;
-use tokio::net::TcpStream;
+use std::time::Duration;
+use tls::TlsOptions;
+use tokio::io::Interest;
+use tokio::net::{TcpListener, TcpStream};
 use wasi_common::sync::net::Socket;
+use wasi_common::tokio::socket_file;
 use wasi_common::WasiFile;
 
+/// the default timeout of the tcp connection.
+pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
 pub struct TcpDriver {}
 
+/// connect to the address, the connection is failed with `Timeout` after the timeout.
+pub async fn connect(addr: &str, timeout: Duration) -> Result<TcpStream, BlocklessSocketErrorKind> {
+    match tokio::time::timeout(timeout, TcpStream::connect(addr)).await {
+        Ok(Ok(s)) => Ok(s),
+        Ok(Err(e)) => {
+            error!("error connect {}: {}", addr, e);
+            Err(e.into())
+        }
+        Err(_) => {
+            error!("error connect {}: timeout", addr);
+            Err(BlocklessSocketErrorKind::Timeout)
+        }
+    }
+}
+
+/// the file of the tcp stream, it's polled by the tokio scheduler and the blocking reads
+/// and writes don't stall the other tasks of the runtime.
+pub fn stream_file(stream: TcpStream) -> Result<Box<dyn WasiFile>, BlocklessSocketErrorKind> {
+    let stream = stream.into_std()?;
+    // the guest sets the non-blocking mode by `fd_fdstat_set_flags`.
+    stream.set_nonblocking(false)?;
+    let stream = cap_std::net::TcpStream::from_std(stream);
+    Ok(socket_file(Socket::from(stream)))
+}
+
+/// bind the tcp listener, it's polled by the tokio scheduler like the stream.
+pub async fn bind(addr: &str) -> Result<Box<dyn WasiFile>, BlocklessSocketErrorKind> {
+    let listener = TcpListener::bind(addr).await.map_err(|e| {
+        error!("error bind {}: {}", addr, e);
+        BlocklessSocketErrorKind::from(e)
+    })?;
+    let listener = listener.into_std()?;
+    listener.set_nonblocking(false)?;
+    let listener = cap_std::net::TcpListener::from_std(listener);
+    Ok(socket_file(Socket::from(listener)))
+}
+
+/// The socket registered in tokio once when it's created, it's polled for the readiness
+/// of the interest by the concurrent calls.
+#[cfg(unix)]
+pub(crate) struct PollSocket<T: AsRawFd>(tokio::io::unix::AsyncFd<T>);
+
+#[cfg(unix)]
+impl<T: AsRawFd> PollSocket<T> {
+    pub(crate) fn new(socket: T) -> io::Result<Self> {
+        Ok(Self(tokio::io::unix::AsyncFd::new(socket)?))
+    }
+
+    pub(crate) fn get_ref(&self) -> &T {
+        self.0.get_ref()
+    }
+
+    /// wait until the socket is ready for the interest, the readiness is cleared, so the
+    /// io after it waits for the next readiness when it would block.
+    pub(crate) async fn ready(&self, interest: Interest) -> io::Result<()> {
+        let mut guard = if interest.is_readable() {
+            self.0.readable().await?
+        } else {
+            self.0.writable().await?
+        };
+        guard.clear_ready();
+        Ok(())
+    }
+}
+
+/// The socket is tried again after the other tasks run.
+#[cfg(not(unix))]
+pub(crate) struct PollSocket<T>(T);
+
+#[cfg(not(unix))]
+impl<T> PollSocket<T> {
+    pub(crate) fn new(socket: T) -> io::Result<Self> {
+        Ok(Self(socket))
+    }
+
+    pub(crate) fn get_ref(&self) -> &T {
+        &self.0
+    }
+
+    pub(crate) async fn ready(&self, _interest: Interest) -> io::Result<()> {
+        tokio::task::yield_now().await;
+        Ok(())
+    }
+}
+
+/// set the CA file of the configure into the tls options, the CA file of the guest is removed,
+/// the options without tls are not changed.
+pub fn with_ca_file(opts: &str, ca_file: Option<&str>) -> String {
+    let mut json = match json::parse(opts) {
+        Ok(json) => json,
+        Err(_) => return opts.to_string(),
+    };
+    if json["tls"] == JsonValue::Boolean(true) {
+        json["tls"] = JsonValue::new_object();
+    }
+    if !json["tls"].is_object() {
+        return opts.to_string();
+    }
+    json["tls"].remove("ca_file");
+    if let Some(ca_file) = ca_file {
+        json["tls"]["ca_file"] = ca_file.into();
+    }
+    json.dump()
+}
+
+/// resolve all the addresses of the host name, the duplicated addresses are removed.
+pub async fn resolve(host: &str) -> Result<Vec<IpAddr>, BlocklessSocketErrorKind> {
+    if host.is_empty() {
+        return Err(BlocklessSocketErrorKind::ParameterError);
+    }
+    let addrs = tokio::net::lookup_host((host, 0)).await.map_err(|e| {
+        error!("error resolve {}: {}", host, e);
+        BlocklessSocketErrorKind::NameNotResolved
+    })?;
+    let mut ips: Vec<IpAddr> = Vec::new();
+    for addr in addrs {
+        if !ips.contains(&addr.ip()) {
+            ips.push(addr.ip());
+        }
+    }
+    Ok(ips)
+}
+
+/// the `connectTimeout` in seconds of the open options, e.g. `{"connectTimeout": 10}`.
+fn connect_timeout(opts: &str) -> Result<Duration, ErrorKind> {
+    if opts.is_empty() {
+        return Ok(CONNECT_TIMEOUT);
+    }
+    let json = json::parse(opts).map_err(|_| ErrorKind::DriverBadParams)?;
+    match json["connectTimeout"] {
+        JsonValue::Null => Ok(CONNECT_TIMEOUT),
+        ref secs => secs
+            .as_u64()
+            .map(Duration::from_secs)
+            .ok_or(ErrorKind::DriverBadParams),
+    }
+}
+
+/// the tls options of the open options, e.g. `{"tls": {"server_name": "example.com"}}`.
+fn tls_options(opts: &str, addr: &str) -> Result<Option<TlsOptions>, ErrorKind> {
+    if opts.is_empty() {
+        return Ok(None);
+    }
+    let json = json::parse(opts).map_err(|_| ErrorKind::DriverBadParams)?;
+    let tls = match json["tls"] {
+        JsonValue::Null | JsonValue::Boolean(false) => return Ok(None),
+        JsonValue::Boolean(true) => JsonValue::Null,
+        ref tls if tls.is_object() => tls.clone(),
+        _ => return Err(ErrorKind::DriverBadParams),
+    };
+    let mut tls_opts = TlsOptions::from_json(&tls, addr).map_err(|_| ErrorKind::DriverBadParams)?;
+    tls_opts.ca_file = tls["ca_file"].as_str().map(String::from);
+    Ok(Some(tls_opts))
+}
+
 impl Driver for TcpDriver {
     fn name(&self) -> &str {
         "tcp"
@@ -21,7 +190,7 @@ impl Driver for TcpDriver {
     ) -> Pin<Box<dyn Future<Output = Result<Box<dyn WasiFile>, ErrorKind>> + Send>> {
         let socket: String = socket.into();
         //this open options.
-        let _opts: String = opts.into();
+        let opts: String = opts.into();
         return Box::pin(async move {
             let ma = multiaddr::parse(socket.as_bytes()).map_err(|e| {
                 error!("error open:{:?}", e);
@@ -32,20 +201,131 @@ impl Driver for TcpDriver {
                 return Err(ErrorKind::DriverBadOpen);
             }
             let socket = ma.paths_ref()[1].value_to_str();
-            let stream = match TcpStream::connect(socket).await {
-                Ok(s) => s,
-                Err(e) => {
-                    error!("error connect in driver {}: {}", socket, e);
-                    return Err(ErrorKind::ConnectError);
-                }
-            };
-            let stream = cap_std::net::TcpStream::from_std(stream.into_std().unwrap());
-            let socket: Socket = Socket::from(stream);
-            let stream: Box<dyn WasiFile> = Box::<dyn WasiFile>::from(socket);
-            Ok(stream)
+            let timeout = connect_timeout(&opts)?;
+            if let Some(mut tls_opts) = tls_options(&opts, socket)? {
+                tls_opts.connect_timeout = Some(timeout);
+                let stream = tls::tls_connect(socket, &tls_opts)
+                    .await
+                    .map_err(|e| match e {
+                        BlocklessSocketErrorKind::ParameterError => ErrorKind::DriverBadParams,
+                        _ => ErrorKind::ConnectError,
+                    })?;
+                let stream: Box<dyn WasiFile> = Box::new(stream);
+                return Ok(stream);
+            }
+            connect(socket, timeout)
+                .await
+                .and_then(stream_file)
+                .map_err(|_| ErrorKind::ConnectError)
         });
     }
 }
 
 unsafe impl std::marker::Send for TcpDriver {}
 unsafe impl std::marker::Sync for TcpDriver {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Write;
+    use tokio::runtime::Builder;
+
+    #[test]
+    fn test_with_ca_file() {
+        assert_eq!(with_ca_file("", Some("/ca.pem")), "");
+        assert_eq!(with_ca_file(r#"{"a": 1}"#, Some("/ca.pem")), r#"{"a": 1}"#);
+        assert_eq!(
+            with_ca_file(r#"{"tls": true}"#, Some("/ca.pem")),
+            r#"{"tls":{"ca_file":"/ca.pem"}}"#
+        );
+        // the CA file of the guest is never used.
+        assert_eq!(
+            with_ca_file(r#"{"tls": {"ca_file": "/etc/passwd"}}"#, None),
+            r#"{"tls":{}}"#
+        );
+    }
+
+    #[test]
+    fn test_resolve() {
+        let rt = Builder::new_current_thread().enable_all().build().unwrap();
+        rt.block_on(async move {
+            let ips = resolve("localhost").await.unwrap();
+            assert!(ips.iter().all(|ip| ip.is_loopback()));
+            assert!(!ips.is_empty());
+            let ips = resolve("127.0.0.1").await.unwrap();
+            assert_eq!(ips, vec![IpAddr::from([127, 0, 0, 1])]);
+            assert!(matches!(
+                resolve("").await,
+                Err(BlocklessSocketErrorKind::ParameterError)
+            ));
+            assert!(matches!(
+                resolve("name.invalid").await,
+                Err(BlocklessSocketErrorKind::NameNotResolved)
+            ));
+        });
+    }
+
+    #[test]
+    fn test_tcp_driver_tls() {
+        let rt = Builder::new_current_thread().enable_all().build().unwrap();
+        rt.block_on(async move {
+            let addr = tls::test::echo_server();
+            let opts = r#"{"tls": {"server_name": "localhost"}}"#;
+            let opts = with_ca_file(opts, Some(tls::test::CERT_FILE));
+            let driver = TcpDriver {};
+            let stream = driver.open(&format!("tcp://{addr}"), &opts).await.unwrap();
+            stream
+                .write_vectored(&[std::io::IoSlice::new(b"ping")])
+                .await
+                .unwrap();
+            let mut buf = [0u8; 4];
+            let n = stream
+                .read_vectored(&mut [std::io::IoSliceMut::new(&mut buf)])
+                .await
+                .unwrap();
+            assert_eq!(&buf[..n as usize], b"ping");
+            assert!(matches!(
+                driver.open(&format!("tcp://{addr}"), r#"{"tls": 1}"#).await,
+                Err(ErrorKind::DriverBadParams)
+            ));
+        });
+    }
+
+    #[test]
+    fn test_tcp_driver_stream() {
+        let rt = Builder::new_multi_thread().enable_all().build().unwrap();
+        rt.block_on(async move {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            let addr = listener.local_addr().unwrap();
+            let driver = TcpDriver {};
+            let url = format!("tcp://{addr}");
+            let stream = driver.open(&url, r#"{"connectTimeout": 5}"#).await.unwrap();
+            let (mut peer, _) = listener.accept().unwrap();
+            // the guest waits for the stream by `poll_oneoff`.
+            let readable = tokio::spawn(async move {
+                stream.readable().await.unwrap();
+                stream
+            });
+            // the other tasks are not blocked by the waiting.
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            assert!(!readable.is_finished());
+            peer.write_all(b"ping").unwrap();
+            let stream = readable.await.unwrap();
+            let mut buf = [0u8; 4];
+            let n = stream
+                .read_vectored(&mut [io::IoSliceMut::new(&mut buf)])
+                .await
+                .unwrap();
+            assert_eq!(&buf[..n as usize], b"ping");
+            assert!(matches!(
+                driver.open(&url, r#"{"connectTimeout": "5"}"#).await,
+                Err(ErrorKind::DriverBadParams)
+            ));
+            drop(listener);
+            assert!(matches!(
+                connect(&addr.to_string(), CONNECT_TIMEOUT).await,
+                Err(BlocklessSocketErrorKind::ConnectRefused)
+            ));
+        });
+    }
+}
@@ -0,0 +1,426 @@
+use std::any::Any;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Write};
+use std::net::TcpStream;
+#[cfg(unix)]
+use std::os::fd::AsFd;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use json::JsonValue;
+use log::error;
+use rustls::pki_types::ServerName;
+use rustls::{ClientConfig, ClientConnection, RootCertStore, StreamOwned};
+use tokio::io::Interest;
+use wasi_common::file::{FileType, RiFlags, RoFlags, SdFlags, SiFlags};
+use wasi_common::{Error, ErrorExt, WasiFile};
+
+use crate::BlocklessSocketErrorKind;
+
+/// The options of the tls connection.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TlsOptions {
+    /// the server name of the SNI and the certificate verification.
+    pub server_name: String,
+    /// the ALPN protocols, e.g. `h2`.
+    pub alpn: Vec<String>,
+    /// the PEM file of the CA certificates trusted besides the webpki roots.
+    pub ca_file: Option<String>,
+    /// the timeout of the tcp connection, the default is `CONNECT_TIMEOUT`.
+    pub connect_timeout: Option<Duration>,
+}
+
+impl TlsOptions {
+    /// parse the options json, e.g. `{"server_name": "example.com", "alpn": ["h2"]}`,
+    /// the server name is the host of the address if not set, the `connectTimeout` is in seconds.
+    pub fn from_json(json: &JsonValue, addr: &str) -> Result<Self, BlocklessSocketErrorKind> {
+        let server_name = match json["server_name"].as_str() {
+            Some(name) => name.to_string(),
+            None => addr
+                .rsplit_once(':')
+                .map(|(host, _)| host.trim_start_matches('[').trim_end_matches(']'))
+                .ok_or(BlocklessSocketErrorKind::ParameterError)?
+                .to_string(),
+        };
+        let alpn = match json["alpn"] {
+            JsonValue::Null => Vec::new(),
+            JsonValue::Array(ref protocols) => protocols
+                .iter()
+                .map(|p| p.as_str().map(String::from))
+                .collect::<Option<Vec<_>>>()
+                .ok_or(BlocklessSocketErrorKind::ParameterError)?,
+            _ => return Err(BlocklessSocketErrorKind::ParameterError),
+        };
+        let connect_timeout = match json["connectTimeout"] {
+            JsonValue::Null => None,
+            ref secs => Some(Duration::from_secs(
+                secs.as_u64()
+                    .ok_or(BlocklessSocketErrorKind::ParameterError)?,
+            )),
+        };
+        Ok(Self {
+            server_name,
+            alpn,
+            ca_file: None,
+            connect_timeout,
+        })
+    }
+
+    fn client_config(&self) -> Result<ClientConfig, BlocklessSocketErrorKind> {
+        let mut roots = RootCertStore::empty();
+        roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+        if let Some(ref ca_file) = self.ca_file {
+            let file = File::open(ca_file).map_err(|e| {
+                error!("open the ca file {} error: {}", ca_file, e);
+                BlocklessSocketErrorKind::TlsError
+            })?;
+            for cert in rustls_pemfile::certs(&mut BufReader::new(file)) {
+                let added = cert
+                    .map_err(|e| e.to_string())
+                    .and_then(|cert| roots.add(cert).map_err(|e| e.to_string()));
+                if let Err(e) = added {
+                    error!("load the ca file {} error: {}", ca_file, e);
+                    return Err(BlocklessSocketErrorKind::TlsError);
+                }
+            }
+        }
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let mut config = ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .map_err(|_| BlocklessSocketErrorKind::TlsError)?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        config.alpn_protocols = self.alpn.iter().map(|p| p.as_bytes().to_vec()).collect();
+        Ok(config)
+    }
+}
+
+/// connect to the address and finish the tls handshake.
+pub async fn tls_connect(
+    addr: &str,
+    opts: &TlsOptions,
+) -> Result<TlsStream, BlocklessSocketErrorKind> {
+    let config = Arc::new(opts.client_config()?);
+    let server_name = ServerName::try_from(opts.server_name.clone())
+        .map_err(|_| BlocklessSocketErrorKind::ParameterError)?;
+    let timeout = opts.connect_timeout.unwrap_or(super::CONNECT_TIMEOUT);
+    let tcp = super::connect(addr, timeout).await?.into_std()?;
+    // the handshake is blocking in the blocking thread, the stream is non-blocking after.
+    // the reads and writes of the handshake time out like the connection.
+    tcp.set_nonblocking(false)?;
+    tcp.set_read_timeout(Some(timeout))?;
+    tcp.set_write_timeout(Some(timeout))?;
+    let handshake = move || {
+        let mut conn = ClientConnection::new(config, server_name)
+            .map_err(|_| BlocklessSocketErrorKind::TlsError)?;
+        let mut tcp = tcp;
+        while conn.is_handshaking() {
+            conn.complete_io(&mut tcp).map_err(|e| {
+                error!("tls handshake error: {}", e);
+                match e.kind() {
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => {
+                        BlocklessSocketErrorKind::Timeout
+                    }
+                    _ => BlocklessSocketErrorKind::TlsError,
+                }
+            })?;
+        }
+        tcp.set_read_timeout(None)?;
+        tcp.set_write_timeout(None)?;
+        tcp.set_nonblocking(true)?;
+        Ok(StreamOwned::new(conn, tcp))
+    };
+    let stream = tokio::task::spawn_blocking(handshake)
+        .await
+        .map_err(|_| BlocklessSocketErrorKind::TlsError)??;
+    TlsStream::new(stream)
+}
+
+/// The tls client stream, the guest reads and writes the decrypted bytes.
+pub struct TlsStream {
+    stream: Mutex<StreamOwned<ClientConnection, TcpStream>>,
+    // the socket for polling, it's registered in tokio once.
+    socket: super::PollSocket<TcpStream>,
+}
+
+impl TlsStream {
+    fn new(
+        stream: StreamOwned<ClientConnection, TcpStream>,
+    ) -> Result<Self, BlocklessSocketErrorKind> {
+        let socket = stream
+            .sock
+            .try_clone()
+            .and_then(super::PollSocket::new)
+            .map_err(|_| BlocklessSocketErrorKind::ConnectionReset)?;
+        Ok(Self {
+            stream: Mutex::new(stream),
+            socket,
+        })
+    }
+
+    /// the ALPN protocol negotiated with the server.
+    pub fn alpn_protocol(&self) -> Option<Vec<u8>> {
+        let stream = self.stream.lock().unwrap();
+        stream.conn.alpn_protocol().map(|p| p.to_vec())
+    }
+
+    /// the decrypted bytes are buffered and ready to read.
+    fn has_plaintext(&self) -> bool {
+        let mut stream = self.stream.lock().unwrap();
+        match stream.conn.process_new_packets() {
+            Ok(state) => state.plaintext_bytes_to_read() > 0,
+            // the error is returned by the next read.
+            Err(_) => true,
+        }
+    }
+
+    async fn read(&self, bufs: &mut [io::IoSliceMut<'_>]) -> io::Result<usize> {
+        loop {
+            let rs = self.stream.lock().unwrap().read_vectored(bufs);
+            match rs {
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    self.socket.ready(Interest::READABLE).await?
+                }
+                // the peer closed the tcp connection without the close notify.
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(0),
+                rs => return rs,
+            }
+        }
+    }
+
+    async fn flush(&self) -> io::Result<()> {
+        loop {
+            let rs = self.stream.lock().unwrap().flush();
+            match rs {
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    self.socket.ready(Interest::WRITABLE).await?
+                }
+                rs => return rs,
+            }
+        }
+    }
+
+    async fn write(&self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
+        let n = loop {
+            let rs = self.stream.lock().unwrap().write_vectored(bufs);
+            match rs {
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    self.socket.ready(Interest::WRITABLE).await?
+                }
+                rs => break rs?,
+            }
+        };
+        // the bytes written are buffered in the connection, the retry only flushes them.
+        self.flush().await?;
+        Ok(n)
+    }
+}
+
+#[async_trait::async_trait]
+impl WasiFile for TlsStream {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    #[cfg(unix)]
+    fn pollable(&self) -> Option<std::os::fd::BorrowedFd> {
+        Some(self.socket.get_ref().as_fd())
+    }
+
+    async fn get_filetype(&self) -> Result<FileType, Error> {
+        Ok(FileType::SocketStream)
+    }
+
+    async fn read_vectored<'a>(&self, bufs: &mut [io::IoSliceMut<'a>]) -> Result<u64, Error> {
+        Ok(self.read(bufs).await? as u64)
+    }
+
+    async fn write_vectored<'a>(&self, bufs: &[io::IoSlice<'a>]) -> Result<u64, Error> {
+        Ok(self.write(bufs).await? as u64)
+    }
+
+    async fn readable(&self) -> Result<(), Error> {
+        if self.has_plaintext() {
+            return Ok(());
+        }
+        Ok(self.socket.ready(Interest::READABLE).await?)
+    }
+
+    async fn writable(&self) -> Result<(), Error> {
+        Ok(self.socket.ready(Interest::WRITABLE).await?)
+    }
+
+    async fn sock_recv<'a>(
+        &self,
+        ri_data: &mut [io::IoSliceMut<'a>],
+        ri_flags: RiFlags,
+    ) -> Result<(u64, RoFlags), Error> {
+        if ri_flags != RiFlags::empty() {
+            return Err(Error::not_supported());
+        }
+        Ok((self.read(ri_data).await? as u64, RoFlags::empty()))
+    }
+
+    async fn sock_send<'a>(
+        &self,
+        si_data: &[io::IoSlice<'a>],
+        si_flags: SiFlags,
+    ) -> Result<u64, Error> {
+        if si_flags != SiFlags::empty() {
+            return Err(Error::not_supported());
+        }
+        Ok(self.write(si_data).await? as u64)
+    }
+
+    async fn sock_shutdown(&self, how: SdFlags) -> Result<(), Error> {
+        let how = if how == SdFlags::RD | SdFlags::WR {
+            std::net::Shutdown::Both
+        } else if how == SdFlags::RD {
+            std::net::Shutdown::Read
+        } else if how == SdFlags::WR {
+            std::net::Shutdown::Write
+        } else {
+            return Err(Error::invalid_argument());
+        };
+        if how != std::net::Shutdown::Read {
+            // send the close notify before the socket is shut down.
+            self.stream.lock().unwrap().conn.send_close_notify();
+            self.flush().await?;
+        }
+        self.socket.get_ref().shutdown(how)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use rustls::{ServerConfig, ServerConnection};
+    use std::net::TcpListener;
+    use tokio::runtime::Builder;
+
+    pub(crate) const CERT_FILE: &str =
+        concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/localhost.crt");
+    const KEY_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/localhost.key");
+
+    /// the tls echo server with the self-signed certificate, the ALPN protocol is `bls`.
+    pub(crate) fn echo_server() -> String {
+        let certs = rustls_pemfile::certs(&mut BufReader::new(File::open(CERT_FILE).unwrap()))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let key = rustls_pemfile::private_key(&mut BufReader::new(File::open(KEY_FILE).unwrap()))
+            .unwrap()
+            .unwrap();
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let mut config = ServerConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .unwrap();
+        config.alpn_protocols = vec![b"bls".to_vec()];
+        let config = Arc::new(config);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for tcp in listener.incoming() {
+                let config = config.clone();
+                std::thread::spawn(move || {
+                    let conn = ServerConnection::new(config).unwrap();
+                    let mut stream = StreamOwned::new(conn, tcp.unwrap());
+                    let mut buf = [0u8; 1024];
+                    loop {
+                        match stream.read(&mut buf) {
+                            Ok(0) | Err(_) => break,
+                            Ok(n) => {
+                                if stream.write_all(&buf[..n]).is_err() {
+                                    break;
+                                }
+                            }
+                        }
+                    }
+                });
+            }
+        });
+        addr.to_string()
+    }
+
+    #[test]
+    fn test_tls_options() {
+        let json = json::parse(r#"{"alpn": ["h2", "http/1.1"]}"#).unwrap();
+        let opts = TlsOptions::from_json(&json, "example.com:443").unwrap();
+        assert_eq!(opts.server_name, "example.com");
+        assert_eq!(opts.alpn, vec!["h2".to_string(), "http/1.1".to_string()]);
+        let json = json::parse(r#"{"server_name": "a.example.com"}"#).unwrap();
+        let opts = TlsOptions::from_json(&json, "[::1]:443").unwrap();
+        assert_eq!(opts.server_name, "a.example.com");
+        let opts = TlsOptions::from_json(&JsonValue::Null, "[::1]:443").unwrap();
+        assert_eq!(opts.server_name, "::1");
+        let json = json::parse(r#"{"alpn": "h2"}"#).unwrap();
+        assert!(TlsOptions::from_json(&json, "example.com:443").is_err());
+    }
+
+    #[test]
+    fn test_tls_connect() {
+        let rt = Builder::new_current_thread().enable_all().build().unwrap();
+        rt.block_on(async move {
+            let addr = echo_server();
+            let mut opts = TlsOptions {
+                server_name: "localhost".into(),
+                alpn: vec!["bls".into()],
+                ca_file: None,
+                connect_timeout: None,
+            };
+            // the self-signed certificate is not trusted without the ca file.
+            assert!(matches!(
+                tls_connect(&addr, &opts).await,
+                Err(BlocklessSocketErrorKind::TlsError)
+            ));
+            opts.ca_file = Some(CERT_FILE.into());
+            let stream = tls_connect(&addr, &opts).await.unwrap();
+            assert_eq!(stream.alpn_protocol().as_deref(), Some(&b"bls"[..]));
+            let n = stream
+                .write_vectored(&[io::IoSlice::new(b"hello tls")])
+                .await
+                .unwrap();
+            assert_eq!(n, 9);
+            let mut buf = [0u8; 16];
+            let n = stream
+                .read_vectored(&mut [io::IoSliceMut::new(&mut buf)])
+                .await
+                .unwrap();
+            assert_eq!(&buf[..n as usize], b"hello tls");
+            stream.sock_shutdown(SdFlags::WR).await.unwrap();
+
+            opts.server_name = "example.com".into();
+            assert!(matches!(
+                tls_connect(&addr, &opts).await,
+                Err(BlocklessSocketErrorKind::TlsError)
+            ));
+        });
+    }
+
+    #[test]
+    fn test_tls_handshake_timeout() {
+        let rt = Builder::new_multi_thread().enable_all().build().unwrap();
+        rt.block_on(async move {
+            // the server accepts the connection and never answers the handshake.
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let addr = listener.local_addr().unwrap().to_string();
+            std::thread::spawn(move || {
+                let _streams = listener.incoming().take(1).collect::<Vec<_>>();
+                std::thread::sleep(Duration::from_secs(5));
+            });
+            let opts = TlsOptions {
+                server_name: "localhost".into(),
+                alpn: Vec::new(),
+                ca_file: None,
+                connect_timeout: Some(Duration::from_secs(1)),
+            };
+            assert!(matches!(
+                tls_connect(&addr, &opts).await,
+                Err(BlocklessSocketErrorKind::Timeout)
+            ));
+        });
+    }
+}
@@ -0,0 +1,184 @@
+use std::any::Any;
+use std::io;
+use std::net::SocketAddr;
+#[cfg(unix)]
+use std::os::fd::AsFd;
+use std::time::Duration;
+
+use log::error;
+use wasi_common::file::{FileType, RiFlags, RoFlags, SiFlags};
+use wasi_common::{Error, ErrorExt, WasiFile};
+
+use crate::BlocklessSocketErrorKind;
+
+/// the max size of the udp datagram, the receive buffer is never larger.
+pub const MAX_DATAGRAM_SIZE: usize = 65535;
+
+/// The udp socket of the guest, the connected socket can be read and written as the file.
+pub struct UdpSocket(tokio::net::UdpSocket);
+
+impl UdpSocket {
+    pub async fn bind(addr: &str) -> Result<Self, BlocklessSocketErrorKind> {
+        match tokio::net::UdpSocket::bind(addr).await {
+            Ok(s) => Ok(Self(s)),
+            Err(e) => {
+                error!("error bind udp {}: {}", addr, e);
+                Err(e.into())
+            }
+        }
+    }
+
+    pub async fn connect(&self, addr: &str) -> Result<(), BlocklessSocketErrorKind> {
+        self.0.connect(addr).await.map_err(|e| {
+            error!("error connect udp {}: {}", addr, e);
+            e.into()
+        })
+    }
+
+    pub async fn send_to(&self, buf: &[u8], addr: &str) -> Result<usize, BlocklessSocketErrorKind> {
+        Ok(self.0.send_to(buf, addr).await?)
+    }
+
+    /// receive the datagram, the bytes of the datagram larger than the buffer are discarded.
+    pub async fn recv_from(
+        &self,
+        buf: &mut [u8],
+        timeout: Option<Duration>,
+    ) -> Result<(usize, SocketAddr), BlocklessSocketErrorKind> {
+        let rs = match timeout {
+            Some(t) => tokio::time::timeout(t, self.0.recv_from(buf))
+                .await
+                .map_err(|_| BlocklessSocketErrorKind::Timeout)?,
+            None => self.0.recv_from(buf).await,
+        };
+        Ok(rs?)
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, BlocklessSocketErrorKind> {
+        Ok(self.0.local_addr()?)
+    }
+}
+
+fn first_buf<'a, 'b>(bufs: &'a mut [io::IoSliceMut<'b>]) -> &'a mut [u8] {
+    bufs.iter_mut()
+        .find(|b| !b.is_empty())
+        .map_or(&mut [][..], |b| &mut **b)
+}
+
+#[async_trait::async_trait]
+impl WasiFile for UdpSocket {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    #[cfg(unix)]
+    fn pollable(&self) -> Option<std::os::fd::BorrowedFd> {
+        Some(self.0.as_fd())
+    }
+
+    async fn get_filetype(&self) -> Result<FileType, Error> {
+        Ok(FileType::SocketDgram)
+    }
+
+    async fn readable(&self) -> Result<(), Error> {
+        Ok(self.0.readable().await?)
+    }
+
+    async fn writable(&self) -> Result<(), Error> {
+        Ok(self.0.writable().await?)
+    }
+
+    async fn read_vectored<'a>(&self, bufs: &mut [io::IoSliceMut<'a>]) -> Result<u64, Error> {
+        let n = self.0.recv(first_buf(bufs)).await?;
+        Ok(n as u64)
+    }
+
+    async fn write_vectored<'a>(&self, bufs: &[io::IoSlice<'a>]) -> Result<u64, Error> {
+        // the slices are sent in one datagram.
+        let data: Vec<u8> = bufs.iter().flat_map(|b| b.iter().copied()).collect();
+        let n = self.0.send(&data).await?;
+        Ok(n as u64)
+    }
+
+    async fn sock_recv<'a>(
+        &self,
+        ri_data: &mut [io::IoSliceMut<'a>],
+        ri_flags: RiFlags,
+    ) -> Result<(u64, RoFlags), Error> {
+        let buf = first_buf(ri_data);
+        let n = if ri_flags == RiFlags::RECV_PEEK {
+            self.0.peek_from(buf).await?.0
+        } else if ri_flags.is_empty() {
+            self.0.recv(buf).await?
+        } else {
+            return Err(Error::not_supported());
+        };
+        Ok((n as u64, RoFlags::empty()))
+    }
+
+    async fn sock_send<'a>(
+        &self,
+        si_data: &[io::IoSlice<'a>],
+        si_flags: SiFlags,
+    ) -> Result<u64, Error> {
+        if si_flags != SiFlags::empty() {
+            return Err(Error::not_supported());
+        }
+        self.write_vectored(si_data).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokio::runtime::Builder;
+
+    #[test]
+    fn test_udp_socket() {
+        let rt = Builder::new_current_thread().enable_all().build().unwrap();
+        rt.block_on(async move {
+            let a = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+            let b = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+            let a_addr = a.local_addr().unwrap();
+            let b_addr = b.local_addr().unwrap().to_string();
+            assert!(matches!(
+                UdpSocket::bind(&b_addr).await,
+                Err(BlocklessSocketErrorKind::AddressInUse)
+            ));
+
+            assert_eq!(a.send_to(b"hello", &b_addr).await.unwrap(), 5);
+            let mut buf = [0u8; 16];
+            let timeout = Some(Duration::from_secs(5));
+            let (n, from) = b.recv_from(&mut buf, timeout).await.unwrap();
+            assert_eq!(&buf[..n], b"hello");
+            assert_eq!(from, a_addr);
+
+            let timeout = Some(Duration::from_millis(50));
+            assert!(matches!(
+                b.recv_from(&mut buf, timeout).await,
+                Err(BlocklessSocketErrorKind::Timeout)
+            ));
+
+            // the connected socket is read and written as the file.
+            a.connect(&b_addr).await.unwrap();
+            let n = a
+                .write_vectored(&[io::IoSlice::new(b"ab"), io::IoSlice::new(b"c")])
+                .await
+                .unwrap();
+            assert_eq!(n, 3);
+            let (n, _) = b.recv_from(&mut buf, None).await.unwrap();
+            assert_eq!(&buf[..n], b"abc");
+            b.send_to(b"pong", &a_addr.to_string()).await.unwrap();
+            a.readable().await.unwrap();
+            let n = a
+                .read_vectored(&mut [io::IoSliceMut::new(&mut buf)])
+                .await
+                .unwrap();
+            assert_eq!(&buf[..n as usize], b"pong");
+            assert!(matches!(
+                a.send_to(b"x", "not an address").await,
+                Err(BlocklessSocketErrorKind::ParameterError)
+            ));
+        });
+    }
+}
@@ -0,0 +1,161 @@
+#![allow(non_upper_case_globals)]
+use crate::car_driver;
+use crate::CarErrorKind;
+use log::error;
+use wasi_common::WasiCtx;
+use wiggle::{GuestMemory, GuestPtr};
+
+wiggle::from_witx!({
+    witx: ["$BLOCKLESS_DRIVERS_ROOT/witx/blockless_car.witx"],
+    errors: { car_error => CarErrorKind },
+    async: *,
+    wasmtime: false,
+});
+
+impl types::UserErrorConversion for WasiCtx {
+    fn car_error_from_car_error_kind(
+        &mut self,
+        e: self::CarErrorKind,
+    ) -> wiggle::anyhow::Result<types::CarError> {
+        e.try_into()
+            .map_err(|e| wiggle::anyhow::anyhow!(format!("{:?}", e)))
+    }
+}
+
+impl From<CarErrorKind> for types::CarError {
+    fn from(e: CarErrorKind) -> types::CarError {
+        use types::CarError;
+        match e {
+            CarErrorKind::InvalidHandle => CarError::InvalidHandle,
+            CarErrorKind::InvalidParameter => CarError::InvalidParameter,
+            CarErrorKind::InvalidCar => CarError::InvalidCar,
+            CarErrorKind::InvalidCid => CarError::InvalidCid,
+            CarErrorKind::InvalidNode => CarError::InvalidNode,
+            CarErrorKind::UnsupportedCid => CarError::UnsupportedCid,
+            CarErrorKind::HashMismatch => CarError::HashMismatch,
+            CarErrorKind::NotFound => CarError::NotFound,
+            CarErrorKind::NotFile => CarError::NotFile,
+            CarErrorKind::BufferTooSmall => CarError::BufferTooSmall,
+            CarErrorKind::PermissionDeny => CarError::PermissionDeny,
+            CarErrorKind::RuntimeError => CarError::RuntimeError,
+        }
+    }
+}
+
+impl wiggle::GuestErrorType for types::CarError {
+    fn success() -> Self {
+        Self::Success
+    }
+}
+
+fn guest_str<'a>(memory: &'a GuestMemory<'_>, s: GuestPtr<str>) -> Result<&'a str, CarErrorKind> {
+    memory
+        .as_str(s)
+        .map_err(|e| {
+            error!("guest string error: {}", e);
+            CarErrorKind::InvalidParameter
+        })?
+        .ok_or(CarErrorKind::InvalidParameter)
+}
+
+/// copy the string into the guest buffer, the string is not truncated.
+fn copy_to_guest(
+    memory: &mut GuestMemory<'_>,
+    s: &str,
+    buf: GuestPtr<u8>,
+    buf_len: u32,
+) -> Result<u32, CarErrorKind> {
+    let len = s.len() as u32;
+    if len > buf_len {
+        return Err(CarErrorKind::BufferTooSmall);
+    }
+    memory
+        .copy_from_slice(s.as_bytes(), buf.as_array(len))
+        .map_err(|_| CarErrorKind::RuntimeError)?;
+    Ok(len)
+}
+
+#[wiggle::async_trait]
+impl blockless_car::BlocklessCar for WasiCtx {
+    async fn car_open(
+        &mut self,
+        memory: &mut GuestMemory<'_>,
+        path: GuestPtr<str>,
+    ) -> Result<types::CarHandle, CarErrorKind> {
+        let path = guest_str(memory, path)?;
+        let root = self.config_fs_root_path_ref();
+        let fd = car_driver::open(self.resource_table(), root.as_deref(), path).await?;
+        Ok(types::CarHandle::from(fd))
+    }
+
+    async fn car_roots(
+        &mut self,
+        memory: &mut GuestMemory<'_>,
+        handle: types::CarHandle,
+        buf: GuestPtr<u8>,
+        buf_len: u32,
+    ) -> Result<u32, CarErrorKind> {
+        let roots = car_driver::roots(self.resource_table(), handle.into()).await?;
+        copy_to_guest(memory, &roots, buf, buf_len)
+    }
+
+    async fn car_resolve(
+        &mut self,
+        memory: &mut GuestMemory<'_>,
+        handle: types::CarHandle,
+        path: GuestPtr<str>,
+        buf: GuestPtr<u8>,
+        buf_len: u32,
+    ) -> Result<u32, CarErrorKind> {
+        let path = guest_str(memory, path)?;
+        let cid = car_driver::resolve(self.resource_table(), handle.into(), path).await?;
+        copy_to_guest(memory, &cid, buf, buf_len)
+    }
+
+    async fn car_open_block(
+        &mut self,
+        memory: &mut GuestMemory<'_>,
+        handle: types::CarHandle,
+        cid: GuestPtr<str>,
+    ) -> Result<types::CarHandle, CarErrorKind> {
+        let cid = guest_str(memory, cid)?;
+        let fd = car_driver::open_block(self.resource_table(), handle.into(), cid).await?;
+        Ok(types::CarHandle::from(fd))
+    }
+
+    async fn car_open_file(
+        &mut self,
+        memory: &mut GuestMemory<'_>,
+        handle: types::CarHandle,
+        path: GuestPtr<str>,
+    ) -> Result<types::CarHandle, CarErrorKind> {
+        let path = guest_str(memory, path)?;
+        let fd = car_driver::open_file(self.resource_table(), handle.into(), path).await?;
+        Ok(types::CarHandle::from(fd))
+    }
+
+    async fn car_read(
+        &mut self,
+        memory: &mut GuestMemory<'_>,
+        handle: types::CarHandle,
+        buf: GuestPtr<u8>,
+        buf_len: u32,
+    ) -> Result<u32, CarErrorKind> {
+        let mut dest_buf = vec![0; buf_len as _];
+        let rs = car_driver::read(self.resource_table(), handle.into(), &mut dest_buf[..]).await?;
+        if rs > 0 {
+            memory
+                .copy_from_slice(&dest_buf[0..rs as _], buf.as_array(rs))
+                .map_err(|_| CarErrorKind::RuntimeError)?;
+        }
+        Ok(rs)
+    }
+
+    async fn car_close(
+        &mut self,
+        _memory: &mut GuestMemory<'_>,
+        handle: types::CarHandle,
+    ) -> Result<(), CarErrorKind> {
+        car_driver::close(self.resource_table(), handle.into()).await
+    }
+}
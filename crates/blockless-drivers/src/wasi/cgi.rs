@@ -1,11 +1,11 @@
 #![allow(non_upper_case_globals)]
 use log::error;
-use wasi_common::WasiCtx;
+use wasi_common::{Extension, WasiCtx};
 use wiggle::{GuestMemory, GuestPtr};
 
 use crate::cgi_driver::{
     self, cgi_directory_list_exec, cgi_directory_list_read, child_stderr_read, child_stdin_write,
-    child_stdout_read, command_and_exec,
+    child_stdout_read, command_alias, command_and_exec,
 };
 use crate::CgiErrorKind;
 
@@ -34,6 +34,7 @@ impl From<CgiErrorKind> for types::CgiError {
             CgiErrorKind::InvalidParameter => CgiError::InvalidParameter,
             CgiErrorKind::RuntimeError => CgiError::RuntimeError,
             CgiErrorKind::InvalidExtension => CgiError::InvalidExtension,
+            CgiErrorKind::PermissionDeny => CgiError::PermissionDeny,
         }
     }
 }
@@ -59,8 +60,16 @@ impl blockless_cgi::BlocklessCgi for WasiCtx {
                 CgiErrorKind::InvalidParameter
             })?
             .unwrap();
+        let alias = command_alias(cmd)?;
+        if !self.schema_permission("cgi", &format!("cgi://{alias}")) {
+            error!("Permission Deny");
+            return Err(CgiErrorKind::PermissionDeny);
+        }
         let root_path = self.config_drivers_root_path_ref().unwrap();
-        command_and_exec(&root_path, cmd).await.map(|r| r.into())
+        self.usage.call(Extension::Cgi);
+        command_and_exec(self.resource_table(), &root_path, cmd)
+            .await
+            .map(|r| r.into())
     }
 
     async fn cgi_list_exec(
@@ -68,7 +77,9 @@ impl blockless_cgi::BlocklessCgi for WasiCtx {
         _memory: &mut GuestMemory<'_>,
     ) -> Result<types::CgiHandle, CgiErrorKind> {
         let root_path = self.config_drivers_root_path_ref().unwrap();
-        cgi_directory_list_exec(&root_path).await.map(|r| r.into())
+        cgi_directory_list_exec(self.resource_table(), &root_path)
+            .await
+            .map(|r| r.into())
     }
 
     /// read the cgi list
@@ -81,12 +92,14 @@ impl blockless_cgi::BlocklessCgi for WasiCtx {
     ) -> Result<u32, CgiErrorKind> {
         let mut dest_buf = vec![0; buf_len as _];
         let buf = buf.clone();
-        let rs = cgi_directory_list_read(handle.into(), &mut dest_buf[..]).await?;
+        let rs = cgi_directory_list_read(self.resource_table(), handle.into(), &mut dest_buf[..])
+            .await?;
         if rs > 0 {
             memory
                 .copy_from_slice(&dest_buf[0..rs as _], buf.as_array(rs))
                 .map_err(|_| CgiErrorKind::RuntimeError)?;
         }
+        self.usage.bytes_in(Extension::Cgi, rs as _);
         Ok(rs)
     }
 
@@ -99,12 +112,13 @@ impl blockless_cgi::BlocklessCgi for WasiCtx {
     ) -> Result<u32, CgiErrorKind> {
         let mut dest_buf = vec![0; buf_len as _];
         let buf = buf.clone();
-        let rs = child_stdout_read(handle.into(), &mut dest_buf[..]).await?;
+        let rs = child_stdout_read(self.resource_table(), handle.into(), &mut dest_buf[..]).await?;
         if rs > 0 {
             memory
                 .copy_from_slice(&dest_buf[0..rs as _], buf.as_array(rs))
                 .map_err(|_| CgiErrorKind::RuntimeError)?;
         }
+        self.usage.bytes_in(Extension::Cgi, rs as _);
         Ok(rs)
     }
 
@@ -117,12 +131,13 @@ impl blockless_cgi::BlocklessCgi for WasiCtx {
     ) -> Result<u32, CgiErrorKind> {
         let mut dest_buf = vec![0; buf_len as _];
         let buf = buf.clone();
-        let rs = child_stderr_read(handle.into(), &mut dest_buf[..]).await?;
+        let rs = child_stderr_read(self.resource_table(), handle.into(), &mut dest_buf[..]).await?;
         if rs > 0 {
             memory
                 .copy_from_slice(&dest_buf[0..rs as _], buf.as_array(rs))
                 .map_err(|_| CgiErrorKind::RuntimeError)?;
         }
+        self.usage.bytes_in(Extension::Cgi, rs as _);
         Ok(rs)
     }
 
@@ -141,7 +156,9 @@ impl blockless_cgi::BlocklessCgi for WasiCtx {
             })?
             .unwrap();
         let buf = unsafe { std::slice::from_raw_parts(buf.as_ptr(), buf_len as _) };
-        child_stdin_write(handle.into(), buf).await
+        let rs = child_stdin_write(self.resource_table(), handle.into(), buf).await?;
+        self.usage.bytes_out(Extension::Cgi, rs as _);
+        Ok(rs)
     }
 
     async fn cgi_close(
@@ -149,6 +166,6 @@ impl blockless_cgi::BlocklessCgi for WasiCtx {
         _memory: &mut GuestMemory<'_>,
         handle: types::CgiHandle,
     ) -> Result<(), CgiErrorKind> {
-        cgi_driver::close(handle.into())
+        cgi_driver::close(self.resource_table(), handle.into())
     }
 }
@@ -1,7 +1,7 @@
 #![allow(non_upper_case_globals)]
 use crate::{http_driver, HttpErrorKind};
 use log::error;
-use wasi_common::WasiCtx;
+use wasi_common::{CredentialError, Extension, WasiCtx};
 use wiggle::{GuestMemory, GuestPtr};
 
 wiggle::from_witx!({
@@ -93,6 +93,37 @@ impl wiggle::GuestErrorType for types::HttpError {
     }
 }
 
+/// parse the guest url like the http client does, the permissions and the credential
+/// are checked on the returned url and the request is sent to the same url.
+pub(crate) fn request_url(url: &str) -> Result<String, HttpErrorKind> {
+    url::Url::parse(url).map(String::from).map_err(|e| {
+        error!("parse the url {} error: {}", url, e);
+        HttpErrorKind::InvalidUrl
+    })
+}
+
+/// replace the credential profile of the options with the `Authorization` header,
+/// so the guest never sees the secrets, the url must be one of the urls of the profile.
+pub(crate) fn http_opts(ctx: &WasiCtx, url: &str, opts: &str) -> Result<String, HttpErrorKind> {
+    let name = match http_driver::profile_name(opts) {
+        Some(name) => name,
+        None => return Ok(opts.to_string()),
+    };
+    let auth = match ctx.credential(&name, url) {
+        Ok(cred) => cred.authorization().ok_or_else(|| {
+            error!("the profile {} is not the http credential", name);
+            HttpErrorKind::HeadersValidationError
+        })?,
+        Err(CredentialError::PermissionDeny) => {
+            error!("Permission Deny");
+            return Err(HttpErrorKind::PermissionDeny);
+        }
+        Err(CredentialError::NotFound) => return Err(HttpErrorKind::HeadersValidationError),
+        Err(CredentialError::Unavailable) => return Err(HttpErrorKind::RuntimeError),
+    };
+    http_driver::with_authorization(opts, &auth)
+}
+
 #[wiggle::async_trait]
 impl blockless_http::BlocklessHttp for WasiCtx {
     async fn http_req(
@@ -108,10 +139,40 @@ impl blockless_http::BlocklessHttp for WasiCtx {
                 HttpErrorKind::Utf8Error
             })?
             .unwrap();
-        if !self.resource_permission(url) {
+        let opts: &str = memory
+            .as_str(opts)
+            .map_err(|e| {
+                error!("guest options error: {}", e);
+                HttpErrorKind::Utf8Error
+            })?
+            .unwrap();
+        let url = &request_url(url)?;
+        let method = http_driver::request_method(opts);
+        if !self.method_permission(url, method.as_deref()) {
             error!("Permission Deny");
             return Err(HttpErrorKind::PermissionDeny);
         }
+        let opts = http_opts(self, url, opts)?;
+        self.usage.call(Extension::Http);
+        self.usage
+            .bytes_out(Extension::Http, http_driver::request_body_len(&opts));
+        let (fd, code) = http_driver::http_req(self.resource_table(), url, &opts).await?;
+        Ok((types::HttpHandle::from(fd), types::CodeType::from(code)))
+    }
+
+    async fn http_open(
+        &mut self,
+        memory: &mut GuestMemory<'_>,
+        url: GuestPtr<str>,
+        opts: GuestPtr<str>,
+    ) -> Result<types::HttpHandle, HttpErrorKind> {
+        let url: &str = memory
+            .as_str(url)
+            .map_err(|e| {
+                error!("guest url error: {}", e);
+                HttpErrorKind::Utf8Error
+            })?
+            .unwrap();
         let opts: &str = memory
             .as_str(opts)
             .map_err(|e| {
@@ -119,8 +180,44 @@ impl blockless_http::BlocklessHttp for WasiCtx {
                 HttpErrorKind::Utf8Error
             })?
             .unwrap();
-        let (fd, code) = http_driver::http_req(url, opts).await?;
-        Ok((types::HttpHandle::from(fd), types::CodeType::from(code)))
+        let url = &request_url(url)?;
+        let method = http_driver::request_method(opts);
+        if !self.method_permission(url, method.as_deref()) {
+            error!("Permission Deny");
+            return Err(HttpErrorKind::PermissionDeny);
+        }
+        let opts = http_opts(self, url, opts)?;
+        self.usage.call(Extension::Http);
+        let fd = http_driver::http_open(self.resource_table(), url, &opts).await?;
+        Ok(types::HttpHandle::from(fd))
+    }
+
+    async fn http_write_body(
+        &mut self,
+        memory: &mut GuestMemory<'_>,
+        handle: types::HttpHandle,
+        buf: GuestPtr<u8>,
+        buf_len: u32,
+    ) -> Result<u32, HttpErrorKind> {
+        let buf = memory
+            .as_slice(buf.as_array(buf_len))
+            .map_err(|e| {
+                error!("guest body buf error: {}", e);
+                HttpErrorKind::MemoryAccessError
+            })?
+            .unwrap();
+        let n = http_driver::http_write_body(self.resource_table(), handle.into(), buf).await?;
+        self.usage.bytes_out(Extension::Http, n as _);
+        Ok(n)
+    }
+
+    async fn http_send(
+        &mut self,
+        _memory: &mut GuestMemory<'_>,
+        handle: types::HttpHandle,
+    ) -> Result<types::CodeType, HttpErrorKind> {
+        let code = http_driver::http_send(self.resource_table(), handle.into()).await?;
+        Ok(types::CodeType::from(code))
     }
 
     async fn http_close(
@@ -128,7 +225,7 @@ impl blockless_http::BlocklessHttp for WasiCtx {
         _memory: &mut GuestMemory<'_>,
         handle: types::HttpHandle,
     ) -> Result<(), HttpErrorKind> {
-        http_driver::http_close(handle.into()).await
+        http_driver::http_close(self.resource_table(), handle.into()).await
     }
 
     async fn http_read_header(
@@ -148,10 +245,35 @@ impl blockless_http::BlocklessHttp for WasiCtx {
             .unwrap();
         let mut dest_buf = vec![0; buf_len as _];
         let buf = buf.clone();
-        let rs = http_driver::http_read_head(handle.into(), head, &mut dest_buf[..]).await?;
+        let rs = http_driver::http_read_head(
+            self.resource_table(),
+            handle.into(),
+            head,
+            &mut dest_buf[..],
+        )
+        .await?;
+        memory
+            .copy_from_slice(&dest_buf[0..rs as _], buf.as_array(rs))
+            .map_err(|_| HttpErrorKind::MemoryAccessError)?;
+        self.usage.bytes_in(Extension::Http, rs as _);
+        Ok(rs)
+    }
+
+    async fn http_read_headers(
+        &mut self,
+        memory: &mut GuestMemory<'_>,
+        handle: types::HttpHandle,
+        buf: GuestPtr<u8>,
+        buf_len: u32,
+    ) -> Result<u32, HttpErrorKind> {
+        let mut dest_buf = vec![0; buf_len as _];
+        let rs =
+            http_driver::http_read_headers(self.resource_table(), handle.into(), &mut dest_buf[..])
+                .await?;
         memory
             .copy_from_slice(&dest_buf[0..rs as _], buf.as_array(rs))
             .map_err(|_| HttpErrorKind::MemoryAccessError)?;
+        self.usage.bytes_in(Extension::Http, rs as _);
         Ok(rs)
     }
 
@@ -164,12 +286,15 @@ impl blockless_http::BlocklessHttp for WasiCtx {
     ) -> Result<u32, HttpErrorKind> {
         let mut dest_buf = vec![0; buf_len as _];
         let buf = buf.clone();
-        let rs = http_driver::http_read_body(handle.into(), &mut dest_buf[..]).await?;
+        let rs =
+            http_driver::http_read_body(self.resource_table(), handle.into(), &mut dest_buf[..])
+                .await?;
         if rs > 0 {
             memory
                 .copy_from_slice(&dest_buf[0..rs as _], buf.as_array(rs))
                 .map_err(|_| HttpErrorKind::MemoryAccessError)?;
         }
+        self.usage.bytes_in(Extension::Http, rs as _);
         Ok(rs)
     }
 }
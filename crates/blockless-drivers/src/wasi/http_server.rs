@@ -0,0 +1,127 @@
+#![allow(non_upper_case_globals)]
+use crate::http_server_driver;
+use crate::HttpServerErrorKind;
+use log::error;
+use wasi_common::WasiCtx;
+use wiggle::{GuestMemory, GuestPtr};
+
+wiggle::from_witx!({
+    witx: ["$BLOCKLESS_DRIVERS_ROOT/witx/blockless_http_server.witx"],
+    errors: { http_server_error => HttpServerErrorKind },
+    async: *,
+    wasmtime: false,
+});
+
+impl types::UserErrorConversion for WasiCtx {
+    fn http_server_error_from_http_server_error_kind(
+        &mut self,
+        e: self::HttpServerErrorKind,
+    ) -> wiggle::anyhow::Result<types::HttpServerError> {
+        e.try_into()
+            .map_err(|e| wiggle::anyhow::anyhow!(format!("{:?}", e)))
+    }
+}
+
+impl From<HttpServerErrorKind> for types::HttpServerError {
+    fn from(e: HttpServerErrorKind) -> types::HttpServerError {
+        use types::HttpServerError;
+        match e {
+            HttpServerErrorKind::InvalidHandle => HttpServerError::InvalidHandle,
+            HttpServerErrorKind::InvalidParameter => HttpServerError::InvalidParameter,
+            HttpServerErrorKind::BufferTooSmall => HttpServerError::BufferTooSmall,
+            HttpServerErrorKind::RuntimeError => HttpServerError::RuntimeError,
+        }
+    }
+}
+
+impl wiggle::GuestErrorType for types::HttpServerError {
+    fn success() -> Self {
+        Self::Success
+    }
+}
+
+fn guest_str<'a>(
+    memory: &'a GuestMemory<'_>,
+    s: GuestPtr<str>,
+) -> Result<&'a str, HttpServerErrorKind> {
+    memory
+        .as_str(s)
+        .map_err(|e| {
+            error!("guest string error: {}", e);
+            HttpServerErrorKind::InvalidParameter
+        })?
+        .ok_or(HttpServerErrorKind::InvalidParameter)
+}
+
+#[wiggle::async_trait]
+impl blockless_http_server::BlocklessHttpServer for WasiCtx {
+    async fn request_head(
+        &mut self,
+        memory: &mut GuestMemory<'_>,
+        handle: types::RequestHandle,
+        buf: GuestPtr<u8>,
+        buf_len: u32,
+    ) -> Result<u32, HttpServerErrorKind> {
+        let head = http_server_driver::request_head(self.resource_table(), handle.into()).await?;
+        let len = head.len() as u32;
+        if len > buf_len {
+            return Err(HttpServerErrorKind::BufferTooSmall);
+        }
+        memory
+            .copy_from_slice(head.as_bytes(), buf.as_array(len))
+            .map_err(|_| HttpServerErrorKind::RuntimeError)?;
+        Ok(len)
+    }
+
+    async fn request_body_read(
+        &mut self,
+        memory: &mut GuestMemory<'_>,
+        handle: types::RequestHandle,
+        buf: GuestPtr<u8>,
+        buf_len: u32,
+    ) -> Result<u32, HttpServerErrorKind> {
+        let mut dest_buf = vec![0; buf_len as _];
+        let rs = http_server_driver::request_body_read(
+            self.resource_table(),
+            handle.into(),
+            &mut dest_buf,
+        )
+        .await?;
+        if rs > 0 {
+            memory
+                .copy_from_slice(&dest_buf[0..rs as _], buf.as_array(rs))
+                .map_err(|_| HttpServerErrorKind::RuntimeError)?;
+        }
+        Ok(rs)
+    }
+
+    async fn response_head(
+        &mut self,
+        memory: &mut GuestMemory<'_>,
+        handle: types::RequestHandle,
+        status: u16,
+        headers: GuestPtr<str>,
+    ) -> Result<(), HttpServerErrorKind> {
+        let headers = guest_str(memory, headers)?;
+        http_server_driver::response_head(self.resource_table(), handle.into(), status, headers)
+            .await
+    }
+
+    async fn response_write(
+        &mut self,
+        memory: &mut GuestMemory<'_>,
+        handle: types::RequestHandle,
+        buf: GuestPtr<u8>,
+        buf_len: u32,
+    ) -> Result<(), HttpServerErrorKind> {
+        let data = memory
+            .as_slice(buf.as_array(buf_len))
+            .map_err(|e| {
+                error!("guest buffer error: {}", e);
+                HttpServerErrorKind::InvalidParameter
+            })?
+            .ok_or(HttpServerErrorKind::InvalidParameter)?
+            .to_vec();
+        http_server_driver::response_write(self.resource_table(), handle.into(), &data).await
+    }
+}
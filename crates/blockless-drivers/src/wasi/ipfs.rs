@@ -2,7 +2,7 @@
 use crate::ipfs_driver;
 use crate::IpfsErrorKind;
 use log::error;
-use wasi_common::WasiCtx;
+use wasi_common::{Extension, WasiCtx};
 use wiggle::GuestMemory;
 use wiggle::GuestPtr;
 
@@ -60,7 +60,14 @@ impl blockless_ipfs::BlocklessIpfs for WasiCtx {
                 IpfsErrorKind::Utf8Error
             })?
             .unwrap();
-        let (status, fd) = ipfs_driver::command(params).await?;
+        let api = ipfs_driver::command_api(params)?;
+        if !self.schema_permission("ipfs", &format!("ipfs://{api}")) {
+            error!("Permission Deny");
+            return Err(IpfsErrorKind::PermissionDeny);
+        }
+        let ipfs_api = self.ipfs_api();
+        self.usage.call(Extension::Ipfs);
+        let (status, fd) = ipfs_driver::command(self.resource_table(), &ipfs_api, params).await?;
         Ok((types::IpfsHandle::from(fd), types::StatusCode::from(status)))
     }
 
@@ -73,12 +80,14 @@ impl blockless_ipfs::BlocklessIpfs for WasiCtx {
     ) -> Result<u32, IpfsErrorKind> {
         let mut dest_buf = vec![0; buf_len as _];
         let buf = buf.clone();
-        let rs = ipfs_driver::read_body(handle.into(), &mut dest_buf[..]).await?;
+        let rs =
+            ipfs_driver::read_body(self.resource_table(), handle.into(), &mut dest_buf[..]).await?;
         if rs > 0 {
             memory
                 .copy_from_slice(&dest_buf[0..rs as _], buf.as_array(rs))
                 .map_err(|_| IpfsErrorKind::RuntimeError)?;
         }
+        self.usage.bytes_in(Extension::Ipfs, rs as _);
         Ok(rs)
     }
 
@@ -87,7 +96,7 @@ impl blockless_ipfs::BlocklessIpfs for WasiCtx {
         _memory: &mut GuestMemory<'_>,
         handle: types::IpfsHandle,
     ) -> Result<(), IpfsErrorKind> {
-        ipfs_driver::close(handle.into()).await?;
+        ipfs_driver::close(self.resource_table(), handle.into()).await?;
         Ok(())
     }
 
@@ -105,7 +114,8 @@ impl blockless_ipfs::BlocklessIpfs for WasiCtx {
                 IpfsErrorKind::InvalidParameter
             })?
             .unwrap();
-        let rs = ipfs_driver::write_body(handle.into(), buf).await?;
+        let rs = ipfs_driver::write_body(self.resource_table(), handle.into(), buf).await?;
+        self.usage.bytes_out(Extension::Ipfs, rs as _);
         Ok(rs)
     }
 }
@@ -0,0 +1,233 @@
+#![allow(non_upper_case_globals)]
+use crate::kv_driver;
+use crate::KvErrorKind;
+use log::error;
+use wasi_common::{Extension, WasiCtx};
+use wiggle::{GuestMemory, GuestPtr};
+
+wiggle::from_witx!({
+    witx: ["$BLOCKLESS_DRIVERS_ROOT/witx/blockless_kv.witx"],
+    errors: { kv_error => KvErrorKind },
+    async: *,
+    wasmtime: false,
+});
+
+impl types::UserErrorConversion for WasiCtx {
+    fn kv_error_from_kv_error_kind(
+        &mut self,
+        e: self::KvErrorKind,
+    ) -> wiggle::anyhow::Result<types::KvError> {
+        e.try_into()
+            .map_err(|e| wiggle::anyhow::anyhow!(format!("{:?}", e)))
+    }
+}
+
+impl From<KvErrorKind> for types::KvError {
+    fn from(e: KvErrorKind) -> types::KvError {
+        use types::KvError;
+        match e {
+            KvErrorKind::InvalidHandle => KvError::InvalidHandle,
+            KvErrorKind::InvalidParameter => KvError::InvalidParameter,
+            KvErrorKind::NotFound => KvError::NotFound,
+            KvErrorKind::BufferTooSmall => KvError::BufferTooSmall,
+            KvErrorKind::ValueTooLarge => KvError::ValueTooLarge,
+            KvErrorKind::QuotaExceeded => KvError::QuotaExceeded,
+            KvErrorKind::PermissionDeny => KvError::PermissionDeny,
+            KvErrorKind::RuntimeError => KvError::RuntimeError,
+        }
+    }
+}
+
+impl wiggle::GuestErrorType for types::KvError {
+    fn success() -> Self {
+        Self::Success
+    }
+}
+
+fn guest_str<'a>(memory: &'a GuestMemory<'_>, s: GuestPtr<str>) -> Result<&'a str, KvErrorKind> {
+    memory
+        .as_str(s)
+        .map_err(|e| {
+            error!("guest string error: {}", e);
+            KvErrorKind::InvalidParameter
+        })?
+        .ok_or(KvErrorKind::InvalidParameter)
+}
+
+fn guest_bytes(
+    memory: &GuestMemory<'_>,
+    buf: GuestPtr<u8>,
+    buf_len: u32,
+) -> Result<Vec<u8>, KvErrorKind> {
+    memory
+        .as_slice(buf.as_array(buf_len))
+        .map_err(|e| {
+            error!("guest buffer error: {}", e);
+            KvErrorKind::InvalidParameter
+        })?
+        .map(|s| s.to_vec())
+        .ok_or(KvErrorKind::InvalidParameter)
+}
+
+/// copy the bytes into the guest buffer, the bytes are not truncated.
+fn copy_to_guest(
+    memory: &mut GuestMemory<'_>,
+    data: &[u8],
+    buf: GuestPtr<u8>,
+    buf_len: u32,
+) -> Result<u32, KvErrorKind> {
+    let len = data.len() as u32;
+    if len > buf_len {
+        return Err(KvErrorKind::BufferTooSmall);
+    }
+    memory
+        .copy_from_slice(data, buf.as_array(len))
+        .map_err(|_| KvErrorKind::RuntimeError)?;
+    Ok(len)
+}
+
+#[wiggle::async_trait]
+impl blockless_kv::BlocklessKv for WasiCtx {
+    async fn kv_open(
+        &mut self,
+        memory: &mut GuestMemory<'_>,
+        namespace: GuestPtr<str>,
+    ) -> Result<types::KvHandle, KvErrorKind> {
+        let namespace = guest_str(memory, namespace)?;
+        if !self.schema_permission("kv", &format!("kv://{namespace}")) {
+            error!("Permission Deny");
+            return Err(KvErrorKind::PermissionDeny);
+        }
+        let Some((conf, app)) = self.kv_config() else {
+            error!("the app of the kv is not configured");
+            return Err(KvErrorKind::PermissionDeny);
+        };
+        let fd = kv_driver::open(self.resource_table(), &conf, &app, namespace).await?;
+        Ok(types::KvHandle::from(fd))
+    }
+
+    async fn kv_size(
+        &mut self,
+        memory: &mut GuestMemory<'_>,
+        handle: types::KvHandle,
+        key: GuestPtr<str>,
+    ) -> Result<u32, KvErrorKind> {
+        let key = guest_str(memory, key)?;
+        self.usage.call(Extension::Kv);
+        kv_driver::size(self.resource_table(), handle.into(), key).await
+    }
+
+    async fn kv_get(
+        &mut self,
+        memory: &mut GuestMemory<'_>,
+        handle: types::KvHandle,
+        key: GuestPtr<str>,
+        buf: GuestPtr<u8>,
+        buf_len: u32,
+    ) -> Result<u32, KvErrorKind> {
+        let key = guest_str(memory, key)?;
+        self.usage.call(Extension::Kv);
+        let value = kv_driver::get(self.resource_table(), handle.into(), key).await?;
+        let rs = copy_to_guest(memory, &value, buf, buf_len)?;
+        self.usage.bytes_in(Extension::Kv, rs as _);
+        Ok(rs)
+    }
+
+    async fn kv_put(
+        &mut self,
+        memory: &mut GuestMemory<'_>,
+        handle: types::KvHandle,
+        key: GuestPtr<str>,
+        value: GuestPtr<u8>,
+        value_len: u32,
+    ) -> Result<(), KvErrorKind> {
+        let key = guest_str(memory, key)?;
+        let value = guest_bytes(memory, value, value_len)?;
+        self.usage.call(Extension::Kv);
+        kv_driver::put(self.resource_table(), handle.into(), key, value).await?;
+        self.usage.bytes_out(Extension::Kv, value_len as _);
+        Ok(())
+    }
+
+    async fn kv_delete(
+        &mut self,
+        memory: &mut GuestMemory<'_>,
+        handle: types::KvHandle,
+        key: GuestPtr<str>,
+    ) -> Result<(), KvErrorKind> {
+        let key = guest_str(memory, key)?;
+        self.usage.call(Extension::Kv);
+        kv_driver::delete(self.resource_table(), handle.into(), key).await
+    }
+
+    async fn kv_scan(
+        &mut self,
+        memory: &mut GuestMemory<'_>,
+        handle: types::KvHandle,
+        prefix: GuestPtr<str>,
+        start: GuestPtr<str>,
+        limit: u32,
+        buf: GuestPtr<u8>,
+        buf_len: u32,
+    ) -> Result<u32, KvErrorKind> {
+        let prefix = guest_str(memory, prefix)?;
+        let start = guest_str(memory, start)?;
+        self.usage.call(Extension::Kv);
+        let keys =
+            kv_driver::scan(self.resource_table(), handle.into(), prefix, start, limit).await?;
+        let rs = copy_to_guest(memory, keys.as_bytes(), buf, buf_len)?;
+        self.usage.bytes_in(Extension::Kv, rs as _);
+        Ok(rs)
+    }
+
+    async fn kv_batch_open(
+        &mut self,
+        _memory: &mut GuestMemory<'_>,
+        handle: types::KvHandle,
+    ) -> Result<types::KvHandle, KvErrorKind> {
+        let fd = kv_driver::batch_open(self.resource_table(), handle.into()).await?;
+        Ok(types::KvHandle::from(fd))
+    }
+
+    async fn kv_batch_put(
+        &mut self,
+        memory: &mut GuestMemory<'_>,
+        handle: types::KvHandle,
+        key: GuestPtr<str>,
+        value: GuestPtr<u8>,
+        value_len: u32,
+    ) -> Result<(), KvErrorKind> {
+        let key = guest_str(memory, key)?;
+        let value = guest_bytes(memory, value, value_len)?;
+        kv_driver::batch_put(self.resource_table(), handle.into(), key, value).await?;
+        self.usage.bytes_out(Extension::Kv, value_len as _);
+        Ok(())
+    }
+
+    async fn kv_batch_delete(
+        &mut self,
+        memory: &mut GuestMemory<'_>,
+        handle: types::KvHandle,
+        key: GuestPtr<str>,
+    ) -> Result<(), KvErrorKind> {
+        let key = guest_str(memory, key)?;
+        kv_driver::batch_delete(self.resource_table(), handle.into(), key).await
+    }
+
+    async fn kv_batch_commit(
+        &mut self,
+        _memory: &mut GuestMemory<'_>,
+        handle: types::KvHandle,
+    ) -> Result<(), KvErrorKind> {
+        self.usage.call(Extension::Kv);
+        kv_driver::batch_commit(self.resource_table(), handle.into()).await
+    }
+
+    async fn kv_close(
+        &mut self,
+        _memory: &mut GuestMemory<'_>,
+        handle: types::KvHandle,
+    ) -> Result<(), KvErrorKind> {
+        kv_driver::close(self.resource_table(), handle.into()).await
+    }
+}
@@ -1,6 +1,6 @@
 #![allow(non_upper_case_globals)]
 use crate::{memory_driver, BlocklessMemoryErrorKind};
-use std::env;
+use log::error;
 use wasi_common::WasiCtx;
 use wiggle::{GuestMemory, GuestPtr};
 
@@ -45,9 +45,15 @@ impl blockless_memory::BlocklessMemory for WasiCtx {
         buf: GuestPtr<u8>,
         buf_len: u32,
     ) -> Result<u32, BlocklessMemoryErrorKind> {
-        let stdin = self.config_stdin_ref().unwrap();
+        if buf_len == 0 {
+            return Err(BlocklessMemoryErrorKind::InvalidParameter);
+        }
+        // the stdin is read from the cursor, so the large stdin can be read in chunks.
         let mut dest_buf = vec![0; buf_len as _];
-        let rs = memory_driver::read(&mut dest_buf, stdin.to_string()).await?;
+        let rs = self.config_stdin_read(&mut dest_buf).await.map_err(|e| {
+            error!("read the stdin error: {}", e);
+            BlocklessMemoryErrorKind::RuntimeError
+        })? as u32;
         if rs > 0 {
             memory
                 .copy_from_slice(&dest_buf[0..rs as _], buf.as_array(rs))
@@ -62,26 +68,11 @@ impl blockless_memory::BlocklessMemory for WasiCtx {
         buf: GuestPtr<u8>,
         buf_len: u32,
     ) -> Result<u32, BlocklessMemoryErrorKind> {
-        // get the list of env_vars to load into the wasi assembly
-        // from the BLS_LIST_VARS env var
-        let env_var = match env::var_os("BLS_LIST_VARS") {
-            Some(v) => v.into_string().unwrap(),
-            None => "".to_string(),
-        };
-
-        let mut owned_string: String = "{".to_owned();
-        for s in env_var.split(";") {
-            let env_var = match env::var_os(s) {
-                Some(v) => v.into_string().unwrap(),
-                None => "".to_string(),
-            };
-            owned_string.push_str(&format!("\"{}\": \"{}\",", s, env_var));
-        }
-        owned_string.pop();
-        owned_string.push_str(&"}");
-
+        // the env json is read from the cursor, the guest reads the next page
+        // if the buffer is too small.
+        let json = memory_driver::env_json(&self.config_env_vars());
         let mut dest_buf = vec![0; buf_len as _];
-        let rs = memory_driver::read(&mut dest_buf, owned_string.to_string()).await?;
+        let rs = memory_driver::read_at(&mut dest_buf, json.as_bytes(), &self.env_offset)?;
         if rs > 0 {
             memory
                 .copy_from_slice(&dest_buf[0..rs as _], buf.as_array(rs))
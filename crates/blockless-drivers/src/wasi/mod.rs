@@ -1,15 +1,20 @@
 #![allow(non_upper_case_globals)]
+pub mod car;
 pub mod cgi;
 pub mod guest_ptr;
 pub mod http;
+pub mod http_server;
 pub mod ipfs;
+pub mod kv;
 pub mod memory;
 pub mod s3;
 pub mod socket;
+pub mod websocket;
 use crate::ErrorKind;
-use crate::{Driver, DriverConetxt};
+use crate::{multiaddr, tcp_driver, Driver, DriverConetxt};
+use log::error;
 // pub use guest_ptr::ArrayTuple;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 use wasi_common::file::{FileAccessMode, FileEntry};
 use wasi_common::WasiCtx;
 use wiggle::{GuestMemory, GuestPtr};
@@ -64,7 +69,8 @@ enum_2_u32!(
     BadOpen,
     Badf,
     Eof,
-    Unknown
+    Unknown,
+    PermissionDeny
 );
 
 impl From<u32> for ErrorKind {
@@ -79,6 +85,7 @@ impl From<u32> for ErrorKind {
             BadParams => ErrorKind::DriverBadParams,
             Unknown => ErrorKind::Unknown,
             Badf => ErrorKind::BadFileDescriptor,
+            PermissionDeny => ErrorKind::PermissionDeny,
             _ => ErrorKind::Unknown,
         }
     }
@@ -106,13 +113,28 @@ impl blockless_drivers::BlocklessDrivers for WasiCtx {
             .as_str(opts)
             .map_err(|_| ErrorKind::DriverBadParams)?
             .unwrap();
-        let drv: Arc<dyn Driver + Sync + Send> = match DriverConetxt::find_driver(path) {
+        let addr = multiaddr::parse(path.as_bytes()).map_err(|_| ErrorKind::DriverBadParams)?;
+        let schema = addr.schema().map_err(|_| ErrorKind::DriverBadParams)?;
+        let url = addr
+            .to_url_string()
+            .map_err(|_| ErrorKind::DriverBadParams)?;
+        if !self.schema_permission(schema, &url) {
+            error!("Permission Deny");
+            return Err(ErrorKind::PermissionDeny);
+        }
+        // the CA file of the tls socket is only from the configure.
+        let ca_file = self.config_tls_ca_file();
+        let opts = tcp_driver::with_ca_file(opts, ca_file.as_deref());
+        let drv: Arc<dyn Driver + Sync + Send> = match self
+            .drivers::<RwLock<DriverConetxt>>()
+            .and_then(|drivers| drivers.read().unwrap().find_driver(path))
+        {
             Some(d) => d,
             None => return Err(ErrorKind::DriverNotFound),
         };
         let mode = FileAccessMode::READ | FileAccessMode::WRITE;
         match drv
-            .open(path, opts)
+            .open(path, &opts)
             .await
             .map(|f| Arc::new(FileEntry::new(f, mode)))
         {
@@ -1,7 +1,7 @@
 #![allow(non_upper_case_globals)]
 use crate::{s3_driver, S3ErrorKind};
 use log::error;
-use wasi_common::WasiCtx;
+use wasi_common::{Credential, CredentialError, Extension, WasiCtx};
 use wiggle::{GuestMemory, GuestPtr};
 
 wiggle::from_witx!({
@@ -46,6 +46,50 @@ impl wiggle::GuestErrorType for types::S3Error {
     }
 }
 
+/// check the permission of the bucket, then replace the credential profile of the parameters
+/// with the credentials, so the guest never sees the secrets.
+pub(crate) fn s3_params(ctx: &WasiCtx, params: &str) -> Result<String, S3ErrorKind> {
+    let resource = s3_driver::bucket_resource(params)?;
+    if !ctx.schema_permission("s3", &resource) {
+        error!("Permission Deny");
+        return Err(S3ErrorKind::PermissionDeny);
+    }
+    let name = match s3_driver::profile_name(params)? {
+        Some(name) => name,
+        None => return Ok(params.to_string()),
+    };
+    let url = s3_driver::bucket_url(params)?;
+    match ctx.credential(&name, &url) {
+        Ok(Credential::S3 {
+            access_key,
+            secret_key,
+            session_token,
+        }) => {
+            s3_driver::with_credentials(params, &access_key, &secret_key, session_token.as_deref())
+        }
+        Ok(_) => {
+            error!("the profile {} is not the s3 credential", name);
+            Err(S3ErrorKind::CredentialsError)
+        }
+        Err(CredentialError::PermissionDeny) => {
+            error!("Permission Deny");
+            Err(S3ErrorKind::PermissionDeny)
+        }
+        Err(CredentialError::NotFound) => Err(S3ErrorKind::InvalidParameter),
+        Err(CredentialError::Unavailable) => Err(S3ErrorKind::CredentialsError),
+    }
+}
+
+/// the parameters of the bucket command, the presigned url outlives the call and is signed
+/// with the secrets, so the presign is not allowed with the credential profile.
+pub(crate) fn bucket_params(ctx: &WasiCtx, cmd: u16, params: &str) -> Result<String, S3ErrorKind> {
+    if cmd == s3_driver::PRESIGN_COMMAND && s3_driver::profile_name(params)?.is_some() {
+        error!("the presign is not allowed with the credential profile");
+        return Err(S3ErrorKind::PermissionDeny);
+    }
+    s3_params(ctx, params)
+}
+
 #[wiggle::async_trait]
 impl blockless_s3::BlocklessS3 for WasiCtx {
     async fn bucket_command(
@@ -61,7 +105,9 @@ impl blockless_s3::BlocklessS3 for WasiCtx {
                 S3ErrorKind::Utf8Error
             })?
             .unwrap();
-        let rs = s3_driver::bucket_command(cmd, &params).await?;
+        let params = bucket_params(self, cmd, params)?;
+        self.usage.call(Extension::S3);
+        let rs = s3_driver::bucket_command(self.resource_table(), cmd, &params).await?;
         Ok(rs.into())
     }
 
@@ -79,6 +125,7 @@ impl blockless_s3::BlocklessS3 for WasiCtx {
                 S3ErrorKind::Utf8Error
             })?
             .unwrap();
+        let cfg = s3_params(self, cfg)?;
 
         let params = memory
             .as_slice(buf.as_array(buf_len))
@@ -87,7 +134,55 @@ impl blockless_s3::BlocklessS3 for WasiCtx {
                 S3ErrorKind::InvalidParameter
             })?
             .unwrap();
-        s3_driver::bucket_put_object(&cfg, &params).await
+        self.usage.call(Extension::S3);
+        s3_driver::bucket_put_object(&cfg, &params).await?;
+        self.usage.bytes_out(Extension::S3, buf_len as _);
+        Ok(())
+    }
+
+    async fn s3_multipart_open(
+        &mut self,
+        memory: &mut GuestMemory<'_>,
+        param: GuestPtr<str>,
+    ) -> Result<types::S3Handle, S3ErrorKind> {
+        let params = memory
+            .as_str(param)
+            .map_err(|e| {
+                error!("guest url error: {}", e);
+                S3ErrorKind::Utf8Error
+            })?
+            .unwrap();
+        let params = s3_params(self, params)?;
+        self.usage.call(Extension::S3);
+        let rs = s3_driver::multipart_open(self.resource_table(), &params).await?;
+        Ok(rs.into())
+    }
+
+    async fn s3_multipart_write(
+        &mut self,
+        memory: &mut GuestMemory<'_>,
+        handle: types::S3Handle,
+        buf: GuestPtr<u8>,
+        buf_len: u32,
+    ) -> Result<u32, S3ErrorKind> {
+        let body = memory
+            .as_slice(buf.as_array(buf_len))
+            .map_err(|e| {
+                error!("guest body error: {}", e);
+                S3ErrorKind::InvalidParameter
+            })?
+            .unwrap();
+        let rs = s3_driver::multipart_write(self.resource_table(), handle.into(), &body).await?;
+        self.usage.bytes_out(Extension::S3, rs as _);
+        Ok(rs)
+    }
+
+    async fn s3_multipart_complete(
+        &mut self,
+        _memory: &mut GuestMemory<'_>,
+        handle: types::S3Handle,
+    ) -> Result<(), S3ErrorKind> {
+        s3_driver::multipart_complete(self.resource_table(), handle.into()).await
     }
 
     async fn s3_read(
@@ -98,12 +193,13 @@ impl blockless_s3::BlocklessS3 for WasiCtx {
         buf_len: u32,
     ) -> Result<u32, S3ErrorKind> {
         let mut dest_buf = vec![0; buf_len as _];
-        let rs = s3_driver::read(handle.into(), &mut dest_buf).await?;
+        let rs = s3_driver::read(self.resource_table(), handle.into(), &mut dest_buf).await?;
         if rs > 0 {
             memory
                 .copy_from_slice(&dest_buf[0..rs as _], buf.as_array(rs))
                 .map_err(|_| S3ErrorKind::RuntimeError)?;
         }
+        self.usage.bytes_in(Extension::S3, rs as _);
         Ok(rs)
     }
 
@@ -112,6 +208,6 @@ impl blockless_s3::BlocklessS3 for WasiCtx {
         _memory: &mut GuestMemory<'_>,
         handle: types::S3Handle,
     ) -> Result<(), S3ErrorKind> {
-        s3_driver::close(handle.into()).await
+        s3_driver::close(self.resource_table(), handle.into()).await
     }
 }
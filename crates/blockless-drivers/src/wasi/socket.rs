@@ -2,13 +2,17 @@ use std::sync::Arc;
 
 use wasi_common::{
     file::{FileAccessMode, FileEntry},
-    sync::net::Socket,
-    WasiCtx, WasiFile,
+    WasiCtx,
 };
 
+use crate::tcp_driver::{
+    self,
+    tls::{self, TlsOptions},
+};
+use crate::udp_driver::{UdpSocket, MAX_DATAGRAM_SIZE};
 use crate::BlocklessSocketErrorKind;
 use log::error;
-use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
 use wiggle::{GuestMemory, GuestPtr};
 
 wiggle::from_witx!({
@@ -42,36 +46,68 @@ impl From<BlocklessSocketErrorKind> for types::SocketError {
             BlocklessSocketErrorKind::ConnectRefused => SocketError::ConnectionRefused,
             BlocklessSocketErrorKind::ConnectionReset => SocketError::ConnectionReset,
             BlocklessSocketErrorKind::ParameterError => SocketError::ParameterError,
+            BlocklessSocketErrorKind::PermissionDeny => SocketError::PermissionDeny,
+            BlocklessSocketErrorKind::TlsError => SocketError::TlsError,
+            BlocklessSocketErrorKind::Timeout => SocketError::Timeout,
+            BlocklessSocketErrorKind::HostUnreachable => SocketError::HostUnreachable,
+            BlocklessSocketErrorKind::WouldBlock => SocketError::WouldBlock,
+            BlocklessSocketErrorKind::NameNotResolved => SocketError::NameNotResolved,
+            BlocklessSocketErrorKind::InvalidHandle => SocketError::InvalidHandle,
+            BlocklessSocketErrorKind::BufferTooSmall => SocketError::BufferTooSmall,
+            BlocklessSocketErrorKind::RuntimeError => SocketError::RuntimeError,
         }
     }
 }
 
-async fn tcp_connect(addr: &str) -> Result<Box<dyn WasiFile>, BlocklessSocketErrorKind> {
-    let stream = match TcpStream::connect(addr) {
-        Ok(s) => s,
-        Err(e) => {
-            error!("error connect in driver {}: {}", addr, e);
-            return Err(BlocklessSocketErrorKind::ConnectRefused);
-        }
-    };
-    let stream = cap_std::net::TcpStream::from_std(stream);
-    let socket: Socket = Socket::from(stream);
-    let wasi_file: Box<dyn WasiFile> = Box::<dyn WasiFile>::from(socket);
-    Ok(wasi_file)
+fn guest_str<'a>(
+    memory: &'a GuestMemory<'_>,
+    s: GuestPtr<str>,
+) -> Result<&'a str, BlocklessSocketErrorKind> {
+    memory
+        .as_str(s)
+        .map_err(|_| BlocklessSocketErrorKind::ParameterError)?
+        .ok_or(BlocklessSocketErrorKind::ParameterError)
 }
 
-async fn tcp_bind(addr: &str) -> Result<Box<dyn WasiFile>, BlocklessSocketErrorKind> {
-    let listener = match TcpListener::bind(addr) {
-        Ok(s) => s,
-        Err(e) => {
-            error!("error connect in driver {}: {}", addr, e);
-            return Err(BlocklessSocketErrorKind::ConnectRefused);
-        }
-    };
-    let listener = cap_std::net::TcpListener::from_std(listener);
-    let socket: Socket = Socket::from(listener);
-    let wasi_file: Box<dyn WasiFile> = Box::<dyn WasiFile>::from(socket);
-    Ok(wasi_file)
+/// copy the bytes into the guest buffer, the bytes are not truncated.
+fn copy_to_guest(
+    memory: &mut GuestMemory<'_>,
+    data: &[u8],
+    buf: GuestPtr<u8>,
+    buf_len: u32,
+) -> Result<u32, BlocklessSocketErrorKind> {
+    let len = data.len() as u32;
+    if len > buf_len {
+        return Err(BlocklessSocketErrorKind::BufferTooSmall);
+    }
+    memory
+        .copy_from_slice(data, buf.as_array(len))
+        .map_err(|_| BlocklessSocketErrorKind::RuntimeError)?;
+    Ok(len)
+}
+
+fn udp_permission(ctx: &WasiCtx, addr: &str) -> Result<(), BlocklessSocketErrorKind> {
+    if !ctx.schema_permission("udp", &format!("udp://{addr}")) {
+        error!("Permission Deny");
+        return Err(BlocklessSocketErrorKind::PermissionDeny);
+    }
+    Ok(())
+}
+
+/// get the file entry of the udp socket, the entry holds the socket across the await.
+fn udp_socket(ctx: &WasiCtx, fd: u32) -> Result<Arc<FileEntry>, BlocklessSocketErrorKind> {
+    let entry = ctx
+        .table()
+        .get::<FileEntry>(fd)
+        .map_err(|_| BlocklessSocketErrorKind::InvalidHandle)?;
+    if !entry.file.as_any().is::<UdpSocket>() {
+        return Err(BlocklessSocketErrorKind::InvalidHandle);
+    }
+    Ok(entry)
+}
+
+fn as_udp(entry: &FileEntry) -> &UdpSocket {
+    entry.file.as_any().downcast_ref::<UdpSocket>().unwrap()
 }
 
 #[wiggle::async_trait]
@@ -85,8 +121,12 @@ impl blockless_socket::BlocklessSocket for WasiCtx {
             .as_str(bind)
             .map_err(|_| BlocklessSocketErrorKind::ParameterError)?
             .unwrap();
+        if !self.schema_permission("tcp", &format!("tcp://{addr}")) {
+            error!("Permission Deny");
+            return Err(BlocklessSocketErrorKind::PermissionDeny);
+        }
         let mode = FileAccessMode::READ | FileAccessMode::WRITE;
-        match tcp_bind(&addr)
+        match tcp_driver::bind(addr)
             .await
             .map(|f| Arc::new(FileEntry::new(f, mode)))
         {
@@ -108,9 +148,14 @@ impl blockless_socket::BlocklessSocket for WasiCtx {
             .as_str(target)
             .map_err(|_| BlocklessSocketErrorKind::ParameterError)?
             .unwrap();
+        if !self.schema_permission("tcp", &format!("tcp://{addr}")) {
+            error!("Permission Deny");
+            return Err(BlocklessSocketErrorKind::PermissionDeny);
+        }
         let mode = FileAccessMode::READ | FileAccessMode::WRITE;
-        match tcp_connect(&addr)
+        match tcp_driver::connect(addr, tcp_driver::CONNECT_TIMEOUT)
             .await
+            .and_then(tcp_driver::stream_file)
             .map(|f| Arc::new(FileEntry::new(f, mode)))
         {
             Ok(f) => {
@@ -121,4 +166,128 @@ impl blockless_socket::BlocklessSocket for WasiCtx {
             Err(e) => Err(e),
         }
     }
+
+    async fn tls_connect(
+        &mut self,
+        memory: &mut GuestMemory<'_>,
+        target: GuestPtr<str>,
+        opts: GuestPtr<str>,
+    ) -> Result<types::SocketHandle, BlocklessSocketErrorKind> {
+        let addr = memory
+            .as_str(target)
+            .map_err(|_| BlocklessSocketErrorKind::ParameterError)?
+            .unwrap();
+        let opts = memory
+            .as_str(opts)
+            .map_err(|_| BlocklessSocketErrorKind::ParameterError)?
+            .unwrap();
+        if !self.schema_permission("tcp", &format!("tcp://{addr}")) {
+            error!("Permission Deny");
+            return Err(BlocklessSocketErrorKind::PermissionDeny);
+        }
+        let opts = match opts {
+            "" => json::JsonValue::Null,
+            opts => json::parse(opts).map_err(|_| BlocklessSocketErrorKind::ParameterError)?,
+        };
+        let mut opts = TlsOptions::from_json(&opts, addr)?;
+        opts.ca_file = self.config_tls_ca_file();
+        let stream = tls::tls_connect(addr, &opts).await?;
+        let mode = FileAccessMode::READ | FileAccessMode::WRITE;
+        let f = Arc::new(FileEntry::new(Box::new(stream), mode));
+        let fd_num = self.table().push(f).unwrap();
+        Ok(types::SocketHandle::from(fd_num))
+    }
+
+    async fn udp_bind(
+        &mut self,
+        memory: &mut GuestMemory<'_>,
+        target: GuestPtr<str>,
+    ) -> Result<types::SocketHandle, BlocklessSocketErrorKind> {
+        let addr = guest_str(memory, target)?;
+        udp_permission(self, addr)?;
+        let socket = UdpSocket::bind(addr).await?;
+        let mode = FileAccessMode::READ | FileAccessMode::WRITE;
+        let f = Arc::new(FileEntry::new(Box::new(socket), mode));
+        let fd_num = self.table().push(f).unwrap();
+        Ok(types::SocketHandle::from(fd_num))
+    }
+
+    async fn udp_connect(
+        &mut self,
+        memory: &mut GuestMemory<'_>,
+        handle: types::SocketHandle,
+        target: GuestPtr<str>,
+    ) -> Result<(), BlocklessSocketErrorKind> {
+        let addr = guest_str(memory, target)?;
+        udp_permission(self, addr)?;
+        let entry = udp_socket(self, handle.into())?;
+        as_udp(&entry).connect(addr).await
+    }
+
+    async fn udp_send_to(
+        &mut self,
+        memory: &mut GuestMemory<'_>,
+        handle: types::SocketHandle,
+        target: GuestPtr<str>,
+        buf: GuestPtr<u8>,
+        buf_len: u32,
+    ) -> Result<u32, BlocklessSocketErrorKind> {
+        let addr = guest_str(memory, target)?;
+        udp_permission(self, addr)?;
+        let entry = udp_socket(self, handle.into())?;
+        let data = memory
+            .as_slice(buf.as_array(buf_len))
+            .map_err(|_| BlocklessSocketErrorKind::ParameterError)?
+            .ok_or(BlocklessSocketErrorKind::ParameterError)?
+            .to_vec();
+        let n = as_udp(&entry).send_to(&data, addr).await?;
+        Ok(n as u32)
+    }
+
+    async fn udp_recv_from(
+        &mut self,
+        memory: &mut GuestMemory<'_>,
+        handle: types::SocketHandle,
+        timeout_ms: u32,
+        buf: GuestPtr<u8>,
+        buf_len: u32,
+        addr_buf: GuestPtr<u8>,
+        addr_buf_len: u32,
+    ) -> Result<types::UdpRecv, BlocklessSocketErrorKind> {
+        let entry = udp_socket(self, handle.into())?;
+        let timeout = match timeout_ms {
+            0 => None,
+            ms => Some(Duration::from_millis(ms as _)),
+        };
+        let mut dest_buf = vec![0; (buf_len as usize).min(MAX_DATAGRAM_SIZE)];
+        let (n, from) = as_udp(&entry).recv_from(&mut dest_buf, timeout).await?;
+        let addr_len = copy_to_guest(memory, from.to_string().as_bytes(), addr_buf, addr_buf_len)?;
+        let len = copy_to_guest(memory, &dest_buf[..n], buf, buf_len)?;
+        Ok(types::UdpRecv { len, addr_len })
+    }
+
+    async fn resolve(
+        &mut self,
+        memory: &mut GuestMemory<'_>,
+        host: GuestPtr<str>,
+        buf: GuestPtr<u8>,
+        buf_len: u32,
+    ) -> Result<u32, BlocklessSocketErrorKind> {
+        let host = guest_str(memory, host)?;
+        if !self.host_permission(&["tcp", "udp"], host) {
+            error!("Permission Deny");
+            return Err(BlocklessSocketErrorKind::PermissionDeny);
+        }
+        let ips = tcp_driver::resolve(host).await?;
+        let ips = ips
+            .iter()
+            .map(|ip| json::JsonValue::from(ip.to_string()))
+            .collect();
+        copy_to_guest(
+            memory,
+            json::JsonValue::Array(ips).dump().as_bytes(),
+            buf,
+            buf_len,
+        )
+    }
 }
@@ -0,0 +1,151 @@
+#![allow(non_upper_case_globals)]
+use crate::ws_driver::{self, WsFrameKind};
+use crate::WsErrorKind;
+use log::error;
+use std::time::Duration;
+use wasi_common::WasiCtx;
+use wiggle::{GuestMemory, GuestPtr};
+
+wiggle::from_witx!({
+    witx: ["$BLOCKLESS_DRIVERS_ROOT/witx/blockless_websocket.witx"],
+    errors: { ws_error => WsErrorKind },
+    async: *,
+    wasmtime: false,
+});
+
+impl types::UserErrorConversion for WasiCtx {
+    fn ws_error_from_ws_error_kind(
+        &mut self,
+        e: self::WsErrorKind,
+    ) -> wiggle::anyhow::Result<types::WsError> {
+        e.try_into()
+            .map_err(|e| wiggle::anyhow::anyhow!(format!("{:?}", e)))
+    }
+}
+
+impl From<WsErrorKind> for types::WsError {
+    fn from(e: WsErrorKind) -> types::WsError {
+        use types::WsError;
+        match e {
+            WsErrorKind::InvalidHandle => WsError::InvalidHandle,
+            WsErrorKind::InvalidParameter => WsError::InvalidParameter,
+            WsErrorKind::InvalidUrl => WsError::InvalidUrl,
+            WsErrorKind::ConnectError => WsError::ConnectError,
+            WsErrorKind::Timeout => WsError::Timeout,
+            WsErrorKind::Closed => WsError::Closed,
+            WsErrorKind::ProtocolError => WsError::ProtocolError,
+            WsErrorKind::PermissionDeny => WsError::PermissionDeny,
+            WsErrorKind::RuntimeError => WsError::RuntimeError,
+        }
+    }
+}
+
+impl From<types::WsFrameKind> for WsFrameKind {
+    fn from(kind: types::WsFrameKind) -> WsFrameKind {
+        match kind {
+            types::WsFrameKind::Text => WsFrameKind::Text,
+            types::WsFrameKind::Binary => WsFrameKind::Binary,
+            types::WsFrameKind::Close => WsFrameKind::Close,
+        }
+    }
+}
+
+impl From<WsFrameKind> for types::WsFrameKind {
+    fn from(kind: WsFrameKind) -> types::WsFrameKind {
+        match kind {
+            WsFrameKind::Text => types::WsFrameKind::Text,
+            WsFrameKind::Binary => types::WsFrameKind::Binary,
+            WsFrameKind::Close => types::WsFrameKind::Close,
+        }
+    }
+}
+
+impl wiggle::GuestErrorType for types::WsError {
+    fn success() -> Self {
+        Self::Success
+    }
+}
+
+fn guest_str<'a>(memory: &'a GuestMemory<'_>, s: GuestPtr<str>) -> Result<&'a str, WsErrorKind> {
+    memory
+        .as_str(s)
+        .map_err(|e| {
+            error!("guest string error: {}", e);
+            WsErrorKind::InvalidParameter
+        })?
+        .ok_or(WsErrorKind::InvalidParameter)
+}
+
+#[wiggle::async_trait]
+impl blockless_websocket::BlocklessWebsocket for WasiCtx {
+    async fn ws_connect(
+        &mut self,
+        memory: &mut GuestMemory<'_>,
+        url: GuestPtr<str>,
+        opts: GuestPtr<str>,
+    ) -> Result<types::WsHandle, WsErrorKind> {
+        let url = guest_str(memory, url)?;
+        let opts = guest_str(memory, opts)?;
+        let url = ws_driver::parse_url(url)?;
+        if !self.schema_permission(url.scheme(), url.as_str()) {
+            error!("Permission Deny");
+            return Err(WsErrorKind::PermissionDeny);
+        }
+        let fd = ws_driver::connect(self.resource_table(), &url, opts).await?;
+        Ok(types::WsHandle::from(fd))
+    }
+
+    async fn ws_send(
+        &mut self,
+        memory: &mut GuestMemory<'_>,
+        handle: types::WsHandle,
+        kind: types::WsFrameKind,
+        buf: GuestPtr<u8>,
+        buf_len: u32,
+    ) -> Result<(), WsErrorKind> {
+        let data = memory
+            .as_slice(buf.as_array(buf_len))
+            .map_err(|e| {
+                error!("guest buffer error: {}", e);
+                WsErrorKind::InvalidParameter
+            })?
+            .ok_or(WsErrorKind::InvalidParameter)?
+            .to_vec();
+        ws_driver::send(self.resource_table(), handle.into(), kind.into(), data).await
+    }
+
+    async fn ws_recv(
+        &mut self,
+        memory: &mut GuestMemory<'_>,
+        handle: types::WsHandle,
+        timeout_ms: u32,
+        buf: GuestPtr<u8>,
+        buf_len: u32,
+    ) -> Result<types::WsFrame, WsErrorKind> {
+        let timeout = match timeout_ms {
+            0 => None,
+            ms => Some(Duration::from_millis(ms as _)),
+        };
+        let mut dest_buf = vec![0; buf_len as _];
+        let (rs, kind, remaining) =
+            ws_driver::recv(self.resource_table(), handle.into(), timeout, &mut dest_buf).await?;
+        if rs > 0 {
+            memory
+                .copy_from_slice(&dest_buf[0..rs as _], buf.as_array(rs))
+                .map_err(|_| WsErrorKind::RuntimeError)?;
+        }
+        Ok(types::WsFrame {
+            len: rs,
+            kind: kind.into(),
+            remaining,
+        })
+    }
+
+    async fn ws_close(
+        &mut self,
+        _memory: &mut GuestMemory<'_>,
+        handle: types::WsHandle,
+    ) -> Result<(), WsErrorKind> {
+        ws_driver::close(self.resource_table(), handle.into()).await
+    }
+}
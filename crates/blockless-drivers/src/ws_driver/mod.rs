@@ -0,0 +1,289 @@
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use json::JsonValue;
+use log::error;
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::{HeaderName, HeaderValue};
+use tokio_tungstenite::tungstenite::{Error as WsError, Message};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use url::Url;
+use wasi_common::Table;
+
+use crate::{resource, WsErrorKind};
+
+/// The kind of the message sent or received by the guest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WsFrameKind {
+    Text,
+    Binary,
+    Close,
+}
+
+/// The websocket connection of the guest.
+struct WsConnection {
+    stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    // the message partially read by the guest and the read position.
+    pending: Option<(WsFrameKind, Vec<u8>, usize)>,
+}
+
+fn ws_error(e: WsError) -> WsErrorKind {
+    error!("websocket error: {}", e);
+    match e {
+        WsError::ConnectionClosed | WsError::AlreadyClosed => WsErrorKind::Closed,
+        WsError::Url(_) => WsErrorKind::InvalidUrl,
+        WsError::Io(_) | WsError::Tls(_) | WsError::Http(_) | WsError::HttpFormat(_) => {
+            WsErrorKind::ConnectError
+        }
+        WsError::Protocol(_) | WsError::Utf8 | WsError::Capacity(_) => WsErrorKind::ProtocolError,
+        _ => WsErrorKind::RuntimeError,
+    }
+}
+
+/// the headers of the options json, the value of the header is the string.
+fn build_headers(value: &JsonValue) -> Result<Vec<(HeaderName, HeaderValue)>, WsErrorKind> {
+    if value.is_null() {
+        return Ok(Vec::new());
+    }
+    if !value.is_object() {
+        return Err(WsErrorKind::InvalidParameter);
+    }
+    value
+        .entries()
+        .map(|(key, value)| {
+            let name = HeaderName::from_bytes(key.as_bytes())
+                .map_err(|_| WsErrorKind::InvalidParameter)?;
+            let value = value
+                .as_str()
+                .and_then(|v| HeaderValue::from_str(v).ok())
+                .ok_or(WsErrorKind::InvalidParameter)?;
+            Ok((name, value))
+        })
+        .collect()
+}
+
+/// parse the websocket url, the scheme must be `ws` or `wss`. The permission is checked
+/// on the parsed url, it's the url connected by `connect`.
+pub fn parse_url(url: &str) -> Result<Url, WsErrorKind> {
+    match Url::parse(url) {
+        Ok(u) if u.scheme() == "ws" || u.scheme() == "wss" => Ok(u),
+        _ => Err(WsErrorKind::InvalidUrl),
+    }
+}
+
+/// connect to the websocket server, the options is the json with the `headers` and
+/// the `connectTimeout` in seconds.
+pub async fn connect(table: &Table, url: &Url, opts: &str) -> Result<u32, WsErrorKind> {
+    let opts = match opts {
+        "" => JsonValue::new_object(),
+        opts => json::parse(opts).map_err(|_| WsErrorKind::InvalidParameter)?,
+    };
+    let mut request = url.as_str().into_client_request().map_err(ws_error)?;
+    for (name, value) in build_headers(&opts["headers"])? {
+        request.headers_mut().append(name, value);
+    }
+    let connect = tokio_tungstenite::connect_async(request);
+    let (stream, _) = match opts["connectTimeout"].as_u64() {
+        Some(secs) => tokio::time::timeout(Duration::from_secs(secs), connect)
+            .await
+            .map_err(|_| WsErrorKind::Timeout)?,
+        None => connect.await,
+    }
+    .map_err(ws_error)?;
+    let conn = WsConnection {
+        stream,
+        pending: None,
+    };
+    resource::push(table, conn).ok_or(WsErrorKind::RuntimeError)
+}
+
+fn get_conn(table: &Table, fd: u32) -> Result<resource::Resource<WsConnection>, WsErrorKind> {
+    resource::get::<WsConnection>(table, fd).ok_or(WsErrorKind::InvalidHandle)
+}
+
+pub async fn send(
+    table: &Table,
+    fd: u32,
+    kind: WsFrameKind,
+    data: Vec<u8>,
+) -> Result<(), WsErrorKind> {
+    let msg = match kind {
+        WsFrameKind::Text => {
+            Message::Text(String::from_utf8(data).map_err(|_| WsErrorKind::InvalidParameter)?)
+        }
+        WsFrameKind::Binary => Message::Binary(data),
+        WsFrameKind::Close => return Err(WsErrorKind::InvalidParameter),
+    };
+    let conn = get_conn(table, fd)?;
+    let mut conn = conn.lock().await;
+    conn.stream.send(msg).await.map_err(ws_error)
+}
+
+/// read the next text or binary message, the ping is answered by the stream.
+async fn next_message(
+    stream: &mut WebSocketStream<MaybeTlsStream<TcpStream>>,
+) -> Result<(WsFrameKind, Vec<u8>), WsErrorKind> {
+    loop {
+        match stream.next().await {
+            Some(Ok(Message::Text(s))) => return Ok((WsFrameKind::Text, s.into_bytes())),
+            Some(Ok(Message::Binary(b))) => return Ok((WsFrameKind::Binary, b)),
+            Some(Ok(Message::Close(_))) => return Ok((WsFrameKind::Close, Vec::new())),
+            Some(Ok(_)) => continue,
+            Some(Err(e)) => return Err(ws_error(e)),
+            None => return Err(WsErrorKind::Closed),
+        }
+    }
+}
+
+/// receive the message into the buffer, the message larger than the buffer is read by
+/// the next calls. Return the bytes read, the kind and the bytes left of the message.
+pub async fn recv(
+    table: &Table,
+    fd: u32,
+    timeout: Option<Duration>,
+    buf: &mut [u8],
+) -> Result<(u32, WsFrameKind, u32), WsErrorKind> {
+    let conn = get_conn(table, fd)?;
+    let mut conn = conn.lock().await;
+    let conn = &mut *conn;
+    let (kind, data, pos) = match conn.pending.take() {
+        Some(pending) => pending,
+        None => {
+            let (kind, data) = match timeout {
+                Some(t) => tokio::time::timeout(t, next_message(&mut conn.stream))
+                    .await
+                    .map_err(|_| WsErrorKind::Timeout)??,
+                None => next_message(&mut conn.stream).await?,
+            };
+            (kind, data, 0)
+        }
+    };
+    let n = buf.len().min(data.len() - pos);
+    buf[..n].copy_from_slice(&data[pos..pos + n]);
+    let remaining = data.len() - pos - n;
+    if remaining > 0 {
+        conn.pending = Some((kind, data, pos + n));
+    }
+    Ok((n as u32, kind, remaining as u32))
+}
+
+/// close the connection, the close frame is sent to the peer.
+pub async fn close(table: &Table, fd: u32) -> Result<(), WsErrorKind> {
+    let conn = resource::remove::<WsConnection>(table, fd).ok_or(WsErrorKind::InvalidHandle)?;
+    let mut conn = conn.lock().await;
+    if let Err(e) = conn.stream.close(None).await {
+        error!("close the websocket error: {}", e);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokio::net::TcpListener;
+    use tokio::runtime::Builder;
+    use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+    use tokio_tungstenite::tungstenite::http::StatusCode;
+
+    fn check_token(req: &Request, resp: Response) -> Result<Response, ErrorResponse> {
+        match req.headers().get("x-token") {
+            Some(v) if v == "secret" => Ok(resp),
+            _ => {
+                let mut resp = ErrorResponse::new(Some("no token".into()));
+                *resp.status_mut() = StatusCode::UNAUTHORIZED;
+                Err(resp)
+            }
+        }
+    }
+
+    /// the echo server, the `x-token` header is required.
+    async fn echo_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let ws = tokio_tungstenite::accept_hdr_async(stream, check_token).await;
+                    let mut ws = match ws {
+                        Ok(ws) => ws,
+                        Err(_) => return,
+                    };
+                    while let Some(Ok(msg)) = ws.next().await {
+                        if (msg.is_text() || msg.is_binary()) && ws.send(msg).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+        format!("ws://{addr}")
+    }
+
+    #[test]
+    fn test_ws_driver() {
+        let rt = Builder::new_current_thread().enable_all().build().unwrap();
+        rt.block_on(async move {
+            let url = echo_server().await;
+            let table = Table::new();
+            assert!(matches!(
+                parse_url("not a url"),
+                Err(WsErrorKind::InvalidUrl)
+            ));
+            assert!(matches!(
+                parse_url("http://127.0.0.1/"),
+                Err(WsErrorKind::InvalidUrl)
+            ));
+            // the backslash ends the host like the connection does.
+            let evil = parse_url(r"ws://evil.com\@a.example.com/").unwrap();
+            assert_eq!(evil.host_str(), Some("evil.com"));
+            let url = parse_url(&url).unwrap();
+            assert!(matches!(
+                connect(&table, &url, "").await,
+                Err(WsErrorKind::ConnectError)
+            ));
+            let opts = r#"{"headers": {"x-token": "secret"}, "connectTimeout": 5}"#;
+            let fd = connect(&table, &url, opts).await.unwrap();
+
+            send(&table, fd, WsFrameKind::Text, b"hello".to_vec())
+                .await
+                .unwrap();
+            let mut buf = [0u8; 3];
+            let timeout = Some(Duration::from_secs(5));
+            assert_eq!(
+                recv(&table, fd, timeout, &mut buf).await.unwrap(),
+                (3, WsFrameKind::Text, 2)
+            );
+            assert_eq!(&buf, b"hel");
+            assert_eq!(
+                recv(&table, fd, timeout, &mut buf).await.unwrap(),
+                (2, WsFrameKind::Text, 0)
+            );
+            assert_eq!(&buf[..2], b"lo");
+
+            send(&table, fd, WsFrameKind::Binary, vec![0, 1, 2])
+                .await
+                .unwrap();
+            assert_eq!(
+                recv(&table, fd, timeout, &mut buf).await.unwrap(),
+                (3, WsFrameKind::Binary, 0)
+            );
+            assert_eq!(buf, [0, 1, 2]);
+            assert!(matches!(
+                send(&table, fd, WsFrameKind::Text, vec![0xff]).await,
+                Err(WsErrorKind::InvalidParameter)
+            ));
+
+            let timeout = Some(Duration::from_millis(50));
+            assert!(matches!(
+                recv(&table, fd, timeout, &mut buf).await,
+                Err(WsErrorKind::Timeout)
+            ));
+            close(&table, fd).await.unwrap();
+            assert!(matches!(
+                close(&table, fd).await,
+                Err(WsErrorKind::InvalidHandle)
+            ));
+        });
+    }
+}
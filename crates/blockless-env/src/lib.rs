@@ -42,3 +42,36 @@ linker_integration!({
     target: blockless_drivers::wasi::socket,
     link_method: "add_socket_to_linker",
 });
+
+linker_integration!({
+    witx: ["$BLOCKLESS_DRIVERS_ROOT/witx/blockless_car.witx"],
+    target: blockless_drivers::wasi::car,
+    link_method: "add_car_to_linker",
+});
+
+linker_integration!({
+    witx: ["$BLOCKLESS_DRIVERS_ROOT/witx/blockless_kv.witx"],
+    target: blockless_drivers::wasi::kv,
+    link_method: "add_kv_to_linker",
+});
+
+linker_integration!({
+    witx: ["$BLOCKLESS_DRIVERS_ROOT/witx/blockless_websocket.witx"],
+    target: blockless_drivers::wasi::websocket,
+    link_method: "add_websocket_to_linker",
+});
+
+linker_integration!({
+    witx: ["$BLOCKLESS_DRIVERS_ROOT/witx/blockless_http_server.witx"],
+    target: blockless_drivers::wasi::http_server,
+    link_method: "add_http_server_to_linker",
+});
+
+/// add the extensions of the wit to the component linker, the host functions share
+/// the drivers and the permissions with the wasi modules.
+pub fn add_extensions_to_component_linker<T: Send>(
+    linker: &mut wasmtime::component::Linker<T>,
+    get: impl Fn(&mut T) -> &mut wasi_common::WasiCtx + Send + Sync + Copy + 'static,
+) -> wasmtime::Result<()> {
+    blockless_drivers::component::Extensions::add_to_linker(linker, get)
+}
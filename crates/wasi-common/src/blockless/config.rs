@@ -1,4 +1,6 @@
-use crate::Permission;
+use crate::{
+    host_permissions_check, permissions_check, CredentialProfile, Permission, StdinStream,
+};
 use anyhow::{bail, Ok};
 use std::{
     collections::HashMap,
@@ -36,6 +38,17 @@ impl From<&str> for LoggerLevel {
 pub enum Stdin {
     Inherit,
     Fixed(String),
+    //the streaming stdin, the guest reads it incrementally by `memory_read`.
+    Stream(StdinStream),
+}
+
+/// The size limit of the captured output.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CaptureLimit {
+    /// the bytes beyond the size are dropped.
+    Truncate(usize),
+    /// the guest traps when it writes beyond the size.
+    Trap(usize),
 }
 
 #[derive(Debug, Clone)]
@@ -46,6 +59,8 @@ pub enum Stdout {
     Inherit,
     //stdout redirect to file.
     FileName(String),
+    //stdout captured in memory, the bytes are returned in the exit status.
+    Capture(Option<CaptureLimit>),
 }
 
 #[derive(Debug, Clone)]
@@ -56,6 +71,8 @@ pub enum Stderr {
     Inherit,
     //stderr redirect to file.
     FileName(String),
+    //stderr captured in memory, the bytes are returned in the exit status.
+    Capture(Option<CaptureLimit>),
 }
 
 #[derive(Clone)]
@@ -447,6 +464,52 @@ pub struct BlsNnGraph {
     pub dir: String,
 }
 
+/// the default api endpoint of the ipfs node.
+pub const DEFAULT_IPFS_API: &str = "http://127.0.0.1:5001";
+
+/// The api endpoint of the ipfs node, the `auth` is the value of the `Authorization` header.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IpfsApi {
+    pub url: String,
+    pub auth: Option<String>,
+}
+
+impl Default for IpfsApi {
+    fn default() -> Self {
+        Self {
+            url: DEFAULT_IPFS_API.into(),
+            auth: None,
+        }
+    }
+}
+
+/// The key-value store of the guest, the namespaces of the store are scoped by the app.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KvConfig {
+    /// the database file of the store, the store is in memory if not set.
+    pub path: Option<String>,
+    /// the app of the namespaces, the entry is used if not set.
+    pub app: Option<String>,
+    /// the max bytes of the keys and values in a namespace.
+    pub quota: Option<u64>,
+    /// the max bytes of a value.
+    pub max_value_size: Option<u64>,
+}
+
+/// The limits of the outgoing `wasi:http` requests of the components, the timeouts are in milliseconds.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HttpConfig {
+    pub connect_timeout: Option<u64>,
+    /// the timeout of the first byte of the response.
+    pub first_byte_timeout: Option<u64>,
+    /// the timeout between the bytes of the response body.
+    pub between_bytes_timeout: Option<u64>,
+    /// the max bytes of the request body.
+    pub max_request_body: Option<u64>,
+    /// the max bytes of the response body.
+    pub max_response_body: Option<u64>,
+}
+
 #[derive(Clone)]
 pub struct BlocklessConfig {
     pub entry: String,
@@ -466,9 +529,13 @@ pub struct BlocklessConfig {
     pub unknown_imports_trap: bool,
     pub store_limited: StoreLimited,
     pub envs: Vec<(String, String)>,
+    /// the host env vars passed to the guest by `env_var_read`.
+    pub env_passthrough: Vec<String>,
     pub tcp_listens: Vec<(SocketAddr, Option<u32>)>,
     pub permisions: Vec<Permission>,
     pub dirs: Vec<(String, String)>,
+    /// the host dirs mapped to the guest as read-only.
+    pub readonly_dirs: Vec<(String, String)>,
     pub fs_root_path: Option<String>,
     pub modules: Vec<BlocklessModule>,
     pub runtime_logger: Option<String>,
@@ -480,6 +547,18 @@ pub struct BlocklessConfig {
     pub cli_exit_with_code: bool,
     pub network_error_code: bool,
     pub group_permisions: HashMap<String, Vec<Permission>>,
+    pub ipfs_api: IpfsApi,
+    pub credentials: HashMap<String, CredentialProfile>,
+    pub kv: KvConfig,
+    pub http: HttpConfig,
+    /// the PEM file of the CA certificates trusted by the tls sockets.
+    pub tls_ca_file: Option<String>,
+    /// the exported function of the guest handles the http requests on the tcp listens.
+    pub http_handler: Option<String>,
+    /// the directory of the compiled modules cache, the cache is disabled if it's not set.
+    pub module_cache: Option<String>,
+    /// the exported function called with the app arguments instead of the entry.
+    pub invoke: Option<String>,
 }
 
 impl BlocklessConfig {
@@ -489,8 +568,10 @@ impl BlocklessConfig {
             run_time: None,
             coredump: None,
             envs: Vec::new(),
+            env_passthrough: Vec::new(),
             debug_info: false,
             dirs: Vec::new(),
+            readonly_dirs: Vec::new(),
             is_carfile: false,
             fs_root_path: None,
             drivers: Vec::new(),
@@ -515,6 +596,14 @@ impl BlocklessConfig {
             entry: String::from(entry),
             permisions: Default::default(),
             group_permisions: HashMap::new(),
+            ipfs_api: Default::default(),
+            credentials: HashMap::new(),
+            kv: Default::default(),
+            http: Default::default(),
+            tls_ca_file: None,
+            http_handler: None,
+            module_cache: None,
+            invoke: None,
             opts: Default::default(),
             runtime_logger_level: LoggerLevel::WARN,
             version: BlocklessConfigVersion::Version0,
@@ -551,6 +640,16 @@ impl BlocklessConfig {
         self.envs = envs;
     }
 
+    #[inline(always)]
+    pub fn env_passthrough_ref(&self) -> &[String] {
+        &self.env_passthrough
+    }
+
+    #[inline(always)]
+    pub fn set_env_passthrough(&mut self, names: Vec<String>) {
+        self.env_passthrough = names;
+    }
+
     #[inline(always)]
     pub fn set_stdin_args(&mut self, args: Vec<String>) {
         self.stdin_args = args;
@@ -561,6 +660,11 @@ impl BlocklessConfig {
         self.dirs = dirs;
     }
 
+    #[inline(always)]
+    pub fn set_readonly_dirs(&mut self, dirs: Vec<(String, String)>) {
+        self.readonly_dirs = dirs;
+    }
+
     #[inline(always)]
     pub fn set_entry(&mut self, entry: String) {
         self.entry = entry;
@@ -644,7 +748,7 @@ impl BlocklessConfig {
         let mut g_perms: HashMap<String, Vec<_>> = HashMap::new();
         perms.iter().for_each(|p| {
             g_perms
-                .entry(p.schema.clone())
+                .entry(p.schema.to_ascii_lowercase())
                 .or_insert_with(Vec::new)
                 .push(p.clone());
         });
@@ -662,6 +766,92 @@ impl BlocklessConfig {
         self.drivers_root_path.as_ref().map(String::as_ref)
     }
 
+    #[inline(always)]
+    pub fn ipfs_api_ref(&self) -> &IpfsApi {
+        &self.ipfs_api
+    }
+
+    #[inline(always)]
+    pub fn set_ipfs_api(&mut self, api: IpfsApi) {
+        self.ipfs_api = api;
+    }
+
+    #[inline(always)]
+    pub fn credentials_ref(&self) -> &HashMap<String, CredentialProfile> {
+        &self.credentials
+    }
+
+    #[inline(always)]
+    pub fn set_credentials(&mut self, credentials: HashMap<String, CredentialProfile>) {
+        self.credentials = credentials;
+    }
+
+    #[inline(always)]
+    pub fn kv_ref(&self) -> &KvConfig {
+        &self.kv
+    }
+
+    #[inline(always)]
+    pub fn set_kv(&mut self, kv: KvConfig) {
+        self.kv = kv;
+    }
+
+    #[inline(always)]
+    pub fn http_ref(&self) -> &HttpConfig {
+        &self.http
+    }
+
+    #[inline(always)]
+    pub fn set_http(&mut self, http: HttpConfig) {
+        self.http = http;
+    }
+
+    /// the app of the kv namespaces, the kv is not available if the app is not configured,
+    /// the manifests with the same entry file would share the namespaces otherwise.
+    pub fn kv_app(&self) -> Option<&str> {
+        self.kv.app.as_deref()
+    }
+
+    #[inline(always)]
+    pub fn tls_ca_file_ref(&self) -> Option<&str> {
+        self.tls_ca_file.as_deref()
+    }
+
+    #[inline(always)]
+    pub fn set_tls_ca_file(&mut self, ca_file: Option<String>) {
+        self.tls_ca_file = ca_file;
+    }
+
+    #[inline(always)]
+    pub fn http_handler_ref(&self) -> Option<&str> {
+        self.http_handler.as_deref()
+    }
+
+    #[inline(always)]
+    pub fn set_http_handler(&mut self, handler: Option<String>) {
+        self.http_handler = handler;
+    }
+
+    #[inline(always)]
+    pub fn module_cache_ref(&self) -> Option<&str> {
+        self.module_cache.as_deref()
+    }
+
+    #[inline(always)]
+    pub fn set_module_cache(&mut self, dir: Option<String>) {
+        self.module_cache = dir;
+    }
+
+    #[inline(always)]
+    pub fn invoke_ref(&self) -> Option<&str> {
+        self.invoke.as_deref()
+    }
+
+    #[inline(always)]
+    pub fn set_invoke(&mut self, invoke: Option<String>) {
+        self.invoke = invoke;
+    }
+
     #[inline(always)]
     pub fn set_drivers_root_path(&mut self, r: Option<String>) {
         self.drivers_root_path = r;
@@ -694,6 +884,13 @@ impl BlocklessConfig {
         self.stdio.stdout = stdout
     }
 
+    /// stderr file must be work in sandbox root_path,
+    /// if root_path is not setting, the stderr file will use Inherit
+    #[inline(always)]
+    pub fn stderr(&mut self, stderr: Stderr) {
+        self.stdio.stderr = stderr
+    }
+
     /// the runtime log file name, if the value is None
     /// the runtime log will ouput to Stdout.
     /// the file is in fs_root_path
@@ -777,6 +974,15 @@ impl BlocklessConfig {
         self.limited_time
     }
 
+    /// the wall-clock limit of the app in milliseconds, it's the smaller one
+    /// of `run_time` and `limited_time`.
+    pub fn time_limit(&self) -> Option<u64> {
+        match (self.run_time, self.limited_time) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
     #[inline(always)]
     pub fn limited_fuel(&mut self, fuel: Option<u64>) {
         self.limited_fuel = fuel
@@ -798,7 +1004,32 @@ impl BlocklessConfig {
     }
 
     pub fn resource_permission(&self, url: &str) -> bool {
-        self.permisions.iter().any(|p| p.is_permision(url))
+        permissions_check(&self.permisions, url, None)
+    }
+
+    /// check the http resource with the request method, the rules with methods
+    /// only permit the request with one of the methods.
+    pub fn method_permission(&self, url: &str, method: Option<&str>) -> bool {
+        permissions_check(&self.permisions, url, method)
+    }
+
+    /// check the resource only with the permissions of the schema group,
+    /// e.g. the `tcp` schema will check the `tcp://host:port` resource.
+    pub fn schema_permission(&self, schema: &str, url: &str) -> bool {
+        self.group_permisions
+            .get(&schema.to_ascii_lowercase())
+            .map(|perms| permissions_check(perms, url, None))
+            .unwrap_or(false)
+    }
+
+    /// check the host with the permissions of the schemas no matter the ports,
+    /// e.g. the `tcp` and `udp` schemas for resolving the host.
+    pub fn host_permission(&self, schemas: &[&str], host: &str) -> bool {
+        let perms = schemas
+            .iter()
+            .filter_map(|s| self.group_permisions.get(&s.to_ascii_lowercase()))
+            .flatten();
+        host_permissions_check(perms, host)
     }
 
     #[inline(always)]
@@ -818,22 +1049,22 @@ mod test {
         let mut config = BlocklessConfig::new("test");
         assert!(matches!(config.version(), BlocklessConfigVersion::Version0));
         let permisions = vec![
-            Permission {
-                url: "/test1".to_string(),
-                schema: "http".to_string(),
-            },
-            Permission {
-                url: "/test2".to_string(),
-                schema: "http".to_string(),
-            },
+            Permission::parse("http://test/test1").unwrap(),
+            Permission::parse("http://test/test2").unwrap(),
+            Permission::parse("!http://test/test2/deny").unwrap(),
         ];
         config.set_permisions(permisions);
         let grps = config.group_permisions.get("http");
         if let Some(grps) = grps {
-            assert_eq!(grps.len(), 2);
+            assert_eq!(grps.len(), 3);
         } else {
             unreachable!("should not reach.");
         }
+        assert!(config.schema_permission("http", "http://test/test1/a"));
+        assert!(config.schema_permission("HTTP", "http://test/test2"));
+        assert!(!config.schema_permission("http", "http://test/test2/deny/a"));
+        assert!(!config.schema_permission("tcp", "http://test/test1"));
+        assert!(!config.schema_permission("http", "http://test/test3"));
         let root = Some("/root".into());
         config.set_fs_root_path(root);
         let test = Some("test.log".into());
@@ -0,0 +1,212 @@
+use super::{permissions_check, Permission};
+use anyhow::Context;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use std::fmt;
+
+/// The source of the secret, the secret is read from the host when the profile is used,
+/// so it's never written in the manifest or passed into the guest memory.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SecretSource {
+    /// the environment variable of the host.
+    Env(String),
+    /// the file of the host, the trailing newline is trimmed.
+    File(String),
+}
+
+impl SecretSource {
+    pub fn read(&self) -> anyhow::Result<String> {
+        match self {
+            SecretSource::Env(name) => {
+                std::env::var(name).with_context(|| format!("read the env {name} error"))
+            }
+            SecretSource::File(path) => std::fs::read_to_string(path)
+                .map(|s| s.trim_end_matches(['\r', '\n']).to_string())
+                .with_context(|| format!("read the file {path} error")),
+        }
+    }
+}
+
+/// The named credential profile of the manifest, the drivers inject the credential host-side,
+/// only into the requests to the urls of the profile.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CredentialProfile {
+    pub kind: CredentialKind,
+    /// the urls the credential is sent to, e.g. `https://api.example.com/**`.
+    pub urls: Vec<Permission>,
+}
+
+/// The secrets of the credential profile.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CredentialKind {
+    S3 {
+        access_key: SecretSource,
+        secret_key: SecretSource,
+        session_token: Option<SecretSource>,
+    },
+    Bearer {
+        token: SecretSource,
+    },
+    Basic {
+        username: String,
+        password: SecretSource,
+    },
+}
+
+impl CredentialProfile {
+    /// the credential is only injected into the request of the urls of the profile,
+    /// the profile without urls is never injected. The url is parsed like the http client
+    /// parses it, so the host is the host the request is sent to.
+    pub fn allows(&self, url: &str) -> bool {
+        permissions_check(&self.urls, url, None)
+    }
+
+    /// read the secrets of the profile from the host.
+    pub fn resolve(&self) -> anyhow::Result<Credential> {
+        Ok(match &self.kind {
+            CredentialKind::S3 {
+                access_key,
+                secret_key,
+                session_token,
+            } => Credential::S3 {
+                access_key: access_key.read()?,
+                secret_key: secret_key.read()?,
+                session_token: session_token.as_ref().map(SecretSource::read).transpose()?,
+            },
+            CredentialKind::Bearer { token } => Credential::Bearer(token.read()?),
+            CredentialKind::Basic { username, password } => Credential::Basic {
+                username: username.clone(),
+                password: password.read()?,
+            },
+        })
+    }
+}
+
+/// The credential with the secrets, the `Debug` doesn't print the secrets.
+#[derive(Clone, PartialEq)]
+pub enum Credential {
+    S3 {
+        access_key: String,
+        secret_key: String,
+        session_token: Option<String>,
+    },
+    Bearer(String),
+    Basic {
+        username: String,
+        password: String,
+    },
+}
+
+impl fmt::Debug for Credential {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Credential::S3 { .. } => write!(f, "Credential::S3(..)"),
+            Credential::Bearer(_) => write!(f, "Credential::Bearer(..)"),
+            Credential::Basic { username, .. } => write!(f, "Credential::Basic({username}, ..)"),
+        }
+    }
+}
+
+impl Credential {
+    /// the value of the http `Authorization` header, the S3 credential has no header.
+    pub fn authorization(&self) -> Option<String> {
+        match self {
+            Credential::S3 { .. } => None,
+            Credential::Bearer(token) => Some(format!("Bearer {token}")),
+            Credential::Basic { username, password } => Some(format!(
+                "Basic {}",
+                STANDARD.encode(format!("{username}:{password}"))
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CredentialError {
+    NotFound,
+    PermissionDeny,
+    Unavailable,
+}
+
+impl std::error::Error for CredentialError {}
+
+impl fmt::Display for CredentialError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFound => write!(f, "Credential profile not found"),
+            Self::PermissionDeny => write!(f, "Permision deny."),
+            Self::Unavailable => write!(f, "Credential unavailable"),
+        }
+    }
+}
+
+/// the resource of the profile for the permission check, e.g. `cred://s3-prod`.
+pub fn credential_resource(name: &str) -> String {
+    format!("cred://{name}")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn profile(kind: CredentialKind) -> CredentialProfile {
+        CredentialProfile {
+            kind,
+            urls: vec![Permission::parse("https://api.example.com/**").unwrap()],
+        }
+    }
+
+    #[test]
+    fn test_resolve_profile() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("secret");
+        std::fs::write(&path, "file-secret\n").unwrap();
+        std::env::set_var("BLS_TEST_CREDENTIAL_KEY", "env-key");
+        let profile = profile(CredentialKind::S3 {
+            access_key: SecretSource::Env("BLS_TEST_CREDENTIAL_KEY".into()),
+            secret_key: SecretSource::File(path.to_str().unwrap().into()),
+            session_token: None,
+        });
+        let cred = profile.resolve().unwrap();
+        assert_eq!(
+            cred,
+            Credential::S3 {
+                access_key: "env-key".into(),
+                secret_key: "file-secret".into(),
+                session_token: None,
+            }
+        );
+        assert_eq!(cred.authorization(), None);
+        assert_eq!(format!("{cred:?}"), "Credential::S3(..)");
+
+        let profile = profile(CredentialKind::Basic {
+            username: "user".into(),
+            password: SecretSource::File(path.to_str().unwrap().into()),
+        });
+        assert_eq!(
+            profile.resolve().unwrap().authorization().unwrap(),
+            "Basic dXNlcjpmaWxlLXNlY3JldA=="
+        );
+
+        let profile = profile(CredentialKind::Bearer {
+            token: SecretSource::Env("BLS_TEST_CREDENTIAL_MISSING".into()),
+        });
+        assert!(profile.resolve().is_err());
+    }
+
+    #[test]
+    fn test_profile_allows() {
+        let mut profile = profile(CredentialKind::Bearer {
+            token: SecretSource::Env("TOKEN".into()),
+        });
+        assert!(profile.allows("https://api.example.com/v1/users"));
+        assert!(!profile.allows("https://evil.example.com/v1/users"));
+        // the request of these urls is sent to `evil.com`.
+        profile.urls = vec![Permission::parse("https://*.example.com").unwrap()];
+        assert!(profile.allows("https://api.example.com/v1/users"));
+        assert!(!profile.allows(r"https://evil.com\@api.example.com/"));
+        assert!(!profile.allows("https://api.example.com@evil.com/"));
+        assert!(!profile.allows("https://evil.com#.example.com"));
+        profile.urls.clear();
+        assert!(!profile.allows("https://api.example.com/v1/users"));
+    }
+}
@@ -0,0 +1,166 @@
+use crate::dir::{OpenResult, ReaddirCursor, ReaddirEntity, WasiDir};
+use crate::file::{FdFlags, Filestat, OFlags};
+use crate::snapshots::preview_1::error::Errno;
+use crate::{Error, SystemTimeSpec};
+use std::any::Any;
+use std::path::PathBuf;
+
+/// The read-only directory of the guest, the files are opened without the write access
+/// and the changes of the directory are denied.
+pub struct ReadOnlyDir(pub Box<dyn WasiDir>);
+
+fn read_only() -> Error {
+    Errno::Rofs.into()
+}
+
+#[wiggle::async_trait]
+impl WasiDir for ReadOnlyDir {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    async fn open_file(
+        &self,
+        symlink_follow: bool,
+        path: &str,
+        oflags: OFlags,
+        read: bool,
+        write: bool,
+        fdflags: FdFlags,
+    ) -> Result<OpenResult, Error> {
+        let changes = OFlags::CREATE | OFlags::EXCLUSIVE | OFlags::TRUNCATE;
+        if write || oflags.intersects(changes) || fdflags.contains(FdFlags::APPEND) {
+            return Err(read_only());
+        }
+        let rs = self
+            .0
+            .open_file(symlink_follow, path, oflags, read, false, fdflags)
+            .await?;
+        // the sub directories are also read-only.
+        Ok(match rs {
+            OpenResult::Dir(dir) => OpenResult::Dir(Box::new(ReadOnlyDir(dir))),
+            file => file,
+        })
+    }
+
+    async fn create_dir(&self, _path: &str) -> Result<(), Error> {
+        Err(read_only())
+    }
+
+    async fn readdir(
+        &self,
+        cursor: ReaddirCursor,
+    ) -> Result<Box<dyn Iterator<Item = Result<ReaddirEntity, Error>> + Send>, Error> {
+        self.0.readdir(cursor).await
+    }
+
+    async fn symlink(&self, _old_path: &str, _new_path: &str) -> Result<(), Error> {
+        Err(read_only())
+    }
+
+    async fn remove_dir(&self, _path: &str) -> Result<(), Error> {
+        Err(read_only())
+    }
+
+    async fn unlink_file(&self, _path: &str) -> Result<(), Error> {
+        Err(read_only())
+    }
+
+    async fn read_link(&self, path: &str) -> Result<PathBuf, Error> {
+        self.0.read_link(path).await
+    }
+
+    async fn get_filestat(&self) -> Result<Filestat, Error> {
+        self.0.get_filestat().await
+    }
+
+    async fn get_path_filestat(
+        &self,
+        path: &str,
+        follow_symlinks: bool,
+    ) -> Result<Filestat, Error> {
+        self.0.get_path_filestat(path, follow_symlinks).await
+    }
+
+    async fn rename(
+        &self,
+        _path: &str,
+        _dest_dir: &dyn WasiDir,
+        _dest_path: &str,
+    ) -> Result<(), Error> {
+        Err(read_only())
+    }
+
+    async fn hard_link(
+        &self,
+        _path: &str,
+        _target_dir: &dyn WasiDir,
+        _target_path: &str,
+    ) -> Result<(), Error> {
+        Err(read_only())
+    }
+
+    async fn set_times(
+        &self,
+        _path: &str,
+        _atime: Option<SystemTimeSpec>,
+        _mtime: Option<SystemTimeSpec>,
+        _follow_symlinks: bool,
+    ) -> Result<(), Error> {
+        Err(read_only())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cap_std::ambient_authority;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_readonly_dir() {
+        let tempdir = tempfile::Builder::new()
+            .prefix("readonly-dir")
+            .tempdir()
+            .expect("create temporary dir");
+        std::fs::write(tempdir.path().join("data.txt"), b"data").unwrap();
+        let dir = cap_std::fs::Dir::open_ambient_dir(tempdir.path(), ambient_authority())
+            .expect("open ambient temporary dir");
+        let dir = ReadOnlyDir(Box::new(crate::tokio::Dir::from_cap_std(dir)));
+        let rs = dir
+            .open_file(
+                false,
+                "data.txt",
+                OFlags::empty(),
+                true,
+                false,
+                FdFlags::empty(),
+            )
+            .await;
+        assert!(matches!(rs, Ok(OpenResult::File(_))));
+        let rs = dir
+            .open_file(
+                false,
+                "data.txt",
+                OFlags::empty(),
+                true,
+                true,
+                FdFlags::empty(),
+            )
+            .await;
+        assert!(rs.is_err());
+        let rs = dir
+            .open_file(
+                false,
+                "new.txt",
+                OFlags::CREATE,
+                true,
+                false,
+                FdFlags::empty(),
+            )
+            .await;
+        assert!(rs.is_err());
+        assert!(dir.unlink_file("data.txt").await.is_err());
+        assert!(dir.create_dir("sub").await.is_err());
+        assert!(tempdir.path().join("data.txt").exists());
+    }
+}
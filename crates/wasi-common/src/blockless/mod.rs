@@ -1,4 +1,12 @@
 mod config;
+mod credential;
+mod dir;
 mod permission;
+mod stdin;
+mod usage;
 pub use config::*;
+pub use credential::*;
+pub use dir::*;
 pub use permission::*;
+pub use stdin::*;
+pub use usage::*;
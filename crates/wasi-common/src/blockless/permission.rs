@@ -1,11 +1,506 @@
+use anyhow::{bail, Context};
+use std::net::IpAddr;
+use url::{Host, Url};
+
+/// The host part of the permission url.
+#[derive(Clone, Debug, PartialEq)]
+pub enum HostPattern {
+    /// `*` matches any host.
+    Any,
+    /// `*.example.com` matches all the subdomains of `example.com`, but not itself.
+    Subdomain(String),
+    /// matches the host exactly.
+    Exact(String),
+}
+
+impl HostPattern {
+    fn parse(host: &str) -> Self {
+        let host = host.to_ascii_lowercase();
+        if host == "*" {
+            HostPattern::Any
+        } else if let Some(domain) = host.strip_prefix("*.") {
+            HostPattern::Subdomain(domain.to_string())
+        } else if let Ok(ip) = host.parse::<IpAddr>() {
+            // the address is in the form of the parsed url, e.g. `::1`.
+            HostPattern::Exact(ip.to_string())
+        } else {
+            HostPattern::Exact(host)
+        }
+    }
+
+    /// the pattern matches the host names, the exact IP address only matches itself.
+    pub fn is_name(&self) -> bool {
+        match self {
+            HostPattern::Exact(host) => host.parse::<IpAddr>().is_err(),
+            _ => true,
+        }
+    }
+
+    fn is_match(&self, host: &str) -> bool {
+        match self {
+            HostPattern::Any => true,
+            HostPattern::Subdomain(domain) => host
+                .strip_suffix(domain.as_str())
+                .map(|prefix| prefix.len() > 1 && prefix.ends_with('.'))
+                .unwrap_or(false),
+            HostPattern::Exact(h) => h == host,
+        }
+    }
+}
+
+/// The parsed url of the permission, the form is `scheme://host[:port]/path`.
+/// The port can be `*`, a single port or a range such as `8000-8100`, if the port is
+/// not present, all ports are matched. The path is a glob, `*` matches inside one segment
+/// and `**` matches any segments, the path without glob matches itself and the sub paths.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UrlPattern {
+    pub scheme: String,
+    pub host: HostPattern,
+    pub ports: Option<(u16, u16)>,
+    pub path: String,
+}
+
+/// The components of the permission url, the port can be the wildcard or the range,
+/// so the pattern is split by hand.
+struct PatternParts<'a> {
+    scheme: &'a str,
+    host: &'a str,
+    port: Option<&'a str>,
+    path: &'a str,
+}
+
+impl<'a> PatternParts<'a> {
+    fn split(url: &'a str) -> Option<Self> {
+        let (scheme, rest) = url.split_once("://")?;
+        if scheme.is_empty() {
+            return None;
+        }
+        let rest = rest.split(['?', '#']).next().unwrap_or_default();
+        let (authority, path) = match rest.find('/') {
+            Some(idx) => rest.split_at(idx),
+            None => (rest, ""),
+        };
+        // the ipv6 address is wrapped by the brackets, e.g. `[::1]:80`.
+        let (host, port) = if let Some(v6) = authority.strip_prefix('[') {
+            let (host, rest) = v6.split_once(']')?;
+            (host, rest.strip_prefix(':'))
+        } else {
+            match authority.rsplit_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (authority, None),
+            }
+        };
+        Some(Self {
+            scheme,
+            host,
+            port,
+            path,
+        })
+    }
+}
+
+/// The components of the checked url. The url is parsed by `url::Url` like the clients,
+/// so the host, the port and the path are the same as the request.
+struct UrlParts {
+    scheme: String,
+    host: String,
+    port: Option<u16>,
+    path: String,
+}
+
+impl UrlParts {
+    /// parse the url, the url without the host is not matched.
+    fn parse(url: &str) -> Option<Self> {
+        let url = Url::parse(url).ok()?;
+        let host = match url.host()? {
+            Host::Domain(domain) => domain.to_ascii_lowercase(),
+            Host::Ipv4(ip) => ip.to_string(),
+            Host::Ipv6(ip) => ip.to_string(),
+        };
+        Some(Self {
+            scheme: url.scheme().to_string(),
+            host,
+            port: url.port_or_known_default(),
+            path: normalize_path(url.path()),
+        })
+    }
+}
+
+/// decode the percent-encoded path and remove the empty and dot segments as the servers
+/// resolve them, so the path can't escape the rules, e.g. `/api/%2e%2e//admin` is `/admin`.
+fn normalize_path(path: &str) -> String {
+    let decoded = percent_encoding::percent_decode_str(path).decode_utf8_lossy();
+    let mut segments: Vec<&str> = Vec::new();
+    for seg in decoded.split('/') {
+        match seg {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            seg => segments.push(seg),
+        }
+    }
+    let mut normalized = format!("/{}", segments.join("/"));
+    if decoded.ends_with('/') && !segments.is_empty() {
+        normalized.push('/');
+    }
+    normalized
+}
+
+/// match the segment with `*`, the `*` matches any characters inside the segment.
+fn segment_match(pattern: &str, seg: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == seg,
+        Some((prefix, rest)) => {
+            let Some(seg) = seg.strip_prefix(prefix) else {
+                return false;
+            };
+            (0..=seg.len())
+                .filter(|i| seg.is_char_boundary(*i))
+                .any(|i| segment_match(rest, &seg[i..]))
+        }
+    }
+}
+
+fn glob_match(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|i| glob_match(rest, &path[i..])),
+        Some((p, rest)) => match path.split_first() {
+            Some((seg, path)) => segment_match(p, seg) && glob_match(rest, path),
+            None => false,
+        },
+    }
+}
+
+impl UrlPattern {
+    pub fn parse(url: &str) -> anyhow::Result<Self> {
+        let parts =
+            PatternParts::split(url).context("the permission url must be `scheme://host`")?;
+        let ports = match parts.port {
+            None | Some("*") => None,
+            Some(port) => {
+                let (lo, hi) = port.split_once('-').unwrap_or((port, port));
+                let lo: u16 = lo.parse().context("invalid port of permission")?;
+                let hi: u16 = hi.parse().context("invalid port of permission")?;
+                if lo > hi {
+                    bail!("invalid port range of permission: {port}");
+                }
+                Some((lo, hi))
+            }
+        };
+        Ok(Self {
+            scheme: parts.scheme.to_ascii_lowercase(),
+            host: HostPattern::parse(parts.host),
+            ports,
+            path: parts.path.to_string(),
+        })
+    }
+
+    fn path_match(&self, path: &str) -> bool {
+        let pattern = self.path.as_str();
+        if pattern.is_empty() || pattern == "/" {
+            return true;
+        }
+        if pattern.contains('*') {
+            let pattern: Vec<&str> = pattern.trim_start_matches('/').split('/').collect();
+            let path: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+            return glob_match(&pattern, &path);
+        }
+        match path.strip_prefix(pattern) {
+            Some(rest) => rest.is_empty() || pattern.ends_with('/') || rest.starts_with('/'),
+            None => false,
+        }
+    }
+
+    /// check the url is matched by the pattern, the url which fails to parse is never matched.
+    pub fn is_match(&self, url: &str) -> bool {
+        let Some(parts) = UrlParts::parse(url) else {
+            return false;
+        };
+        if parts.scheme != self.scheme || !self.host.is_match(&parts.host) {
+            return false;
+        }
+        if let Some((lo, hi)) = self.ports {
+            match parts.port {
+                Some(port) if port >= lo && port <= hi => {}
+                _ => return false,
+            }
+        }
+        self.path_match(&parts.path)
+    }
+}
+
+/// The permission of the resource, the string form is `[!][METHODS ]scheme://host[:port]/path`,
+/// e.g. `GET,POST https://*.example.com/api/**` or `!http://example.com:8000-8100`.
+/// The leading `!` marks the deny rule, which overrides the allow rules.
 #[derive(Clone, Debug, PartialEq)]
 pub struct Permission {
     pub url: String,
     pub schema: String,
+    /// the allowed methods of http, empty means all methods.
+    pub methods: Vec<String>,
+    pub deny: bool,
+    pattern: UrlPattern,
 }
 
 impl Permission {
+    pub fn new(url: &str) -> anyhow::Result<Self> {
+        let pattern = UrlPattern::parse(url)?;
+        Ok(Self {
+            url: url.to_string(),
+            schema: pattern.scheme.clone(),
+            methods: Vec::new(),
+            deny: false,
+            pattern,
+        })
+    }
+
+    /// parse the permission from the string form.
+    pub fn parse(perm: &str) -> anyhow::Result<Self> {
+        let perm = perm.trim();
+        let (deny, perm) = match perm.strip_prefix('!') {
+            Some(perm) => (true, perm.trim_start()),
+            None => (false, perm),
+        };
+        let (methods, url) = match perm.split_once(char::is_whitespace) {
+            Some((methods, url)) => (Some(methods), url.trim()),
+            None => (None, perm),
+        };
+        let mut permission = Self::new(url)?.with_deny(deny);
+        if let Some(methods) = methods {
+            permission = permission.with_methods(methods.split(','));
+        }
+        Ok(permission)
+    }
+
+    pub fn with_methods<I, S>(mut self, methods: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.methods = methods
+            .into_iter()
+            .map(|m| m.as_ref().trim().to_ascii_uppercase())
+            .filter(|m| !m.is_empty())
+            .collect();
+        self
+    }
+
+    pub fn with_deny(mut self, deny: bool) -> Self {
+        self.deny = deny;
+        self
+    }
+
+    #[inline(always)]
+    pub fn pattern(&self) -> &UrlPattern {
+        &self.pattern
+    }
+
+    /// check the url and method is matched by the rule, no matter the rule is allow or deny.
+    /// the rule with methods only matches the request with one of the methods.
+    pub fn is_match(&self, url: &str, method: Option<&str>) -> bool {
+        if !self.methods.is_empty() {
+            match method {
+                Some(m) if self.methods.iter().any(|s| s.eq_ignore_ascii_case(m)) => {}
+                _ => return false,
+            }
+        }
+        self.pattern.is_match(url)
+    }
+
     pub fn is_permision(&self, url: &str) -> bool {
-        url.to_ascii_lowercase().starts_with(&self.url)
+        !self.deny && self.is_match(url, None)
+    }
+}
+
+/// check the url with the permissions, the url is permitted when any allow rule is matched
+/// and no deny rule is matched.
+pub fn permissions_check<'a>(
+    perms: impl IntoIterator<Item = &'a Permission>,
+    url: &str,
+    method: Option<&str>,
+) -> bool {
+    let mut allowed = false;
+    for perm in perms {
+        if perm.is_match(url, method) {
+            if perm.deny {
+                return false;
+            }
+            allowed = true;
+        }
+    }
+    allowed
+}
+
+/// check the host with the permissions no matter the ports and paths, e.g. to resolve the host
+/// of `tcp://example.com:443`. The deny rule only denies the host when it has no ports.
+pub fn host_permissions_check<'a>(
+    perms: impl IntoIterator<Item = &'a Permission>,
+    host: &str,
+) -> bool {
+    let host = match host.parse::<IpAddr>() {
+        Ok(ip) => ip.to_string(),
+        Err(_) => host.to_ascii_lowercase(),
+    };
+    let mut allowed = false;
+    for perm in perms {
+        if perm.pattern.host.is_match(&host) {
+            if !perm.deny {
+                allowed = true;
+            } else if perm.pattern.ports.is_none() {
+                return false;
+            }
+        }
+    }
+    allowed
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn check(perms: &[&str], url: &str, method: Option<&str>) -> bool {
+        let perms: Vec<Permission> = perms
+            .iter()
+            .map(|p| Permission::parse(p).unwrap())
+            .collect();
+        permissions_check(&perms, url, method)
+    }
+
+    #[test]
+    fn test_permission_parse() {
+        let perm = Permission::parse("!get,Post https://*.example.com:8000-8100/api/**").unwrap();
+        assert!(perm.deny);
+        assert_eq!(perm.schema, "https");
+        assert_eq!(perm.methods, vec!["GET", "POST"]);
+        assert_eq!(
+            perm.pattern(),
+            &UrlPattern {
+                scheme: "https".to_string(),
+                host: HostPattern::Subdomain("example.com".to_string()),
+                ports: Some((8000, 8100)),
+                path: "/api/**".to_string(),
+            }
+        );
+        assert!(Permission::parse("example.com").is_err());
+        assert!(Permission::parse("http://example.com:90-80").is_err());
+        assert!(Permission::parse("http://example.com:http").is_err());
+    }
+
+    #[test]
+    fn test_permission_legacy() {
+        let perms = ["http://httpbin.org/anything"];
+        assert!(check(&perms, "http://httpbin.org/anything", None));
+        assert!(check(&perms, "http://httpbin.org/anything/1?a=1", None));
+        assert!(check(&perms, "HTTP://HTTPBIN.ORG:8080/anything", None));
+        assert!(!check(&perms, "http://httpbin.org/anythingelse", None));
+        assert!(!check(&perms, "https://httpbin.org/anything", None));
+        assert!(!check(&perms, "http://httpbin.org.evil.com/anything", None));
+        assert!(check(&["tcp://127.0.0.1:80"], "tcp://127.0.0.1:80", None));
+        assert!(check(&["ipfs://files"], "ipfs://files/ls", None));
+    }
+
+    #[test]
+    fn test_permission_host_port() {
+        let perms = ["https://*.example.com:443"];
+        assert!(check(&perms, "https://api.example.com/v1", None));
+        assert!(check(&perms, "https://a.b.example.com:443", None));
+        assert!(!check(&perms, "https://example.com", None));
+        assert!(!check(&perms, "https://api.example.com:8443", None));
+        assert!(!check(&perms, "https://badexample.com", None));
+        let perms = ["tcp://*:8000-8100"];
+        assert!(check(&perms, "tcp://10.0.0.1:8000", None));
+        assert!(check(&perms, "tcp://10.0.0.1:8100", None));
+        assert!(!check(&perms, "tcp://10.0.0.1:8101", None));
+        assert!(!check(&perms, "tcp://10.0.0.1", None));
+        assert!(check(&["tcp://[::1]:80"], "tcp://[::1]:80", None));
+        assert!(check(&["tcp://[::1]:80"], "tcp://[0:0::1]:80", None));
+    }
+
+    #[test]
+    fn test_permission_url_parse() {
+        let perms = ["http://*.example.com"];
+        // the backslash ends the host, the request goes to `evil.com`.
+        assert!(!check(&perms, r"http://evil.com\@a.example.com/", None));
+        assert!(!check(&perms, "http://a.example.com@evil.com/", None));
+        assert!(check(&perms, "http://user@a.example.com/", None));
+        assert!(!check(&perms, "http://a.example.com:port/", None));
+        assert!(!check(&perms, "a.example.com", None));
+    }
+
+    #[test]
+    fn test_permission_path_normalize() {
+        let perms = ["http://example.com/api/**"];
+        assert!(!check(&perms, "http://example.com/api/../admin", None));
+        assert!(!check(&perms, "http://example.com/api/%2e%2e/admin", None));
+        assert!(!check(
+            &perms,
+            "http://example.com/api/%2E%2E%2Fadmin",
+            None
+        ));
+        assert!(check(&perms, "http://example.com/api/./v1/../v2", None));
+        let perms = ["http://example.com", "!http://example.com/admin"];
+        assert!(!check(&perms, "http://example.com//admin", None));
+        assert!(!check(&perms, "http://example.com/%61dmin/users", None));
+        assert!(!check(&perms, "http://example.com/a/../admin", None));
+        assert!(check(&perms, "http://example.com/administrator", None));
+    }
+
+    #[test]
+    fn test_permission_path_glob() {
+        let perms = ["http://example.com/api/*/items"];
+        assert!(check(&perms, "http://example.com/api/v1/items", None));
+        assert!(!check(&perms, "http://example.com/api/v1/v2/items", None));
+        let perms = ["http://example.com/api/**"];
+        assert!(check(&perms, "http://example.com/api/v1/v2/items", None));
+        assert!(!check(&perms, "http://example.com/other", None));
+        let perms = ["http://example.com/files/*.json"];
+        assert!(check(&perms, "http://example.com/files/a.json", None));
+        assert!(!check(&perms, "http://example.com/files/a.txt", None));
+    }
+
+    #[test]
+    fn test_permission_methods_and_deny() {
+        let perms = ["GET,HEAD http://example.com"];
+        assert!(check(&perms, "http://example.com/a", Some("get")));
+        assert!(!check(&perms, "http://example.com/a", Some("POST")));
+        assert!(!check(&perms, "http://example.com/a", None));
+        let perms = ["http://example.com", "!http://example.com/admin"];
+        assert!(check(&perms, "http://example.com/a", None));
+        assert!(!check(&perms, "http://example.com/admin/users", None));
+        let perms = ["http://example.com", "!DELETE http://example.com"];
+        assert!(check(&perms, "http://example.com/a", Some("GET")));
+        assert!(!check(&perms, "http://example.com/a", Some("DELETE")));
+        assert!(!check(&[], "http://example.com", None));
+    }
+
+    #[test]
+    fn test_host_permissions_check() {
+        let perms: Vec<Permission> = [
+            "tcp://example.com:443",
+            "udp://*.example.org",
+            "!tcp://bad.example.org:53",
+        ]
+        .iter()
+        .map(|p| Permission::parse(p).unwrap())
+        .collect();
+        assert!(host_permissions_check(&perms, "Example.com"));
+        assert!(host_permissions_check(&perms, "a.example.org"));
+        // the deny rule with the port doesn't deny the host.
+        assert!(host_permissions_check(&perms, "bad.example.org"));
+        assert!(!host_permissions_check(&perms, "example.net"));
+        let perms = vec![Permission::parse("!tcp://bad.example.org").unwrap()];
+        assert!(!host_permissions_check(&perms, "bad.example.org"));
+    }
+
+    #[test]
+    fn test_host_pattern_is_name() {
+        let is_name = |p: &str| Permission::parse(p).unwrap().pattern().host.is_name();
+        assert!(is_name("tcp://example.com:443"));
+        assert!(is_name("udp://*.example.org"));
+        assert!(is_name("tcp://*:80"));
+        assert!(!is_name("tcp://127.0.0.1:8080"));
+        assert!(!is_name("tcp://[::1]:8080"));
     }
 }
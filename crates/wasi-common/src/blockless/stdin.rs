@@ -0,0 +1,29 @@
+use std::fmt;
+use std::sync::Arc;
+
+/// The async source of the streaming stdin, e.g. the stdin pipe of the host.
+#[wiggle::async_trait]
+pub trait StdinSource: Send + Sync {
+    /// read the bytes into the buffer, 0 is returned at the end of the stdin.
+    async fn read(&self, buf: &mut [u8]) -> std::io::Result<usize>;
+}
+
+/// The streaming stdin, the guest reads it incrementally, it's shared by the guest threads.
+#[derive(Clone)]
+pub struct StdinStream(Arc<dyn StdinSource>);
+
+impl StdinStream {
+    pub fn new(source: impl StdinSource + 'static) -> Self {
+        Self(Arc::new(source))
+    }
+
+    pub async fn read(&self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.0.read(buf).await
+    }
+}
+
+impl fmt::Debug for StdinStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "StdinStream(..)")
+    }
+}
@@ -0,0 +1,98 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// The host extensions counted in the execution report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Extension {
+    Http = 0,
+    Ipfs,
+    S3,
+    Cgi,
+    Kv,
+}
+
+impl Extension {
+    pub const ALL: [Extension; 5] = [
+        Extension::Http,
+        Extension::Ipfs,
+        Extension::S3,
+        Extension::Cgi,
+        Extension::Kv,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Extension::Http => "http",
+            Extension::Ipfs => "ipfs",
+            Extension::S3 => "s3",
+            Extension::Cgi => "cgi",
+            Extension::Kv => "kv",
+        }
+    }
+}
+
+/// The usage of the extension, the `calls` is the requests or commands started by the guest,
+/// the `bytes_in` is the bytes read into the guest and `bytes_out` is the bytes written by the guest.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExtensionUsage {
+    pub calls: u64,
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+}
+
+/// The usage counters of the extensions, the counters are shared by the threads of the guest.
+#[derive(Debug, Default)]
+pub struct UsageCounter {
+    // the calls, bytes in and bytes out of the extensions.
+    counters: [[AtomicU64; 3]; Extension::ALL.len()],
+}
+
+impl UsageCounter {
+    fn add(&self, ext: Extension, idx: usize, n: u64) {
+        self.counters[ext as usize][idx].fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub fn call(&self, ext: Extension) {
+        self.add(ext, 0, 1);
+    }
+
+    pub fn bytes_in(&self, ext: Extension, n: u64) {
+        self.add(ext, 1, n);
+    }
+
+    pub fn bytes_out(&self, ext: Extension, n: u64) {
+        self.add(ext, 2, n);
+    }
+
+    pub fn usage(&self, ext: Extension) -> ExtensionUsage {
+        let [calls, bytes_in, bytes_out] = &self.counters[ext as usize];
+        ExtensionUsage {
+            calls: calls.load(Ordering::Relaxed),
+            bytes_in: bytes_in.load(Ordering::Relaxed),
+            bytes_out: bytes_out.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_usage_counter() {
+        let counter = UsageCounter::default();
+        counter.call(Extension::Http);
+        counter.call(Extension::Http);
+        counter.bytes_in(Extension::Http, 10);
+        counter.bytes_out(Extension::S3, 5);
+        assert_eq!(
+            counter.usage(Extension::Http),
+            ExtensionUsage {
+                calls: 2,
+                bytes_in: 10,
+                bytes_out: 0,
+            }
+        );
+        assert_eq!(counter.usage(Extension::S3).bytes_out, 5);
+        assert_eq!(counter.usage(Extension::Cgi), ExtensionUsage::default());
+    }
+}
@@ -4,13 +4,29 @@ use crate::file::{FileAccessMode, FileEntry, WasiFile};
 use crate::sched::WasiSched;
 use crate::string_array::StringArray;
 use crate::table::Table;
-use crate::BlocklessConfig;
+use crate::{
+    credential_resource, BlocklessConfig, Credential, CredentialError, HttpConfig, IpfsApi,
+    KvConfig, Stdin, UsageCounter,
+};
 use crate::{Error, StringArrayError};
 use cap_rand::RngCore;
+use std::any::Any;
 use std::ops::Deref;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 
+/// read the data from the cursor into the buffer, the cursor is advanced by the read,
+/// 0 is returned at the end of the data.
+pub fn read_at_cursor(cursor: &AtomicUsize, data: &[u8], buf: &mut [u8]) -> usize {
+    let offset = cursor.load(Ordering::Relaxed);
+    let rest = data.get(offset..).unwrap_or_default();
+    let n = rest.len().min(buf.len());
+    buf[..n].copy_from_slice(&rest[..n]);
+    cursor.store(offset + n, Ordering::Relaxed);
+    n
+}
+
 /// An `Arc`-wrapper around the wasi-common context to allow mutable access to
 /// the file descriptor table. This wrapper is only necessary due to the
 /// signature of `fd_fdstat_set_flags`; if that changes, there are a variety of
@@ -29,7 +45,19 @@ pub struct WasiCtxInner {
     pub clocks: WasiClocks,
     pub sched: Box<dyn WasiSched>,
     pub table: Table,
+    /// the resources of the host extensions, e.g. the http responses, the handles
+    /// are scoped to the guest and dropped with the context.
+    pub resources: Table,
     pub blockless_config: Mutex<Option<BlocklessConfig>>,
+    /// the driver registry of the runner, `blockless_open` resolves the drivers through it.
+    /// The type of the registry is defined by the drivers crate.
+    pub drivers: Mutex<Option<Arc<dyn Any + Send + Sync>>>,
+    /// the usage of the host extensions, it's reported after the run.
+    pub usage: UsageCounter,
+    /// the cursor of the fixed stdin read by `memory_read`.
+    pub stdin_offset: AtomicUsize,
+    /// the cursor of the env json read by `env_var_read`.
+    pub env_offset: AtomicUsize,
 }
 
 impl WasiCtx {
@@ -44,6 +72,11 @@ impl WasiCtx {
             env: StringArray::new(),
             random: Mutex::new(random),
             blockless_config: Mutex::new(None),
+            drivers: Mutex::new(None),
+            resources: Table::new(),
+            usage: UsageCounter::default(),
+            stdin_offset: AtomicUsize::new(0),
+            env_offset: AtomicUsize::new(0),
             clocks,
             sched,
             table,
@@ -59,6 +92,16 @@ impl WasiCtx {
         c.map(|c| lock.replace(c));
     }
 
+    pub fn set_drivers(&mut self, drivers: Arc<dyn Any + Send + Sync>) {
+        self.0.drivers.lock().unwrap().replace(drivers);
+    }
+
+    /// get the driver registry, it's `None` if the registry is not set or not the type `T`.
+    pub fn drivers<T: Any + Send + Sync>(&self) -> Option<Arc<T>> {
+        let drivers = self.0.drivers.lock().unwrap().clone()?;
+        drivers.downcast().ok()
+    }
+
     pub fn config_drivers_root_path_ref(&mut self) -> Option<String> {
         let lock = self.0.blockless_config.lock().unwrap();
         lock.as_ref()
@@ -71,6 +114,99 @@ impl WasiCtx {
             .and_then(|l| l.fix_stdin_ref().map(String::from))
     }
 
+    /// read the stdin of the configure from the cursor, the cursor is advanced by the read,
+    /// 0 is returned at the end of the stdin.
+    pub async fn config_stdin_read(&self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let stream = {
+            let lock = self.0.blockless_config.lock().unwrap();
+            match lock.as_ref().map(|c| &c.stdio.stdin) {
+                Some(Stdin::Fixed(s)) => {
+                    return Ok(read_at_cursor(&self.stdin_offset, s.as_bytes(), buf))
+                }
+                Some(Stdin::Stream(stream)) => stream.clone(),
+                _ => return Ok(0),
+            }
+        };
+        stream.read(buf).await
+    }
+
+    /// the env vars of the configure for `env_var_read`, the allowed host env vars are
+    /// passed through, and the env vars of the configure override them.
+    pub fn config_env_vars(&self) -> Vec<(String, String)> {
+        let lock = self.0.blockless_config.lock().unwrap();
+        let conf = match lock.as_ref() {
+            Some(conf) => conf,
+            None => return Vec::new(),
+        };
+        let mut vars = conf
+            .env_passthrough_ref()
+            .iter()
+            .filter(|name| !conf.envs_ref().iter().any(|(k, _)| k == *name))
+            .filter_map(|name| std::env::var(name).ok().map(|v| (name.clone(), v)))
+            .collect::<Vec<_>>();
+        vars.extend(conf.envs_ref().iter().cloned());
+        vars
+    }
+
+    pub fn config_fs_root_path_ref(&self) -> Option<String> {
+        let lock = self.0.blockless_config.lock().unwrap();
+        lock.as_ref()
+            .and_then(|l| l.fs_root_path_ref().map(String::from))
+    }
+
+    pub fn config_tls_ca_file(&self) -> Option<String> {
+        let lock = self.0.blockless_config.lock().unwrap();
+        lock.as_ref()
+            .and_then(|l| l.tls_ca_file_ref().map(String::from))
+    }
+
+    /// the ipfs api endpoint of the configure, the default endpoint is used if no configure.
+    pub fn ipfs_api(&self) -> IpfsApi {
+        let lock = self.0.blockless_config.lock().unwrap();
+        lock.as_ref()
+            .map(|l| l.ipfs_api_ref().clone())
+            .unwrap_or_default()
+    }
+
+    /// the kv store of the configure and the app of the namespaces, it's none if the app
+    /// is not configured.
+    pub fn kv_config(&self) -> Option<(KvConfig, String)> {
+        let lock = self.0.blockless_config.lock().unwrap();
+        lock.as_ref()
+            .and_then(|l| Some((l.kv_ref().clone(), l.kv_app()?.to_string())))
+    }
+
+    pub fn http_config(&self) -> HttpConfig {
+        let lock = self.0.blockless_config.lock().unwrap();
+        lock.as_ref()
+            .map(|l| l.http_ref().clone())
+            .unwrap_or_default()
+    }
+
+    /// resolve the credential profile of the configure for the request url, the guest must
+    /// have the permission of the profile, e.g. `cred://s3-prod`, and the url must be one
+    /// of the urls of the profile.
+    pub fn credential(&self, name: &str, url: &str) -> Result<Credential, CredentialError> {
+        let profile = match self.blockless_config.lock().unwrap().deref() {
+            Some(ref c) => {
+                if !c.schema_permission("cred", &credential_resource(name)) {
+                    return Err(CredentialError::PermissionDeny);
+                }
+                c.credentials_ref().get(name).cloned()
+            }
+            None => None,
+        };
+        let profile = profile.ok_or(CredentialError::NotFound)?;
+        if !profile.allows(url) {
+            tracing::error!("the credential profile {name} is not allowed for {url}");
+            return Err(CredentialError::PermissionDeny);
+        }
+        profile.resolve().map_err(|e| {
+            tracing::error!("resolve the credential profile {name} error: {e}");
+            CredentialError::Unavailable
+        })
+    }
+
     pub fn resource_permission(&self, resource: &str) -> bool {
         match self.blockless_config.lock().unwrap().deref() {
             Some(ref c) => c.resource_permission(resource),
@@ -78,6 +214,27 @@ impl WasiCtx {
         }
     }
 
+    pub fn method_permission(&self, resource: &str, method: Option<&str>) -> bool {
+        match self.blockless_config.lock().unwrap().deref() {
+            Some(ref c) => c.method_permission(resource, method),
+            None => false,
+        }
+    }
+
+    pub fn schema_permission(&self, schema: &str, resource: &str) -> bool {
+        match self.blockless_config.lock().unwrap().deref() {
+            Some(ref c) => c.schema_permission(schema, resource),
+            None => false,
+        }
+    }
+
+    pub fn host_permission(&self, schemas: &[&str], host: &str) -> bool {
+        match self.blockless_config.lock().unwrap().deref() {
+            Some(ref c) => c.host_permission(schemas, host),
+            None => false,
+        }
+    }
+
     pub fn insert_file(&self, fd: u32, file: Box<dyn WasiFile>, access_mode: FileAccessMode) {
         self.table()
             .insert_at(fd, Arc::new(FileEntry::new(file, access_mode)));
@@ -105,6 +262,10 @@ impl WasiCtx {
         &self.table
     }
 
+    pub fn resource_table(&self) -> &Table {
+        &self.resources
+    }
+
     pub fn table_mut(&mut self) -> Option<&mut Table> {
         Arc::get_mut(&mut self.0).map(|c| &mut c.table)
     }
@@ -7,7 +7,7 @@ pub mod stdio;
 use self::sched::sched_ctx;
 use crate::sync::net::Socket;
 pub use crate::sync::{clocks_ctx, random_ctx};
-use crate::{file::FileAccessMode, Error, Table, WasiCtx, WasiFile};
+use crate::{file::FileAccessMode, Error, ErrorExt, Table, WasiCtx, WasiFile};
 pub use dir::Dir;
 pub use file::File;
 pub use net::*;
@@ -92,13 +92,23 @@ impl WasiCtxBuilder {
         self.ctx.push_preopened_dir(dir, guest_path)?;
         Ok(self)
     }
+    /// preopen the directory as read-only, the guest can't change the directory.
+    pub fn preopened_readonly_dir(
+        &mut self,
+        dir: cap_std::fs::Dir,
+        guest_path: impl AsRef<Path>,
+    ) -> Result<&mut Self, Error> {
+        let dir = Box::new(crate::tokio::dir::Dir::from_cap_std(dir));
+        let dir = Box::new(crate::ReadOnlyDir(dir));
+        self.ctx.push_preopened_dir(dir, guest_path)?;
+        Ok(self)
+    }
     pub fn preopened_socket(
         &mut self,
         fd: u32,
         socket: impl Into<Socket>,
     ) -> Result<&mut Self, Error> {
-        let socket: Socket = socket.into();
-        let file: Box<dyn WasiFile> = socket.into();
+        let file = socket_file(socket.into());
         self.ctx
             .insert_file(fd, file, FileAccessMode::READ | FileAccessMode::WRITE);
         Ok(self)
@@ -120,12 +130,22 @@ impl WasiCtxBuilder {
 // This function takes this blocking code and executes it using a dummy executor
 // to assert its immediate readiness. We tell tokio this is a blocking operation
 // with the block_in_place function.
+//
+// The WasiCtx of this mod requires the multi-thread runtime. The block_in_place
+// function panics in the current thread runtime, and running the blocking code in
+// place would stall the only thread of the runtime, so the operation fails there.
 pub(crate) fn block_on_dummy_executor<'a, F, Fut, T>(f: F) -> Result<T, Error>
 where
     F: FnOnce() -> Fut + Send + 'a,
     Fut: Future<Output = Result<T, Error>>,
     T: Send + 'static,
 {
+    if let Ok(tokio::runtime::RuntimeFlavor::CurrentThread) =
+        tokio::runtime::Handle::try_current().map(|h| h.runtime_flavor())
+    {
+        return Err(Error::not_supported()
+            .context("the blocking operation requires the multi-thread tokio runtime"));
+    }
     tokio::task::block_in_place(move || {
         wiggle::run_in_dummy_executor(f()).expect("wrapped operation should be synchronous")
     })
@@ -4,3 +4,18 @@ pub use super::file::TcpStream;
 pub use super::file::UnixListener;
 #[cfg(unix)]
 pub use super::file::UnixStream;
+
+use crate::sync::net::Socket;
+use crate::WasiFile;
+
+/// wrap the socket into the file of tokio, it's polled by the tokio scheduler.
+pub fn socket_file(socket: Socket) -> Box<dyn WasiFile> {
+    match socket {
+        Socket::TcpListener(l) => Box::new(TcpListener::from_cap_std(l)),
+        Socket::TcpStream(s) => Box::new(TcpStream::from_cap_std(s)),
+        #[cfg(unix)]
+        Socket::UnixListener(l) => Box::new(UnixListener::from_cap_std(l)),
+        #[cfg(unix)]
+        Socket::UnixStream(s) => Box::new(UnixStream::from_cap_std(s)),
+    }
+}